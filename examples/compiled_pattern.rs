@@ -0,0 +1,43 @@
+use std::time::Instant;
+
+use symbolica::{
+    atom::Atom,
+    id::{CompiledPattern, Pattern},
+};
+
+fn main() {
+    let exprs: Vec<_> = (0..1000)
+        .map(|i| Atom::parse(&format!("f(x^{})*y^2+g(z^2,{})", i % 5 + 1, i)).unwrap())
+        .collect();
+    let rhs = Pattern::parse("h(x_)").unwrap();
+
+    let n_repeats = 10;
+    let t = Instant::now();
+    let mut recompiled = vec![];
+    for _ in 0..n_repeats {
+        recompiled.clear();
+        for e in &exprs {
+            let p = Pattern::parse("x_^2*y^2").unwrap();
+            recompiled.push(p.replace_all(e.as_view(), &rhs, None, None));
+        }
+    }
+    println!(
+        "Recompiling the pattern for {} expressions took {:#?} per iteration",
+        exprs.len(),
+        t.elapsed() / n_repeats
+    );
+
+    let t = Instant::now();
+    let p = CompiledPattern::new("x_^2*y^2").unwrap();
+    let mut compiled = vec![];
+    for _ in 0..n_repeats {
+        compiled = exprs.iter().map(|e| e.replace_all(&p, &rhs)).collect();
+    }
+    println!(
+        "Reusing the compiled pattern for {} expressions took {:#?} per iteration",
+        exprs.len(),
+        t.elapsed() / n_repeats
+    );
+
+    assert_eq!(recompiled, compiled);
+}
@@ -55,6 +55,9 @@ fn main() {
         square_brackets_for_function: false,
         num_exp_as_superscript: false,
         latex: false,
+        decimal_precision: None,
+        wildcard_prefix: None,
+        max_coeff_digits: None,
     };
 
     buffer.clear();
@@ -0,0 +1,42 @@
+use std::time::Instant;
+
+use symbolica::domains::{
+    integer::Integer,
+    lazy_rational::{LazyRational, LazyRationalField},
+    rational::{Rational, Q},
+    Ring,
+};
+
+fn main() {
+    // sum many reduced fractions with a large common denominator
+    let den = Integer::new(3).pow(100) * &Integer::new(2).pow(50);
+    let coeffs: Vec<_> = (0..2000)
+        .map(|i| Rational::from((Integer::new(6 * i + 1).pow(20), den.clone())))
+        .collect();
+    let n_repeats = 100;
+
+    let t = Instant::now();
+    let mut eager = Q.zero();
+    for _ in 0..n_repeats {
+        eager = Q.zero();
+        for c in &coeffs {
+            Q.add_assign(&mut eager, c);
+        }
+    }
+    println!("Eager evaluation took {:#?}", t.elapsed() / n_repeats);
+
+    let field = LazyRationalField::default();
+    let coeffs: Vec<LazyRational> = coeffs.into_iter().map(|c| c.into()).collect();
+
+    let t = Instant::now();
+    let mut lazy = field.zero();
+    for _ in 0..n_repeats {
+        lazy = field.zero();
+        for c in &coeffs {
+            field.add_assign(&mut lazy, c);
+        }
+    }
+    println!("Lazy evaluation took {:#?}", t.elapsed() / n_repeats);
+
+    assert_eq!(lazy.to_rational(), eager);
+}
@@ -4,6 +4,8 @@ use symbolica::{
     poly::evaluate::{BorrowedHornerScheme, InstructionSetPrinter},
 };
 
+use std::time::Instant;
+
 use symbolica::poly::polynomial::MultivariatePolynomial;
 use wide::f64x4;
 
@@ -75,4 +77,44 @@ fn main() {
                 .collect::<Vec<_>>()
         )[0]
     );
+
+    // compare point-wise and batched evaluation
+    let n_points = 100_000;
+    let columns: Vec<Vec<f64>> = (0..poly.nvars())
+        .map(|x| {
+            (0..n_points)
+                .map(|p| x as f64 + 1. / (p as f64 + 1.))
+                .collect()
+        })
+        .collect();
+    let column_refs: Vec<&[f64]> = columns.iter().map(|c| c.as_slice()).collect();
+
+    let mut evaluator = o_f64.evaluator();
+    let t = Instant::now();
+    let mut point = vec![0.; poly.nvars()];
+    let mut sum = 0.;
+    for p in 0..n_points {
+        for (x, c) in point.iter_mut().zip(&columns) {
+            *x = c[p];
+        }
+        sum += evaluator.evaluate_with_input(&point)[0];
+    }
+    println!(
+        "Point-wise evaluation of {} points: {:?}",
+        n_points,
+        t.elapsed()
+    );
+
+    let t = Instant::now();
+    let mut out = vec![0.; n_points];
+    evaluator.eval_slice(&column_refs, &mut out).unwrap();
+    println!(
+        "Batched evaluation of {} points: {:?}",
+        n_points,
+        t.elapsed()
+    );
+    println!(
+        "Difference between sums = {}",
+        sum - out.iter().sum::<f64>()
+    );
 }
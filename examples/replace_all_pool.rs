@@ -0,0 +1,54 @@
+use std::time::Instant;
+
+use symbolica::{
+    atom::Atom,
+    id::{AtomMatchIterator, Condition, MatchSettings, MatchStack, Matcher, Pattern},
+};
+
+fn main() {
+    let terms: Vec<_> = (0..2000)
+        .map(|i| Atom::parse(&format!("f({},x,y)*g(x+{})*h({})", i, i, i % 7)).unwrap())
+        .collect();
+    let pat = Pattern::parse("f(z_,x,y)*g(w__)").unwrap();
+    let conditions = Condition::default();
+    let settings = MatchSettings::default();
+
+    let n_repeats = 100;
+    let t = Instant::now();
+    let mut fresh = 0;
+    for _ in 0..n_repeats {
+        for term in &terms {
+            let mut stack = MatchStack::new(&conditions, &settings);
+            let mut it = AtomMatchIterator::new(&pat, term.as_view());
+            while it.next(&mut stack).is_some() {
+                fresh += 1;
+            }
+        }
+    }
+    println!(
+        "Matching {} terms with a fresh match stack took {:#?} per iteration",
+        terms.len(),
+        t.elapsed() / n_repeats
+    );
+
+    let t = Instant::now();
+    let mut reused = 0;
+    let mut matcher = Matcher::new();
+    for _ in 0..n_repeats {
+        for term in &terms {
+            let mut stack = matcher.acquire(&conditions, &settings);
+            let mut it = AtomMatchIterator::new(&pat, term.as_view());
+            while it.next(&mut stack).is_some() {
+                reused += 1;
+            }
+            matcher.release(stack);
+        }
+    }
+    println!(
+        "Matching {} terms with a reused match stack took {:#?} per iteration",
+        terms.len(),
+        t.elapsed() / n_repeats
+    );
+
+    assert_eq!(fresh, reused);
+}
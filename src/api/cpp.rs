@@ -168,6 +168,9 @@ unsafe extern "C" fn simplify(
         square_brackets_for_function: false,
         num_exp_as_superscript: false,
         latex: false,
+        decimal_precision: None,
+        wildcard_prefix: None,
+        max_coeff_digits: None,
     };
 
     macro_rules! to_rational {
@@ -304,6 +307,9 @@ unsafe extern "C" fn simplify_factorized(
         square_brackets_for_function: false,
         num_exp_as_superscript: false,
         latex: false,
+        decimal_precision: None,
+        wildcard_prefix: None,
+        max_coeff_digits: None,
     };
 
     macro_rules! to_rational {
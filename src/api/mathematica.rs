@@ -94,6 +94,9 @@ fn simplify(input: String, prime: i64, explicit_rational_polynomial: bool) -> St
                             square_brackets_for_function: false,
                             num_exp_as_superscript: false,
                             latex: false,
+                            decimal_precision: None,
+                            wildcard_prefix: None,
+                            max_coeff_digits: None,
                         },
                         add_parentheses: false
                     }
@@ -129,6 +132,9 @@ fn simplify(input: String, prime: i64, explicit_rational_polynomial: bool) -> St
                                 square_brackets_for_function: false,
                                 num_exp_as_superscript: false,
                                 latex: false,
+                                decimal_precision: None,
+                                wildcard_prefix: None,
+                                max_coeff_digits: None,
                             },
                             add_parentheses: false
                         }
@@ -163,6 +169,9 @@ fn simplify(input: String, prime: i64, explicit_rational_polynomial: bool) -> St
                                 square_brackets_for_function: false,
                                 num_exp_as_superscript: false,
                                 latex: false,
+                                decimal_precision: None,
+                                wildcard_prefix: None,
+                                max_coeff_digits: None,
                             },
                             add_parentheses: false
                         }
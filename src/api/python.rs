@@ -866,7 +866,10 @@ impl PythonPattern {
                 multiplication_operator,
                 square_brackets_for_function,
                 num_exp_as_superscript,
-                latex
+                latex,
+                decimal_precision: None,
+                wildcard_prefix: None,
+                max_coeff_digits: None,
             },)
         );
     }
@@ -1571,7 +1574,10 @@ impl PythonExpression {
                     multiplication_operator,
                     square_brackets_for_function,
                     num_exp_as_superscript,
-                    latex
+                    latex,
+                    decimal_precision: None,
+                    wildcard_prefix: None,
+                    max_coeff_digits: None,
                 },
             )
         ))
@@ -3923,7 +3929,10 @@ macro_rules! generate_methods {
                                 multiplication_operator,
                                 square_brackets_for_function,
                                 num_exp_as_superscript,
-                                latex
+                                latex,
+                                decimal_precision: None,
+                                wildcard_prefix: None,
+                                max_coeff_digits: None,
                             },
                         )
                     ))
@@ -2,16 +2,22 @@ mod coefficient;
 pub mod representation;
 
 use crate::{
-    coefficient::Coefficient,
-    parser::Token,
+    coefficient::{Coefficient, CoefficientView},
+    domains::{integer::Integer, rational::Rational},
+    parser::{ParseSettings, Token},
     printer::AtomPrinter,
     state::{RecycledAtom, Workspace},
 };
+use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, hash::Hash, ops::DerefMut};
 
+pub use self::coefficient::{
+    dump_rational_buffer, ff_add, ff_div, ff_inv, ff_mul, index_rationals, parse_rational_dump,
+    read_packed_galois_field, write_packed_galois_field,
+};
 pub use self::representation::{
-    Add, AddView, Fun, ListIterator, ListSlice, Mul, MulView, Num, NumView, Pow, PowView, Var,
-    VarView,
+    Add, AddView, AtomBatch, Fun, ListIterator, ListSlice, Mul, MulView, Num, NumView, Pow,
+    PowView, Var, VarView,
 };
 use self::representation::{FunView, RawAtom};
 
@@ -89,7 +95,7 @@ impl std::fmt::Debug for Symbol {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AtomType {
     Num,
     Var,
@@ -258,14 +264,40 @@ impl<'a> AtomView<'a> {
         }
     }
 
+    /// Get the rational number of a numeric atom, or `None` if the
+    /// atom is not a rational number.
+    pub fn as_rational(&self) -> Option<Rational> {
+        if let AtomView::Num(n) = self {
+            match n.get_coeff_view() {
+                CoefficientView::Natural(n, d) => Some(Rational::Natural(n, d)),
+                CoefficientView::Large(r) => Some(Rational::from_large(r.to_rat())),
+                CoefficientView::FiniteField(_, _) | CoefficientView::RationalPolynomial(_) => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Get the integer of a numeric atom, or `None` if the
+    /// atom is not an integer.
+    pub fn as_integer(&self) -> Option<Integer> {
+        let r = self.as_rational()?;
+        if r.is_integer() {
+            Some(r.numerator())
+        } else {
+            None
+        }
+    }
+
     /// Add two atoms and return the buffer that contains the unnormalized result.
     fn add_no_norm(&self, workspace: &Workspace, rhs: AtomView<'_>) -> RecycledAtom {
         let mut e = workspace.new_atom();
         let a = e.to_add();
 
         // TODO: check if self or rhs is add
-        a.extend(*self);
-        a.extend(rhs);
+        let flatten = workspace.flatten_assoc();
+        a.extend_with(*self, flatten);
+        a.extend_with(rhs, flatten);
         e
     }
 
@@ -275,8 +307,9 @@ impl<'a> AtomView<'a> {
         let a = e.to_add();
 
         // TODO: check if self or rhs is add
-        a.extend(*self);
-        a.extend(rhs.neg_no_norm(workspace).as_view());
+        let flatten = workspace.flatten_assoc();
+        a.extend_with(*self, flatten);
+        a.extend_with(rhs.neg_no_norm(workspace).as_view(), flatten);
         e
     }
 
@@ -286,8 +319,9 @@ impl<'a> AtomView<'a> {
         let a = e.to_mul();
 
         // TODO: check if self or rhs is mul
-        a.extend(*self);
-        a.extend(rhs);
+        let flatten = workspace.flatten_assoc();
+        a.extend_with(*self, flatten);
+        a.extend_with(rhs, flatten);
         e
     }
 
@@ -364,6 +398,34 @@ impl<'a> AtomView<'a> {
         }
     }
 
+    /// Get the symbol and an iterator over the arguments of a function,
+    /// or `None` if the atom is not a function.
+    pub fn as_function(&self) -> Option<(Symbol, ListIterator<'a>)> {
+        if let AtomView::Fun(f) = self {
+            Some((f.get_symbol(), f.iter()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if the memory of the two views overlaps, i.e., if one expression
+    /// is stored inside the other or if both views point to the same expression.
+    /// This is intended for debugging.
+    pub fn is_shared_with(&self, other: AtomView<'_>) -> bool {
+        let (a, b) = (
+            self.get_data().as_ptr_range(),
+            other.get_data().as_ptr_range(),
+        );
+        a.start < b.end && b.start < a.end
+    }
+
+    /// Get the number of bytes used to store the expression, including its coefficients.
+    /// Since an expression is stored in a single contiguous buffer, this is the same as
+    /// [AtomView::get_byte_size].
+    pub fn memory_usage(&self) -> usize {
+        self.get_byte_size()
+    }
+
     pub fn get_byte_size(&self) -> usize {
         match self {
             AtomView::Num(n) => n.get_byte_size(),
@@ -374,6 +436,26 @@ impl<'a> AtomView<'a> {
             AtomView::Add(a) => a.get_byte_size(),
         }
     }
+
+    /// Apply `f` to every term of a sum, or to the whole expression if
+    /// it is not a sum, and add the results. Terms that map to zero are dropped.
+    pub fn map_terms(&self, f: impl Fn(AtomView) -> Atom) -> Atom {
+        let AtomView::Add(a) = self else {
+            return f(*self);
+        };
+
+        let mut out = Atom::new();
+        Workspace::get_local().with(|ws| {
+            let mut t = ws.new_atom();
+            let add = t.to_add();
+            for arg in a.iter() {
+                add.extend(f(arg).as_view());
+            }
+
+            t.as_view().normalize(ws, &mut out);
+        });
+        out
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -457,10 +539,19 @@ impl Atom {
     }
 
     /// Parse and atom from a string.
+    ///
+    /// Input that is nested deeper than 10000 levels, counting parentheses, function calls
+    /// and exponentiations, yields an error instead of overflowing the stack. Use
+    /// [Atom::parse_with_settings] with [ParseSettings::max_depth] to change this limit.
     pub fn parse(input: &str) -> Result<Atom, String> {
         Workspace::get_local().with(|ws| Token::parse(input)?.to_atom(ws))
     }
 
+    /// Parse an atom from a string with custom settings.
+    pub fn parse_with_settings(input: &str, settings: &ParseSettings) -> Result<Atom, String> {
+        Workspace::get_local().with(|ws| Token::parse_with_settings(input, settings)?.to_atom(ws))
+    }
+
     #[inline]
     pub fn new_var(id: Symbol) -> Atom {
         Var::new(id).into()
@@ -481,6 +572,24 @@ impl Atom {
         self.as_view().is_one()
     }
 
+    /// Get the number of bytes allocated for this atom, including the unused capacity
+    /// of its buffer.
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Atom>() + self.get_capacity()
+    }
+
+    /// Get the rational number of a numeric atom, or `None` if the
+    /// atom is not a rational number.
+    pub fn as_rational(&self) -> Option<Rational> {
+        self.as_view().as_rational()
+    }
+
+    /// Get the integer of a numeric atom, or `None` if the
+    /// atom is not an integer.
+    pub fn as_integer(&self) -> Option<Integer> {
+        self.as_view().as_integer()
+    }
+
     #[inline]
     pub fn to_num(&mut self, coeff: Coefficient) -> &mut Num {
         let buffer = std::mem::replace(self, Atom::Empty).into_raw();
@@ -596,6 +705,28 @@ impl Atom {
         }
     }
 
+    /// Get the symbol and an iterator over the arguments of a function,
+    /// or `None` if the atom is not a function.
+    pub fn as_function(&self) -> Option<(Symbol, ListIterator<'_>)> {
+        self.as_view().as_function()
+    }
+
+    /// Create a copy of the expression that does not share any memory with `self`.
+    ///
+    /// An atom owns the buffer that stores its expression, so that this is the same
+    /// as [Clone::clone]: structure is never shared between atoms and mutating one atom
+    /// can never affect another. Structure is only shared by an [AtomView],
+    /// which borrows the buffer of an atom.
+    pub fn deep_clone(&self) -> Atom {
+        self.clone()
+    }
+
+    /// Returns `true` if the two atoms share memory, which is only the case when
+    /// they are the same atom. See [AtomView::is_shared_with].
+    pub fn is_shared_with(&self, other: &Atom) -> bool {
+        self.as_view().is_shared_with(other.as_view())
+    }
+
     #[inline(always)]
     pub(crate) fn set_normalized(&mut self, normalized: bool) {
         match self {
@@ -729,6 +860,12 @@ impl Atom {
         out
     }
 
+    /// Apply `f` to every term of a sum and add the results.
+    /// See [AtomView::map_terms].
+    pub fn map_terms(&self, f: impl Fn(AtomView) -> Atom) -> Atom {
+        self.as_view().map_terms(f)
+    }
+
     /// Multiply the atoms in `args`.
     pub fn mul_many<'a, T: AsAtomView<'a> + Copy>(args: &[T]) -> Atom {
         let mut out = Atom::new();
@@ -940,6 +1077,89 @@ impl std::ops::Neg for Atom {
     }
 }
 
+impl std::ops::Add<Atom> for Atom {
+    type Output = Atom;
+
+    fn add(self, rhs: Atom) -> Atom {
+        self + &rhs
+    }
+}
+
+impl std::ops::Sub<Atom> for Atom {
+    type Output = Atom;
+
+    fn sub(self, rhs: Atom) -> Atom {
+        self - &rhs
+    }
+}
+
+impl std::ops::Mul<Atom> for Atom {
+    type Output = Atom;
+
+    fn mul(self, rhs: Atom) -> Atom {
+        self * &rhs
+    }
+}
+
+impl std::ops::Div<Atom> for Atom {
+    type Output = Atom;
+
+    fn div(self, rhs: Atom) -> Atom {
+        self / &rhs
+    }
+}
+
+/// Add (if `sum` is `true`) or multiply all atoms at once, normalizing only the final result.
+fn sum_or_product<A: std::borrow::Borrow<Atom>>(iter: impl Iterator<Item = A>, sum: bool) -> Atom {
+    Workspace::get_local().with(|ws| {
+        let flatten = ws.flatten_assoc();
+        let mut t = ws.new_atom();
+        if sum {
+            let add = t.to_add();
+            for x in iter {
+                add.extend_with(x.borrow().as_view(), flatten);
+            }
+        } else {
+            let mul = t.to_mul();
+            for x in iter {
+                mul.extend_with(x.borrow().as_view(), flatten);
+            }
+        }
+
+        let mut out = Atom::new();
+        t.as_view().normalize(ws, &mut out);
+        out
+    })
+}
+
+impl std::iter::Sum<Atom> for Atom {
+    /// Add all atoms at once, normalizing only the final sum.
+    fn sum<I: Iterator<Item = Atom>>(iter: I) -> Atom {
+        sum_or_product(iter, true)
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Atom> for Atom {
+    /// Add all atoms at once, normalizing only the final sum.
+    fn sum<I: Iterator<Item = &'a Atom>>(iter: I) -> Atom {
+        sum_or_product(iter, true)
+    }
+}
+
+impl std::iter::Product<Atom> for Atom {
+    /// Multiply all atoms at once, normalizing only the final product.
+    fn product<I: Iterator<Item = Atom>>(iter: I) -> Atom {
+        sum_or_product(iter, false)
+    }
+}
+
+impl<'a> std::iter::Product<&'a Atom> for Atom {
+    /// Multiply all atoms at once, normalizing only the final product.
+    fn product<I: Iterator<Item = &'a Atom>>(iter: I) -> Atom {
+        sum_or_product(iter, false)
+    }
+}
+
 impl std::ops::Add<AtomView<'_>> for AtomView<'_> {
     type Output = Atom;
 
@@ -1118,20 +1338,235 @@ impl<T: Into<Coefficient>> std::ops::Div<T> for Atom {
     }
 }
 
+/// A key that orders atoms in the same way as [AtomView::cmp], but that can be
+/// computed once and then compared without walking the expression again.
+/// Obtained with [AtomView::sort_key].
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SortKey(pub(crate) Vec<SortKeyItem>);
+
+/// An element of a [SortKey]. The key of a node starts with a tag that
+/// determines which items follow, so that the elementwise comparison of two keys is
+/// the same as the recursive comparison of the atoms.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum SortKeyItem {
+    Tag(u8),
+    Len(usize),
+    Symbol(Symbol),
+    Num(Atom),
+    Data(Vec<u8>),
+}
+
+impl PartialOrd for SortKeyItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortKeyItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortKeyItem::Tag(t1), SortKeyItem::Tag(t2)) => t1.cmp(t2),
+            (SortKeyItem::Len(l1), SortKeyItem::Len(l2)) => l1.cmp(l2),
+            (SortKeyItem::Symbol(s1), SortKeyItem::Symbol(s2)) => s1.cmp(s2),
+            (SortKeyItem::Num(n1), SortKeyItem::Num(n2)) => n1.as_view().cmp(&n2.as_view()),
+            (SortKeyItem::Data(d1), SortKeyItem::Data(d2)) => d1.cmp(d2),
+            // items of a different kind are never at the same position after an equal prefix
+            _ => self.kind().cmp(&other.kind()),
+        }
+    }
+}
+
+impl SortKeyItem {
+    fn kind(&self) -> u8 {
+        match self {
+            SortKeyItem::Tag(_) => 0,
+            SortKeyItem::Len(_) => 1,
+            SortKeyItem::Symbol(_) => 2,
+            SortKeyItem::Num(_) => 3,
+            SortKeyItem::Data(_) => 4,
+        }
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
-        atom::{Atom, FunctionBuilder},
+        atom::{Atom, AtomView, FunctionBuilder},
+        domains::{integer::Integer, rational::Rational},
         fun,
         state::State,
     };
 
+    #[test]
+    fn as_function() {
+        let a = Atom::parse("f1(v1, v2^2)").unwrap();
+        let (name, args) = a.as_function().unwrap();
+        assert_eq!(name, State::get_symbol("f1"));
+        assert_eq!(
+            args.collect::<Vec<_>>(),
+            vec![
+                Atom::parse("v1").unwrap().as_view(),
+                Atom::parse("v2^2").unwrap().as_view()
+            ]
+        );
+
+        let a = Atom::parse("f1()").unwrap();
+        let (name, mut args) = a.as_function().unwrap();
+        assert_eq!(name, State::get_symbol("f1"));
+        assert!(args.next().is_none());
+
+        assert!(Atom::parse("v1").unwrap().as_function().is_none());
+        assert!(Atom::parse("f1(v1)+1").unwrap().as_function().is_none());
+    }
+
+    #[test]
+    fn deep_clone() {
+        let a = Atom::parse("f1(v1, v2^2)").unwrap();
+        let mut b = a.deep_clone();
+        assert_eq!(a, b);
+        assert!(!a.is_shared_with(&b));
+        assert!(!a.is_shared_with(&a.clone()));
+        assert!(a.is_shared_with(&a));
+
+        // views borrow the buffer of the atom
+        let (_, mut args) = a.as_function().unwrap();
+        let arg = args.next().unwrap();
+        assert!(arg.is_shared_with(a.as_view()));
+        assert!(!arg.is_shared_with(b.as_view()));
+        assert!(!arg.is_shared_with(args.next().unwrap()));
+
+        b = &b + &Atom::new_num(1);
+        assert_eq!(a, Atom::parse("f1(v1, v2^2)").unwrap());
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn memory_usage() {
+        let mut a = Atom::parse("v1").unwrap();
+        let mut last = a.as_view().memory_usage();
+        for i in 2..10 {
+            a = a + &Atom::parse(&format!("v{}^{}", i, i)).unwrap();
+            let size = a.as_view().memory_usage();
+            assert!(size > last);
+            assert!(a.memory_usage() >= size);
+            last = size;
+        }
+
+        let a = Atom::parse("123456789012345678901234567890").unwrap();
+        assert!(a.as_view().memory_usage() > Atom::parse("1").unwrap().as_view().memory_usage());
+    }
+
+    #[test]
+    fn as_number() {
+        let a = Atom::parse("42").unwrap();
+        assert_eq!(a.as_rational(), Some(Rational::Natural(42, 1)));
+        assert_eq!(a.as_integer(), Some(Integer::Natural(42)));
+
+        let a = Atom::parse("-3/4").unwrap();
+        assert_eq!(a.as_rational(), Some(Rational::Natural(-3, 4)));
+        assert_eq!(a.as_integer(), None);
+
+        let a = Atom::parse("-123456789012345678901234567890").unwrap();
+        assert_eq!(
+            a.as_integer(),
+            Some(Integer::from_large(
+                "-123456789012345678901234567890".parse().unwrap()
+            ))
+        );
+
+        let a = Atom::parse("x").unwrap();
+        assert_eq!(a.as_rational(), None);
+        assert_eq!(a.as_integer(), None);
+
+        let a = Atom::parse("2*x").unwrap();
+        assert_eq!(a.as_rational(), None);
+    }
+
+    #[test]
+    fn build() {
+        let x = Atom::new_var(State::get_symbol("v1"));
+        let y = Atom::new_var(State::get_symbol("v2"));
+
+        let a = x.npow(2) + &y * 3;
+        assert_eq!(a, Atom::parse("v1^2+3*v2").unwrap());
+
+        // owned and borrowed operands can be mixed
+        let b = x.clone() * y.clone() - &x / y.clone() + (&x + 1) * Rational::new(1, 2);
+        assert_eq!(b, Atom::parse("v1*v2-v1/v2+1/2*(v1+1)").unwrap());
+
+        let terms: Vec<_> = (0..100).map(|i| x.npow(i % 10) * i).collect();
+        let s: Atom = terms.iter().sum();
+        assert_eq!(s, terms.iter().fold(Atom::new_num(0), |acc, t| acc + t));
+        assert_eq!(terms.into_iter().sum::<Atom>(), s);
+
+        let p: Atom = [x.clone(), y.clone(), x.clone(), Atom::new_num(2)]
+            .into_iter()
+            .product();
+        assert_eq!(p, Atom::parse("2*v1^2*v2").unwrap());
+
+        assert_eq!(std::iter::empty::<Atom>().sum::<Atom>(), Atom::new_num(0));
+        assert_eq!(
+            std::iter::empty::<Atom>().product::<Atom>(),
+            Atom::new_num(1)
+        );
+    }
+
+    #[test]
+    fn sort_key() {
+        let mut atoms: Vec<_> = [
+            "v1",
+            "v2^2",
+            "v1^2",
+            "v1*v2",
+            "2*v1*v2",
+            "v1+v2",
+            "v1+v2+1",
+            "f1(v1)",
+            "f1(v2,v1)",
+            "f2(v1)",
+            "f1(f1(v1))",
+            "-1/2",
+            "3",
+            "v1^v2",
+            "(v1+1)^2",
+            "v2",
+        ]
+        .iter()
+        .map(|x| Atom::parse(x).unwrap())
+        .collect();
+        atoms.push(atoms[0].clone());
+
+        let mut by_key = atoms.clone();
+        by_key.sort_by_cached_key(|a| a.sort_key());
+        atoms.sort();
+        assert_eq!(atoms, by_key);
+
+        for a in &atoms {
+            for b in &atoms {
+                assert_eq!(a.cmp(b), a.sort_key().cmp(&b.sort_key()));
+            }
+        }
+        assert_eq!(atoms[0].sort_key(), atoms[1].sort_key());
+    }
+
     #[test]
     fn debug() {
         let x = Atom::parse("v1+f1(v2)").unwrap();
         assert_eq!(
             format!("{:?}", x),
-            "AddView { data: [5, 17, 2, 13, 2, 1, 12, 3, 5, 0, 0, 0, 1, 42, 2, 1, 13] }"
+            "AddView { data: [5, 17, 2, 13, 2, 1, 17, 3, 5, 0, 0, 0, 1, 47, 2, 1, 18] }"
         );
         assert_eq!(
             x.get_all_symbols(true),
@@ -1159,4 +1594,26 @@ mod test {
         let res = Atom::parse("1/4*(v2^v1)^-1*(-6*v2*(v1+v2+2))^5*f1(v1,v2,2)").unwrap();
         assert_eq!(res, r);
     }
+
+    #[test]
+    fn map_terms() {
+        let a = Atom::parse("v1+v2").unwrap();
+        let r = a.map_terms(|t| t.to_owned().npow(2));
+        assert_eq!(r, Atom::parse("v1^2+v2^2").unwrap());
+
+        // terms that map to zero are dropped and equal terms are combined
+        let a = Atom::parse("v1+2*v2+f1(v1)").unwrap();
+        let r = a.map_terms(|t| match t {
+            AtomView::Fun(_) => Atom::new_num(0),
+            _ => t.rename_symbol(State::get_symbol("v2"), State::get_symbol("v1")),
+        });
+        assert_eq!(r, Atom::parse("3*v1").unwrap());
+
+        assert_eq!(
+            Atom::parse("f1(v1)")
+                .unwrap()
+                .map_terms(|t| t.to_owned() * 3),
+            Atom::parse("3*f1(v1)").unwrap()
+        );
+    }
 }
@@ -1,62 +1,27 @@
 use bytes::{Buf, BufMut};
 use rug::{integer::Order, Integer as MultiPrecisionInteger};
+use serde::{
+    de::{SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
 use crate::{
     coefficient::{Coefficient, CoefficientView, SerializedRational, SerializedRationalPolynomial},
     domains::{
-        finite_field::FiniteFieldElement,
+        finite_field::{FiniteFieldCore, FiniteFieldElement, Zp64},
+        galois_field::GaloisFieldElement,
         integer::{Integer, IntegerRing, Z},
         rational::Rational,
         rational_polynomial::RationalPolynomial,
+        Field, Ring,
+    },
+    packed::{
+        natural_width, read_natural_frac, PackedRationalNumberReader, PackedRationalNumberWriter,
+        ARB_DEN, ARB_NUM, DEN_MASK, FIN_NUM, GAL_NUM, NUM_MASK, RAT_POLY, SIGN,
     },
-    state::{FiniteFieldIndex, State, VariableListIndex},
+    state::{FiniteFieldIndex, GaloisFieldIndex, State, StateMap, VariableListIndex},
 };
 
-const U8_NUM: u8 = 0b00000001;
-const U16_NUM: u8 = 0b00000010;
-const U32_NUM: u8 = 0b00000011;
-const U64_NUM: u8 = 0b00000100;
-const FIN_NUM: u8 = 0b00000101;
-const ARB_NUM: u8 = 0b00000111;
-const RAT_POLY: u8 = 0b00001000;
-const U8_DEN: u8 = 0b00010000;
-const U16_DEN: u8 = 0b00100000;
-const U32_DEN: u8 = 0b00110000;
-const U64_DEN: u8 = 0b01000000;
-const ARB_DEN: u8 = 0b01110000;
-const NUM_MASK: u8 = 0b00001111;
-const DEN_MASK: u8 = 0b01110000;
-const SIGN: u8 = 0b10000000;
-
-const U8_NUM_U8_DEN: u8 = U8_NUM | U8_DEN;
-const U16_NUM_U8_DEN: u8 = U16_NUM | U8_DEN;
-const U32_NUM_U8_DEN: u8 = U32_NUM | U8_DEN;
-const U64_NUM_U8_DEN: u8 = U64_NUM | U8_DEN;
-const U8_NUM_U16_DEN: u8 = U8_NUM | U16_DEN;
-const U16_NUM_U16_DEN: u8 = U16_NUM | U16_DEN;
-const U32_NUM_U16_DEN: u8 = U32_NUM | U16_DEN;
-const U64_NUM_U16_DEN: u8 = U64_NUM | U16_DEN;
-const U8_NUM_U32_DEN: u8 = U8_NUM | U32_DEN;
-const U16_NUM_U32_DEN: u8 = U16_NUM | U32_DEN;
-const U32_NUM_U32_DEN: u8 = U32_NUM | U32_DEN;
-const U64_NUM_U32_DEN: u8 = U64_NUM | U32_DEN;
-const U8_NUM_U64_DEN: u8 = U8_NUM | U64_DEN;
-const U16_NUM_U64_DEN: u8 = U16_NUM | U64_DEN;
-const U32_NUM_U64_DEN: u8 = U32_NUM | U64_DEN;
-const U64_NUM_U64_DEN: u8 = U64_NUM | U64_DEN;
-
-#[inline(always)]
-const fn get_size_of_natural(num_type: u8) -> u8 {
-    match num_type {
-        0 => 0,
-        U8_NUM => 1,
-        U16_NUM => 2,
-        U32_NUM => 4,
-        U64_NUM => 8,
-        _ => unreachable!(),
-    }
-}
-
 impl<'a> SerializedRationalPolynomial<'a> {
     pub fn deserialize(self) -> RationalPolynomial<IntegerRing, u16> {
         let mut source = self.0;
@@ -117,17 +82,6 @@ impl<'a> SerializedRationalPolynomial<'a> {
     }
 }
 
-/// A generalized rational number. The first byte indicates the sign, size and type of the numerator and denominator.
-/// The highest four bits give the byte size of the numerator and the lower bits of the denominator.
-pub trait PackedRationalNumberWriter {
-    /// Write a single number.
-    fn write_packed(&self, dest: &mut Vec<u8>);
-    /// Write a fraction to a fixed-size buffer.
-    fn write_packed_fixed(&self, dest: &mut [u8]);
-    /// Get the number of bytes of the packed representation.
-    fn get_packed_size(&self) -> u64;
-}
-
 impl PackedRationalNumberWriter for Coefficient {
     fn write_packed(&self, dest: &mut Vec<u8>) {
         match self {
@@ -244,17 +198,13 @@ impl PackedRationalNumberWriter for Coefficient {
     }
 }
 
-/// A reader for generalized rational numbers. See [`MultiPrecisionRationalNumberWriter`].
-pub trait PackedRationalNumberReader {
+/// A reader for coefficients, which extends [PackedRationalNumberReader] to large numbers,
+/// finite field elements and rational polynomials.
+pub trait PackedCoefficientReader {
     fn get_coeff_view(&self) -> (CoefficientView, &[u8]);
-    fn get_frac_u64(&self) -> (u64, u64, &[u8]);
-    fn get_frac_i64(&self) -> (i64, i64, &[u8]);
-    fn skip_rational(&self) -> &[u8];
-    fn is_zero_rat(&self) -> bool;
-    fn is_one_rat(&self) -> bool;
 }
 
-impl PackedRationalNumberReader for [u8] {
+impl PackedCoefficientReader for [u8] {
     #[inline(always)]
     fn get_coeff_view(&self) -> (CoefficientView, &[u8]) {
         let mut source = self;
@@ -298,370 +248,760 @@ impl PackedRationalNumberReader for [u8] {
             (CoefficientView::Natural(num, den), source)
         }
     }
+}
 
-    #[inline(always)]
-    fn get_frac_u64(&self) -> (u64, u64, &[u8]) {
-        let mut source = self;
-        let disc = source.get_u8();
-        match disc & (NUM_MASK | DEN_MASK) {
-            U8_NUM => {
-                let n = source.get_u8();
-                (n as u64, 1, source)
-            }
-            U16_NUM => {
-                let n = source.get_u16_le();
-                (n as u64, 1, source)
-            }
-            U32_NUM => {
-                let n = source.get_u32_le();
-                (n as u64, 1, source)
-            }
-            U64_NUM => {
-                let n = source.get_u64_le();
-                (n, 1, source)
-            }
-            U8_NUM_U8_DEN => {
-                let n = source.get_u8();
-                let d = source.get_u8();
-                (n as u64, d as u64, source)
-            }
-            U16_NUM_U8_DEN => {
-                let n = source.get_u16_le();
-                let d = source.get_u8();
-                (n as u64, d as u64, source)
-            }
-            U32_NUM_U8_DEN => {
-                let n = source.get_u32_le();
-                let d = source.get_u8();
-                (n as u64, d as u64, source)
-            }
-            U64_NUM_U8_DEN => {
-                let n = source.get_u64_le();
-                let d = source.get_u8();
-                (n, d as u64, source)
-            }
-            U8_NUM_U16_DEN => {
-                let n = source.get_u8();
-                let d = source.get_u16_le();
-                (n as u64, d as u64, source)
-            }
-            U16_NUM_U16_DEN => {
-                let n = source.get_u16_le();
-                let d = source.get_u16_le();
-                (n as u64, d as u64, source)
-            }
-            U32_NUM_U16_DEN => {
-                let n = source.get_u32_le();
-                let d = source.get_u16_le();
-                (n as u64, d as u64, source)
-            }
-            U64_NUM_U16_DEN => {
-                let n = source.get_u64_le();
-                let d = source.get_u16_le();
-                (n, d as u64, source)
-            }
-            U8_NUM_U32_DEN => {
-                let n = source.get_u8();
-                let d = source.get_u32_le();
-                (n as u64, d as u64, source)
-            }
-            U16_NUM_U32_DEN => {
-                let n = source.get_u16_le();
-                let d = source.get_u32_le();
-                (n as u64, d as u64, source)
-            }
-            U32_NUM_U32_DEN => {
-                let n = source.get_u32_le();
-                let d = source.get_u32_le();
-                (n as u64, d as u64, source)
-            }
-            U64_NUM_U32_DEN => {
-                let n = source.get_u64_le();
-                let d = source.get_u32_le();
-                (n, d as u64, source)
-            }
-            U8_NUM_U64_DEN => {
-                let n = source.get_u8();
-                let d = source.get_u64_le();
-                (n as u64, d, source)
-            }
-            U16_NUM_U64_DEN => {
-                let n = source.get_u16_le();
-                let d = source.get_u64_le();
-                (n as u64, d, source)
-            }
-            U32_NUM_U64_DEN => {
-                let n = source.get_u32_le();
-                let d = source.get_u64_le();
-                (n as u64, d, source)
-            }
-            U64_NUM_U64_DEN => {
-                let n = source.get_u64_le();
-                let d = source.get_u64_le();
-                (n, d, source)
-            }
-            x => {
-                unreachable!("Unsupported numerator/denominator type {}", x)
-            }
-        }
+/// Write a packed fraction of natural numbers using the widths given by `tag`.
+fn write_natural_frac(tag: u8, num: u64, den: u64, dest: &mut Vec<u8>) -> Result<(), String> {
+    let num_width = natural_width(tag & NUM_MASK)
+        .filter(|w| *w > 0)
+        .ok_or_else(|| format!("Invalid numerator type in tag {:#04x}", tag))?;
+    let den_width = natural_width((tag & DEN_MASK) >> 4)
+        .ok_or_else(|| format!("Invalid denominator type in tag {:#04x}", tag))?;
+
+    if num_width < 8 && num >> (8 * num_width) != 0 {
+        return Err(format!(
+            "Numerator {} does not fit in {} bytes",
+            num, num_width
+        ));
+    }
+    if den_width == 0 && den != 1 {
+        return Err(format!("Tag {:#04x} requires a denominator of 1", tag));
+    }
+    if den_width > 0 && den_width < 8 && den >> (8 * den_width) != 0 {
+        return Err(format!(
+            "Denominator {} does not fit in {} bytes",
+            den, den_width
+        ));
     }
 
-    #[inline(always)]
-    fn get_frac_i64(&self) -> (i64, i64, &[u8]) {
-        let mut source = self;
-        let disc = source.get_u8();
-        let num;
-        (num, source) = match disc & NUM_MASK {
-            U8_NUM => {
-                let v = source.get_u8();
-                (v as i64, source)
-            }
-            U16_NUM => {
-                let v = source.get_u16_le();
-                (v as i64, source)
-            }
-            U32_NUM => {
-                let v = source.get_u32_le();
-                (v as i64, source)
-            }
-            U64_NUM => {
-                let v = source.get_u64_le();
-                (v as i64, source)
-            }
-            x => {
-                unreachable!("Unsupported numerator type {}", x)
-            }
-        };
+    dest.push(tag);
+    dest.extend_from_slice(&num.to_le_bytes()[..num_width]);
+    dest.extend_from_slice(&den.to_le_bytes()[..den_width]);
+    Ok(())
+}
 
-        let den;
-        (den, source) = match disc & DEN_MASK {
-            0 => (1i64, source),
-            U8_DEN => {
-                let v = source.get_u8();
-                (v as i64, source)
-            }
-            U16_DEN => {
-                let v = source.get_u16_le();
-                (v as i64, source)
-            }
-            U32_DEN => {
-                let v = source.get_u32_le();
-                (v as i64, source)
-            }
-            U64_DEN => {
-                let v = source.get_u64_le();
-                (v as i64, source)
-            }
-            x => {
-                unreachable!("Unsupported denominator type {}", x)
-            }
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() % 2 != 0 {
+        return Err(format!("Odd number of hex digits in {}", s));
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| format!("Invalid hex string {}", s))
+        })
+        .collect()
+}
+
+/// Dump a single packed number as a line of text, returning the remaining bytes.
+fn dump_packed_number(source: &[u8]) -> Option<(String, &[u8])> {
+    let (&disc, rest) = source.split_first()?;
+
+    if disc == RAT_POLY {
+        let len = u32::from_le_bytes(rest.get(..4)?.try_into().unwrap()) as usize;
+        let data = rest.get(4..4 + len)?;
+        Some((
+            format!(
+                "{:#04x} len={} data={} # rational polynomial",
+                disc,
+                len,
+                to_hex(data)
+            ),
+            &rest[4 + len..],
+        ))
+    } else if disc & NUM_MASK == ARB_NUM {
+        let (tag, num, den, r) = read_natural_frac(rest)?;
+        let len = (num as usize).checked_add(den as usize)?;
+        let digits = r.get(..len)?;
+        // only decode valid numbers, as a zero denominator cannot be converted to a rational
+        let value = match validate_packed_number(source) {
+            Ok(_) => {
+                let (CoefficientView::Large(l), _) = source.get_coeff_view() else {
+                    unreachable!()
+                };
+                l.to_rat().to_string()
+            }
+            Err(e) => format!("invalid: {}", e),
         };
+        Some((
+            format!(
+                "{:#04x} {:#04x} num={} den={} digits={} # {}",
+                disc,
+                tag,
+                num,
+                den,
+                to_hex(digits),
+                value
+            ),
+            &r[len..],
+        ))
+    } else if disc & NUM_MASK == FIN_NUM {
+        let (tag, num, den, r) = read_natural_frac(rest)?;
+        Some((
+            format!(
+                "{:#04x} {:#04x} num={} den={} # {} in finite field {}",
+                disc, tag, num, den, num, den
+            ),
+            r,
+        ))
+    } else {
+        let (tag, num, den, r) = read_natural_frac(source)?;
+        let sign = if tag & SIGN != 0 { "-" } else { "" };
+        let value = if den == 0 {
+            "invalid: zero denominator".to_owned()
+        } else if den == 1 {
+            format!("{}{}", sign, num)
+        } else {
+            format!("{}{}/{}", sign, num, den)
+        };
+        Some((
+            format!("{:#04x} num={} den={} # {}", tag, num, den, value),
+            r,
+        ))
+    }
+}
 
-        if disc & SIGN != 0 {
-            (-num, den, source)
+/// Dump a buffer of packed numbers to text, with one line per number that contains
+/// the discriminant flags, the raw fields and the decoded value as a comment.
+/// Numbers with a zero denominator have an `invalid` comment instead of a value,
+/// and bytes that cannot be decoded are written as a `raw` line.
+/// The buffer can be reconstructed with [parse_rational_dump].
+pub fn dump_rational_buffer(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let mut source = bytes;
+
+    while !source.is_empty() {
+        if let Some((line, rest)) = dump_packed_number(source) {
+            out.push_str(&line);
+            source = rest;
         } else {
-            (num, den, source)
+            out.push_str(&format!("raw {} # undecodable", to_hex(source)));
+            source = &[];
         }
+        out.push('\n');
     }
 
-    #[inline(always)]
-    fn skip_rational(&self) -> &[u8] {
-        let mut dest = self;
-        let disc = dest.get_u8();
+    out
+}
 
-        match disc & (NUM_MASK | DEN_MASK) {
-            U8_NUM => {
-                dest.advance(1);
-            }
-            U16_NUM | U8_NUM_U8_DEN => {
-                dest.advance(2);
-            }
-            U16_NUM_U8_DEN | U8_NUM_U16_DEN => {
-                dest.advance(3);
-            }
-            U32_NUM | U16_NUM_U16_DEN => {
-                dest.advance(4);
-            }
-            U32_NUM_U8_DEN | U8_NUM_U32_DEN => {
-                dest.advance(5);
-            }
-            U32_NUM_U16_DEN | U16_NUM_U32_DEN => {
-                dest.advance(6);
-            }
-            U64_NUM | U32_NUM_U32_DEN => {
-                dest.advance(8);
-            }
-            U64_NUM_U8_DEN | U8_NUM_U64_DEN => {
-                dest.advance(9);
-            }
-            U64_NUM_U16_DEN | U16_NUM_U64_DEN => {
-                dest.advance(10);
-            }
-            U64_NUM_U32_DEN | U32_NUM_U64_DEN => {
-                dest.advance(12);
-            }
-            U64_NUM_U64_DEN => {
-                dest.advance(16);
-            }
-            x => {
-                let v_num = x & NUM_MASK;
-                if v_num == ARB_NUM {
-                    let (num_size, den_size);
-                    (num_size, den_size, dest) = dest.get_frac_i64();
-                    let num_size = num_size.unsigned_abs() as usize;
-                    let den_size = den_size.unsigned_abs() as usize;
-                    dest.advance(num_size + den_size);
-                } else if v_num == RAT_POLY {
-                    let size = dest.get_u32_le() as usize;
-                    dest.advance(size);
-                } else if v_num == FIN_NUM {
-                    let var_size = dest.get_u8();
-                    let size = get_size_of_natural(var_size & NUM_MASK)
-                        + get_size_of_natural((var_size & DEN_MASK) >> 4);
-                    dest.advance(size as usize);
-                } else {
-                    unreachable!("Unsupported numerator/denominator type {}", disc)
-                }
-            }
+/// Parse a dump created with [dump_rational_buffer] back into the exact bytes.
+pub fn parse_rational_dump(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
         }
 
-        dest
+        parse_dump_line(line, &mut out).map_err(|e| format!("Line {}: {}", i + 1, e))?;
     }
 
-    #[inline(always)]
-    fn is_zero_rat(&self) -> bool {
-        // TODO: make a zero have no number at all (i.e., self[1] = 0)
-        self[1] == 1 && self[2] == 0
+    Ok(out)
+}
+
+fn parse_dump_line(line: &str, out: &mut Vec<u8>) -> Result<(), String> {
+    let mut tokens = line.split_whitespace();
+
+    let parse_tag = |t: Option<&str>| -> Result<u8, String> {
+        let t = t.ok_or("Missing tag")?;
+        u8::from_str_radix(t.trim_start_matches("0x"), 16).map_err(|_| format!("Invalid tag {}", t))
+    };
+
+    fn field<'a>(
+        tokens: &mut impl Iterator<Item = &'a str>,
+        name: &str,
+    ) -> Result<&'a str, String> {
+        let t = tokens
+            .next()
+            .ok_or_else(|| format!("Missing field {}", name))?;
+        t.strip_prefix(name)
+            .and_then(|t| t.strip_prefix('='))
+            .ok_or_else(|| format!("Expected field {}, found {}", name, t))
     }
 
-    #[inline(always)]
-    fn is_one_rat(&self) -> bool {
-        self[1] == 1 && self[2] == 1
+    let parse_u64 = |s: &str| -> Result<u64, String> {
+        s.parse()
+            .map_err(|_| format!("Invalid natural number {}", s))
+    };
+
+    let first = tokens.next().unwrap();
+    if first == "raw" {
+        out.extend(from_hex(tokens.next().ok_or("Missing raw data")?)?);
+        return Ok(());
+    }
+
+    let disc = parse_tag(Some(first))?;
+
+    if disc == RAT_POLY {
+        let len = parse_u64(field(&mut tokens, "len")?)? as usize;
+        let data = from_hex(field(&mut tokens, "data")?)?;
+        if data.len() != len || len > u32::MAX as usize {
+            return Err(format!(
+                "Length {} does not match the data length {}",
+                len,
+                data.len()
+            ));
+        }
+        out.push(disc);
+        out.extend_from_slice(&(len as u32).to_le_bytes());
+        out.extend(data);
+    } else if disc & NUM_MASK == ARB_NUM || disc & NUM_MASK == FIN_NUM {
+        let tag = parse_tag(tokens.next())?;
+        let num = parse_u64(field(&mut tokens, "num")?)?;
+        let den = parse_u64(field(&mut tokens, "den")?)?;
+        out.push(disc);
+        write_natural_frac(tag, num, den, out)?;
+
+        if disc & NUM_MASK == ARB_NUM {
+            let digits = from_hex(field(&mut tokens, "digits")?)?;
+            if Some(digits.len() as u64) != num.checked_add(den) {
+                return Err(format!(
+                    "Expected {} digits, found {}",
+                    num.saturating_add(den),
+                    digits.len()
+                ));
+            }
+            out.extend(digits);
+        }
+    } else {
+        let num = parse_u64(field(&mut tokens, "num")?)?;
+        let den = parse_u64(field(&mut tokens, "den")?)?;
+        write_natural_frac(disc, num, den, out)?;
     }
+
+    if let Some(t) = tokens.next() {
+        return Err(format!("Unexpected token {}", t));
+    }
+
+    Ok(())
 }
 
-impl PackedRationalNumberWriter for (i64, i64) {
-    #[inline(always)]
-    fn write_packed(&self, dest: &mut Vec<u8>) {
-        let p = dest.len();
+/// Check the packed number at the start of `source` and return its length in bytes.
+/// Returns an error if the number is truncated, if its discriminant or the tag of a fraction
+/// is not one that is written by [PackedRationalNumberWriter], or if it is a fraction with a zero denominator.
+/// The contents of rational polynomials are not checked.
+fn validate_packed_number(source: &[u8]) -> Result<usize, String> {
+    let truncated = || "Truncated packed number".to_owned();
+    let disc = *source.first().ok_or_else(truncated)?;
+
+    let rest = if disc == RAT_POLY {
+        let len = u32::from_le_bytes(source.get(1..5).ok_or_else(truncated)?.try_into().unwrap());
+        source.get(5 + len as usize..).ok_or_else(truncated)?
+    } else if disc == ARB_NUM | ARB_DEN {
+        let (_, num, den, r) = read_natural_frac(&source[1..])
+            .ok_or_else(|| "Invalid length of a large packed number".to_owned())?;
+        let num = usize::try_from(num).map_err(|_| truncated())?;
+        let den = usize::try_from(den).map_err(|_| truncated())?;
+        let den_digits = r
+            .get(num..num.checked_add(den).ok_or_else(truncated)?)
+            .ok_or_else(truncated)?;
+        if den_digits.iter().all(|d| *d == 0) {
+            return Err("Packed number has a zero denominator".to_owned());
+        }
+        &r[num + den..]
+    } else if disc == FIN_NUM {
+        read_natural_frac(&source[1..])
+            .ok_or_else(|| "Invalid packed finite field element".to_owned())?
+            .3
+    } else {
+        let (_, _, den, r) = read_natural_frac(source)
+            .ok_or_else(|| format!("Invalid packed number tag {:#04x}", disc))?;
+        if den == 0 {
+            return Err("Packed number has a zero denominator".to_owned());
+        }
+        r
+    };
+
+    Ok(source.len() - rest.len())
+}
+
+/// Build an index of the start offsets of all packed numbers in `blob`, so that
+/// the `k`-th number can later be read directly at `&blob[index[k]..]`.
+/// Returns an error with the offset of the first number that is corrupt or truncated,
+/// has an invalid tag or has a zero denominator.
+pub fn index_rationals(blob: &[u8]) -> Result<Vec<usize>, String> {
+    let mut index = vec![];
+    let mut source = blob;
+
+    while !source.is_empty() {
+        let offset = blob.len() - source.len();
+        let len = validate_packed_number(source)
+            .map_err(|e| format!("Corrupt packed number at offset {}: {}", offset, e))?;
+
+        index.push(offset);
+        source = &source[len..];
+    }
 
-        let num_u64 = self.0.unsigned_abs();
-        let den_u64 = self.1.unsigned_abs();
-        (num_u64, den_u64).write_packed(dest);
+    Ok(index)
+}
+
+/// Read a single packed finite-field number from the start of `source`.
+/// Returns an error if `source` is truncated, does not hold a finite-field number,
+/// refers to a finite field that is not registered or holds an element that is not reduced.
+fn read_finite_field(source: &[u8]) -> Result<(FiniteFieldElement<u64>, FiniteFieldIndex), String> {
+    if source.first() != Some(&FIN_NUM) {
+        return Err("Expected a packed finite-field number".to_owned());
+    }
 
-        if self.0 >= 0 && self.1 < 0 || self.0 < 0 && self.1 >= 0 {
-            dest[p] |= SIGN;
+    validate_packed_number(source)?;
+    let (n, i, _) = source[1..].try_get_frac_u64()?;
+
+    let field = State::try_get_finite_field(FiniteFieldIndex(i as usize))
+        .ok_or_else(|| format!("Unknown finite field index {}", i))?;
+    if n >= field.get_prime() {
+        return Err(format!(
+            "Finite field element {} is not reduced modulo {}",
+            n,
+            field.get_prime()
+        ));
+    }
+
+    Ok((FiniteFieldElement(n), FiniteFieldIndex(i as usize)))
+}
+
+/// Read two packed finite-field numbers and check that they belong to the same field.
+fn read_finite_field_pair(
+    a: &[u8],
+    b: &[u8],
+) -> Result<
+    (
+        FiniteFieldElement<u64>,
+        FiniteFieldElement<u64>,
+        FiniteFieldIndex,
+    ),
+    String,
+> {
+    let (n1, i1) = read_finite_field(a)?;
+    let (n2, i2) = read_finite_field(b)?;
+    if i1 != i2 {
+        return Err(format!(
+            "Cannot operate on elements of different finite fields: {} and {}",
+            State::get_finite_field(i1).get_prime(),
+            State::get_finite_field(i2).get_prime()
+        ));
+    }
+    Ok((n1, n2, i1))
+}
+
+fn write_finite_field(n: FiniteFieldElement<u64>, i: FiniteFieldIndex) -> Vec<u8> {
+    let mut dest = vec![];
+    Coefficient::FiniteField(n, i).write_packed(&mut dest);
+    dest
+}
+
+/// Add two packed finite-field numbers of the same field, without
+/// constructing an atom.
+pub fn ff_add(a: &[u8], b: &[u8]) -> Result<Vec<u8>, String> {
+    let (n1, n2, i) = read_finite_field_pair(a, b)?;
+    Ok(write_finite_field(
+        State::get_finite_field(i).add(&n1, &n2),
+        i,
+    ))
+}
+
+/// Multiply two packed finite-field numbers of the same field, without
+/// constructing an atom.
+pub fn ff_mul(a: &[u8], b: &[u8]) -> Result<Vec<u8>, String> {
+    let (n1, n2, i) = read_finite_field_pair(a, b)?;
+    Ok(write_finite_field(
+        State::get_finite_field(i).mul(&n1, &n2),
+        i,
+    ))
+}
+
+/// Compute the modular inverse of a packed finite-field number.
+pub fn ff_inv(a: &[u8]) -> Result<Vec<u8>, String> {
+    let (n, i) = read_finite_field(a)?;
+    if Zp64::is_zero(&n) {
+        return Err("Cannot invert zero".to_owned());
+    }
+    Ok(write_finite_field(State::get_finite_field(i).inv(&n), i))
+}
+
+/// Divide two packed finite-field numbers of the same field, without
+/// constructing an atom.
+pub fn ff_div(a: &[u8], b: &[u8]) -> Result<Vec<u8>, String> {
+    let (n1, n2, i) = read_finite_field_pair(a, b)?;
+    if Zp64::is_zero(&n2) {
+        return Err("Division by zero".to_owned());
+    }
+    Ok(write_finite_field(
+        State::get_finite_field(i).div(&n1, &n2),
+        i,
+    ))
+}
+
+/// Write an element of the Galois field with index `index`, which is registered with
+/// [State::get_or_insert_galois_field], in packed form. The defining polynomial of the
+/// field is recorded in the exported state, see [State::export].
+pub fn write_packed_galois_field(
+    element: &GaloisFieldElement,
+    index: GaloisFieldIndex,
+    dest: &mut Vec<u8>,
+) {
+    dest.put_u8(GAL_NUM);
+    (index.0 as u64, element.coefficients().len() as u64).write_packed(dest);
+    for c in element.coefficients() {
+        (*c, 1u64).write_packed(dest);
+    }
+}
+
+/// Read a packed Galois field element from the start of `source`, returning the element,
+/// the index of its field and the remaining bytes. If the element was written in a different
+/// state, the `state_map` obtained from importing that state maps the field index to the current state.
+///
+/// Returns an error if `source` is truncated, does not hold a Galois field element,
+/// refers to a field that is not registered or holds an element that is not reduced.
+pub fn read_packed_galois_field<'a>(
+    source: &'a [u8],
+    state_map: Option<&StateMap>,
+) -> Result<(GaloisFieldElement, GaloisFieldIndex, &'a [u8]), String> {
+    if source.first() != Some(&GAL_NUM) {
+        return Err("Expected a packed Galois field element".to_owned());
+    }
+
+    let (i, n_coeffs, mut rest) = source[1..].try_get_frac_u64()?;
+    let mut index = GaloisFieldIndex(i as usize);
+    if let Some(state_map) = state_map {
+        index = state_map.get_galois_field_index(index);
+    }
+
+    let field = State::try_get_galois_field(index)
+        .ok_or_else(|| format!("Unknown Galois field index {}", i))?;
+    if n_coeffs > field.get_degree() as u64 {
+        return Err(format!(
+            "Galois field element has {} coefficients, but the field has degree {}",
+            n_coeffs,
+            field.get_degree()
+        ));
+    }
+
+    let mut coeffs = Vec::with_capacity(n_coeffs as usize);
+    for _ in 0..n_coeffs {
+        let (c, den, r) = rest.try_get_frac_u64()?;
+        if den != 1 || c >= field.get_prime() {
+            return Err(format!(
+                "Galois field coefficient {}/{} is not reduced modulo {}",
+                c,
+                den,
+                field.get_prime()
+            ));
         }
+        coeffs.push(c);
+        rest = r;
     }
 
-    #[inline(always)]
-    fn write_packed_fixed(&self, dest: &mut [u8]) {
-        let num_u64 = self.0.unsigned_abs();
-        let den_u64 = self.1.unsigned_abs();
-        (num_u64, den_u64).write_packed_fixed(dest);
+    Ok((field.to_element(&coeffs), index, rest))
+}
+
+/// Collect a byte string from a deserializer that may represent bytes as a sequence.
+pub(crate) fn deserialize_bytes<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a byte string")
+        }
+
+        fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(v.to_vec())
+        }
 
-        if self.0 >= 0 && self.1 < 0 || self.0 < 0 && self.1 >= 0 {
-            dest[0] |= SIGN;
+        fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(v)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut v = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(b) = seq.next_element()? {
+                v.push(b);
+            }
+            Ok(v)
         }
     }
 
-    fn get_packed_size(&self) -> u64 {
-        (self.0 as u64, self.1 as u64).get_packed_size()
+    d.deserialize_bytes(BytesVisitor)
+}
+
+/// Read a rational number that was written in the packed format.
+fn read_packed_rational(bytes: &[u8]) -> Result<Rational, String> {
+    if bytes.is_empty() || bytes[0] == FIN_NUM || bytes[0] == RAT_POLY {
+        return Err("Expected a packed rational number".to_owned());
+    }
+    // check the number before reading, as the reader does not validate its input
+    if validate_packed_number(bytes)? != bytes.len() {
+        return Err("Unexpected bytes after the packed rational number".to_owned());
+    }
+
+    match bytes.get_coeff_view().0 {
+        CoefficientView::Natural(n, d) => Ok(Rational::Natural(n, d)),
+        CoefficientView::Large(r) => Ok(Rational::from_large(r.to_rat())),
+        _ => unreachable!(),
     }
 }
 
-impl PackedRationalNumberWriter for (u64, u64) {
-    #[inline(always)]
-    fn write_packed(&self, dest: &mut Vec<u8>) {
-        let p = dest.len();
-
-        if self.0 <= u8::MAX as u64 {
-            dest.put_u8(U8_NUM);
-            dest.put_u8(self.0 as u8);
-        } else if self.0 <= u16::MAX as u64 {
-            dest.put_u8(U16_NUM);
-            dest.put_u16_le(self.0 as u16);
-        } else if self.0 <= u32::MAX as u64 {
-            dest.put_u8(U32_NUM);
-            dest.put_u32_le(self.0 as u32);
-        } else {
-            dest.put_u8(U64_NUM);
-            dest.put_u64_le(self.0);
+impl Serialize for Rational {
+    /// Serialize the rational number using the packed byte encoding.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut dest = vec![];
+        Coefficient::Rational(self.clone()).write_packed(&mut dest);
+        serializer.serialize_bytes(&dest)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rational {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        read_packed_rational(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Integer {
+    /// Serialize the integer using the packed byte encoding of rationals.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Rational::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Integer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let r = Rational::deserialize(deserializer)?;
+        if !r.is_integer() {
+            return Err(serde::de::Error::custom(format!("{} is not an integer", r)));
         }
+        Ok(r.numerator())
+    }
+}
 
-        if self.1 == 1 {
-        } else if self.1 <= u8::MAX as u64 {
-            dest[p] |= U8_DEN;
-            dest.put_u8(self.1 as u8);
-        } else if self.1 <= u16::MAX as u64 {
-            dest[p] |= U16_DEN;
-            dest.put_u16_le(self.1 as u16);
-        } else if self.1 <= u32::MAX as u64 {
-            dest[p] |= U32_DEN;
-            dest.put_u32_le(self.1 as u32);
-        } else {
-            dest[p] |= U64_DEN;
-            dest.put_u64_le(self.1);
+#[cfg(test)]
+mod test {
+    use crate::{
+        coefficient::Coefficient,
+        domains::{
+            finite_field::{FiniteFieldCore, FiniteFieldElement, Zp64},
+            galois_field::GaloisField,
+            rational::Rational,
+            Ring,
+        },
+        packed::{U8_DEN, U8_NUM},
+        state::{FiniteFieldIndex, GaloisFieldIndex, State},
+    };
+
+    use super::{
+        dump_rational_buffer, ff_add, ff_div, ff_inv, ff_mul, index_rationals, parse_rational_dump,
+        read_packed_galois_field, validate_packed_number, write_packed_galois_field,
+        PackedCoefficientReader, PackedRationalNumberReader, PackedRationalNumberWriter, ARB_NUM,
+        FIN_NUM, SIGN,
+    };
+
+    #[test]
+    fn rational_dump() {
+        let mut buffer = vec![];
+        for c in [
+            Coefficient::from(3),
+            Coefficient::from((-3, 7)),
+            Coefficient::from((1, 100000)),
+            Coefficient::from(i64::MIN),
+            Coefficient::Rational(Rational::from_large(
+                "-123456789012345678901234567891/7".parse().unwrap(),
+            )),
+            Coefficient::FiniteField(FiniteFieldElement(5), FiniteFieldIndex(0)),
+        ] {
+            c.write_packed(&mut buffer);
         }
+        buffer.push(0xff);
+
+        let dump = dump_rational_buffer(&buffer);
+        assert_eq!(dump.lines().count(), 7);
+        assert!(dump.lines().nth(1).unwrap().ends_with("# -3/7"));
+        assert!(dump
+            .lines()
+            .nth(4)
+            .unwrap()
+            .ends_with("# -123456789012345678901234567891/7"));
+        assert_eq!(dump.lines().nth(6).unwrap(), "raw ff # undecodable");
+
+        assert_eq!(parse_rational_dump(&dump), Ok(buffer));
+
+        let err = parse_rational_dump("0x01 num=3 den=1\n0x01 num=300 den=1").unwrap_err();
+        assert!(err.starts_with("Line 2:"), "{}", err);
+        assert!(parse_rational_dump("0x01 num=3").is_err());
+        assert!(parse_rational_dump("0x11 num=3 den=1000").is_err());
+
+        // a large number with a zero denominator is dumped with an error marker
+        let mut zero_den = vec![];
+        Coefficient::Rational(Rational::from_large(
+            "123456789012345678901234567891/7".parse().unwrap(),
+        ))
+        .write_packed(&mut zero_den);
+        *zero_den.last_mut().unwrap() = 0;
+        zero_den.extend([U8_NUM | U8_DEN, 3, 0]);
+
+        let dump = dump_rational_buffer(&zero_den);
+        assert_eq!(dump.lines().count(), 2);
+        assert!(dump
+            .lines()
+            .all(|l| !l.starts_with("raw") && l.contains("# invalid: ")));
+        assert_eq!(parse_rational_dump(&dump), Ok(zero_den));
     }
 
-    #[inline(always)]
-    fn write_packed_fixed(&self, dest: &mut [u8]) {
-        let (tag, mut dest) = dest.split_first_mut().unwrap();
-
-        if self.0 <= u8::MAX as u64 {
-            *tag = U8_NUM;
-            dest.put_u8(self.0 as u8);
-        } else if self.0 <= u16::MAX as u64 {
-            *tag = U16_NUM;
-            dest.put_u16_le(self.0 as u16);
-        } else if self.0 <= u32::MAX as u64 {
-            *tag = U32_NUM;
-            dest.put_u32_le(self.0 as u32);
-        } else {
-            *tag = U64_NUM;
-            dest.put_u64_le(self.0);
+    #[test]
+    fn rational_index() {
+        let numbers = [
+            Coefficient::from((-3, 7)),
+            Coefficient::Rational(Rational::from_large(
+                "123456789012345678901234567891/7".parse().unwrap(),
+            )),
+            Coefficient::from(100000),
+        ];
+
+        let mut blob = vec![];
+        for c in &numbers {
+            c.write_packed(&mut blob);
         }
 
-        if self.1 == 1 {
-        } else if self.1 <= u8::MAX as u64 {
-            *tag |= U8_DEN;
-            dest.put_u8(self.1 as u8);
-        } else if self.1 <= u16::MAX as u64 {
-            *tag |= U16_DEN;
-            dest.put_u16_le(self.1 as u16);
-        } else if self.1 <= u32::MAX as u64 {
-            *tag |= U32_DEN;
-            dest.put_u32_le(self.1 as u32);
-        } else {
-            *tag |= U64_DEN;
-            dest.put_u64_le(self.1);
+        let index = index_rationals(&blob).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index[0], 0);
+        for (c, i) in numbers.iter().zip(&index) {
+            assert_eq!(blob[*i..].get_coeff_view().0.to_owned(), *c);
         }
+
+        assert!(index_rationals(&blob[..blob.len() - 1]).is_err());
+        assert!(index_rationals(&blob[..index[2] - 1]).is_err());
+        assert_eq!(index_rationals(&blob[..index[2]]), Ok(index[..2].to_vec()));
+
+        // zero the denominator of the large number
+        let mut zero_den = blob.clone();
+        zero_den[index[2] - 1] = 0;
+        let err = index_rationals(&zero_den).unwrap_err();
+        assert!(err.contains(&format!("offset {}", index[1])) && err.contains("zero denominator"));
+
+        let mut bad_tag = blob.clone();
+        bad_tag[index[2]] = 0x06;
+        assert!(index_rationals(&bad_tag)
+            .unwrap_err()
+            .contains(&format!("offset {}", index[2])));
     }
 
-    fn get_packed_size(&self) -> u64 {
-        let mut size = 1;
-        size += if self.0 <= u8::MAX as u64 {
-            get_size_of_natural(U8_NUM)
-        } else if self.0 <= u16::MAX as u64 {
-            get_size_of_natural(U16_NUM)
-        } else if self.0 <= u32::MAX as u64 {
-            get_size_of_natural(U32_NUM)
-        } else {
-            get_size_of_natural(U64_NUM)
-        };
+    #[test]
+    fn invalid_packed_numbers() {
+        let mut large = vec![];
+        Coefficient::Rational(Rational::from_large(
+            "123456789012345678901234567891/7".parse().unwrap(),
+        ))
+        .write_packed(&mut large);
+        assert_eq!(validate_packed_number(&large), Ok(large.len()));
+
+        // the denominator digits are zero
+        let mut zero_den = large.clone();
+        *zero_den.last_mut().unwrap() = 0;
+        assert!(validate_packed_number(&zero_den)
+            .unwrap_err()
+            .contains("zero denominator"));
+        assert!(bincode::deserialize::<Rational>(&bincode::serialize(&zero_den).unwrap()).is_err());
+
+        // a natural fraction with a zero denominator
+        assert!(validate_packed_number(&[U8_NUM | U8_DEN, 3, 0]).is_err());
+        assert_eq!(validate_packed_number(&[U8_NUM | U8_DEN, 3, 2]), Ok(3));
+
+        // discriminants that are never written
+        for disc in [0x00, 0x06, 0x09, 0x15, 0x57, ARB_NUM, FIN_NUM | SIGN] {
+            let mut b = large.clone();
+            b[0] = disc;
+            assert!(validate_packed_number(&b).is_err(), "{:#04x}", disc);
+        }
 
-        size += if self.1 == 1 {
-            0
-        } else if self.1 <= u8::MAX as u64 {
-            get_size_of_natural(U8_NUM)
-        } else if self.1 <= u16::MAX as u64 {
-            get_size_of_natural(U16_NUM)
-        } else if self.1 <= u32::MAX as u64 {
-            get_size_of_natural(U32_NUM)
-        } else {
-            get_size_of_natural(U64_NUM)
+        assert!(validate_packed_number(&large[..large.len() - 1]).is_err());
+        assert!(validate_packed_number(&[]).is_err());
+    }
+
+    #[test]
+    fn signed_frac_round_trip() {
+        for frac in [(i64::MIN, 1), (i64::MIN + 1, 3), (-3, 7), (-300, i64::MAX)] {
+            let mut buffer = vec![];
+            frac.write_packed(&mut buffer);
+            assert_eq!(buffer.len() as u64, frac.get_packed_size());
+
+            let (num, den, rest) = buffer.as_slice().get_frac_i64();
+            assert_eq!((num, den), frac);
+            assert!(rest.is_empty());
+        }
+    }
+
+    #[test]
+    fn finite_field_ops() {
+        let field = Zp64::new(7);
+        let index = State::get_or_insert_finite_field(field.clone());
+        let pack = |n: u64| {
+            let mut dest = vec![];
+            Coefficient::FiniteField(field.to_element(n), index).write_packed(&mut dest);
+            dest
         };
-        size as u64
+
+        assert_eq!(ff_mul(&pack(3), &pack(5)), Ok(pack(1)));
+        assert_eq!(ff_add(&pack(3), &pack(5)), Ok(pack(1)));
+        assert_eq!(ff_inv(&pack(3)), Ok(pack(5)));
+        assert_eq!(ff_div(&pack(1), &pack(3)), Ok(pack(5)));
+        assert!(ff_inv(&pack(0)).is_err());
+        assert!(ff_div(&pack(1), &pack(0)).is_err());
+
+        let other = State::get_or_insert_finite_field(Zp64::new(11));
+        let mut b = vec![];
+        Coefficient::FiniteField(FiniteFieldElement(1), other).write_packed(&mut b);
+        assert!(ff_mul(&pack(3), &b).is_err());
+
+        let mut r = vec![];
+        Coefficient::from(3).write_packed(&mut r);
+        assert!(ff_add(&r, &pack(3)).is_err());
+
+        // truncated input
+        let a = pack(3);
+        for len in 0..a.len() {
+            assert!(ff_inv(&a[..len]).is_err());
+            assert!(ff_mul(&a[..len], &a).is_err());
+        }
+
+        // a field index that is not registered
+        let mut u = vec![];
+        Coefficient::FiniteField(FiniteFieldElement(1), FiniteFieldIndex(usize::MAX >> 8))
+            .write_packed(&mut u);
+        assert!(ff_inv(&u).is_err());
+        assert!(ff_add(&u, &pack(3)).is_err());
+        assert!(ff_add(&pack(3), &u).is_err());
+    }
+
+    #[test]
+    fn galois_field_packed() {
+        // GF(2^3) = Z_2[x] / (x^3 + x + 1)
+        let field = GaloisField::new(2, &[1, 1, 0, 1]).unwrap();
+        let index = State::get_or_insert_galois_field(field.clone());
+        assert_eq!(State::get_galois_field(index), &field);
+
+        let a = field.to_element(&[1, 0, 1]);
+        let mut dest = vec![];
+        write_packed_galois_field(&a, index, &mut dest);
+        write_packed_galois_field(&field.zero(), index, &mut dest);
+
+        let (b, i, rest) = read_packed_galois_field(&dest, None).unwrap();
+        assert_eq!((b, i), (a.clone(), index));
+        let (z, _, rest) = read_packed_galois_field(rest, None).unwrap();
+        assert!(GaloisField::is_zero(&z));
+        assert!(rest.is_empty());
+
+        assert!(read_packed_galois_field(&dest[..3], None).is_err());
+
+        let mut unknown = vec![];
+        write_packed_galois_field(&a, GaloisFieldIndex(usize::MAX >> 8), &mut unknown);
+        assert!(read_packed_galois_field(&unknown, None).is_err());
     }
 }
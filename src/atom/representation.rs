@@ -1,5 +1,6 @@
-use byteorder::{LittleEndian, WriteBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use bytes::{Buf, BufMut};
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     cmp::Ordering,
     io::{Read, Write},
@@ -7,11 +8,12 @@ use std::{
 
 use crate::{
     coefficient::{Coefficient, CoefficientView},
-    state::{StateMap, Workspace},
+    packed::{PackedRationalNumberReader, PackedRationalNumberWriter},
+    state::{State, StateMap, Workspace},
 };
 
 use super::{
-    coefficient::{PackedRationalNumberReader, PackedRationalNumberWriter},
+    coefficient::{deserialize_bytes, PackedCoefficientReader},
     Atom, AtomView, SliceType, Symbol,
 };
 
@@ -69,6 +71,19 @@ impl Atom {
         Ok(())
     }
 
+    /// Get the number of bytes allocated for the backing storage of the atom.
+    pub(crate) fn get_capacity(&self) -> usize {
+        match self {
+            Atom::Num(n) => n.data.capacity(),
+            Atom::Var(v) => v.data.capacity(),
+            Atom::Fun(f) => f.data.capacity(),
+            Atom::Pow(p) => p.data.capacity(),
+            Atom::Mul(m) => m.data.capacity(),
+            Atom::Add(a) => a.data.capacity(),
+            Atom::Empty => 0,
+        }
+    }
+
     pub fn import<R: Read>(source: R, state_map: &StateMap) -> Result<Atom, std::io::Error> {
         let mut a = Atom::new();
         a.read(source)?;
@@ -76,6 +91,159 @@ impl Atom {
     }
 }
 
+/// Export and import of a batch of atoms that share a single state header.
+///
+/// The format is the exported [State], followed by the atoms
+/// written with [AtomView::write], followed by an index with the byte offset of every atom,
+/// the number of atoms and the byte offset of the index.
+/// The index allows for loading a single atom without reading the others.
+pub struct AtomBatch;
+
+impl AtomBatch {
+    /// Write the state and all `atoms` to `dest`.
+    pub fn export(atoms: &[Atom], dest: &mut Vec<u8>) -> Result<(), std::io::Error> {
+        let start = dest.len();
+        State::export(&mut *dest)?;
+
+        let mut offsets = Vec::with_capacity(atoms.len());
+        for a in atoms {
+            offsets.push((dest.len() - start) as u64);
+            a.as_view().write(&mut *dest)?;
+        }
+
+        let index_offset = (dest.len() - start) as u64;
+        for o in offsets {
+            dest.write_u64::<LittleEndian>(o)?;
+        }
+        dest.write_u64::<LittleEndian>(atoms.len() as u64)?;
+        dest.write_u64::<LittleEndian>(index_offset)
+    }
+
+    /// Get the number of atoms in an exported batch.
+    pub fn len(bytes: &[u8]) -> Result<usize, std::io::Error> {
+        Ok(Self::read_trailer(bytes)?.0)
+    }
+
+    /// Import the `i`th atom of an exported batch, merging the state of the batch
+    /// with the current state.
+    pub fn load_one(bytes: &[u8], i: usize) -> Result<Atom, std::io::Error> {
+        let (n, index_offset) = Self::read_trailer(bytes)?;
+        if i >= n {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Atom index {} out of bounds for batch of {} atoms", i, n),
+            ));
+        }
+
+        let state_map = State::import(bytes, None)?;
+        let (start, end) = Self::read_range(bytes, n, index_offset, i)?;
+        Atom::import(&bytes[start..end], &state_map)
+    }
+
+    /// Import all atoms of an exported batch, merging the state of the batch
+    /// with the current state.
+    pub fn load(bytes: &[u8]) -> Result<Vec<Atom>, std::io::Error> {
+        let (n, index_offset) = Self::read_trailer(bytes)?;
+        let state_map = State::import(bytes, None)?;
+
+        (0..n)
+            .map(|i| {
+                let (start, end) = Self::read_range(bytes, n, index_offset, i)?;
+                Atom::import(&bytes[start..end], &state_map)
+            })
+            .collect()
+    }
+
+    /// Read the number of atoms and the offset of the index.
+    fn read_trailer(bytes: &[u8]) -> Result<(usize, usize), std::io::Error> {
+        if bytes.len() < 16 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Batch is too short",
+            ));
+        }
+
+        let n = Self::read_offset(bytes, bytes.len() - 16)?;
+        let index_offset = Self::read_offset(bytes, bytes.len() - 8)?;
+
+        if n.checked_mul(8).and_then(|l| l.checked_add(index_offset)) != Some(bytes.len() - 16) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Corrupt batch index",
+            ));
+        }
+
+        Ok((n, index_offset))
+    }
+
+    /// Read the byte range of the `i`th atom from the index, which ends where the next atom
+    /// or the index starts.
+    fn read_range(
+        bytes: &[u8],
+        n: usize,
+        index_offset: usize,
+        i: usize,
+    ) -> Result<(usize, usize), std::io::Error> {
+        let start = Self::read_offset(bytes, index_offset + 8 * i)?;
+        let end = if i + 1 < n {
+            Self::read_offset(bytes, index_offset + 8 * (i + 1))?
+        } else {
+            index_offset
+        };
+
+        if start > end || end > index_offset {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Corrupt batch index",
+            ));
+        }
+        Ok((start, end))
+    }
+
+    fn read_offset(bytes: &[u8], pos: usize) -> Result<usize, std::io::Error> {
+        let mut b = bytes.get(pos..pos + 8).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Batch is too short")
+        })?;
+        let o = b.read_u64::<LittleEndian>()? as usize;
+        if o > bytes.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Corrupt batch index",
+            ));
+        }
+        Ok(o)
+    }
+}
+
+impl Serialize for Atom {
+    /// Serialize the atom together with the symbol table, as in [AtomBatch::export].
+    /// An empty atom is written as an empty byte string.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Atom::Empty = self {
+            return serializer.serialize_bytes(&[]);
+        }
+
+        let mut dest = vec![];
+        AtomBatch::export(std::slice::from_ref(self), &mut dest).map_err(S::Error::custom)?;
+        serializer.serialize_bytes(&dest)
+    }
+}
+
+impl<'de> Deserialize<'de> for Atom {
+    /// Deserialize an atom, merging its symbol table with the current state.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        if bytes.is_empty() {
+            return Ok(Atom::Empty);
+        }
+
+        if AtomBatch::len(&bytes).map_err(D::Error::custom)? != 1 {
+            return Err(D::Error::custom("Expected a single atom"));
+        }
+        AtomBatch::load_one(&bytes, 0).map_err(D::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Num {
     data: RawAtom,
@@ -518,6 +686,11 @@ impl Mul {
     }
 
     pub(crate) fn extend(&mut self, other: AtomView<'_>) {
+        self.extend_with(other, true)
+    }
+
+    /// Add `other` as a factor. If `flatten` is `false`, a product `other` is added as a single factor.
+    pub(crate) fn extend_with(&mut self, other: AtomView<'_>, flatten: bool) {
         self.data[0] |= NOT_NORMALIZED;
 
         // may increase size of the num of args
@@ -531,7 +704,7 @@ impl Mul {
         let old_size = unsafe { c.as_ptr().offset_from(self.data.as_ptr()) } as usize - 1 - 4;
 
         let new_slice = match other {
-            AtomView::Mul(m) => m.to_slice(),
+            AtomView::Mul(m) if flatten => m.to_slice(),
             _ => ListSlice::from_one(other),
         };
 
@@ -689,6 +862,11 @@ impl Add {
     }
 
     pub(crate) fn extend(&mut self, other: AtomView<'_>) {
+        self.extend_with(other, true)
+    }
+
+    /// Add `other` as a term. If `flatten` is `false`, a sum `other` is added as a single term.
+    pub(crate) fn extend_with(&mut self, other: AtomView<'_>, flatten: bool) {
         self.data[0] |= NOT_NORMALIZED;
 
         let mut c = &self.data[1..];
@@ -699,7 +877,7 @@ impl Add {
         let old_header_size = unsafe { c.as_ptr().offset_from(self.data.as_ptr()) } as usize;
 
         let new_slice = match other {
-            AtomView::Add(m) => m.to_slice(),
+            AtomView::Add(m) if flatten => m.to_slice(),
             _ => ListSlice::from_one(other),
         };
 
@@ -1269,14 +1447,23 @@ impl<'a> AtomView<'a> {
     pub(crate) fn rename(&self, state_map: &StateMap) -> Atom {
         Workspace::get_local().with(|ws| {
             let mut a = ws.new_atom();
-            self.rename_no_norm(state_map, ws, &mut a);
+            self.rename_no_norm(state_map, false, ws, &mut a);
             let mut r = Atom::new();
             a.as_view().normalize(ws, &mut r);
             r
         })
     }
 
-    fn rename_no_norm(&self, state_map: &StateMap, ws: &Workspace, out: &mut Atom) {
+    /// Rename the symbols, finite fields and variable lists of `self` using `state_map`.
+    /// If `keep_normalized` is `true`, every rebuilt subexpression keeps the normalization flag of the
+    /// original, which is only correct if the renaming does not change the normal form.
+    pub(crate) fn rename_no_norm(
+        &self,
+        state_map: &StateMap,
+        keep_normalized: bool,
+        ws: &Workspace,
+        out: &mut Atom,
+    ) {
         match self {
             AtomView::Num(n) => match n.get_coeff_view() {
                 CoefficientView::FiniteField(e, i) => {
@@ -1308,44 +1495,78 @@ impl<'a> AtomView<'a> {
                 }
             }
             AtomView::Fun(f) => {
-                if let Some(s) = state_map.symbols.get(&f.get_symbol().get_id()) {
-                    let nf = out.to_fun(*s);
+                // the arguments may need to be renamed even if the function symbol is unchanged
+                let s = state_map
+                    .symbols
+                    .get(&f.get_symbol().get_id())
+                    .copied()
+                    .unwrap_or(f.get_symbol());
+                let nf = out.to_fun(s);
 
-                    let mut na = ws.new_atom();
-                    for a in f.iter() {
-                        a.rename_no_norm(state_map, ws, &mut na);
-                        nf.add_arg(na.as_view());
-                    }
-                } else {
-                    out.set_from_view(self);
+                let mut na = ws.new_atom();
+                for a in f.iter() {
+                    a.rename_no_norm(state_map, keep_normalized, ws, &mut na);
+                    nf.add_arg(na.as_view());
+                }
+
+                if keep_normalized {
+                    out.set_normalized(!self.needs_normalization());
                 }
             }
-            AtomView::Pow(p) => {
-                let (b, e) = p.get_base_exp();
+            _ => {
+                self.map_children_no_norm(ws, out, |a, o| {
+                    a.rename_no_norm(state_map, keep_normalized, ws, o)
+                });
 
-                let mut nb = ws.new_atom();
-                b.rename_no_norm(state_map, ws, &mut nb);
-                let mut ne = ws.new_atom();
-                e.rename_no_norm(state_map, ws, &mut ne);
+                if keep_normalized {
+                    out.set_normalized(!self.needs_normalization());
+                }
+            }
+        }
+    }
 
-                out.to_pow(nb.as_view(), ne.as_view());
+    /// Write `self` to `out`, where every argument of a function, the base and exponent
+    /// of a power, every factor of a product and every term of a sum is replaced by its image under `map`.
+    /// Numbers and variables are copied. The result is not normalized.
+    pub(crate) fn map_children_no_norm<F: FnMut(AtomView<'a>, &mut Atom)>(
+        &self,
+        ws: &Workspace,
+        out: &mut Atom,
+        mut map: F,
+    ) {
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => out.set_from_view(self),
+            AtomView::Fun(f) => {
+                let fun = out.to_fun(f.get_symbol());
+                let mut arg_buf = ws.new_atom();
+                for arg in f.iter() {
+                    map(arg, &mut arg_buf);
+                    fun.add_arg(arg_buf.as_view());
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let mut base_out = ws.new_atom();
+                map(base, &mut base_out);
+                let mut exp_out = ws.new_atom();
+                map(exp, &mut exp_out);
+                out.to_pow(base_out.as_view(), exp_out.as_view());
             }
             AtomView::Mul(m) => {
-                let nm = out.to_mul();
-
-                let mut na = ws.new_atom();
-                for a in m.iter() {
-                    a.rename_no_norm(state_map, ws, &mut na);
-                    nm.extend(na.as_view());
+                let mul = out.to_mul();
+                let mut arg_buf = ws.new_atom();
+                for arg in m.iter() {
+                    map(arg, &mut arg_buf);
+                    mul.extend(arg_buf.as_view());
                 }
+                mul.set_has_coefficient(m.has_coefficient());
             }
-            AtomView::Add(add) => {
-                let nm = out.to_add();
-
-                let mut na = ws.new_atom();
-                for a in add.iter() {
-                    a.rename_no_norm(state_map, ws, &mut na);
-                    nm.extend(na.as_view());
+            AtomView::Add(a) => {
+                let add = out.to_add();
+                let mut arg_buf = ws.new_atom();
+                for arg in a.iter() {
+                    map(arg, &mut arg_buf);
+                    add.extend(arg_buf.as_view());
                 }
             }
         }
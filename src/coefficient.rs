@@ -6,9 +6,8 @@ use std::{
 
 use ahash::HashMap;
 use rug::{
-    integer::Order,
-    ops::{NegAssign, Pow as RPow},
-    Integer as MultiPrecisionInteger, Rational as MultiPrecisionRational,
+    integer::Order, ops::NegAssign, Integer as MultiPrecisionInteger,
+    Rational as MultiPrecisionRational,
 };
 use smallvec::{smallvec, SmallVec};
 
@@ -439,10 +438,7 @@ impl CoefficientView<'_> {
                         }
                     }
 
-                    (
-                        MultiPrecisionRational::from((n1, d1)).pow(n2 as u32).into(),
-                        (1, d2).into(),
-                    )
+                    (Rational::new(n1, d1).pow(n2 as u64).into(), (1, d2).into())
                 } else {
                     panic!("Power is too large: {}", n2);
                 }
@@ -470,12 +466,12 @@ impl CoefficientView<'_> {
                     panic!("Power is too large: {}", n2);
                 }
 
-                if n2 < 0 {
-                    let r = r.to_rat().clone().recip();
-                    (r.pow(n2.unsigned_abs() as u32).into(), (1, d2).into())
+                let r = if n2 < 0 {
+                    Rational::from_large(r.to_rat().recip())
                 } else {
-                    (r.to_rat().pow(n2 as u32).into(), (1, d2).into())
-                }
+                    Rational::from_large(r.to_rat())
+                };
+                (r.pow(n2.unsigned_abs()).into(), (1, d2).into())
             }
             _ => {
                 unimplemented!(
@@ -529,8 +525,8 @@ impl Ord for CoefficientView<'_> {
             (CoefficientView::Large(n1), CoefficientView::Large(n2)) => {
                 n1.to_rat().cmp(&n2.to_rat())
             }
-            (CoefficientView::FiniteField(n1, _), CoefficientView::FiniteField(n2, _)) => {
-                n1.0.cmp(&n2.0)
+            (CoefficientView::FiniteField(n1, i1), CoefficientView::FiniteField(n2, i2)) => {
+                i1.0.cmp(&i2.0).then_with(|| n1.0.cmp(&n2.0))
             }
             (&CoefficientView::Natural(n1, d1), CoefficientView::Large(n2)) => {
                 MultiPrecisionRational::from((n1, d1)).cmp(&n2.to_rat())
@@ -538,7 +534,15 @@ impl Ord for CoefficientView<'_> {
             (CoefficientView::Large(n1), &CoefficientView::Natural(n2, d2)) => {
                 n1.to_rat().cmp(&MultiPrecisionRational::from((n2, d2)))
             }
-            _ => unreachable!(),
+            (CoefficientView::RationalPolynomial(p1), CoefficientView::RationalPolynomial(p2)) => {
+                p1.0.cmp(p2.0)
+            }
+            // rationals are placed before finite field numbers,
+            // which are placed before rational polynomials
+            (CoefficientView::Natural(..) | CoefficientView::Large(_), _) => Ordering::Less,
+            (_, CoefficientView::Natural(..) | CoefficientView::Large(_)) => Ordering::Greater,
+            (CoefficientView::FiniteField(..), _) => Ordering::Less,
+            (_, CoefficientView::FiniteField(..)) => Ordering::Greater,
         }
     }
 }
@@ -628,7 +632,7 @@ impl Mul for CoefficientView<'_> {
                 Coefficient::Rational(Rational::Natural(n1, d1) * &Rational::Large(r2.to_rat()))
             }
             (CoefficientView::Large(r1), CoefficientView::Large(r2)) => {
-                (r1.to_rat() * r2.to_rat()).into()
+                Coefficient::Rational(Rational::Large(r1.to_rat()) * &Rational::Large(r2.to_rat()))
             }
             (CoefficientView::FiniteField(n1, i1), CoefficientView::FiniteField(n2, i2)) => {
                 if i1 != i2 {
@@ -720,9 +724,58 @@ impl Atom {
     pub fn set_coefficient_ring(&self, vars: &Arc<Vec<Variable>>) -> Atom {
         self.as_view().set_coefficient_ring(vars)
     }
+
+    /// Split a term into its rational coefficient and its remaining factors.
+    /// See [AtomView::coefficient_part].
+    pub fn coefficient_part(&self) -> (Rational, Atom) {
+        self.as_view().coefficient_part()
+    }
 }
 
 impl<'a> AtomView<'a> {
+    /// Split a term into its rational coefficient and its remaining factors, such that
+    /// `3*x*y` yields `(3, x*y)`. A number yields `(n, 1)` and any other term, including a sum,
+    /// yields `(1, term)`. Finite field and rational polynomial coefficients are considered part of the remaining factors.
+    pub fn coefficient_part(&self) -> (Rational, Atom) {
+        match self {
+            AtomView::Num(n) => match n.get_coeff_view() {
+                CoefficientView::Natural(n, d) => (Rational::Natural(n, d), Atom::new_num(1)),
+                CoefficientView::Large(r) => (Rational::Large(r.to_rat()), Atom::new_num(1)),
+                CoefficientView::FiniteField(_, _) | CoefficientView::RationalPolynomial(_) => {
+                    (Rational::one(), self.to_owned())
+                }
+            },
+            AtomView::Mul(m) if m.has_coefficient() => {
+                let mut coeff = Rational::one();
+                let mut rest = vec![];
+                for f in m.iter() {
+                    if let AtomView::Num(_) = f {
+                        let (c, r) = f.coefficient_part();
+                        if !r.is_one() {
+                            rest.push(f);
+                        }
+                        coeff = &coeff * &c;
+                    } else {
+                        rest.push(f);
+                    }
+                }
+
+                Workspace::get_local().with(|ws| {
+                    let mut t = ws.new_atom();
+                    let mul = t.to_mul();
+                    for f in rest {
+                        mul.extend(f);
+                    }
+
+                    let mut out = Atom::new();
+                    t.as_view().normalize(ws, &mut out);
+                    (coeff, out)
+                })
+            }
+            _ => (Rational::one(), self.to_owned()),
+        }
+    }
+
     pub fn set_coefficient_ring(&self, vars: &Arc<Vec<Variable>>) -> Atom {
         Workspace::get_local().with(|ws| {
             let mut out = ws.new_atom();
@@ -902,6 +955,32 @@ mod test {
 
     use crate::{atom::Atom, domains::rational::Rational, state::State};
 
+    #[test]
+    fn coefficient_part() {
+        for (term, coeff, rest) in [
+            ("3*v1*v2", (3, 1), "v1*v2"),
+            ("-v1/2", (-1, 2), "v1"),
+            ("v1", (1, 1), "v1"),
+            ("5/7", (5, 7), "1"),
+            ("v1+2", (1, 1), "v1+2"),
+            ("2*f1(3*v1)^2", (2, 1), "f1(3*v1)^2"),
+        ] {
+            let (c, r) = Atom::parse(term).unwrap().coefficient_part();
+            assert_eq!(c, Rational::Natural(coeff.0, coeff.1));
+            assert_eq!(r, Atom::parse(rest).unwrap());
+        }
+
+        let (c, r) = Atom::parse("-12345678901234567890123*v1")
+            .unwrap()
+            .coefficient_part();
+        let (n, one) = Atom::parse("-12345678901234567890123")
+            .unwrap()
+            .coefficient_part();
+        assert_eq!(c, n);
+        assert!(matches!(c, Rational::Large(_)) && one.is_one());
+        assert_eq!(r, Atom::parse("v1").unwrap());
+    }
+
     #[test]
     fn coeff_conversion() {
         let expr = Atom::parse("v1*coeff(v2+v3/v4)+v1*coeff(v2)").unwrap();
@@ -922,6 +1001,35 @@ mod test {
         assert_eq!(expr + &res, Atom::new());
     }
 
+    #[test]
+    fn coeff_overflow() {
+        let a = Atom::new_num(i64::MAX) * &Atom::new_num(i64::MAX);
+        assert_eq!(
+            a,
+            Atom::parse("85070591730234615847396907784232501249").unwrap()
+        );
+
+        let a = Atom::new_num(i64::MIN) * &Atom::new_num(-1);
+        assert_eq!(a, Atom::parse("9223372036854775808").unwrap());
+
+        let a = Atom::new_num(i64::MAX) + &Atom::new_num(i64::MAX);
+        assert_eq!(a, Atom::parse("18446744073709551614").unwrap());
+
+        // i64::MIN fits in the packed representation and must be read back intact
+        let a = Atom::new_num(i64::MIN);
+        assert_eq!(a.to_string(), "-9223372036854775808");
+        assert_eq!(a.as_view().as_integer(), Some(i64::MIN.into()));
+
+        let a = Atom::parse("-9223372036854775808*v1+v1").unwrap();
+        assert_eq!(a, Atom::parse("-9223372036854775807*v1").unwrap());
+
+        let a = Atom::parse("(1/9223372036854775807)^2*4611686018427387904*2").unwrap();
+        assert_eq!(
+            a,
+            Atom::parse("9223372036854775808/85070591730234615847396907784232501249").unwrap()
+        );
+    }
+
     #[test]
     fn coefficient_ring() {
         let expr = Atom::parse("v1*v3+v1*(v2+2)^-1*(v2+v3+1)").unwrap();
@@ -2,7 +2,12 @@ use ahash::HashMap;
 
 use crate::{
     atom::{Add, AsAtomView, Atom, AtomView, Symbol},
-    domains::{integer::Z, rational::Q},
+    coefficient::CoefficientView,
+    domains::{
+        integer::Z,
+        rational::{Rational, Q},
+        EuclideanDomain,
+    },
     state::Workspace,
 };
 
@@ -62,6 +67,21 @@ impl Atom {
     pub fn apart(&self, x: Symbol) -> Atom {
         self.as_view().apart(x)
     }
+
+    /// Get the maximal degree of every variable in `vars`. See [AtomView::multidegree].
+    pub fn multidegree(&self, vars: &[Symbol]) -> Option<Vec<i64>> {
+        self.as_view().multidegree(vars)
+    }
+
+    /// Get the total degree in the variables `vars`. See [AtomView::total_degree].
+    pub fn total_degree(&self, vars: &[Symbol]) -> Option<i64> {
+        self.as_view().total_degree(vars)
+    }
+
+    /// Factor out the rational content of the expression. See [AtomView::primitive].
+    pub fn primitive(&self) -> (Rational, Atom) {
+        self.as_view().primitive()
+    }
 }
 
 impl<'a> AtomView<'a> {
@@ -427,6 +447,129 @@ impl<'a> AtomView<'a> {
             out.set_from_view(self);
         }
     }
+
+    /// Get the maximal degree of every variable in `vars`, where all other
+    /// symbols and functions are treated as coefficients. Variables that do not
+    /// appear have degree 0.
+    ///
+    /// Returns `None` if the expression is not a polynomial in `vars`, for example
+    /// when a variable appears with a negative or symbolic exponent or in a function argument.
+    /// The expression is not expanded, so that the degree of an unexpanded expression
+    /// may be higher than that of its expanded form if terms cancel.
+    pub fn multidegree(&self, vars: &[Symbol]) -> Option<Vec<i64>> {
+        self.degree_impl(vars).map(|(d, _)| d)
+    }
+
+    /// Get the total degree in the variables `vars`, i.e., the maximal sum of the degrees
+    /// of the variables in a term. See [AtomView::multidegree] for the conventions.
+    pub fn total_degree(&self, vars: &[Symbol]) -> Option<i64> {
+        self.degree_impl(vars).map(|(_, t)| t)
+    }
+
+    /// Factor out the rational content of the expression, i.e., the positive gcd of the
+    /// coefficients of all terms, and return the content together with the primitive expression, e.g.
+    ///
+    /// ```math
+    /// primitive(6*x + 9*y + 3) = (3, 2*x + 3*y + 1)
+    /// ```
+    ///
+    /// The content of a single term is the absolute value of its coefficient.
+    /// If the expression is zero or has a coefficient that is not rational, the
+    /// content is 1.
+    pub fn primitive(&self) -> (Rational, Atom) {
+        let coeff = |t: AtomView| match t {
+            AtomView::Num(_) => t.as_rational(),
+            AtomView::Mul(m) if m.has_coefficient() => m.iter().last().unwrap().as_rational(),
+            _ => Some(Rational::one()),
+        };
+
+        let content = if let AtomView::Add(a) = self {
+            a.iter()
+                .try_fold(Rational::zero(), |g, t| Some(Q.gcd(&g, &coeff(t)?)))
+        } else {
+            coeff(*self).map(|c| c.abs())
+        };
+
+        match content {
+            Some(c) if !c.is_zero() && !c.is_one() => {
+                let inv = c.inv();
+                let p = if let AtomView::Add(a) = self {
+                    a.iter().map(|t| t.to_owned() * inv.clone()).sum()
+                } else {
+                    self.to_owned() * inv
+                };
+                (c, p)
+            }
+            _ => (Rational::one(), self.to_owned()),
+        }
+    }
+
+    fn degree_impl(&self, vars: &[Symbol]) -> Option<(Vec<i64>, i64)> {
+        match self {
+            AtomView::Num(_) => Some((vec![0; vars.len()], 0)),
+            AtomView::Var(v) => {
+                let mut d = vec![0; vars.len()];
+                if let Some(p) = vars.iter().position(|x| *x == v.get_symbol()) {
+                    d[p] = 1;
+                    Some((d, 1))
+                } else {
+                    Some((d, 0))
+                }
+            }
+            AtomView::Fun(_) => {
+                if vars.iter().any(|x| self.contains_symbol(*x)) {
+                    None
+                } else {
+                    Some((vec![0; vars.len()], 0))
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let (d, t) = base.degree_impl(vars)?;
+                if vars.iter().any(|x| exp.contains_symbol(*x)) {
+                    return None;
+                }
+
+                if t == 0 && d.iter().all(|x| *x == 0) {
+                    return Some((d, 0));
+                }
+
+                if let AtomView::Num(n) = exp {
+                    if let CoefficientView::Natural(n, 1) = n.get_coeff_view() {
+                        if n > 0 {
+                            return Some((d.into_iter().map(|x| x * n).collect(), t * n));
+                        }
+                    }
+                }
+
+                None
+            }
+            AtomView::Mul(m) => {
+                let mut d = vec![0; vars.len()];
+                let mut t = 0;
+                for a in m.iter() {
+                    let (da, ta) = a.degree_impl(vars)?;
+                    for (x, y) in d.iter_mut().zip(da) {
+                        *x += y;
+                    }
+                    t += ta;
+                }
+                Some((d, t))
+            }
+            AtomView::Add(a) => {
+                let mut d = vec![0; vars.len()];
+                let mut t = 0;
+                for a in a.iter() {
+                    let (da, ta) = a.degree_impl(vars)?;
+                    for (x, y) in d.iter_mut().zip(da) {
+                        *x = (*x).max(y);
+                    }
+                    t = t.max(ta);
+                }
+                Some((d, t))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -535,4 +678,54 @@ mod test {
 
         assert_eq!(out, ref_out);
     }
+
+    #[test]
+    fn together_apart() {
+        let input = Atom::parse("1/v1+1/v2").unwrap();
+        assert_eq!(input.together(), Atom::parse("(v1+v2)/(v1*v2)").unwrap());
+
+        // terms sharing a denominator and an integer term
+        let input = Atom::parse("v2/v1 + 3/v1 + 2").unwrap();
+        assert_eq!(input.together(), Atom::parse("(v2+3+2*v1)/v1").unwrap());
+
+        let r = Atom::parse("(v1+1)^-1+v2*(v1+1)^-2+2").unwrap().together();
+        let x = State::get_symbol("v1");
+        assert_eq!(r.apart(x).together(), r);
+    }
+
+    #[test]
+    fn multidegree() {
+        let x = State::get_symbol("v1");
+        let y = State::get_symbol("v2");
+        let z = State::get_symbol("v3");
+
+        let input = Atom::parse("v1^2*v2^3+v1").unwrap();
+        assert_eq!(input.multidegree(&[x, y]), Some(vec![2, 3]));
+        assert_eq!(input.total_degree(&[x, y]), Some(5));
+        assert_eq!(input.multidegree(&[x, y, z]), Some(vec![2, 3, 0]));
+
+        let input = Atom::parse("(v1+v3*v2)^2*f1(v3)+2^v3").unwrap();
+        assert_eq!(input.multidegree(&[x, y]), Some(vec![2, 2]));
+        assert_eq!(input.total_degree(&[x, y]), Some(2));
+
+        for input in ["v1^v3", "v1^-1+v2", "f1(v1)", "2^v1", "v1^(1/2)"] {
+            assert_eq!(Atom::parse(input).unwrap().multidegree(&[x, y]), None);
+        }
+    }
+
+    #[test]
+    fn primitive() {
+        for (input, content, primitive) in [
+            ("6*v1+9*v2+3", (3, 1), "2*v1+3*v2+1"),
+            ("3/4*v1-9/2*v2*f1(v1)", (3, 4), "v1-6*v2*f1(v1)"),
+            ("-6*v1^2", (6, 1), "-v1^2"),
+            ("-2/3", (2, 3), "-1"),
+            ("v1+2*v2", (1, 1), "v1+2*v2"),
+            ("0", (1, 1), "0"),
+        ] {
+            let (c, p) = Atom::parse(input).unwrap().primitive();
+            assert_eq!(c, content.into());
+            assert_eq!(p, Atom::parse(primitive).unwrap());
+        }
+    }
 }
@@ -1,7 +1,9 @@
 use std::{ops::DerefMut, sync::Arc};
 
+use ahash::HashMap;
+
 use crate::{
-    atom::{Atom, AtomView, FunctionBuilder, Symbol},
+    atom::{representation::FunView, Atom, AtomView, FunctionBuilder, Symbol},
     coefficient::{Coefficient, CoefficientView},
     combinatorics::CombinationWithReplacementIterator,
     domains::{atom::AtomField, integer::Integer, rational::Rational},
@@ -22,6 +24,30 @@ impl Atom {
         self.as_view().derivative_into(x, out)
     }
 
+    /// Take the derivative of the expression with respect to every variable in `vars`.
+    pub fn gradient(&self, vars: &[Symbol]) -> Vec<Atom> {
+        self.as_view().gradient(vars)
+    }
+
+    /// Compute the Jacobian matrix of `exprs` with respect to `vars`, with
+    /// one row per expression. The partial derivatives of subexpressions that occur
+    /// in several expressions are computed once.
+    pub fn jacobian(exprs: &[Atom], vars: &[Symbol]) -> Vec<Vec<Atom>> {
+        Workspace::get_local().with(|ws| {
+            let mut cache = HashMap::default();
+            exprs
+                .iter()
+                .map(|e| {
+                    e.as_view()
+                        .gradient_with_ws(vars, ws, &mut cache)
+                        .into_iter()
+                        .map(|d| d.unwrap_or_else(|| Atom::new_num(0)))
+                        .collect()
+                })
+                .collect()
+        })
+    }
+
     /// Series expand in `x` around `expansion_point` to depth `depth`.
     pub fn series(
         &self,
@@ -50,6 +76,337 @@ impl<'a> AtomView<'a> {
         Workspace::get_local().with(|ws| self.derivative_with_ws_into(x, ws, out))
     }
 
+    /// Take the derivative of the expression with respect to every variable in `vars`.
+    /// Variables that do not appear in the expression yield a zero entry.
+    ///
+    /// The expression is traversed once for all variables: the parts of the chain rule that
+    /// do not depend on the variable, such as the other factors of a product or the derivative
+    /// of a function with respect to its argument, are shared by all partial derivatives,
+    /// and the partial derivatives of a repeated subexpression are computed once.
+    pub fn gradient(&self, vars: &[Symbol]) -> Vec<Atom> {
+        Workspace::get_local().with(|ws| {
+            self.gradient_with_ws(vars, ws, &mut HashMap::default())
+                .into_iter()
+                .map(|d| d.unwrap_or_else(|| Atom::new_num(0)))
+                .collect()
+        })
+    }
+
+    /// Take the derivative with respect to every variable in `vars`, yielding `None` for
+    /// a vanishing partial derivative. See [AtomView::gradient].
+    fn gradient_with_ws(
+        &self,
+        vars: &[Symbol],
+        workspace: &Workspace,
+        cache: &mut HashMap<AtomView<'a>, Vec<Option<Atom>>>,
+    ) -> Vec<Option<Atom>> {
+        match self {
+            AtomView::Num(_) => return vec![None; vars.len()],
+            AtomView::Var(v) => {
+                return vars
+                    .iter()
+                    .map(|x| (*x == v.get_symbol()).then(|| Atom::new_num(1)))
+                    .collect();
+            }
+            _ => {}
+        }
+
+        if let Some(r) = cache.get(self) {
+            return r.clone();
+        }
+
+        let r = match self {
+            AtomView::Num(_) | AtomView::Var(_) => unreachable!(),
+            AtomView::Fun(f_orig) => {
+                let (to_derive, f, is_der) = Self::split_derivative_function(*self, *f_orig);
+
+                let args_der: Vec<_> = f
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| (i, arg.gradient_with_ws(vars, workspace, cache)))
+                    .filter(|(_, d)| d.iter().any(|x| x.is_some()))
+                    .collect();
+
+                if args_der.is_empty() {
+                    vec![None; vars.len()]
+                } else if f.get_nargs() == 1
+                    && [State::EXP, State::LOG, State::SIN, State::COS].contains(&f.get_symbol())
+                {
+                    let mut fn_der = workspace.new_atom();
+                    self.special_function_derivative(f, workspace, &mut fn_der);
+
+                    let (_, arg_der) = &args_der[0];
+                    arg_der
+                        .iter()
+                        .map(|d| {
+                            d.as_ref().map(|d| {
+                                Self::mul_normalized(fn_der.as_view(), d.as_view(), workspace)
+                            })
+                        })
+                        .collect()
+                } else {
+                    let mut tags = vec![];
+                    for (index, _) in &args_der {
+                        let mut tag = Atom::new();
+                        Self::derivative_tag(
+                            *f_orig, f, to_derive, is_der, *index, workspace, &mut tag,
+                        );
+                        tags.push(tag);
+                    }
+
+                    (0..vars.len())
+                        .map(|v| {
+                            Self::sum_normalized(
+                                args_der.iter().zip(&tags).filter_map(|((_, d), tag)| {
+                                    d[v].as_ref().map(|d| {
+                                        Self::mul_normalized(tag.as_view(), d.as_view(), workspace)
+                                    })
+                                }),
+                                workspace,
+                            )
+                        })
+                        .collect()
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let base_der = base.gradient_with_ws(vars, workspace, cache);
+                let exp_der = exp.gradient_with_ws(vars, workspace, cache);
+
+                // exp*base^(exp-1), shared by the partial derivatives of the base
+                let mut pow_factor = workspace.new_atom();
+                if base_der.iter().any(|d| d.is_some()) {
+                    let mut new_exp = workspace.new_atom();
+                    if let AtomView::Num(n) = exp {
+                        new_exp.to_num(n.get_coeff_view() + -1);
+                    } else {
+                        let ao = new_exp.to_add();
+                        ao.extend(exp);
+
+                        let mut min_one = workspace.new_atom();
+                        min_one.to_num((-1).into());
+                        ao.extend(min_one.as_view());
+                    }
+
+                    let mut pow_h = workspace.new_atom();
+                    pow_h.to_pow(base, new_exp.as_view());
+
+                    let mut mul_h = workspace.new_atom();
+                    let mul = mul_h.to_mul();
+                    mul.extend(exp);
+                    mul.extend(pow_h.as_view());
+                    mul_h.as_view().normalize(workspace, &mut pow_factor);
+                }
+
+                // base^exp*log(base), shared by the partial derivatives of the exponent
+                let mut log_factor = workspace.new_atom();
+                if exp_der.iter().any(|d| d.is_some()) {
+                    let mut log_base = workspace.new_atom();
+                    let lb = log_base.to_fun(State::LOG);
+                    lb.add_arg(base);
+
+                    let mut mul_h = workspace.new_atom();
+                    let mul = mul_h.to_mul();
+                    mul.extend(*self);
+                    mul.extend(log_base.as_view());
+                    mul_h.as_view().normalize(workspace, &mut log_factor);
+                }
+
+                base_der
+                    .iter()
+                    .zip(&exp_der)
+                    .map(|(bd, ed)| {
+                        let terms = [
+                            bd.as_ref().map(|d| {
+                                Self::mul_normalized(d.as_view(), pow_factor.as_view(), workspace)
+                            }),
+                            ed.as_ref().map(|d| {
+                                Self::mul_normalized(d.as_view(), log_factor.as_view(), workspace)
+                            }),
+                        ];
+                        Self::sum_normalized(terms.into_iter().flatten(), workspace)
+                    })
+                    .collect()
+            }
+            AtomView::Mul(args) => {
+                let mut terms: Vec<Vec<Atom>> = vec![vec![]; vars.len()];
+                for arg in args.iter() {
+                    let arg_der = arg.gradient_with_ws(vars, workspace, cache);
+                    if arg_der.iter().all(|d| d.is_none()) {
+                        continue;
+                    }
+
+                    // the product of the other factors, shared by all partial derivatives of `arg`
+                    let mut others_h = workspace.new_atom();
+                    let others = others_h.to_mul();
+                    for other_arg in args.iter() {
+                        if other_arg != arg {
+                            others.extend(other_arg);
+                        }
+                    }
+                    let mut cofactor = workspace.new_atom();
+                    others_h.as_view().normalize(workspace, &mut cofactor);
+
+                    for (t, d) in terms.iter_mut().zip(&arg_der) {
+                        if let Some(d) = d {
+                            t.push(Self::mul_normalized(
+                                d.as_view(),
+                                cofactor.as_view(),
+                                workspace,
+                            ));
+                        }
+                    }
+                }
+
+                terms
+                    .into_iter()
+                    .map(|t| Self::sum_normalized(t.into_iter(), workspace))
+                    .collect()
+            }
+            AtomView::Add(args) => {
+                let mut terms: Vec<Vec<Atom>> = vec![vec![]; vars.len()];
+                for arg in args.iter() {
+                    for (t, d) in terms
+                        .iter_mut()
+                        .zip(arg.gradient_with_ws(vars, workspace, cache))
+                    {
+                        t.extend(d);
+                    }
+                }
+
+                terms
+                    .into_iter()
+                    .map(|t| Self::sum_normalized(t.into_iter(), workspace))
+                    .collect()
+            }
+        };
+
+        cache.insert(*self, r.clone());
+        r
+    }
+
+    /// Compute the normalized product of `a` and `b`.
+    fn mul_normalized(a: AtomView, b: AtomView, workspace: &Workspace) -> Atom {
+        let mut mul_h = workspace.new_atom();
+        let mul = mul_h.to_mul();
+        mul.extend(a);
+        mul.extend(b);
+
+        let mut out = Atom::new();
+        mul_h.as_view().normalize(workspace, &mut out);
+        out
+    }
+
+    /// Compute the normalized sum of `terms`, or `None` if there are no terms.
+    fn sum_normalized(
+        mut terms: impl Iterator<Item = Atom>,
+        workspace: &Workspace,
+    ) -> Option<Atom> {
+        let first = terms.next()?;
+
+        let mut add_h = workspace.new_atom();
+        let add = add_h.to_add();
+        add.extend(first.as_view());
+        for t in terms {
+            add.extend(t.as_view());
+        }
+
+        let mut out = Atom::new();
+        add_h.as_view().normalize(workspace, &mut out);
+        Some(out)
+    }
+
+    /// If `f_orig` is a derivative function `der(n_1,...,n_k,f(...))`, return `f(...)` as the
+    /// function to derive and set the flag. Otherwise, return `f_orig` itself.
+    fn split_derivative_function(
+        view: AtomView<'a>,
+        f_orig: FunView<'a>,
+    ) -> (AtomView<'a>, FunView<'a>, bool) {
+        if f_orig.get_symbol() == State::DERIVATIVE {
+            let to_derive = f_orig.iter().last().unwrap();
+            (
+                to_derive,
+                match to_derive {
+                    AtomView::Fun(f) => f,
+                    _ => panic!("Last argument of der function must be a function"),
+                },
+                true,
+            )
+        } else {
+            (view, f_orig, false)
+        }
+    }
+
+    /// Write the derivative of the special function `f`, i.e., `exp`, `log`, `sin` or `cos`
+    /// of a single argument, with respect to its argument. `self` is `f`.
+    fn special_function_derivative(&self, f: FunView, workspace: &Workspace, fn_der: &mut Atom) {
+        match f.get_symbol() {
+            State::EXP => {
+                fn_der.set_from_view(self);
+            }
+            State::LOG => {
+                let mut n = workspace.new_atom();
+                n.to_num((-1).into());
+
+                fn_der.to_pow(f.iter().next().unwrap(), n.as_view());
+            }
+            State::SIN => {
+                let p = fn_der.to_fun(State::COS);
+                p.add_arg(f.iter().next().unwrap());
+            }
+            State::COS => {
+                let mut n = workspace.new_atom();
+                n.to_num((-1).into());
+
+                let mut sin = workspace.new_atom();
+                let sin_fun = sin.to_fun(State::SIN);
+                sin_fun.add_arg(f.iter().next().unwrap());
+
+                let m = fn_der.to_mul();
+                m.extend(sin.as_view());
+                m.extend(n.as_view());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Write the derivative function `der(n_1,...,n_k,f(...))` that tags the derivative of
+    /// `to_derive` in its argument `index`, where `f_orig` is a derivative function of
+    /// `to_derive` if `is_der` is set.
+    fn derivative_tag(
+        f_orig: FunView,
+        f: FunView,
+        to_derive: AtomView,
+        is_der: bool,
+        index: usize,
+        workspace: &Workspace,
+        fn_der: &mut Atom,
+    ) {
+        let mut n = workspace.new_atom();
+        let p = fn_der.to_fun(State::DERIVATIVE);
+
+        if is_der {
+            for (i, x_orig) in f_orig.iter().take(f.get_nargs()).enumerate() {
+                if let AtomView::Num(nn) = x_orig {
+                    let num = nn.get_coeff_view() + (if i == index { 1 } else { 0 });
+                    n.to_num(num);
+                    p.add_arg(n.as_view());
+                } else {
+                    panic!(
+                        "Derivative function must contain numbers for all but the last position"
+                    );
+                }
+            }
+        } else {
+            for i in 0..f.get_nargs() {
+                n.to_num((if i == index { 1 } else { 0 }, 1).into());
+                p.add_arg(n.as_view());
+            }
+        }
+
+        p.add_arg(to_derive);
+    }
+
     /// Take a derivative of the expression with respect to `x` and
     /// write the result in `out`.
     /// Returns `true` if the derivative is non-zero.
@@ -78,19 +435,7 @@ impl<'a> AtomView<'a> {
                 // detect if the function to derive is the derivative function itself
                 // if so, derive the last argument of the derivative function and set
                 // a flag to later accumulate previous derivatives
-                let (to_derive, f, is_der) = if f_orig.get_symbol() == State::DERIVATIVE {
-                    let to_derive = f_orig.iter().last().unwrap();
-                    (
-                        to_derive,
-                        match to_derive {
-                            AtomView::Fun(f) => f,
-                            _ => panic!("Last argument of der function must be a function"),
-                        },
-                        true,
-                    )
-                } else {
-                    (*self, *f_orig, false)
-                };
+                let (to_derive, f, is_der) = Self::split_derivative_function(*self, *f_orig);
 
                 // take derivative of all the arguments and store it in a list
                 let mut args_der = Vec::with_capacity(f.get_nargs());
@@ -111,34 +456,7 @@ impl<'a> AtomView<'a> {
                     && [State::EXP, State::LOG, State::SIN, State::COS].contains(&f.get_symbol())
                 {
                     let mut fn_der = workspace.new_atom();
-                    match f.get_symbol() {
-                        State::EXP => {
-                            fn_der.set_from_view(self);
-                        }
-                        State::LOG => {
-                            let mut n = workspace.new_atom();
-                            n.to_num((-1).into());
-
-                            fn_der.to_pow(f.iter().next().unwrap(), n.as_view());
-                        }
-                        State::SIN => {
-                            let p = fn_der.to_fun(State::COS);
-                            p.add_arg(f.iter().next().unwrap());
-                        }
-                        State::COS => {
-                            let mut n = workspace.new_atom();
-                            n.to_num((-1).into());
-
-                            let mut sin = workspace.new_atom();
-                            let sin_fun = sin.to_fun(State::SIN);
-                            sin_fun.add_arg(f.iter().next().unwrap());
-
-                            let m = fn_der.to_mul();
-                            m.extend(sin.as_view());
-                            m.extend(n.as_view());
-                        }
-                        _ => unreachable!(),
-                    }
+                    self.special_function_derivative(f, workspace, &mut fn_der);
 
                     let (_, mut arg_der) = args_der.pop().unwrap();
                     if let Atom::Mul(m) = arg_der.deref_mut() {
@@ -159,29 +477,17 @@ impl<'a> AtomView<'a> {
                 let mut add = workspace.new_atom();
                 let a = add.to_add();
                 let mut fn_der = workspace.new_atom();
-                let mut n = workspace.new_atom();
                 let mut mul = workspace.new_atom();
                 for (index, arg_der) in args_der {
-                    let p = fn_der.to_fun(State::DERIVATIVE);
-
-                    if is_der {
-                        for (i, x_orig) in f_orig.iter().take(f.get_nargs()).enumerate() {
-                            if let AtomView::Num(nn) = x_orig {
-                                let num = nn.get_coeff_view() + (if i == index { 1 } else { 0 });
-                                n.to_num(num);
-                                p.add_arg(n.as_view());
-                            } else {
-                                panic!("Derivative function must contain numbers for all but the last position");
-                            }
-                        }
-                    } else {
-                        for i in 0..f.get_nargs() {
-                            n.to_num((if i == index { 1 } else { 0 }, 1).into());
-                            p.add_arg(n.as_view());
-                        }
-                    }
-
-                    p.add_arg(to_derive);
+                    Self::derivative_tag(
+                        *f_orig,
+                        f,
+                        to_derive,
+                        is_der,
+                        index,
+                        workspace,
+                        &mut fn_der,
+                    );
 
                     let m = mul.to_mul();
                     m.extend(fn_der.as_view());
@@ -630,6 +936,24 @@ mod test {
         assert_eq!(t, Atom::parse("v1^-10").unwrap())
     }
 
+    #[test]
+    fn series_laurent() {
+        let v1 = State::get_symbol("v1");
+        let input = Atom::parse("1/sin(v1)").unwrap();
+
+        let t = input
+            .series(v1, Atom::new_num(0).as_view(), 4.into())
+            .unwrap();
+
+        assert_eq!(t.to_atom(), Atom::parse("v1^-1+1/6*v1+7/360*v1^3").unwrap());
+
+        let t2 = Atom::parse("1/sin(v1)+v1^(1/2)")
+            .unwrap()
+            .series(v1, Atom::new_num(0).as_view(), 2.into())
+            .unwrap();
+        assert_eq!(t2.to_atom(), Atom::parse("v1^-1+v1^(1/2)+1/6*v1").unwrap());
+    }
+
     #[test]
     fn series_user_function() {
         let v1 = State::get_symbol("v1");
@@ -661,4 +985,29 @@ mod test {
 
         assert_eq!(t.to_atom().expand(), Atom::parse("v1^8*exp(1)",).unwrap());
     }
+
+    #[test]
+    fn gradient() {
+        let (v1, v2, v3) = (
+            State::get_symbol("v1"),
+            State::get_symbol("v2"),
+            State::get_symbol("v3"),
+        );
+
+        let input = Atom::parse("v1^2 + v1*v2").unwrap();
+        let r = input.gradient(&[v1, v2, v3]);
+        let res = ["2*v1+v2", "v1", "0"].map(|x| Atom::parse(x).unwrap());
+        assert_eq!(r, res);
+
+        let j = Atom::jacobian(&[input, Atom::parse("v2*v3").unwrap()], &[v1, v2, v3]);
+        assert_eq!(j[1], ["0", "v3", "v2"].map(|x| Atom::parse(x).unwrap()));
+
+        // the shared computation agrees with taking every derivative separately
+        let input = Atom::parse(
+            "(1+2*v1)^(5+v2)+log(2*v1*v2)*exp(v1+v2)+sin(v1^2)*cos(v2)+f(v1,v2^2)+der(0,1,f(v1,v1*v2))+sin(v1^2)^2",
+        )
+        .unwrap();
+        let r = input.gradient(&[v1, v2, v3]);
+        assert_eq!(r, [v1, v2, v3].map(|x| input.derivative(x)));
+    }
 }
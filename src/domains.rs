@@ -3,7 +3,9 @@ pub mod atom;
 pub mod factorized_rational_polynomial;
 pub mod finite_field;
 pub mod float;
+pub mod galois_field;
 pub mod integer;
+pub mod lazy_rational;
 pub mod rational;
 pub mod rational_polynomial;
 
@@ -1099,28 +1099,9 @@ impl Field for FiniteField<Integer> {
     fn inv(&self, a: &Self::Element) -> Self::Element {
         assert!(!a.is_zero(), "0 is not invertible");
 
-        let mut u1 = Integer::one();
-        let mut u3 = a.clone();
-        let mut v1 = Integer::zero();
-        let mut v3 = self.get_prime();
-        let mut even_iter: bool = true;
-
-        while !v3.is_zero() {
-            let (q, t3) = Z.quot_rem(&u3, &v3);
-            let t1 = &u1 + &(&q * &v1);
-            u1 = v1;
-            v1 = t1;
-            u3 = v3;
-            v3 = t3;
-            even_iter = !even_iter;
-        }
-
-        assert!(u3.is_one(), "{} is not invertible mod {}", a, self.p);
-        if even_iter {
-            u1
-        } else {
-            &self.p - &u1
-        }
+        let (g, s, _) = a.extended_gcd(&self.p);
+        assert!(g.is_one(), "{} is not invertible mod {}", a, self.p);
+        s.symmetric_mod(&self.p)
     }
 }
 
@@ -0,0 +1,474 @@
+use std::fmt::{Display, Error, Formatter, Write};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::printer::PrintOptions;
+
+use super::{finite_field::is_prime_u64, EuclideanDomain, Field, Ring};
+
+/// The finite field `GF(p^k)`, represented as the polynomials over `Z_p`
+/// modulo a monic irreducible polynomial of degree `k`.
+///
+/// Unlike [FiniteField](super::finite_field::FiniteField), the characteristic `p`
+/// may be 2.
+///
+/// A field that is registered with [State::get_or_insert_galois_field](crate::state::State::get_or_insert_galois_field)
+/// has its defining polynomial recorded in the exported state, and its elements can be
+/// written in packed form with [write_packed_galois_field](crate::atom::write_packed_galois_field).
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct GaloisField {
+    p: u64,
+    /// The coefficients of the defining polynomial, from low to high degree.
+    modulus: Vec<u64>,
+}
+
+/// An element of a [GaloisField], stored as its coefficients in the
+/// generator from low to high degree, without trailing zeros.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct GaloisFieldElement(Vec<u64>);
+
+impl GaloisFieldElement {
+    /// Get the coefficients in the generator, from low to high degree.
+    pub fn coefficients(&self) -> &[u64] {
+        &self.0
+    }
+}
+
+impl GaloisField {
+    /// Create the field `GF(p^k)` from a prime `p` and a defining polynomial of degree `k`,
+    /// whose coefficients are given from low to high degree. The polynomial is made monic.
+    ///
+    /// Returns an error if `p` is not a prime or if the polynomial is not irreducible over `Z_p`.
+    pub fn new(p: u64, modulus: &[u64]) -> Result<GaloisField, String> {
+        if !is_prime_u64(p) {
+            return Err(format!("{} is not a prime", p));
+        }
+
+        let mut modulus: Vec<_> = modulus.iter().map(|c| c % p).collect();
+        trim(&mut modulus);
+        if modulus.len() < 2 {
+            return Err("The defining polynomial must have a positive degree".to_owned());
+        }
+
+        let inv_lc = inv_mod(*modulus.last().unwrap(), p);
+        for c in &mut modulus {
+            *c = mul_mod(*c, inv_lc, p);
+        }
+
+        let field = GaloisField { p, modulus };
+        if !field.is_irreducible() {
+            return Err(format!(
+                "The defining polynomial {} is reducible",
+                field.printer(&GaloisFieldElement(field.modulus.clone()))
+            ));
+        }
+
+        Ok(field)
+    }
+
+    /// Get the characteristic `p` of the field.
+    pub fn get_prime(&self) -> u64 {
+        self.p
+    }
+
+    /// Get the degree `k` of the field over `Z_p`.
+    pub fn get_degree(&self) -> usize {
+        self.modulus.len() - 1
+    }
+
+    /// Get the monic defining polynomial, with coefficients from low to high degree.
+    pub fn get_modulus(&self) -> &[u64] {
+        &self.modulus
+    }
+
+    /// Create an element from its coefficients in the generator, from low to high degree.
+    pub fn to_element(&self, coeffs: &[u64]) -> GaloisFieldElement {
+        let mut a: Vec<_> = coeffs.iter().map(|c| c % self.p).collect();
+        self.reduce(&mut a);
+        GaloisFieldElement(a)
+    }
+
+    /// Get the generator of the field, i.e., the root of the defining polynomial.
+    pub fn generator(&self) -> GaloisFieldElement {
+        self.to_element(&[0, 1])
+    }
+
+    /// Reduce `a` modulo the defining polynomial.
+    fn reduce(&self, a: &mut Vec<u64>) {
+        poly_rem(a, &self.modulus, self.p);
+    }
+
+    /// Apply the Frobenius map `a -> a^p`.
+    fn frobenius(&self, a: &GaloisFieldElement) -> GaloisFieldElement {
+        self.pow(a, self.p)
+    }
+
+    /// Rabin's irreducibility test: a polynomial `f` of degree `k` is irreducible
+    /// iff `x^(p^k) = x mod f` and `gcd(x^(p^(k/q)) - x, f) = 1` for every prime `q | k`.
+    fn is_irreducible(&self) -> bool {
+        let k = self.get_degree();
+        let x = self.generator();
+
+        let mut powers = vec![x.clone()];
+        for _ in 0..k {
+            let next = self.frobenius(powers.last().unwrap());
+            powers.push(next);
+        }
+
+        if powers[k] != x {
+            return false;
+        }
+
+        let mut n = k;
+        let mut q = 2;
+        while n > 1 {
+            if n % q == 0 {
+                while n % q == 0 {
+                    n /= q;
+                }
+
+                let h = self.sub(&powers[k / q], &x);
+                if poly_gcd(h.0, self.modulus.clone(), self.p).len() != 1 {
+                    return false;
+                }
+            }
+            q += 1;
+        }
+
+        true
+    }
+}
+
+fn trim(a: &mut Vec<u64>) {
+    while a.last() == Some(&0) {
+        a.pop();
+    }
+}
+
+#[inline]
+fn mul_mod(a: u64, b: u64, p: u64) -> u64 {
+    ((a as u128 * b as u128) % p as u128) as u64
+}
+
+#[inline]
+fn add_mod(a: u64, b: u64, p: u64) -> u64 {
+    ((a as u128 + b as u128) % p as u128) as u64
+}
+
+#[inline]
+fn sub_mod(a: u64, b: u64, p: u64) -> u64 {
+    if a >= b {
+        a - b
+    } else {
+        p - (b - a)
+    }
+}
+
+fn pow_mod(mut b: u64, mut e: u64, p: u64) -> u64 {
+    let mut r = 1 % p;
+    while e > 0 {
+        if e & 1 == 1 {
+            r = mul_mod(r, b, p);
+        }
+        b = mul_mod(b, b, p);
+        e >>= 1;
+    }
+    r
+}
+
+fn inv_mod(a: u64, p: u64) -> u64 {
+    pow_mod(a, p - 2, p)
+}
+
+/// Replace `a` by the remainder of the division by the non-zero polynomial `b`.
+fn poly_rem(a: &mut Vec<u64>, b: &[u64], p: u64) {
+    trim(a);
+    let db = b.len() - 1;
+    let inv_lc = inv_mod(b[db], p);
+    while a.len() > db {
+        let shift = a.len() - 1 - db;
+        let c = mul_mod(*a.last().unwrap(), inv_lc, p);
+        for (i, bc) in b.iter().enumerate() {
+            a[shift + i] = sub_mod(a[shift + i], mul_mod(c, *bc, p), p);
+        }
+        trim(a);
+    }
+}
+
+/// Compute the monic gcd of two polynomials.
+fn poly_gcd(mut a: Vec<u64>, mut b: Vec<u64>, p: u64) -> Vec<u64> {
+    trim(&mut a);
+    trim(&mut b);
+    while !b.is_empty() {
+        poly_rem(&mut a, &b, p);
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    if let Some(lc) = a.last() {
+        let inv_lc = inv_mod(*lc, p);
+        for c in &mut a {
+            *c = mul_mod(*c, inv_lc, p);
+        }
+    }
+    a
+}
+
+impl Display for GaloisField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GF({}^{})", self.p, self.get_degree())
+    }
+}
+
+impl Ring for GaloisField {
+    type Element = GaloisFieldElement;
+
+    fn add(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        let (long, short) = if a.0.len() >= b.0.len() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+
+        let mut r = long.0.clone();
+        for (rc, c) in r.iter_mut().zip(&short.0) {
+            *rc = add_mod(*rc, *c, self.p);
+        }
+        trim(&mut r);
+        GaloisFieldElement(r)
+    }
+
+    fn sub(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        self.add(a, &self.neg(b))
+    }
+
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        if a.0.is_empty() || b.0.is_empty() {
+            return self.zero();
+        }
+
+        let mut r = vec![0; a.0.len() + b.0.len() - 1];
+        for (i, ac) in a.0.iter().enumerate() {
+            for (j, bc) in b.0.iter().enumerate() {
+                r[i + j] = add_mod(r[i + j], mul_mod(*ac, *bc, self.p), self.p);
+            }
+        }
+        self.reduce(&mut r);
+        GaloisFieldElement(r)
+    }
+
+    fn add_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.add(a, b);
+    }
+
+    fn sub_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.sub(a, b);
+    }
+
+    fn mul_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.mul(a, b);
+    }
+
+    fn add_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        self.add_assign(a, &self.mul(b, c));
+    }
+
+    fn sub_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        self.sub_assign(a, &self.mul(b, c));
+    }
+
+    fn neg(&self, a: &Self::Element) -> Self::Element {
+        GaloisFieldElement(a.0.iter().map(|c| sub_mod(0, *c, self.p)).collect())
+    }
+
+    fn zero(&self) -> Self::Element {
+        GaloisFieldElement(vec![])
+    }
+
+    fn one(&self) -> Self::Element {
+        GaloisFieldElement(vec![1])
+    }
+
+    fn nth(&self, n: u64) -> Self::Element {
+        self.to_element(&[n])
+    }
+
+    fn pow(&self, b: &Self::Element, mut e: u64) -> Self::Element {
+        let mut r = self.one();
+        let mut b = b.clone();
+        while e > 0 {
+            if e & 1 == 1 {
+                r = self.mul(&r, &b);
+            }
+            b = self.mul(&b, &b);
+            e >>= 1;
+        }
+        r
+    }
+
+    fn is_zero(a: &Self::Element) -> bool {
+        a.0.is_empty()
+    }
+
+    fn is_one(&self, a: &Self::Element) -> bool {
+        a.0 == [1]
+    }
+
+    fn one_is_gcd_unit() -> bool {
+        true
+    }
+
+    fn is_characteristic_zero(&self) -> bool {
+        false
+    }
+
+    fn sample(&self, rng: &mut impl rand::RngCore, range: (i64, i64)) -> Self::Element {
+        let coeffs: Vec<_> = (0..self.get_degree())
+            .map(|_| rng.gen_range(range.0..range.1).rem_euclid(self.p as i64) as u64)
+            .collect();
+        self.to_element(&coeffs)
+    }
+
+    fn fmt_display(
+        &self,
+        element: &Self::Element,
+        _opts: &PrintOptions,
+        in_product: bool,
+        f: &mut Formatter<'_>,
+    ) -> Result<(), Error> {
+        if element.0.is_empty() {
+            return f.write_char('0');
+        }
+
+        let nterms = element.0.iter().filter(|c| **c != 0).count();
+        if in_product && nterms > 1 {
+            f.write_char('(')?;
+        }
+
+        let mut first = true;
+        for (i, c) in element.0.iter().enumerate().rev() {
+            if *c == 0 {
+                continue;
+            }
+
+            if !first {
+                f.write_char('+')?;
+            }
+            first = false;
+
+            match (i, *c) {
+                (0, c) => write!(f, "{}", c)?,
+                (1, 1) => f.write_char('x')?,
+                (1, c) => write!(f, "{}*x", c)?,
+                (i, 1) => write!(f, "x^{}", i)?,
+                (i, c) => write!(f, "{}*x^{}", c, i)?,
+            }
+        }
+
+        if in_product && nterms > 1 {
+            f.write_char(')')?;
+        }
+
+        Ok(())
+    }
+}
+
+impl EuclideanDomain for GaloisField {
+    fn rem(&self, _a: &Self::Element, _b: &Self::Element) -> Self::Element {
+        self.zero()
+    }
+
+    fn quot_rem(&self, a: &Self::Element, b: &Self::Element) -> (Self::Element, Self::Element) {
+        (self.div(a, b), self.zero())
+    }
+
+    fn gcd(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        if Self::is_zero(a) && Self::is_zero(b) {
+            self.zero()
+        } else {
+            self.one()
+        }
+    }
+}
+
+impl Field for GaloisField {
+    fn div(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        self.mul(a, &self.inv(b))
+    }
+
+    fn div_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.div(a, b);
+    }
+
+    /// Compute the inverse using the extended Euclidean algorithm in `Z_p[x]`.
+    fn inv(&self, a: &Self::Element) -> Self::Element {
+        if a.0.is_empty() {
+            panic!("Division by zero");
+        }
+
+        let p = self.p;
+
+        // invariant: s * a = r mod f
+        let (mut r0, mut r1) = (self.modulus.clone(), a.0.clone());
+        let (mut s0, mut s1) = (vec![], vec![1]);
+
+        while r1.len() > 1 {
+            // compute the quotient of r0 / r1
+            let inv_lc = inv_mod(*r1.last().unwrap(), p);
+            let mut q = vec![0; r0.len() - r1.len() + 1];
+            while r0.len() >= r1.len() {
+                let shift = r0.len() - r1.len();
+                let c = mul_mod(*r0.last().unwrap(), inv_lc, p);
+                q[shift] = c;
+                for (i, bc) in r1.iter().enumerate() {
+                    r0[shift + i] = sub_mod(r0[shift + i], mul_mod(c, *bc, p), p);
+                }
+                trim(&mut r0);
+            }
+
+            let qs = self.mul(&GaloisFieldElement(q), &GaloisFieldElement(s1.clone()));
+            let s = self.sub(&GaloisFieldElement(s0), &qs).0;
+
+            s0 = std::mem::replace(&mut s1, s);
+            std::mem::swap(&mut r0, &mut r1);
+        }
+
+        // r1 is a non-zero constant, since the modulus is irreducible
+        let inv_c = inv_mod(r1[0], p);
+        GaloisFieldElement(s1.iter().map(|c| mul_mod(*c, inv_c, p)).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domains::{Field, Ring};
+
+    use super::GaloisField;
+
+    #[test]
+    fn gf8() {
+        // GF(2^3) = Z_2[x] / (x^3 + x + 1)
+        let f = GaloisField::new(2, &[1, 1, 0, 1]).unwrap();
+        let x = f.generator();
+
+        assert_eq!(f.pow(&x, 3), f.to_element(&[1, 1]));
+        assert_eq!(f.pow(&x, 7), f.one());
+        assert_eq!(f.add(&x, &x), f.zero());
+        assert_eq!(f.mul(&x, &f.to_element(&[1, 0, 1])), f.one());
+
+        // every non-zero element has an inverse
+        for i in 1..8u64 {
+            let a = f.to_element(&[i & 1, (i >> 1) & 1, (i >> 2) & 1]);
+            assert!(f.is_one(&f.mul(&a, &f.inv(&a))));
+        }
+
+        assert_eq!(
+            format!("{}", f.printer(&f.to_element(&[1, 1, 1]))),
+            "x^2+x+1"
+        );
+
+        // x^3 + 1 = (x + 1)(x^2 + x + 1) is reducible
+        assert!(GaloisField::new(2, &[1, 0, 0, 1]).is_err());
+        assert!(GaloisField::new(4, &[1, 1, 0, 1]).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     cmp::Ordering,
     fmt::{Display, Error, Formatter},
+    hash::{Hash, Hasher},
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, Sub, SubAssign},
     str::FromStr,
 };
@@ -51,7 +52,10 @@ impl IntegerRing {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+/// An integer. The same value may be stored in different variants,
+/// for example as `Natural(5)` or `Large(5)`. Equality and hashing
+/// compare by value, so that `a == b` implies `hash(a) == hash(b)`.
+#[derive(Clone, Eq)]
 pub enum Integer {
     Natural(i64),
     Double(i128),
@@ -289,6 +293,82 @@ impl Integer {
         Self::from_large(MultiPrecisionInteger::from_f64(f).unwrap())
     }
 
+    /// Parse an integer in base `radix`, which must be between 2 and 36.
+    /// The digits may be uppercase or lowercase and may be preceded by a sign.
+    pub fn from_radix_str(s: &str, radix: u32) -> Result<Integer, &'static str> {
+        if !(2..=36).contains(&radix) {
+            return Err("Radix must be between 2 and 36");
+        }
+
+        if let Ok(n) = i64::from_str_radix(s, radix) {
+            return Ok(Integer::Natural(n));
+        }
+
+        if let Ok(n) = MultiPrecisionInteger::from_str_radix(s, radix as i32) {
+            Ok(Self::from_large(n))
+        } else {
+            Err("Could not parse integer")
+        }
+    }
+
+    /// Write the integer in base `radix`, using lowercase letters for digits above 9.
+    ///
+    /// Panics if `radix` is not between 2 and 36.
+    pub fn to_radix_string(&self, radix: u32) -> String {
+        if !(2..=36).contains(&radix) {
+            panic!("Radix must be between 2 and 36, got {}", radix);
+        }
+
+        const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+        // write the digits of `n` in reverse order, padded with zeros to `width` digits
+        let write_digits = |mut n: u128, width: usize, out: &mut Vec<u8>| {
+            let start = out.len();
+            while n > 0 {
+                out.push(DIGITS[(n % radix as u128) as usize]);
+                n /= radix as u128;
+            }
+            while out.len() - start < width {
+                out.push(b'0');
+            }
+        };
+
+        let mut digits = vec![];
+        let negative = match self {
+            Integer::Natural(n) => {
+                write_digits(n.unsigned_abs() as u128, 1, &mut digits);
+                *n < 0
+            }
+            Integer::Double(n) => {
+                write_digits(n.unsigned_abs(), 1, &mut digits);
+                *n < 0
+            }
+            Integer::Large(n) => {
+                // split off chunks of `width` digits that fit in a u32
+                let (mut chunk, mut width) = (radix, 1);
+                while let Some(c) = chunk.checked_mul(radix) {
+                    chunk = c;
+                    width += 1;
+                }
+
+                let mut abs = n.clone().abs();
+                while abs.to_u128().is_none() {
+                    let r = abs.mod_u(chunk);
+                    abs /= chunk;
+                    write_digits(r as u128, width, &mut digits);
+                }
+                write_digits(abs.to_u128().unwrap(), 1, &mut digits);
+                n.is_negative()
+            }
+        };
+
+        if negative {
+            digits.push(b'-');
+        }
+        digits.reverse();
+        String::from_utf8(digits).unwrap()
+    }
+
     pub fn to_rational(&self) -> Rational {
         match self {
             Integer::Natural(n) => Rational::Natural(*n, 1),
@@ -332,6 +412,66 @@ impl Integer {
         Integer::Natural(1)
     }
 
+    /// Add two integers without promoting to a larger representation.
+    /// Returns `None` if an argument or the result does not fit in an `i64`.
+    #[inline]
+    pub fn checked_add(&self, other: &Integer) -> Option<Integer> {
+        match (self, other) {
+            (Integer::Natural(n1), Integer::Natural(n2)) => {
+                n1.checked_add(*n2).map(Integer::Natural)
+            }
+            _ => None,
+        }
+    }
+
+    /// Multiply two integers without promoting to a larger representation.
+    /// Returns `None` if an argument or the result does not fit in an `i64`.
+    #[inline]
+    pub fn checked_mul(&self, other: &Integer) -> Option<Integer> {
+        match (self, other) {
+            (Integer::Natural(n1), Integer::Natural(n2)) => {
+                n1.checked_mul(*n2).map(Integer::Natural)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns an error if a number of `bits` bits exceeds `max_bits` bits.
+    #[inline]
+    pub(crate) fn check_bit_length(bits: u64, max_bits: u64) -> Result<(), String> {
+        if bits > max_bits {
+            Err(format!(
+                "Coefficient of {} bits exceeds the maximum of {} bits",
+                bits, max_bits
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Add two integers, or return an error if the result may have more than `max_bits` bits.
+    /// The check uses the bit lengths of the arguments and is done before the result is allocated.
+    pub fn try_add(&self, other: &Integer, max_bits: u64) -> Result<Integer, String> {
+        Integer::check_bit_length(self.bit_length().max(other.bit_length()) + 1, max_bits)?;
+        Ok(self + other)
+    }
+
+    /// Multiply two integers, or return an error if the result may have more than `max_bits` bits.
+    /// The check uses the bit lengths of the arguments and is done before the result is allocated.
+    pub fn try_mul(&self, other: &Integer, max_bits: u64) -> Result<Integer, String> {
+        Integer::check_bit_length(self.bit_length() + other.bit_length(), max_bits)?;
+        Ok(self * other)
+    }
+
+    /// Compute `self^e`, or return an error if the result has more than `max_bits` bits.
+    /// The check is done before the result is allocated.
+    pub fn try_pow(&self, e: u64, max_bits: u64) -> Result<Integer, String> {
+        if e > 1 && !self.is_zero() && !self.is_one() && self != &Integer::Natural(-1) {
+            Integer::check_bit_length(self.pow_bit_length(e), max_bits)?;
+        }
+        Ok(self.pow(e))
+    }
+
     #[inline]
     pub fn to_i64(&self) -> Option<i64> {
         match self {
@@ -398,6 +538,23 @@ impl Integer {
         }
     }
 
+    /// Compute `n! mod modulus`, where all intermediate results are reduced.
+    /// The result lies in `[0, modulus)`. Panics if `modulus` is not positive.
+    pub fn factorial_mod(n: u64, modulus: &Integer) -> Integer {
+        if modulus <= &Integer::zero() {
+            panic!("Modulus must be positive: {}", modulus);
+        }
+
+        let mut f = Z.quot_rem(&Integer::one(), modulus).1;
+        for x in 2..=n {
+            if f.is_zero() {
+                break;
+            }
+            f = Z.quot_rem(&(&f * &Integer::from(x)), modulus).1;
+        }
+        f
+    }
+
     /// Compute the binomial coefficient `(n k) = n!/(k!(n-k)!)`.
     ///
     /// The implementation does not to overflow.
@@ -434,6 +591,28 @@ impl Integer {
         mcr
     }
 
+    /// Get the number of bits of the absolute value of the integer.
+    pub fn bit_length(&self) -> u64 {
+        match self {
+            Integer::Natural(n) => (u64::BITS - n.unsigned_abs().leading_zeros()) as u64,
+            Integer::Double(n) => (u128::BITS - n.unsigned_abs().leading_zeros()) as u64,
+            Integer::Large(r) => r.significant_bits_64(),
+        }
+    }
+
+    /// Get the number of bits of the absolute value of `self^e`, without computing the power.
+    pub(crate) fn pow_bit_length(&self, e: u64) -> u64 {
+        let log2 = match self {
+            Integer::Natural(n) => (n.unsigned_abs() as f64).log2(),
+            Integer::Double(n) => (n.unsigned_abs() as f64).log2(),
+            Integer::Large(r) => {
+                let (m, exp) = r.to_f64_exp();
+                m.abs().log2() + exp as f64
+            }
+        };
+        (log2 * e as f64).floor() as u64 + 1
+    }
+
     pub fn pow(&self, e: u64) -> Integer {
         if e > u32::MAX as u64 {
             panic!("Power of exponentation is larger than 2^32: {}", e);
@@ -585,28 +764,70 @@ impl Integer {
         }
     }
 
-    /// Compute the modular inverse of `self` in the ring with size `n`.
-    /// `self` and `n` must be coprime.
-    pub fn mod_inverse(&self, n: &Integer) -> Integer {
-        let mut t0 = Integer::zero();
-        let mut t1 = Integer::one();
-        let mut r0 = n.clone();
-        let mut r1 = self.clone();
+    /// Compute `self^exp mod modulus` using binary exponentiation, where all
+    /// intermediate results are reduced. The result lies in `[0, modulus)`,
+    /// also for a negative base. Panics if `modulus` is not positive or if
+    /// `exp` is negative.
+    pub fn pow_mod(&self, exp: &Integer, modulus: &Integer) -> Integer {
+        if modulus <= &Integer::zero() {
+            panic!("Modulus must be positive: {}", modulus);
+        }
+        if exp.is_negative() {
+            panic!("Exponent must be non-negative: {}", exp);
+        }
+
+        let two = Integer::Natural(2);
+        let mut base = Z.quot_rem(self, modulus).1;
+        let mut exp = exp.clone();
+        let mut res = Z.quot_rem(&Integer::one(), modulus).1;
+        while !exp.is_zero() {
+            let (q, r) = Z.quot_rem(&exp, &two);
+            if !r.is_zero() {
+                res = Z.quot_rem(&(&res * &base), modulus).1;
+            }
+            exp = q;
+            if !exp.is_zero() {
+                base = Z.quot_rem(&(&base * &base), modulus).1;
+            }
+        }
+        res
+    }
+
+    /// Compute `(g, s, t)` such that `s * self + t * other = g`, where `g` is the
+    /// non-negative greatest common divisor of `self` and `other`, using the
+    /// extended Euclidean algorithm. If both integers are zero, `(0, 0, 0)` is returned.
+    pub fn extended_gcd(&self, other: &Integer) -> (Integer, Integer, Integer) {
+        if self.is_zero() && other.is_zero() {
+            return (Integer::zero(), Integer::zero(), Integer::zero());
+        }
+
+        let (mut r0, mut r1) = (self.clone(), other.clone());
+        let (mut s0, mut s1) = (Integer::one(), Integer::zero());
+        let (mut t0, mut t1) = (Integer::zero(), Integer::one());
 
         while !r1.is_zero() {
             let (q, r) = Z.quot_rem(&r0, &r1);
-            (t1, t0) = (&t0 - &(&q * &t1), t1);
             (r1, r0) = (r, r1);
+            (s1, s0) = (&s0 - &(&q * &s1), s1);
+            (t1, t0) = (&t0 - &(&q * &t1), t1);
         }
 
-        if r0 > Integer::one() {
-            panic!("{} is not invertible in ring {}", self, n);
+        if r0.is_negative() {
+            (-&r0, -&s0, -&t0)
+        } else {
+            (r0, s0, t0)
         }
-        if t0.is_negative() {
-            t0 += n;
+    }
+
+    /// Compute the modular inverse of `self` in the ring with size `n`.
+    /// `self` and `n` must be coprime.
+    pub fn mod_inverse(&self, n: &Integer) -> Integer {
+        let (g, s, _) = self.extended_gcd(n);
+        if !g.is_one() {
+            panic!("{} is not invertible in ring {}", self, n);
         }
 
-        t0
+        Z.quot_rem(&s, n).1
     }
 }
 
@@ -648,6 +869,44 @@ impl Ord for Integer {
     }
 }
 
+impl PartialEq for Integer {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Integer::Natural(n1), Integer::Natural(n2)) => n1 == n2,
+            (Integer::Double(n1), Integer::Double(n2)) => n1 == n2,
+            (Integer::Large(n1), Integer::Large(n2)) => n1 == n2,
+            _ => self.cmp(other) == Ordering::Equal,
+        }
+    }
+}
+
+impl Hash for Integer {
+    /// Hash the value in its smallest representation, so that the hash is
+    /// independent of the variant.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Integer::Natural(n) => n.hash(state),
+            Integer::Double(n) => {
+                if let Ok(n) = i64::try_from(*n) {
+                    n.hash(state)
+                } else {
+                    n.hash(state)
+                }
+            }
+            Integer::Large(l) => {
+                if let Some(n) = l.to_i64() {
+                    n.hash(state)
+                } else if let Some(n) = l.to_i128() {
+                    n.hash(state)
+                } else {
+                    l.hash(state)
+                }
+            }
+        }
+    }
+}
+
 impl Ring for IntegerRing {
     type Element = Integer;
 
@@ -1567,3 +1826,170 @@ impl EuclideanDomain for MultiPrecisionIntegerRing {
         a.clone().gcd(b)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::domains::{rational::Rational, EuclideanDomain};
+
+    use super::{Integer, Z};
+
+    #[test]
+    fn extended_gcd() {
+        let large = Integer::from(3).pow(100) * &Integer::from(7);
+        for (a, b) in [
+            (Integer::from(240), Integer::from(46)),
+            (Integer::from(-240), Integer::from(46)),
+            (Integer::from(17), Integer::from(-5)),
+            (Integer::from(0), Integer::from(-5)),
+            (Integer::from(6), Integer::from(0)),
+            (large.clone(), Integer::from(3).pow(50) * &Integer::from(11)),
+            (-&large, Integer::from(2).pow(70) + &Integer::one()),
+        ] {
+            let (g, s, t) = a.extended_gcd(&b);
+            assert_eq!(g, a.gcd(&b).abs());
+            assert_eq!(&(&s * &a) + &(&t * &b), g);
+        }
+
+        assert_eq!(
+            Integer::from(240).extended_gcd(&Integer::from(46)),
+            (2.into(), (-9).into(), 47.into())
+        );
+        let zero = Integer::zero();
+        assert_eq!(zero.extended_gcd(&zero), (0.into(), 0.into(), 0.into()));
+
+        assert_eq!(Integer::from(3).mod_inverse(&Integer::from(7)), 5.into());
+        assert_eq!(Integer::from(-3).mod_inverse(&Integer::from(7)), 2.into());
+    }
+
+    #[test]
+    fn checked_ops() {
+        let max = Integer::Natural(i64::MAX);
+        let two = Integer::Natural(2);
+
+        assert_eq!(max.checked_mul(&two), None);
+        assert_eq!(&max * &two, Integer::from(i64::MAX as i128 * 2));
+        assert_eq!(max.checked_add(&Integer::one()), None);
+        assert_eq!(&max + &Integer::one(), Integer::from(i64::MAX as i128 + 1));
+
+        assert_eq!(two.checked_mul(&two), Some(Integer::Natural(4)));
+        assert_eq!(two.checked_add(&two), Some(Integer::Natural(4)));
+
+        // a number outside the machine range is never used
+        let large = &max * &two;
+        assert_eq!(large.checked_add(&Integer::zero()), None);
+    }
+
+    #[test]
+    fn pow_mod() {
+        let n = Integer::new;
+        assert_eq!(n(2).pow_mod(&n(10), &n(1000)), n(24));
+        assert_eq!(n(5).pow_mod(&n(0), &n(7)), n(1));
+        assert_eq!(n(5).pow_mod(&n(0), &n(1)), n(0));
+        assert_eq!(n(5).pow_mod(&n(3), &n(1)), n(0));
+        assert_eq!(n(-2).pow_mod(&n(3), &n(5)), n(2));
+
+        // Fermat's little theorem
+        let p = Integer::from(1_000_000_007i64);
+        for a in [2, 3, 123456789] {
+            assert_eq!(n(a).pow_mod(&(&p - &Integer::one()), &p), n(1));
+        }
+
+        let large = Integer::new(3).pow(100);
+        let m = &Integer::new(2).pow(89) - &Integer::one();
+        assert_eq!(large.pow_mod(&m, &m), Z.quot_rem(&large, &m).1);
+    }
+
+    #[test]
+    fn factorial_mod() {
+        assert_eq!(
+            Integer::factorial_mod(10, &Integer::new(1000)),
+            Integer::new(800)
+        );
+        assert_eq!(Integer::factorial_mod(0, &Integer::new(7)), Integer::one());
+        assert_eq!(Integer::factorial_mod(3, &Integer::one()), Integer::zero());
+        // Wilson's theorem
+        let p = Integer::new(10007);
+        assert_eq!(Integer::factorial_mod(10006, &p), &p - &Integer::one());
+        assert_eq!(Integer::factorial_mod(20000, &p), Integer::zero());
+    }
+
+    #[test]
+    #[should_panic(expected = "Modulus must be positive")]
+    fn factorial_mod_zero_modulus() {
+        let _ = Integer::factorial_mod(3, &Integer::zero());
+    }
+
+    #[test]
+    #[should_panic(expected = "Exponent must be non-negative")]
+    fn pow_mod_negative_exponent() {
+        let _ = Integer::new(2).pow_mod(&Integer::new(-1), &Integer::new(5));
+    }
+
+    #[test]
+    fn radix() {
+        let large = -&Integer::new(3).pow(200);
+        for radix in [16, 2] {
+            let s = large.to_radix_string(radix);
+            assert_eq!(Integer::from_radix_str(&s, radix), Ok(large.clone()));
+            assert_eq!(
+                Integer::from_radix_str(&s.to_uppercase(), radix),
+                Ok(large.clone())
+            );
+        }
+
+        assert_eq!(large.to_radix_string(10), large.to_string());
+        assert_eq!(Integer::new(-255).to_radix_string(16), "-ff");
+        assert_eq!(Integer::new(5).to_radix_string(2), "101");
+        assert_eq!(Integer::from_radix_str("+Ff", 16), Ok(Integer::new(255)));
+        assert_eq!(
+            Integer::from_radix_str("10000000000000000", 16),
+            Ok(Integer::from(1u128 << 64))
+        );
+        assert!(Integer::from_radix_str("12", 2).is_err());
+        assert!(Integer::from_radix_str("12", 37).is_err());
+        assert!(Integer::from_radix_str("12", 1).is_err());
+    }
+
+    #[test]
+    fn max_coefficient_bits() {
+        let mut a = Integer::new(3);
+        let mut steps = 0;
+        let err = loop {
+            match a.try_mul(&a, 1000) {
+                Ok(r) => a = r,
+                Err(e) => break e,
+            }
+            steps += 1;
+        };
+        assert_eq!(steps, 9);
+        assert_eq!(a.bit_length(), 812);
+        assert!(err.contains("maximum of 1000 bits"));
+
+        assert_eq!(
+            Integer::new(3).try_pow(600, 1000).unwrap().bit_length(),
+            951
+        );
+        assert!(Integer::new(3).try_pow(700, 1000).is_err());
+        assert_eq!(Integer::new(-1).try_pow(1000, 1), Ok(Integer::new(1)));
+        assert_eq!(
+            Integer::new(3).try_pow(20, 64),
+            Ok(Integer::new(3486784401))
+        );
+
+        let b = Integer::new(2).pow(63);
+        assert_eq!(b.try_add(&b, 65), Ok(Integer::new(2).pow(64)));
+        assert!(b.try_add(&b, 64).is_err());
+
+        assert!(Rational::new(3, 7).try_pow(100, 64).is_err());
+        assert_eq!(
+            Rational::new(3, 7).try_mul(&Rational::new(2, 3), 64),
+            Ok(Rational::new(2, 7))
+        );
+        assert_eq!(
+            Rational::new(1, 2).try_add(&Rational::new(1, 3), 64),
+            Ok(Rational::new(5, 6))
+        );
+        let c = Rational::from(Integer::new(3).pow(50));
+        assert!(c.try_add(&Rational::new(1, 3), 64).is_err());
+    }
+}
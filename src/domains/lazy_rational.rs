@@ -0,0 +1,420 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Error, Formatter},
+    hash::{Hash, Hasher},
+};
+
+use rand::Rng;
+
+use crate::printer::PrintOptions;
+
+use super::{
+    integer::Integer,
+    rational::{Rational, Q},
+    EuclideanDomain, Field, Ring,
+};
+
+/// The field of rational numbers, where the reduction of fractions to lowest terms
+/// is deferred until it is needed. This avoids computing a gcd after every
+/// operation in hot loops.
+///
+/// To bound the growth of unreduced numbers, an element is reduced as soon as its
+/// denominator exceeds `max_bits` bits. If the reduced numbers themselves are
+/// larger than this bound, every operation reduces and the domain is slower than [Q].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct LazyRationalField {
+    max_bits: u32,
+}
+
+impl Default for LazyRationalField {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl LazyRationalField {
+    /// Create a new field that reduces elements whose denominator exceeds `max_bits` bits.
+    pub const fn new(max_bits: u32) -> LazyRationalField {
+        LazyRationalField { max_bits }
+    }
+
+    /// Get the maximal number of bits of the denominator of an unreduced element.
+    pub fn max_bits(&self) -> u32 {
+        self.max_bits
+    }
+
+    fn bound(&self, mut a: LazyRational) -> LazyRational {
+        if bits(&a.den) > self.max_bits {
+            a.reduce();
+        }
+        a
+    }
+}
+
+/// A rational number that is possibly not reduced to lowest terms.
+/// The denominator is always positive.
+///
+/// Equality, ordering and hashing compare by value, so that `2/4` equals `1/2`.
+#[derive(Clone, Debug)]
+pub struct LazyRational {
+    num: Integer,
+    den: Integer,
+    reduced: bool,
+}
+
+fn bits(n: &Integer) -> u32 {
+    match n {
+        Integer::Natural(n) => 64 - n.unsigned_abs().leading_zeros(),
+        Integer::Double(n) => 128 - n.unsigned_abs().leading_zeros(),
+        Integer::Large(n) => n.significant_bits(),
+    }
+}
+
+impl LazyRational {
+    /// Create a new rational number `num/den`. The fraction does not need to be reduced.
+    pub fn new(num: Integer, den: Integer) -> LazyRational {
+        if den.is_zero() {
+            panic!("Division by zero");
+        }
+
+        if den.is_negative() {
+            LazyRational {
+                num: -&num,
+                den: -&den,
+                reduced: false,
+            }
+        } else {
+            LazyRational {
+                reduced: den.is_one(),
+                num,
+                den,
+            }
+        }
+    }
+
+    /// Get the numerator of the possibly unreduced fraction.
+    pub fn numerator(&self) -> &Integer {
+        &self.num
+    }
+
+    /// Get the denominator of the possibly unreduced fraction.
+    pub fn denominator(&self) -> &Integer {
+        &self.den
+    }
+
+    /// Returns `true` if the number is known to be reduced to lowest terms.
+    /// If `false`, the number may or may not be reduced.
+    pub fn is_reduced(&self) -> bool {
+        self.reduced
+    }
+
+    /// Reduce the fraction to lowest terms.
+    pub fn reduce(&mut self) {
+        if self.reduced {
+            return;
+        }
+
+        let g = self.num.gcd(&self.den);
+        if !g.is_one() {
+            self.num = &self.num / &g;
+            self.den = &self.den / &g;
+        }
+        self.reduced = true;
+    }
+
+    /// Convert to a reduced rational number.
+    pub fn to_rational(&self) -> Rational {
+        Rational::from((self.num.clone(), self.den.clone()))
+    }
+}
+
+impl From<Rational> for LazyRational {
+    fn from(r: Rational) -> Self {
+        LazyRational {
+            num: r.numerator(),
+            den: r.denominator(),
+            reduced: true,
+        }
+    }
+}
+
+impl From<&LazyRational> for Rational {
+    fn from(r: &LazyRational) -> Self {
+        r.to_rational()
+    }
+}
+
+impl From<LazyRational> for Rational {
+    fn from(r: LazyRational) -> Self {
+        Rational::from((r.num, r.den))
+    }
+}
+
+impl PartialEq for LazyRational {
+    fn eq(&self, other: &Self) -> bool {
+        if self.reduced && other.reduced || self.den == other.den {
+            self.num == other.num && self.den == other.den
+        } else {
+            &self.num * &other.den == &other.num * &self.den
+        }
+    }
+}
+
+impl Eq for LazyRational {}
+
+impl Hash for LazyRational {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if self.reduced {
+            self.num.hash(state);
+            self.den.hash(state);
+        } else {
+            let mut r = self.clone();
+            r.reduce();
+            r.num.hash(state);
+            r.den.hash(state);
+        }
+    }
+}
+
+impl PartialOrd for LazyRational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LazyRational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.den == other.den {
+            self.num.cmp(&other.num)
+        } else {
+            (&self.num * &other.den).cmp(&(&other.num * &self.den))
+        }
+    }
+}
+
+impl Display for LazyRational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.to_rational().fmt(f)
+    }
+}
+
+impl serde::Serialize for LazyRational {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_rational().serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for LazyRational {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Rational::deserialize(deserializer).map(|r| r.into())
+    }
+}
+
+impl Display for LazyRationalField {
+    fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+impl Ring for LazyRationalField {
+    type Element = LazyRational;
+
+    fn add(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        if a.den == b.den {
+            return LazyRational {
+                num: &a.num + &b.num,
+                den: a.den.clone(),
+                reduced: a.den.is_one(),
+            };
+        }
+
+        self.bound(LazyRational {
+            num: &(&a.num * &b.den) + &(&b.num * &a.den),
+            den: &a.den * &b.den,
+            reduced: false,
+        })
+    }
+
+    fn sub(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        self.add(a, &self.neg(b))
+    }
+
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        self.bound(LazyRational {
+            num: &a.num * &b.num,
+            den: &a.den * &b.den,
+            reduced: a.den.is_one() && b.den.is_one(),
+        })
+    }
+
+    fn add_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.add(a, b);
+    }
+
+    fn sub_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.sub(a, b);
+    }
+
+    fn mul_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.mul(a, b);
+    }
+
+    fn add_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        self.add_assign(a, &self.mul(b, c));
+    }
+
+    fn sub_mul_assign(&self, a: &mut Self::Element, b: &Self::Element, c: &Self::Element) {
+        self.sub_assign(a, &self.mul(b, c));
+    }
+
+    fn neg(&self, a: &Self::Element) -> Self::Element {
+        LazyRational {
+            num: -&a.num,
+            den: a.den.clone(),
+            reduced: a.reduced,
+        }
+    }
+
+    fn zero(&self) -> Self::Element {
+        LazyRational {
+            num: Integer::zero(),
+            den: Integer::one(),
+            reduced: true,
+        }
+    }
+
+    fn one(&self) -> Self::Element {
+        LazyRational {
+            num: Integer::one(),
+            den: Integer::one(),
+            reduced: true,
+        }
+    }
+
+    fn nth(&self, n: u64) -> Self::Element {
+        LazyRational {
+            num: n.into(),
+            den: Integer::one(),
+            reduced: true,
+        }
+    }
+
+    fn pow(&self, b: &Self::Element, e: u64) -> Self::Element {
+        LazyRational {
+            num: b.num.pow(e),
+            den: b.den.pow(e),
+            reduced: b.reduced,
+        }
+    }
+
+    fn is_zero(a: &Self::Element) -> bool {
+        a.num.is_zero()
+    }
+
+    fn is_one(&self, a: &Self::Element) -> bool {
+        a.num == a.den
+    }
+
+    fn one_is_gcd_unit() -> bool {
+        false
+    }
+
+    fn is_characteristic_zero(&self) -> bool {
+        true
+    }
+
+    fn sample(&self, rng: &mut impl rand::RngCore, range: (i64, i64)) -> Self::Element {
+        let r = rng.gen_range(range.0..range.1);
+        LazyRational {
+            num: r.into(),
+            den: Integer::one(),
+            reduced: true,
+        }
+    }
+
+    fn fmt_display(
+        &self,
+        element: &Self::Element,
+        _opts: &PrintOptions,
+        _in_product: bool,
+        f: &mut Formatter<'_>,
+    ) -> Result<(), Error> {
+        element.fmt(f)
+    }
+}
+
+impl EuclideanDomain for LazyRationalField {
+    fn rem(&self, _: &Self::Element, _: &Self::Element) -> Self::Element {
+        self.zero()
+    }
+
+    fn quot_rem(&self, a: &Self::Element, b: &Self::Element) -> (Self::Element, Self::Element) {
+        (self.div(a, b), self.zero())
+    }
+
+    fn gcd(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        Q.gcd(&a.to_rational(), &b.to_rational()).into()
+    }
+}
+
+impl Field for LazyRationalField {
+    fn div(&self, a: &Self::Element, b: &Self::Element) -> Self::Element {
+        self.mul(a, &self.inv(b))
+    }
+
+    fn div_assign(&self, a: &mut Self::Element, b: &Self::Element) {
+        *a = self.div(a, b);
+    }
+
+    fn inv(&self, a: &Self::Element) -> Self::Element {
+        if a.num.is_zero() {
+            panic!("Division by zero");
+        }
+
+        LazyRational::new(a.den.clone(), a.num.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domains::{
+        rational::{Rational, Q},
+        Field, Ring,
+    };
+
+    use super::{LazyRational, LazyRationalField};
+
+    #[test]
+    fn lazy_and_eager() {
+        let lazy = LazyRationalField::new(64);
+
+        // evaluate sum_i (i/(i+1))^2 * 2/(i+2)
+        let mut eager_sum = Q.zero();
+        let mut lazy_sum = lazy.zero();
+        for i in 1..50 {
+            let a = Rational::new(i, i + 1);
+            let b = Rational::new(2, i + 2);
+            Q.add_assign(&mut eager_sum, &Q.mul(&Q.pow(&a, 2), &b));
+
+            let (a, b): (LazyRational, LazyRational) = (a.into(), b.into());
+            lazy.add_assign(&mut lazy_sum, &lazy.mul(&lazy.pow(&a, 2), &b));
+            // the denominator growth is bounded
+            assert!(lazy_sum.is_reduced() || super::bits(lazy_sum.denominator()) <= 64);
+        }
+
+        assert_eq!(lazy_sum.to_rational(), eager_sum);
+        assert_eq!(lazy_sum, eager_sum.clone().into());
+
+        let unreduced = LazyRational::new(2.into(), (-4).into());
+        assert!(!unreduced.is_reduced());
+        assert_eq!(unreduced, Rational::new(-1, 2).into());
+        assert_eq!(lazy.inv(&unreduced), Rational::from(-2).into());
+        assert_eq!(format!("{}", unreduced), "-1/2");
+
+        let state = ahash::RandomState::new();
+        assert_eq!(
+            state.hash_one(&unreduced),
+            state.hash_one(LazyRational::from(Rational::new(-1, 2)))
+        );
+    }
+}
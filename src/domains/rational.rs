@@ -1,5 +1,7 @@
 use std::{
+    cmp::Ordering,
     fmt::{Display, Error, Formatter, Write},
+    hash::{Hash, Hasher},
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
@@ -44,9 +46,12 @@ impl RationalField {
 /// is only valid if the conventions are followed:
 /// `Rational::Natural(n,d)` should have `d > 0` and
 /// `gcd(n,d)=1`.
+///
+/// Equality and hashing compare by value, so that a small rational stored
+/// as `Rational::Large` is equal to and hashes like its `Rational::Natural` form.
 // TODO: convert to Rational(Integer, Integer)?
 // TODO: prevent construction of explicit rational
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Eq, Debug)]
 pub enum Rational {
     Natural(i64, i64),
     Large(MultiPrecisionRational),
@@ -233,6 +238,22 @@ impl Rational {
         }
     }
 
+    /// Get the value as an integer, or `None` if the number is not an integer.
+    /// The small representation is converted without allocating.
+    pub fn to_integer(&self) -> Option<Integer> {
+        match self {
+            Rational::Natural(n, 1) => Some(Integer::Natural(*n)),
+            Rational::Natural(..) => None,
+            Rational::Large(r) => {
+                if r.is_integer() {
+                    Some(Integer::from_large(r.numer().clone()))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
     pub fn numerator(&self) -> Integer {
         match self {
             Rational::Natural(n, _) => Integer::Natural(*n),
@@ -263,6 +284,42 @@ impl Rational {
         }
     }
 
+    /// Get the largest integer that is smaller than or equal to the number.
+    pub fn floor(&self) -> Integer {
+        Z.quot_rem(&self.numerator(), &self.denominator()).0
+    }
+
+    /// Get the smallest integer that is larger than or equal to the number.
+    pub fn ceil(&self) -> Integer {
+        -&self.neg().floor()
+    }
+
+    /// Get the integer part of the number, rounding towards zero.
+    pub fn trunc(&self) -> Integer {
+        if self.is_negative() {
+            self.ceil()
+        } else {
+            self.floor()
+        }
+    }
+
+    /// Get the integer that is closest to the number, rounding half-way cases to the even integer.
+    pub fn round(&self) -> Integer {
+        let floor = self.floor();
+        let frac = self - &Rational::from(&floor);
+        match frac.cmp(&Rational::new(1, 2)) {
+            Ordering::Less => floor,
+            Ordering::Greater => &floor + &Integer::one(),
+            Ordering::Equal => {
+                if (&floor % &Integer::Natural(2)).is_zero() {
+                    floor
+                } else {
+                    &floor + &Integer::one()
+                }
+            }
+        }
+    }
+
     pub fn is_zero(&self) -> bool {
         self == &Rational::Natural(0, 1)
     }
@@ -271,6 +328,18 @@ impl Rational {
         self == &Rational::Natural(1, 1)
     }
 
+    /// Get the total number of bits of the absolute values of the numerator and denominator.
+    pub fn bit_length(&self) -> u64 {
+        match self {
+            Rational::Natural(n, d) => {
+                (2 * u64::BITS
+                    - n.unsigned_abs().leading_zeros()
+                    - d.unsigned_abs().leading_zeros()) as u64
+            }
+            Rational::Large(r) => r.numer().significant_bits_64() + r.denom().significant_bits_64(),
+        }
+    }
+
     pub fn pow(&self, e: u64) -> Rational {
         if e > u32::MAX as u64 {
             panic!("Power of exponentation is larger than 2^32: {}", e);
@@ -291,9 +360,162 @@ impl Rational {
         }
     }
 
+    /// Add two numbers, or return an error if the numerator and denominator of the result
+    /// may have more than `max_bits` bits in total. The check uses the bit lengths of
+    /// the arguments and is done before the result is allocated.
+    pub fn try_add(&self, other: &Rational, max_bits: u64) -> Result<Rational, String> {
+        let (n1, d1) = (
+            self.numerator().bit_length(),
+            self.denominator().bit_length(),
+        );
+        let (n2, d2) = (
+            other.numerator().bit_length(),
+            other.denominator().bit_length(),
+        );
+        Integer::check_bit_length((n1 + d2).max(n2 + d1) + 1 + d1 + d2, max_bits)?;
+        Ok(self + other)
+    }
+
+    /// Multiply two numbers, or return an error if the numerator and denominator of the result
+    /// may have more than `max_bits` bits in total. The check uses the bit lengths of
+    /// the arguments and is done before the result is allocated.
+    pub fn try_mul(&self, other: &Rational, max_bits: u64) -> Result<Rational, String> {
+        Integer::check_bit_length(self.bit_length() + other.bit_length(), max_bits)?;
+        Ok(self * other)
+    }
+
+    /// Compute `self^e`, or return an error if the numerator and denominator of the result
+    /// have more than `max_bits` bits in total. The check is done before the result is allocated.
+    pub fn try_pow(&self, e: u64, max_bits: u64) -> Result<Rational, String> {
+        if e > 1 {
+            Integer::check_bit_length(
+                self.numerator().pow_bit_length(e) + self.denominator().pow_bit_length(e),
+                max_bits,
+            )?;
+        }
+        Ok(self.pow(e))
+    }
+
+    /// Compute the exact `n`th root of the number, if it is rational.
+    /// For even `n`, the positive root is returned. The `0`th root does not exist.
+    pub fn nth_root_exact(&self, n: u32) -> Option<Rational> {
+        if n == 0 || self.is_negative() && n % 2 == 0 {
+            return None;
+        }
+
+        let (num, den) = match self {
+            Rational::Natural(num, den) => (
+                MultiPrecisionInteger::from(*num),
+                MultiPrecisionInteger::from(*den),
+            ),
+            Rational::Large(r) => r.clone().into_numer_denom(),
+        };
+
+        // the root of a negative number is only taken for odd n
+        let neg = num < 0;
+        let (num_root, num_rem) = num.abs().root_rem(MultiPrecisionInteger::new(), n);
+        let (den_root, den_rem) = den.root_rem(MultiPrecisionInteger::new(), n);
+
+        if num_rem != 0 || den_rem != 0 {
+            return None;
+        }
+
+        let r = Rational::from_large(MultiPrecisionRational::from((num_root, den_root)));
+        Some(if neg { r.neg() } else { r })
+    }
+
+    /// Write `|self|^(1/n)` as `q * r^(1/n)` and return `(q, r)`, where the numerator and denominator
+    /// of `r` contain no `n`th power of a prime. For `n = 2`, the denominator is moved into `q`
+    /// such that `r` is an integer. For `n = 0`, `(1, |self|)` is returned.
+    ///
+    /// The prime factors below `2^16` are removed by trial division and the remaining cofactor
+    /// is only split off if it is a perfect `n`th power. This finds every `n`th power of a prime in
+    /// integers below `2^(16*(n+1))`, so that `r` is canonical for those. For larger integers, an
+    /// `n`th power of a prime above `2^16` that is multiplied by other such primes is not extracted.
+    pub fn split_nth_root(&self, n: u32) -> (Rational, Rational) {
+        if n == 0 {
+            return (Rational::one(), self.abs());
+        }
+
+        /// Write `a` as `f^n * r`.
+        fn extract(
+            mut a: MultiPrecisionInteger,
+            n: u32,
+        ) -> (MultiPrecisionInteger, MultiPrecisionInteger) {
+            let mut factor = MultiPrecisionInteger::from(1);
+            let mut rest = MultiPrecisionInteger::from(1);
+
+            let mut p = 2u32;
+            while p < 1 << 16 {
+                // if `a < p^(n+1)`, `a` has at most `n` prime factors that are all at least `p`,
+                // so that it only contains an `n`th power of a prime if it is a perfect power
+                let p_bits = (u32::BITS - p.leading_zeros()) as u64;
+                if a.significant_bits_64() <= (n as u64 + 1) * (p_bits - 1) {
+                    break;
+                }
+
+                let mut e = 0;
+                while a.is_divisible_u(p) {
+                    a.div_exact_u_mut(p);
+                    e += 1;
+                }
+
+                if e > 0 {
+                    factor *= MultiPrecisionInteger::from(p).pow(e / n);
+                    rest *= MultiPrecisionInteger::from(p).pow(e % n);
+                }
+
+                p += if p == 2 { 1 } else { 2 };
+            }
+
+            let (root, rem) = a.clone().root_rem(MultiPrecisionInteger::new(), n);
+            if rem == 0 {
+                (factor * root, rest)
+            } else {
+                (factor, rest * a)
+            }
+        }
+
+        let (num, den) = match self {
+            Rational::Natural(num, den) => (
+                MultiPrecisionInteger::from(*num).abs(),
+                MultiPrecisionInteger::from(*den),
+            ),
+            Rational::Large(r) => {
+                let (num, den) = r.clone().into_numer_denom();
+                (num.abs(), den)
+            }
+        };
+
+        if num == 0 {
+            return (Rational::zero(), Rational::one());
+        }
+
+        if n == 2 {
+            // rationalize the denominator
+            let (f, r) = extract(num * &den, n);
+            (
+                Rational::from_large(MultiPrecisionRational::from((f, den))),
+                Rational::from_large(r.into()),
+            )
+        } else {
+            let (f_num, r_num) = extract(num, n);
+            let (f_den, r_den) = extract(den, n);
+            (
+                Rational::from_large(MultiPrecisionRational::from((f_num, f_den))),
+                Rational::from_large(MultiPrecisionRational::from((r_num, r_den))),
+            )
+        }
+    }
+
+    /// Compute the inverse. Panics if the number is zero.
     pub fn inv(&self) -> Rational {
         match self {
             Rational::Natural(n, d) => {
+                if *n == 0 {
+                    panic!("Division by zero");
+                }
+
                 if *n < 0 {
                     if let Some(neg) = n.checked_neg() {
                         Rational::Natural(-d, neg)
@@ -308,6 +530,15 @@ impl Rational {
         }
     }
 
+    /// Divide by `other`, returning `None` if `other` is zero.
+    pub fn checked_div(&self, other: &Rational) -> Option<Rational> {
+        if other.is_zero() {
+            None
+        } else {
+            Some(Q.div(self, other))
+        }
+    }
+
     pub fn neg(&self) -> Rational {
         match self {
             Rational::Natural(n, d) => {
@@ -355,6 +586,91 @@ impl Rational {
         }
     }
 
+    /// Write the rational number as a decimal string with `sig_digits` correctly
+    /// rounded significant digits. Trailing zeros are omitted. Scientific notation,
+    /// e.g. `1.5e-7`, is used when the decimal exponent is smaller than -4 or at least `sig_digits`.
+    pub fn to_decimal_string(&self, sig_digits: usize) -> String {
+        let sig_digits = sig_digits.max(1);
+
+        let (mut num, den) = match self {
+            Rational::Natural(n, d) => (
+                MultiPrecisionInteger::from(*n),
+                MultiPrecisionInteger::from(*d),
+            ),
+            Rational::Large(r) => (r.numer().clone(), r.denom().clone()),
+        };
+
+        if num == 0 {
+            return "0".to_owned();
+        }
+
+        let mut out = String::new();
+        if num < 0 {
+            out.push('-');
+            num = -num;
+        }
+
+        // estimate the decimal exponent and correct it to floor(log10(|self|))
+        let ten = MultiPrecisionInteger::from(10);
+        let mut exp = num.to_string().len() as i64 - den.to_string().len() as i64;
+        let is_larger = if exp >= 0 {
+            num >= den.clone() * ten.clone().pow(exp as u32)
+        } else {
+            num.clone() * ten.clone().pow((-exp) as u32) >= den
+        };
+        if !is_larger {
+            exp -= 1;
+        }
+
+        // compute round(|self| * 10^(sig_digits - 1 - exp)), rounding half away from zero
+        let shift = sig_digits as i64 - 1 - exp;
+        let (scaled_num, scaled_den) = if shift >= 0 {
+            (num * ten.pow(shift as u32), den)
+        } else {
+            (num, den * ten.pow((-shift) as u32))
+        };
+        let mut digits = (scaled_num * 2u32 + &scaled_den) / (scaled_den * 2u32);
+
+        let mut digit_str = digits.to_string();
+        if digit_str.len() > sig_digits {
+            // rounding produced an extra digit, e.g. 9.99 -> 10.0
+            exp += 1;
+            digits /= 10;
+            digit_str = digits.to_string();
+        }
+
+        let digit_str = digit_str.trim_end_matches('0');
+
+        if exp < -4 || exp >= sig_digits as i64 {
+            out.push_str(&digit_str[..1]);
+            if digit_str.len() > 1 {
+                out.push('.');
+                out.push_str(&digit_str[1..]);
+            }
+            out.push_str(&format!("e{}", exp));
+        } else if exp < 0 {
+            out.push_str("0.");
+            for _ in 0..(-exp - 1) {
+                out.push('0');
+            }
+            out.push_str(digit_str);
+        } else {
+            let int_len = exp as usize + 1;
+            if digit_str.len() <= int_len {
+                out.push_str(digit_str);
+                for _ in digit_str.len()..int_len {
+                    out.push('0');
+                }
+            } else {
+                out.push_str(&digit_str[..int_len]);
+                out.push('.');
+                out.push_str(&digit_str[int_len..]);
+            }
+        }
+
+        out
+    }
+
     /// Return a best approximation of the rational number where the denominator
     /// is less than or equal to `max_denominator`.
     pub fn truncate_denominator(&self, max_denominator: &Integer) -> Rational {
@@ -399,6 +715,87 @@ impl Rational {
         }
     }
 
+    /// Get the mediant `(n1+n2)/(d1+d2)` of two numbers in lowest terms, as the pair
+    /// `(n1+n2, d1+d2)`. The pair is not reduced, as in the Stern–Brocot tree.
+    /// For neighbours in the tree, the numerator and denominator are coprime.
+    pub fn mediant(&self, other: &Rational) -> (Integer, Integer) {
+        (
+            &self.numerator() + &other.numerator(),
+            &self.denominator() + &other.denominator(),
+        )
+    }
+
+    /// Get the simplest rational number, i.e., the one with the smallest denominator and numerator,
+    /// whose distance to the number is at most `epsilon`. This is the first number in the interval
+    /// that is encountered when walking down the Stern–Brocot tree, where consecutive steps in the
+    /// same direction are taken at once. If `epsilon` is zero, the number itself is returned.
+    pub fn best_approximation_within(&self, epsilon: Rational) -> Rational {
+        assert!(!epsilon.is_negative(), "The tolerance must be non-negative");
+
+        let (lo, hi) = (self - &epsilon, self + &epsilon);
+        if hi.is_negative() {
+            Rational::simplest_in_positive_interval(hi.neg(), lo.neg()).neg()
+        } else if lo.is_negative() || lo.is_zero() {
+            Rational::zero()
+        } else {
+            Rational::simplest_in_positive_interval(lo, hi)
+        }
+    }
+
+    /// Get the simplest rational number in the interval `[lo, hi]`, with `0 < lo <= hi`.
+    fn simplest_in_positive_interval(mut lo: Rational, mut hi: Rational) -> Rational {
+        let mut quotients = vec![];
+        loop {
+            let c = lo.ceil();
+            if Rational::from(&c) <= hi {
+                quotients.push(c);
+                break;
+            }
+
+            // the interval lies in (f, f+1), so that we continue in the subtree of f
+            let f = Rational::from(&lo.floor());
+            (lo, hi) = ((&hi - &f).inv(), (&lo - &f).inv());
+            quotients.push(f.numerator());
+        }
+
+        Rational::from_continued_fraction(&quotients).unwrap()
+    }
+
+    /// Get an iterator over the partial quotients of the simple continued fraction of the number.
+    /// The first quotient is the floor of the number and all subsequent quotients are positive.
+    pub fn continued_fraction(&self) -> impl Iterator<Item = Integer> {
+        let (mut n, mut d) = (self.numerator(), self.denominator());
+
+        std::iter::from_fn(move || {
+            if d.is_zero() {
+                return None;
+            }
+
+            let (q, r) = Z.quot_rem(&n, &d);
+            n = std::mem::replace(&mut d, r);
+            Some(q)
+        })
+    }
+
+    /// Construct a rational number from the partial quotients of a simple continued fraction
+    /// `a0 + 1/(a1 + 1/(a2 + ...))`. This is the inverse of [`Rational::continued_fraction`].
+    pub fn from_continued_fraction(quotients: &[Integer]) -> Result<Rational, &'static str> {
+        let Some((last, rest)) = quotients.split_last() else {
+            return Err("A continued fraction needs at least one quotient");
+        };
+
+        let (mut p, mut q) = (last.clone(), Integer::one());
+        for a in rest.iter().rev() {
+            if p.is_zero() {
+                return Err("Division by zero in continued fraction");
+            }
+
+            (p, q) = (&(a * &p) + &q, p);
+        }
+
+        Ok((p, q).into())
+    }
+
     /// Reconstruct a rational number `q` from a value `v` in a prime field `p`,
     /// such that `q ≡ v mod p`.
     ///
@@ -546,6 +943,87 @@ impl Rational {
     }
 }
 
+/// Incrementally combine the residues of an integer or rational number modulo distinct primes
+/// using the Chinese remainder theorem, so that residues can be added one prime at a time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrtAccumulator {
+    residue: Integer,
+    modulus: Integer,
+}
+
+impl Default for CrtAccumulator {
+    fn default() -> Self {
+        CrtAccumulator::new()
+    }
+}
+
+impl CrtAccumulator {
+    /// Create an accumulator without any residues.
+    pub fn new() -> CrtAccumulator {
+        CrtAccumulator {
+            residue: Integer::zero(),
+            modulus: Integer::one(),
+        }
+    }
+
+    /// Get the combined residue, which is in the range `[0, modulus)`.
+    pub fn residue(&self) -> &Integer {
+        &self.residue
+    }
+
+    /// Get the product of all primes that have been added.
+    pub fn modulus(&self) -> &Integer {
+        &self.modulus
+    }
+
+    /// Combine the current state with `residue` modulo `prime`.
+    /// Returns an error if `prime` is not coprime to the current modulus, for example
+    /// when the same prime is added twice.
+    pub fn add_residue(&mut self, residue: Integer, prime: u64) -> Result<(), &'static str> {
+        let p = Integer::from(prime);
+        if prime < 2 || !self.modulus.gcd(&p).is_one() {
+            return Err("The prime must be larger than 1 and coprime to the modulus");
+        }
+
+        let mut r = Z.rem(&residue, &p);
+        if r.is_negative() {
+            r += &p;
+        }
+
+        if self.modulus.is_one() {
+            self.residue = r;
+        } else {
+            self.residue = Integer::chinese_remainder(
+                r,
+                self.residue.clone(),
+                p.clone(),
+                self.modulus.clone(),
+            );
+        }
+
+        self.modulus *= &p;
+        if self.residue.is_negative() {
+            self.residue += &self.modulus;
+        }
+
+        Ok(())
+    }
+
+    /// Attempt to reconstruct a rational number from the current residue and modulus,
+    /// using [Rational::maximal_quotient_reconstruction]. Returns `None` if the modulus
+    /// is too small for a reliable reconstruction.
+    ///
+    /// A result may be correct before all primes have been added. It is
+    /// confirmed when adding a new prime reconstructs the same number.
+    pub fn reconstruct(&self) -> Option<Rational> {
+        if self.modulus.is_one() {
+            return None;
+        }
+
+        Rational::maximal_quotient_reconstruction(&self.residue, &self.modulus, None).ok()
+    }
+}
+
 impl Display for Rational {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -812,6 +1290,40 @@ impl Ord for Rational {
     }
 }
 
+impl PartialEq for Rational {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Rational::Natural(n1, d1), Rational::Natural(n2, d2)) => n1 == n2 && d1 == d2,
+            (Rational::Large(r1), Rational::Large(r2)) => r1 == r2,
+            (Rational::Natural(n, d), Rational::Large(r))
+            | (Rational::Large(r), Rational::Natural(n, d)) => r.numer() == n && r.denom() == d,
+        }
+    }
+}
+
+impl Hash for Rational {
+    /// Hash the value in its smallest representation, so that the hash is
+    /// independent of the variant.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Rational::Natural(n, d) => {
+                n.hash(state);
+                d.hash(state);
+            }
+            Rational::Large(r) => {
+                if let (Some(n), Some(d)) = (r.numer().to_i64(), r.denom().to_i64()) {
+                    n.hash(state);
+                    d.hash(state);
+                } else {
+                    r.numer().hash(state);
+                    r.denom().hash(state);
+                }
+            }
+        }
+    }
+}
+
 impl Add<Rational> for Rational {
     type Output = Rational;
 
@@ -839,6 +1351,8 @@ impl Mul<Rational> for Rational {
 impl Div<Rational> for Rational {
     type Output = Rational;
 
+    /// Divide by `other`. Panics if `other` is zero, see [Rational::checked_div]
+    /// for a non-panicking alternative.
     fn div(self, other: Rational) -> Self::Output {
         Q.div(&self, &other)
     }
@@ -871,6 +1385,8 @@ impl<'a> Mul<&'a Rational> for Rational {
 impl<'a> Div<&'a Rational> for Rational {
     type Output = Rational;
 
+    /// Divide by `other`. Panics if `other` is zero, see [Rational::checked_div]
+    /// for a non-panicking alternative.
     fn div(self, other: &'a Rational) -> Self::Output {
         Q.div(&self, other)
     }
@@ -910,6 +1426,8 @@ impl<'a, 'b> Mul<&'a Rational> for &'b Rational {
 impl<'a, 'b> Div<&'a Rational> for &'b Rational {
     type Output = Rational;
 
+    /// Divide by `other`. Panics if `other` is zero, see [Rational::checked_div]
+    /// for a non-panicking alternative.
     fn div(self, other: &'a Rational) -> Self::Output {
         Q.div(self, other)
     }
@@ -968,3 +1486,346 @@ impl<'a> std::iter::Sum<&'a Self> for Rational {
         iter.fold(Rational::zero(), |a, b| a + b)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::hash::Hash;
+
+    use ahash::RandomState;
+    use rug::{Integer as MultiPrecisionInteger, Rational as MultiPrecisionRational};
+
+    use crate::domains::{
+        finite_field::{FiniteFieldCore, ToFiniteField, Zp},
+        integer::Integer,
+    };
+
+    use super::{CrtAccumulator, Rational};
+
+    #[test]
+    fn checked_div() {
+        let half = Rational::Natural(1, 2);
+        assert_eq!(half.checked_div(&Rational::zero()), None);
+        assert_eq!(
+            half.checked_div(&Rational::Natural(-3, 4)),
+            Some(Rational::Natural(-2, 3))
+        );
+
+        let large = Rational::from_large(MultiPrecisionRational::from((
+            MultiPrecisionInteger::from(u64::MAX) * 3u8,
+            7,
+        )));
+        assert_eq!(large.checked_div(&Rational::zero()), None);
+        assert_eq!(large.checked_div(&large), Some(Rational::one()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn div_by_zero() {
+        let _ = Rational::Natural(1, 2) / Rational::zero();
+    }
+
+    #[test]
+    fn decimal_string() {
+        assert_eq!(Rational::new(1, 7).to_decimal_string(10), "0.1428571429");
+        assert_eq!(Rational::new(1, 8).to_decimal_string(10), "0.125");
+        assert_eq!(Rational::new(-2, 3).to_decimal_string(3), "-0.667");
+        assert_eq!(Rational::new(999, 1).to_decimal_string(2), "1e3");
+        assert_eq!(Rational::new(1234, 1).to_decimal_string(6), "1234");
+        assert_eq!(Rational::new(1, 300000).to_decimal_string(4), "3.333e-6");
+        assert_eq!(Rational::new(0, 1).to_decimal_string(4), "0");
+
+        let large = Rational::from_large("123456789012345678901234567890/7".parse().unwrap());
+        assert_eq!(large.to_decimal_string(5), "1.7637e28");
+    }
+
+    #[test]
+    fn nth_root_exact() {
+        assert_eq!(
+            Rational::new(4, 9).nth_root_exact(2),
+            Some(Rational::new(2, 3))
+        );
+        assert_eq!(Rational::new(2, 1).nth_root_exact(2), None);
+        assert_eq!(Rational::new(4, 3).nth_root_exact(2), None);
+        assert_eq!(
+            Rational::new(-8, 27).nth_root_exact(3),
+            Some(Rational::new(-2, 3))
+        );
+        assert_eq!(Rational::new(-4, 1).nth_root_exact(2), None);
+        assert_eq!(
+            Rational::new(0, 1).nth_root_exact(5),
+            Some(Rational::new(0, 1))
+        );
+        assert_eq!(
+            Rational::new(7, 1).nth_root_exact(1),
+            Some(Rational::new(7, 1))
+        );
+
+        assert_eq!(Rational::new(1, 1).nth_root_exact(0), None);
+
+        let large = Rational::from_large("1267650600228229401496703205376/3".parse().unwrap());
+        assert_eq!(large.pow(3).nth_root_exact(3), Some(large));
+    }
+
+    #[test]
+    fn split_nth_root() {
+        assert_eq!(
+            Rational::new(12, 1).split_nth_root(2),
+            (Rational::new(2, 1), Rational::new(3, 1))
+        );
+        assert_eq!(
+            Rational::new(1, 2).split_nth_root(2),
+            (Rational::new(1, 2), Rational::new(2, 1))
+        );
+        assert_eq!(
+            Rational::new(-1, 4).split_nth_root(2),
+            (Rational::new(1, 2), Rational::new(1, 1))
+        );
+        assert_eq!(
+            Rational::new(24, 125).split_nth_root(3),
+            (Rational::new(2, 5), Rational::new(3, 1))
+        );
+        assert_eq!(
+            Rational::new(5, 7).split_nth_root(3),
+            (Rational::new(1, 1), Rational::new(5, 7))
+        );
+
+        assert_eq!(
+            Rational::new(1009 * 1009 * 3, 1).split_nth_root(2),
+            (Rational::new(1009, 1), Rational::new(3, 1))
+        );
+        assert_eq!(
+            Rational::new(1009 * 1009, 1).split_nth_root(2),
+            (Rational::new(1009, 1), Rational::new(1, 1))
+        );
+        assert_eq!(
+            Rational::new(-7, 1).split_nth_root(0),
+            (Rational::new(1, 1), Rational::new(7, 1))
+        );
+        assert_eq!(
+            Rational::new(2 * 2 * 2 * 3 * 3 * 3 * 3 * 5, 7 * 7 * 7 * 7).split_nth_root(3),
+            (Rational::new(6, 7), Rational::new(15, 7))
+        );
+
+        // canonical for integers below 2^(16*(n+1))
+        let p = Rational::new(65537 * 65537 * 3 * 3 * 3, 1);
+        assert_eq!(
+            p.split_nth_root(2),
+            (Rational::new(65537 * 3, 1), Rational::new(3, 1))
+        );
+
+        // the square of a prime above 2^16 is not found next to other such primes
+        let p: Rational = (Integer::new(65537).pow(2) * &Integer::new(1000003 * 1000033)).into();
+        assert_eq!(p.split_nth_root(2), (Rational::one(), p));
+    }
+
+    #[test]
+    fn continued_fraction() {
+        let r = Rational::new(355, 113);
+        let cf: Vec<_> = r.continued_fraction().collect();
+        assert_eq!(cf, vec![3.into(), 7.into(), 16.into()]);
+        assert_eq!(Rational::from_continued_fraction(&cf), Ok(r));
+
+        assert_eq!(
+            Rational::from_continued_fraction(&[5.into()]),
+            Ok(Rational::new(5, 1))
+        );
+        assert!(Rational::from_continued_fraction(&[]).is_err());
+        assert!(Rational::from_continued_fraction(&[1.into(), 0.into()]).is_err());
+
+        for r in [
+            Rational::new(-7, 3),
+            Rational::new(0, 1),
+            Rational::from_large("-123456789012345678901234567890/7".parse().unwrap()),
+        ] {
+            let cf: Vec<_> = r.continued_fraction().collect();
+            assert_eq!(Rational::from_continued_fraction(&cf), Ok(r));
+        }
+    }
+
+    #[test]
+    fn rounding() {
+        let r = Rational::new(-7, 2);
+        assert_eq!(r.floor(), Integer::new(-4));
+        assert_eq!(r.ceil(), Integer::new(-3));
+        assert_eq!(r.trunc(), Integer::new(-3));
+        assert_eq!(r.round(), Integer::new(-4));
+
+        // half-way cases round to the even integer
+        assert_eq!(Rational::new(5, 2).round(), Integer::new(2));
+        assert_eq!(Rational::new(-5, 2).round(), Integer::new(-2));
+        assert_eq!(Rational::new(7, 2).round(), Integer::new(4));
+        assert_eq!(Rational::new(-8, 3).round(), Integer::new(-3));
+        assert_eq!(Rational::new(8, 3).trunc(), Integer::new(2));
+
+        for r in [Rational::new(-3, 1), Rational::zero()] {
+            let i = r.to_integer().unwrap();
+            assert_eq!(
+                (r.floor(), r.ceil(), r.trunc(), r.round()),
+                (i.clone(), i.clone(), i.clone(), i)
+            );
+        }
+
+        let p = Integer::new(3).pow(100);
+        let large = Rational::new(1, 2) + &Rational::from(&p);
+        assert_eq!(large.round(), &p + &Integer::one());
+        assert_eq!(large.neg().round(), -&(&p + &Integer::one()));
+        assert_eq!(large.neg().trunc(), -&p);
+    }
+
+    #[test]
+    fn stern_brocot() {
+        assert_eq!(
+            Rational::new(1, 2).mediant(&Rational::new(2, 3)),
+            (Integer::new(3), Integer::new(5))
+        );
+        assert_eq!(
+            Rational::new(1, 2).mediant(&Rational::new(3, 4)),
+            (Integer::new(4), Integer::new(6))
+        );
+        assert_eq!(
+            Rational::new(-1, 1).mediant(&Rational::new(1, 1)),
+            (Integer::new(0), Integer::new(2))
+        );
+
+        let pi = Rational::from_f64(std::f64::consts::PI);
+        for (eps, r) in [
+            (Rational::new(1, 5), Rational::new(3, 1)),
+            (Rational::new(1, 500), Rational::new(22, 7)),
+            (Rational::new(1, 1000000), Rational::new(355, 113)),
+        ] {
+            assert_eq!(pi.best_approximation_within(eps.clone()), r);
+            assert_eq!(pi.neg().best_approximation_within(eps), r.neg());
+        }
+
+        // exactly representable targets
+        let r = Rational::new(3, 4);
+        assert_eq!(r.best_approximation_within(Rational::new(1, 1000)), r);
+        assert_eq!(r.best_approximation_within(Rational::zero()), r);
+        assert_eq!(pi.best_approximation_within(Rational::zero()), pi);
+        assert_eq!(
+            Rational::new(2, 1).best_approximation_within(Rational::new(1, 2)),
+            Rational::new(2, 1)
+        );
+        assert_eq!(
+            Rational::new(1, 3).best_approximation_within(Rational::new(1, 2)),
+            Rational::zero()
+        );
+    }
+
+    #[test]
+    fn crt_accumulator() {
+        let target = Rational::new(-3, 7);
+
+        let mut acc = CrtAccumulator::new();
+        assert_eq!(acc.reconstruct(), None);
+
+        let mut last = None;
+        for p in [11u64, 13, 17, 19, 23, 29, 1000003, 4294967291] {
+            let field = Zp::new(p as u32);
+            let r = field.from_element(&target.to_finite_field(&field));
+            acc.add_residue(Integer::from(r as u64), p).unwrap();
+
+            let q = acc.reconstruct();
+            if q.is_some() && q == last {
+                break;
+            }
+            last = q;
+        }
+
+        assert_eq!(last, Some(target));
+        // the reconstruction succeeds before all primes are used
+        assert!(acc.modulus() < &Integer::from(11u64 * 13 * 17 * 19 * 23 * 29 * 1000003));
+        assert!(acc.residue() < acc.modulus());
+
+        // negative residues are reduced
+        let mut acc2 = CrtAccumulator::new();
+        acc2.add_residue(Integer::new(-1), 11).unwrap();
+        assert_eq!(acc2.residue(), &Integer::new(10));
+
+        // a repeated prime is an error and leaves the state unchanged
+        let state = acc2.clone();
+        assert!(acc2.add_residue(Integer::new(3), 11).is_err());
+        assert_eq!(acc2, state);
+        assert!(acc2.add_residue(Integer::new(3), 1).is_err());
+
+        // a modulus that shares a factor with the current modulus is an error
+        let mut acc3 = CrtAccumulator::new();
+        acc3.add_residue(Integer::new(1), 2).unwrap();
+        assert!(acc3.add_residue(Integer::new(3), 4).is_err());
+        assert_eq!(acc3.modulus(), &Integer::new(2));
+    }
+
+    #[test]
+    fn to_integer() {
+        let r = Rational::new(6, 3);
+        assert!(r.is_integer());
+        assert_eq!(r.to_integer(), Some(Integer::new(2)));
+
+        let r = Rational::new(1, 2);
+        assert!(!r.is_integer());
+        assert_eq!(r.to_integer(), None);
+
+        let r = Rational::from((
+            &Integer::new(3).pow(100) * &Integer::new(2),
+            Integer::new(2),
+        ));
+        assert_eq!(r.to_integer(), Some(Integer::new(3).pow(100)));
+        assert_eq!((&r / &Rational::new(2, 1)).to_integer(), None);
+    }
+
+    #[test]
+    fn hash_across_representations() {
+        fn hash<T: Hash>(h: &RandomState, v: &T) -> u64 {
+            h.hash_one(v)
+        }
+        let h = RandomState::new();
+
+        let a = Integer::Natural(5);
+        let b = Integer::Large(MultiPrecisionInteger::from(5));
+        let c = Integer::Double(5);
+        assert_eq!(a, b);
+        assert_eq!(a, c);
+        assert_eq!(hash(&h, &a), hash(&h, &b));
+        assert_eq!(hash(&h, &a), hash(&h, &c));
+
+        let d = Integer::Double(1 << 80);
+        let e = Integer::Large(MultiPrecisionInteger::from(1i128 << 80));
+        assert_eq!(d, e);
+        assert_eq!(hash(&h, &d), hash(&h, &e));
+        assert_ne!(a, d);
+
+        let r1 = Rational::new(-3, 7);
+        let r2 = Rational::Large(MultiPrecisionRational::from((-3, 7)));
+        assert_eq!(r1, r2);
+        assert_eq!(r2, r1);
+        assert_eq!(hash(&h, &r1), hash(&h, &r2));
+        assert_ne!(r1, Rational::Large(MultiPrecisionRational::from((3, 7))));
+    }
+
+    #[test]
+    fn serde() {
+        let rationals = [
+            Rational::new(-3, 7),
+            Rational::from(i64::MIN),
+            Rational::from_large("-123456789012345678901234567891/7".parse().unwrap()),
+        ];
+        for r in rationals {
+            let e = bincode::serialize(&r).unwrap();
+            assert_eq!(bincode::deserialize::<Rational>(&e).unwrap(), r);
+        }
+
+        let integers = [
+            Integer::Natural(-5),
+            Integer::Double(1 << 100),
+            Integer::Large("10000000000000000000000000000000000000000".parse().unwrap()),
+        ];
+        for i in integers {
+            let e = bincode::serialize(&i).unwrap();
+            assert_eq!(bincode::deserialize::<Integer>(&e).unwrap(), i);
+        }
+
+        let e = bincode::serialize(&Rational::new(1, 2)).unwrap();
+        assert!(bincode::deserialize::<Integer>(&e).is_err());
+        assert!(bincode::deserialize::<Rational>(&e[..e.len() - 1]).is_err());
+    }
+}
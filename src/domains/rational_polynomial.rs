@@ -10,6 +10,7 @@ use std::{
 use ahash::HashMap;
 
 use crate::{
+    atom::Symbol,
     poly::{
         factor::Factorize, gcd::PolynomialGCD, polynomial::MultivariatePolynomial,
         univariate::UnivariatePolynomial, Exponent, Variable,
@@ -20,7 +21,7 @@ use crate::{
 use super::{
     finite_field::{FiniteField, FiniteFieldCore, FiniteFieldWorkspace, ToFiniteField},
     integer::{IntegerRing, Z},
-    rational::RationalField,
+    rational::{Rational, RationalField, Q},
     EuclideanDomain, Field, Ring,
 };
 
@@ -328,6 +329,37 @@ where
         }
     }
 
+    /// Substitute the variable `var` by the rational polynomial `value`.
+    /// The result is reduced to lowest terms, so that common factors that are created
+    /// by the substitution cancel. Yields an error if the denominator becomes zero.
+    pub fn substitute(&self, var: &Variable, value: &Self) -> Result<Self, String> {
+        let mut r = self.clone();
+        let mut value = value.clone();
+        r.unify_variables(&mut value);
+
+        let Some(n) = r.get_variables().iter().position(|v| v == var) else {
+            return Ok(r);
+        };
+
+        let horner = |p: &MultivariatePolynomial<R, E>| -> Self {
+            let mut acc = Self::new(&p.field, p.variables.clone());
+            for c in p.to_univariate(n).coefficients.iter().rev() {
+                acc = &(&acc * &value) + &Self::from(c.clone());
+            }
+            acc
+        };
+
+        let den = horner(&r.denominator);
+        if den.is_zero() {
+            return Err(format!(
+                "The denominator vanishes after substituting {}",
+                var
+            ));
+        }
+
+        Ok(&horner(&r.numerator) / &den)
+    }
+
     /// Convert the rational polynomial to a polynomial in the specified
     /// variables, with rational polynomial coefficients.
     /// If the specified variables appear in the denominator, an `Err` is returned.
@@ -443,6 +475,108 @@ where
     }
 }
 
+impl<E: Exponent> RationalPolynomial<IntegerRing, E> {
+    /// Evaluate the rational polynomial by binding every variable to a rational number.
+    /// Yields an error if a variable is not bound or if the point is a pole.
+    pub fn evaluate(&self, bindings: &[(Symbol, Rational)]) -> Result<Rational, String> {
+        let den = self
+            .denominator
+            .map_coeff(|c| c.into(), Q)
+            .evaluate(bindings)?;
+        if den.is_zero() {
+            return Err("The denominator vanishes at the evaluation point".to_owned());
+        }
+
+        let num = self
+            .numerator
+            .map_coeff(|c| c.into(), Q)
+            .evaluate(bindings)?;
+        Ok(&num / &den)
+    }
+
+    /// Reconstruct a univariate rational function in `var` from the sample points `(x, f(x))`
+    /// using Thiele's continued fraction interpolation. For `n` distinct points, the numerator
+    /// and denominator degrees are at most `ceil((n-1)/2)` and `floor((n-1)/2)`
+    /// respectively, and are lower if the data allows.
+    ///
+    /// Duplicate points are ignored. Returns `None` if two points have the same `x`
+    /// but a different value, if the continued fraction breaks down, or if the
+    /// resulting function does not reproduce all points, for example because
+    /// it has a pole at a sample point.
+    pub fn interpolate(points: &[(Rational, Rational)], var: Variable) -> Option<Self> {
+        let mut xs: Vec<Rational> = vec![];
+        let mut phi: Vec<Rational> = vec![];
+        for (x, y) in points {
+            if let Some(p) = xs.iter().position(|xx| xx == x) {
+                if phi[p] != *y {
+                    return None;
+                }
+            } else {
+                xs.push(x.clone());
+                phi.push(y.clone());
+            }
+        }
+
+        let zero = MultivariatePolynomial::<_, E>::new(&Q, None, Arc::new(vec![var]));
+        if xs.is_empty() {
+            return Some(MultivariatePolynomial::new(&Z, None, zero.variables.clone()).into());
+        }
+
+        // compute the coefficients of the continued fraction using inverse differences
+        let mut coeffs = vec![];
+        for i in 0..xs.len() {
+            let a = phi[i].clone();
+
+            if phi[i + 1..].iter().all(|p| *p == a) {
+                coeffs.push(a);
+                break;
+            }
+
+            for j in i + 1..xs.len() {
+                let diff = &phi[j] - &a;
+                if diff.is_zero() {
+                    return None;
+                }
+                phi[j] = &(&xs[j] - &xs[i]) / &diff;
+            }
+
+            coeffs.push(a);
+        }
+
+        let constant = |r: &Rational| -> Self {
+            Self::from_num_den(zero.constant(r.clone()), zero.one(), &Z, false)
+        };
+        let x = zero.monomial(Q.one(), vec![E::one()]);
+
+        let mut res = constant(coeffs.last().unwrap());
+        for (a, xi) in coeffs.iter().zip(&xs).rev().skip(1) {
+            if res.is_zero() {
+                return None;
+            }
+
+            let shift = Self::from_num_den(&x - &zero.constant(xi.clone()), zero.one(), &Z, false);
+            res = &constant(a) + &(&shift / &res);
+        }
+
+        let eval = |p: &MultivariatePolynomial<IntegerRing, E>, x: &Rational| -> Rational {
+            let mut r = Rational::zero();
+            for t in p {
+                r += Rational::from(t.coefficient) * x.pow(t.exponents[0].to_u32() as u64);
+            }
+            r
+        };
+
+        for (x, y) in points {
+            let den = eval(&res.denominator, x);
+            if den.is_zero() || &eval(&res.numerator, x) / &den != *y {
+                return None;
+            }
+        }
+
+        Some(res)
+    }
+}
+
 impl<R: Ring, E: Exponent> Display for RationalPolynomial<R, E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         RationalPolynomialPrinter::new(self).fmt(f)
@@ -1368,4 +1502,98 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn interpolate() {
+        use crate::atom::Atom;
+        use crate::domains::rational::Rational;
+
+        let x = State::get_symbol("v1");
+        let points: Vec<_> = [(0, 1), (1, 2), (2, 3)]
+            .iter()
+            .map(|(x, d)| (Rational::from(*x), Rational::new(1, *d)))
+            .collect();
+
+        let r = RationalPolynomial::<_, u8>::interpolate(&points, x.into()).unwrap();
+        let res = Atom::parse("1/(v1+1)")
+            .unwrap()
+            .to_rational_polynomial::<_, _, u8>(&Q, &Z, Some(Arc::new(vec![x.into()])));
+        assert_eq!(r, res);
+
+        // duplicate points are ignored, conflicting ones are rejected
+        let mut dup = points.clone();
+        dup.push(points[1].clone());
+        assert_eq!(
+            RationalPolynomial::<_, u8>::interpolate(&dup, x.into()),
+            Some(res)
+        );
+        dup.push((Rational::from(1), Rational::from(5)));
+        assert_eq!(
+            RationalPolynomial::<_, u8>::interpolate(&dup, x.into()),
+            None
+        );
+
+        // no rational function of degree (1,1) passes through these points
+        let points: Vec<_> = [(0, 0), (1, 1), (2, 0)]
+            .iter()
+            .map(|(x, y)| (Rational::from(*x), Rational::from(*y)))
+            .collect();
+        assert_eq!(
+            RationalPolynomial::<_, u8>::interpolate(&points, x.into()),
+            None
+        );
+    }
+
+    #[test]
+    fn evaluate_substitute() {
+        use crate::atom::Atom;
+        let (v1, v2) = (State::get_symbol("v1"), State::get_symbol("v2"));
+        let vars = Some(Arc::new(vec![v1.into(), v2.into()]));
+        let parse = |s: &str| -> RationalPolynomial<_, u8> {
+            Atom::parse(s)
+                .unwrap()
+                .to_rational_polynomial(&Q, &Z, vars.clone())
+        };
+
+        let p = parse("(v1+1)/(v1-1)");
+        assert_eq!(p.evaluate(&[(v1, 3.into())]), Ok(2.into()));
+        assert!(p.evaluate(&[(v1, 1.into())]).is_err());
+        assert!(p.evaluate(&[(v2, 1.into())]).is_err());
+
+        // the composition with itself is the identity
+        let q = parse("(v2+1)/(v2-1)");
+        assert_eq!(p.substitute(&v1.into(), &q), Ok(parse("v2")));
+
+        // a common factor is created in the denominator
+        let p = parse("(v2^2-1)/(v1-v2)");
+        assert_eq!(
+            p.substitute(&v1.into(), &parse("2*v2+1")),
+            Ok(parse("v2-1"))
+        );
+        assert!(p.substitute(&v1.into(), &parse("v2")).is_err());
+    }
+
+    #[test]
+    fn atom_conversion() {
+        use crate::atom::Atom;
+
+        let a = Atom::parse("(v1+1)/(v1^2+1)").unwrap();
+        let p = RationalPolynomial::<_, u8>::try_from(&a).unwrap();
+        assert_eq!(
+            p.get_variables().as_slice(),
+            &[State::get_symbol("v1").into()]
+        );
+        assert_eq!(Atom::from(p), a);
+
+        // nested fractions are written over a common denominator
+        let a = Atom::parse("1/(1+1/v1)+v2/2").unwrap();
+        let p = RationalPolynomial::<_, u8>::try_from(&a).unwrap();
+        assert_eq!(
+            Atom::from(p),
+            Atom::parse("(2*v1+v2+v1*v2)/(2+2*v1)").unwrap()
+        );
+
+        assert!(RationalPolynomial::<_, u8>::try_from(&Atom::parse("f1(v1)/v1").unwrap()).is_err());
+        assert!(RationalPolynomial::<_, u8>::try_from(&Atom::parse("v1^(1/2)").unwrap()).is_err());
+    }
 }
@@ -1,10 +1,18 @@
-use ahash::HashMap;
+use std::sync::Arc;
+
+use ahash::{HashMap, HashSet};
 
 use crate::{
     atom::{Atom, AtomView, Symbol},
     coefficient::CoefficientView,
-    domains::{float::Real, rational::Rational},
-    state::State,
+    domains::{
+        float::Real,
+        integer::{Integer, Z},
+        rational::{Rational, Q},
+        rational_polynomial::RationalPolynomial,
+    },
+    poly::{evaluate::HornerScheme, Variable},
+    state::{State, Workspace},
 };
 
 type EvalFnType<T> = Box<
@@ -29,7 +37,104 @@ impl<T> EvaluationFn<T> {
     }
 }
 
+/// The number of operations in an expression. See [AtomView::count_ops].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpCounts {
+    pub add: usize,
+    pub mul: usize,
+    pub pow: usize,
+    pub fun: usize,
+}
+
+impl std::ops::AddAssign for OpCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.add += rhs.add;
+        self.mul += rhs.mul;
+        self.pow += rhs.pow;
+        self.fun += rhs.fun;
+    }
+}
+
+/// An error that occurs during the exact evaluation of an expression.
+/// See [AtomView::evaluate_rational_batch].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// The denominator vanishes at the evaluation point.
+    DivisionByZero,
+    /// The number of values in a row is not equal to the number of variables.
+    IncorrectRowLength { expected: usize, found: usize },
+    /// The expression is not a rational function in the variables.
+    NotRational,
+    /// A degree of the rational function may exceed the maximal exponent of [u16].
+    ExponentOverflow,
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::DivisionByZero => f.write_str("Division by zero"),
+            EvalError::IncorrectRowLength { expected, found } => {
+                write!(f, "Expected {} values, but found {}", expected, found)
+            }
+            EvalError::NotRational => {
+                f.write_str("The expression is not a rational function in the variables")
+            }
+            EvalError::ExponentOverflow => {
+                f.write_str("The degree of the rational function is too large")
+            }
+        }
+    }
+}
+
+/// The state of the common subexpression elimination. See [AtomView::cse_with_min_occurrences].
+struct CseState<'a> {
+    counts: HashMap<AtomView<'a>, usize>,
+    min_occurrences: usize,
+    bound: HashMap<AtomView<'a>, Symbol>,
+    bindings: Vec<(Symbol, Atom)>,
+    used_symbols: HashSet<Symbol>,
+}
+
 impl Atom {
+    /// Count the number of operations in the expression. See [AtomView::count_ops].
+    pub fn count_ops(&self, expand_integer_powers: bool) -> OpCounts {
+        self.as_view().count_ops(expand_integer_powers)
+    }
+
+    /// Remove all terms with a rational coefficient of magnitude below `tolerance`.
+    /// See [AtomView::chop].
+    pub fn chop(&self, tolerance: f64) -> Atom {
+        self.as_view().chop(tolerance)
+    }
+
+    /// Replace every fractional coefficient by a best approximation with a denominator of at most `max_den`.
+    /// See [AtomView::rationalize].
+    pub fn rationalize(&self, max_den: Option<u64>) -> Atom {
+        self.as_view().rationalize(max_den)
+    }
+
+    /// Hoist the subexpressions that occur at least twice into bindings.
+    /// See [AtomView::cse_with_min_occurrences].
+    pub fn cse(&self) -> (Vec<(Symbol, Atom)>, Atom) {
+        self.as_view().cse()
+    }
+
+    /// Hoist the subexpressions that occur at least `min_occurrences` times into bindings.
+    /// See [AtomView::cse_with_min_occurrences].
+    pub fn cse_with_min_occurrences(&self, min_occurrences: usize) -> (Vec<(Symbol, Atom)>, Atom) {
+        self.as_view().cse_with_min_occurrences(min_occurrences)
+    }
+
+    /// Evaluate the expression exactly at every row of rational values.
+    /// See [AtomView::evaluate_rational_batch].
+    pub fn evaluate_rational_batch(
+        &self,
+        var_order: &[Symbol],
+        rows: &[&[Rational]],
+    ) -> Vec<Result<Rational, EvalError>> {
+        self.as_view().evaluate_rational_batch(var_order, rows)
+    }
+
     /// Evaluate an expression using a constant map and a function map.
     /// The constant map can map any literal expression to a value, for example
     /// a variable or a function with fixed arguments.
@@ -46,6 +151,383 @@ impl Atom {
 }
 
 impl<'a> AtomView<'a> {
+    /// Count the number of additions, multiplications, powers and function calls
+    /// required to evaluate the expression. A sum or product of `n` arguments
+    /// counts as `n-1` operations, e.g. `a+b+c` has two additions.
+    ///
+    /// If `expand_integer_powers` is set, `x^n` with a positive integer `n` is counted as
+    /// `n-1` multiplications instead of a single power.
+    pub fn count_ops(&self, expand_integer_powers: bool) -> OpCounts {
+        let mut counts = OpCounts::default();
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => {}
+            AtomView::Fun(f) => {
+                counts.fun += 1;
+                for arg in f.iter() {
+                    counts += arg.count_ops(expand_integer_powers);
+                }
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                counts += base.count_ops(expand_integer_powers);
+
+                match exp {
+                    AtomView::Num(n) if expand_integer_powers => match n.get_coeff_view() {
+                        CoefficientView::Natural(e, 1) if e > 0 => counts.mul += e as usize - 1,
+                        _ => counts.pow += 1,
+                    },
+                    _ => {
+                        counts.pow += 1;
+                        counts += exp.count_ops(expand_integer_powers);
+                    }
+                }
+            }
+            AtomView::Mul(m) => {
+                counts.mul += m.get_nargs().saturating_sub(1);
+                for arg in m.iter() {
+                    counts += arg.count_ops(expand_integer_powers);
+                }
+            }
+            AtomView::Add(a) => {
+                counts.add += a.get_nargs().saturating_sub(1);
+                for arg in a.iter() {
+                    counts += arg.count_ops(expand_integer_powers);
+                }
+            }
+        }
+        counts
+    }
+
+    /// Remove all terms with a rational coefficient of magnitude below `tolerance`,
+    /// comparing by their floating point value. This removes terms with
+    /// a negligible numerical coefficient, such as `1/10^15*y` in `x+1/10^15*y`.
+    /// Terms with a symbolic coefficient are kept, and so are small numbers in function
+    /// arguments and exponents that are not a term of a sum, such as in `x^(1/10^20)`.
+    pub fn chop(&self, tolerance: f64) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.chop_no_norm(tolerance, true, ws, &mut out);
+
+            let mut norm = ws.new_atom();
+            out.as_view().normalize(ws, &mut norm);
+            norm.into_inner()
+        })
+    }
+
+    /// Returns `true` if `self` is a rational number with a magnitude below `tolerance`.
+    fn is_negligible(&self, tolerance: f64) -> bool {
+        let AtomView::Num(n) = self else {
+            return false;
+        };
+
+        let r = match n.get_coeff_view() {
+            CoefficientView::Natural(n, d) => Rational::Natural(n, d),
+            CoefficientView::Large(l) => Rational::Large(l.to_rat()),
+            CoefficientView::FiniteField(_, _) | CoefficientView::RationalPolynomial(_) => {
+                return false;
+            }
+        };
+
+        f64::from(&r).abs() < tolerance
+    }
+
+    /// Chop `self`, which is a term of a sum if `is_term` is `true`.
+    fn chop_no_norm(&self, tolerance: f64, is_term: bool, ws: &Workspace, out: &mut Atom) {
+        match self {
+            AtomView::Num(_) => {
+                if is_term && self.is_negligible(tolerance) {
+                    out.to_num(0.into());
+                } else {
+                    out.set_from_view(self);
+                }
+            }
+            AtomView::Mul(m)
+                if is_term
+                    && m.has_coefficient()
+                    && m.iter().last().is_some_and(|c| c.is_negligible(tolerance)) =>
+            {
+                out.to_num(0.into());
+            }
+            _ => {
+                let is_term = matches!(self, AtomView::Add(_));
+                self.map_children_no_norm(ws, out, |a, o| {
+                    a.chop_no_norm(tolerance, is_term, ws, o)
+                });
+            }
+        }
+    }
+
+    /// Replace every fractional coefficient by a best approximation whose denominator is at most `max_den`,
+    /// using [Rational::truncate_denominator]. This turns numbers that were obtained from floating
+    /// point data, such as `3602879701896397/36028797018963968` for `0.1`, into simple fractions.
+    /// Since the coefficients of an atom are always exact, they are kept as they are when `max_den`
+    /// is `None`. A bound of `0` is treated as `1`.
+    ///
+    /// A nonzero coefficient is never approximated by `0`, so that no term disappears: coefficients
+    /// smaller than `1/(2*max_den)` in magnitude become `±1/max_den`. Exponents are kept as they are.
+    pub fn rationalize(&self, max_den: Option<u64>) -> Atom {
+        let Some(max_den) = max_den else {
+            return self.to_owned();
+        };
+        let max_den = Integer::from(max_den.max(1));
+
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.rationalize_no_norm(&max_den, ws, &mut out);
+
+            let mut norm = ws.new_atom();
+            out.as_view().normalize(ws, &mut norm);
+            norm.into_inner()
+        })
+    }
+
+    fn rationalize_no_norm(&self, max_den: &Integer, ws: &Workspace, out: &mut Atom) {
+        match self {
+            AtomView::Num(n) => {
+                let r = match n.get_coeff_view() {
+                    CoefficientView::Natural(n, d) => Rational::Natural(n, d),
+                    CoefficientView::Large(l) => Rational::Large(l.to_rat()),
+                    CoefficientView::FiniteField(_, _) | CoefficientView::RationalPolynomial(_) => {
+                        out.set_from_view(self);
+                        return;
+                    }
+                };
+
+                if r.is_integer() {
+                    out.set_from_view(self);
+                    return;
+                }
+
+                let mut a = r.truncate_denominator(max_den);
+                if a.is_zero() && !r.is_zero() {
+                    a = (Integer::one(), max_den.clone()).into();
+                    if r.is_negative() {
+                        a = a.neg();
+                    }
+                }
+                out.to_num(a.into());
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let mut base_out = ws.new_atom();
+                base.rationalize_no_norm(max_den, ws, &mut base_out);
+                out.to_pow(base_out.as_view(), exp);
+            }
+            _ => self.map_children_no_norm(ws, out, |a, o| a.rationalize_no_norm(max_den, ws, o)),
+        }
+    }
+
+    /// Hoist the subexpressions that occur at least twice into bindings.
+    /// See [AtomView::cse_with_min_occurrences].
+    pub fn cse(&self) -> (Vec<(Symbol, Atom)>, Atom) {
+        self.cse_with_min_occurrences(2)
+    }
+
+    /// Eliminate common subexpressions by hoisting every function, power, product and sum
+    /// that occurs at least `min_occurrences` times into a binding to a fresh symbol `Z0`, `Z1`, ....
+    /// Returns the bindings, where every binding only refers to earlier ones, and the
+    /// expression in terms of the bound symbols.
+    ///
+    /// Occurrences within a repeated subexpression are only counted once, since the
+    /// subexpression is only computed once. Note that the expression is normalized, so that `x*x*x*x`
+    /// is `x^4` and does not contain the subexpression `x*x`.
+    pub fn cse_with_min_occurrences(&self, min_occurrences: usize) -> (Vec<(Symbol, Atom)>, Atom) {
+        let (bindings, mut r) = AtomView::cse_many(&[*self], min_occurrences);
+        (bindings, r.pop().unwrap())
+    }
+
+    /// Eliminate the common subexpressions of several expressions at once, so that a subexpression
+    /// that occurs at least `min_occurrences` times in all expressions combined is bound only once.
+    /// Returns the shared bindings and the expressions in terms of the bound symbols.
+    /// See [AtomView::cse_with_min_occurrences].
+    pub fn cse_many(
+        exprs: &[AtomView<'a>],
+        min_occurrences: usize,
+    ) -> (Vec<(Symbol, Atom)>, Vec<Atom>) {
+        let mut used_symbols = HashSet::default();
+        for e in exprs {
+            e.get_all_symbols_impl(true, &mut used_symbols);
+        }
+
+        let mut state = CseState {
+            counts: HashMap::default(),
+            min_occurrences: min_occurrences.max(2),
+            bound: HashMap::default(),
+            bindings: vec![],
+            used_symbols,
+        };
+        for e in exprs {
+            e.count_subexpressions(&mut state.counts);
+        }
+
+        Workspace::get_local().with(|ws| {
+            let r = exprs
+                .iter()
+                .map(|e| {
+                    let mut out = ws.new_atom();
+                    e.cse_rebuild(&mut state, ws, &mut out);
+                    out.into_inner()
+                })
+                .collect();
+            (state.bindings, r)
+        })
+    }
+
+    fn count_subexpressions(&self, counts: &mut HashMap<AtomView<'a>, usize>) {
+        if matches!(self, AtomView::Num(_) | AtomView::Var(_)) {
+            return;
+        }
+
+        let count = counts.entry(*self).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            return;
+        }
+
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => unreachable!(),
+            AtomView::Fun(f) => f.iter().for_each(|a| a.count_subexpressions(counts)),
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                base.count_subexpressions(counts);
+                exp.count_subexpressions(counts);
+            }
+            AtomView::Mul(m) => m.iter().for_each(|a| a.count_subexpressions(counts)),
+            AtomView::Add(a) => a.iter().for_each(|a| a.count_subexpressions(counts)),
+        }
+    }
+
+    fn cse_rebuild(&self, state: &mut CseState<'a>, ws: &Workspace, out: &mut Atom) {
+        if matches!(self, AtomView::Num(_) | AtomView::Var(_)) {
+            out.set_from_view(self);
+            return;
+        }
+
+        if let Some(s) = state.bound.get(self) {
+            out.to_var(*s);
+            return;
+        }
+
+        let mut t = ws.new_atom();
+        self.map_children_no_norm(ws, &mut t, |a, o| a.cse_rebuild(state, ws, o));
+        t.as_view().normalize(ws, out);
+
+        if state.counts[self] >= state.min_occurrences {
+            let symbol = (state.bindings.len()..)
+                .map(|i| State::get_symbol(format!("Z{}", i)))
+                .find(|s| !state.used_symbols.contains(s))
+                .unwrap();
+            state.used_symbols.insert(symbol);
+
+            state.bindings.push((symbol, out.clone()));
+            state.bound.insert(*self, symbol);
+            out.to_var(symbol);
+        }
+    }
+
+    /// Evaluate the expression exactly at every row of rational values, where the `i`th
+    /// value of a row is bound to the `i`th symbol of `var_order`.
+    ///
+    /// The expression is converted to a rational function once, and its numerator and
+    /// denominator are compiled to a single instruction list that is evaluated for every row.
+    /// A row at which the denominator vanishes yields [EvalError::DivisionByZero], without affecting
+    /// the other rows. If the expression is not a rational function in the variables,
+    /// every row yields [EvalError::NotRational].
+    ///
+    /// Since the rational function is canonical, common factors of the numerator and the denominator
+    /// are cancelled before evaluation. For example, `(x^2-1)/(x-1)` evaluates to `2` at `x=1`.
+    /// If a degree of the numerator or the denominator may exceed [u16::MAX],
+    /// every row yields [EvalError::ExponentOverflow].
+    pub fn evaluate_rational_batch(
+        &self,
+        var_order: &[Symbol],
+        rows: &[&[Rational]],
+    ) -> Vec<Result<Rational, EvalError>> {
+        let check_len = |row: &[Rational]| {
+            if row.len() == var_order.len() {
+                Ok(())
+            } else {
+                Err(EvalError::IncorrectRowLength {
+                    expected: var_order.len(),
+                    found: row.len(),
+                })
+            }
+        };
+
+        if self.rational_degree_bound() > u16::MAX as u64 {
+            return vec![Err(EvalError::ExponentOverflow); rows.len()];
+        }
+
+        let vars = Arc::new(var_order.iter().map(|s| Variable::Symbol(*s)).collect());
+        let r: RationalPolynomial<_, u16> = self.to_rational_polynomial(&Q, &Z, Some(vars));
+        if r.get_variables().len() != var_order.len() {
+            return vec![Err(EvalError::NotRational); rows.len()];
+        }
+
+        if r.numerator.is_zero() {
+            return rows
+                .iter()
+                .map(|row| check_len(row).map(|_| Rational::zero()))
+                .collect();
+        }
+
+        let num = r.numerator.map_coeff(|c| c.into(), Q);
+        let den = r.denominator.map_coeff(|c| c.into(), Q);
+        let (schemes, _, _) = HornerScheme::optimize_multiple(&[&num, &den], 1);
+        let mut instr = HornerScheme::to_instr_multiple(&schemes, var_order.len());
+        instr.fuse_operations();
+        let mut evaluator = instr
+            .to_output(num.variables.as_ref().clone(), true)
+            .evaluator();
+
+        rows.iter()
+            .map(|row| {
+                check_len(row)?;
+                let out = evaluator.evaluate_with_input(row);
+                if out[1].is_zero() {
+                    Err(EvalError::DivisionByZero)
+                } else {
+                    Ok(&out[0] / &out[1])
+                }
+            })
+            .collect()
+    }
+
+    /// Get an upper bound of the sum of the total degrees of the numerator and the denominator
+    /// of the expression as a rational function, during its conversion. Non-polynomial parts
+    /// are not counted.
+    fn rational_degree_bound(&self) -> u64 {
+        match self {
+            AtomView::Num(_) | AtomView::Fun(_) => 0,
+            AtomView::Var(_) => 1,
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                match exp {
+                    AtomView::Num(n) => match n.get_coeff_view() {
+                        CoefficientView::Natural(n, 1) => base
+                            .rational_degree_bound()
+                            .saturating_mul(n.unsigned_abs()),
+                        CoefficientView::Large(l) if l.to_rat().is_integer() => {
+                            if base.rational_degree_bound() == 0 {
+                                0
+                            } else {
+                                u64::MAX
+                            }
+                        }
+                        _ => 0,
+                    },
+                    _ => 0,
+                }
+            }
+            AtomView::Mul(m) => m
+                .iter()
+                .fold(0, |acc, a| acc.saturating_add(a.rational_degree_bound())),
+            AtomView::Add(a) => a
+                .iter()
+                .fold(0, |acc, a| acc.saturating_add(a.rational_degree_bound())),
+        }
+    }
+
     /// Evaluate an expression using a constant map and a function map.
     /// The constant map can map any literal expression to a value, for example
     /// a variable or a function with fixed arguments.
@@ -78,7 +560,16 @@ impl<'a> AtomView<'a> {
             ),
             AtomView::Fun(f) => {
                 let name = f.get_symbol();
-                if [State::EXP, State::LOG, State::SIN, State::COS, State::SQRT].contains(&name) {
+                if [
+                    State::EXP,
+                    State::LOG,
+                    State::SIN,
+                    State::COS,
+                    State::SQRT,
+                    State::ABS,
+                ]
+                .contains(&name)
+                {
                     assert!(f.get_nargs() == 1);
                     let arg = f.iter().next().unwrap();
                     let arg_eval = arg.evaluate(const_map, function_map, cache);
@@ -89,6 +580,7 @@ impl<'a> AtomView<'a> {
                         State::SIN => arg_eval.sin(),
                         State::COS => arg_eval.cos(),
                         State::SQRT => arg_eval.sqrt(),
+                        State::ABS => arg_eval.norm(),
                         _ => unreachable!(),
                     };
                 }
@@ -151,7 +643,12 @@ impl<'a> AtomView<'a> {
 mod test {
     use ahash::HashMap;
 
-    use crate::{atom::Atom, evaluate::EvaluationFn, state::State};
+    use crate::{
+        atom::Atom,
+        domains::rational::Rational,
+        evaluate::{EvalError, EvaluationFn, OpCounts},
+        state::State,
+    };
 
     #[test]
     fn evaluate() {
@@ -189,4 +686,186 @@ mod test {
         let r = a.evaluate::<f64>(&const_map, &fn_map, &mut cache);
         assert_eq!(r, 2905.761021719902);
     }
+
+    #[test]
+    fn count_ops() {
+        let a = Atom::parse("v1^2 + 2*v1*v2 + 1").unwrap();
+        assert_eq!(
+            a.count_ops(false),
+            OpCounts {
+                add: 2,
+                mul: 2,
+                pow: 1,
+                fun: 0
+            }
+        );
+        assert_eq!(
+            a.count_ops(true),
+            OpCounts {
+                add: 2,
+                mul: 3,
+                pow: 0,
+                fun: 0
+            }
+        );
+
+        let a = Atom::parse("f1(v1^v2, v1^(1/2))").unwrap();
+        assert_eq!(
+            a.count_ops(true),
+            OpCounts {
+                add: 0,
+                mul: 0,
+                pow: 2,
+                fun: 1
+            }
+        );
+    }
+
+    #[test]
+    fn chop() {
+        let a = Atom::parse("v1+10^-15*v2").unwrap();
+        assert_eq!(a.chop(1e-12), Atom::parse("v1").unwrap());
+        assert_eq!(a.chop(1e-18), a);
+
+        // symbolic coefficients are kept
+        let a = Atom::parse("v1+v3*v2+f1(1/10^20+v1,2/3)-10^-30").unwrap();
+        assert_eq!(a.chop(1e-12), Atom::parse("v1+v3*v2+f1(v1,2/3)").unwrap());
+
+        // small numbers that are not a term are kept
+        let a = Atom::parse("v1^(1/10^20)+f1(10^-20)+v2*(v1+10^-20)").unwrap();
+        assert_eq!(
+            a.chop(1e-12),
+            Atom::parse("v1^(1/10^20)+f1(10^-20)+v2*v1").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("10^-20*v1").unwrap().chop(1e-12),
+            Atom::new_num(0)
+        );
+    }
+
+    #[test]
+    fn rationalize() {
+        // the exact values of the doubles 0.5 and 0.1
+        let a = Atom::parse("1/2*v1+3602879701896397/36028797018963968*v2").unwrap();
+        assert_eq!(
+            a.rationalize(Some(1000)),
+            Atom::parse("1/2*v1+1/10*v2").unwrap()
+        );
+        assert_eq!(a.rationalize(None), a);
+
+        // nonzero coefficients are not approximated by 0 and exponents are kept
+        let a = Atom::parse("1/3*v1+3*v2+v3^(1/3)").unwrap();
+        assert_eq!(
+            a.rationalize(Some(1)),
+            Atom::parse("v1+3*v2+v3^(1/3)").unwrap()
+        );
+        assert_eq!(a.rationalize(Some(0)), a.rationalize(Some(1)));
+        assert_eq!(
+            Atom::parse("-1/1000*v1").unwrap().rationalize(Some(10)),
+            Atom::parse("-1/10*v1").unwrap()
+        );
+
+        // function arguments are rationalized
+        let a = Atom::parse("3*f1(1234/1001)").unwrap();
+        assert_eq!(a.rationalize(Some(20)), Atom::parse("3*f1(16/13)").unwrap());
+    }
+
+    #[test]
+    fn cse() {
+        let (bindings, r) = Atom::parse("f1(v1^2, v1^2) + v2*v1^2").unwrap().cse();
+        assert_eq!(
+            bindings,
+            vec![(State::get_symbol("Z0"), Atom::parse("v1^2").unwrap())]
+        );
+        assert_eq!(r, Atom::parse("f1(Z0, Z0) + v2*Z0").unwrap());
+
+        // the inner occurrences of a repeated subexpression are not counted
+        let (bindings, r) = Atom::parse("f1(v1+v2, f2(v1+v2)) + f2(v1+v2)*f2(v1+v2)^2")
+            .unwrap()
+            .cse();
+        assert_eq!(
+            bindings,
+            vec![
+                (State::get_symbol("Z0"), Atom::parse("v1+v2").unwrap()),
+                (State::get_symbol("Z1"), Atom::parse("f2(Z0)").unwrap())
+            ]
+        );
+        assert_eq!(r, Atom::parse("f1(Z0, Z1) + Z1^3").unwrap());
+
+        // existing symbols are not reused
+        let (bindings, r) = Atom::parse("f1(Z0 + 1) * f2(Z0 + 1)").unwrap().cse();
+        assert_eq!(
+            bindings,
+            vec![(State::get_symbol("Z1"), Atom::parse("Z0 + 1").unwrap())]
+        );
+        assert_eq!(r, Atom::parse("f1(Z1) * f2(Z1)").unwrap());
+
+        let a = Atom::parse("f1(v1^2) + f2(v1^2)").unwrap();
+        assert_eq!(a.cse_with_min_occurrences(3), (vec![], a));
+    }
+
+    #[test]
+    fn evaluate_rational_batch() {
+        let x = State::get_symbol("v1");
+        let y = State::get_symbol("v2");
+        let a = Atom::parse("1/(v1-1)").unwrap();
+
+        let rows: Vec<Vec<Rational>> = [0, 1, 3, -1].into_iter().map(|v| vec![v.into()]).collect();
+        let rows: Vec<&[Rational]> = rows.iter().map(|r| r.as_slice()).collect();
+        assert_eq!(
+            a.evaluate_rational_batch(&[x], &rows),
+            vec![
+                Ok((-1).into()),
+                Err(EvalError::DivisionByZero),
+                Ok((1, 2).into()),
+                Ok((-1, 2).into())
+            ]
+        );
+
+        let a = Atom::parse("(v1^2+3/4*v2)/(v1*v2+2)").unwrap();
+        let r = a.evaluate_rational_batch(&[x, y], &[&[2.into(), (1, 3).into()], &[1.into()]]);
+        assert_eq!(
+            r,
+            vec![
+                Ok((51, 32).into()),
+                Err(EvalError::IncorrectRowLength {
+                    expected: 2,
+                    found: 1
+                })
+            ]
+        );
+
+        let a = Atom::parse("3/4").unwrap();
+        assert_eq!(
+            a.evaluate_rational_batch(&[x], &[&[1.into()]]),
+            vec![Ok((3, 4).into())]
+        );
+
+        let a = Atom::parse("sin(v1)").unwrap();
+        assert_eq!(
+            a.evaluate_rational_batch(&[x], &[&[1.into()]]),
+            vec![Err(EvalError::NotRational)]
+        );
+
+        // the common factor is cancelled before evaluation
+        let a = Atom::parse("(v1^2-1)/(v1-1)").unwrap();
+        assert_eq!(
+            a.evaluate_rational_batch(&[x], &[&[1.into()], &[3.into()]]),
+            vec![Ok(2.into()), Ok(4.into())]
+        );
+
+        for e in ["v1^70000", "(v1^300+1)^300", "v1^40000+v1^-40000"] {
+            let a = Atom::parse(e).unwrap();
+            assert_eq!(
+                a.evaluate_rational_batch(&[x], &[&[1.into()]]),
+                vec![Err(EvalError::ExponentOverflow)]
+            );
+        }
+
+        let a = Atom::parse("v1^60000+v2").unwrap();
+        assert_eq!(
+            a.evaluate_rational_batch(&[x, y], &[&[1.into(), 2.into()]]),
+            vec![Ok(3.into())]
+        );
+    }
 }
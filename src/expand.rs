@@ -25,6 +25,12 @@ impl Atom {
     pub fn expand_into(&self, out: &mut Atom) -> bool {
         self.as_view().expand_into(None, out)
     }
+
+    /// Only expand powers of sums with an exponent of at most `max_exp`.
+    /// See [AtomView::expand_power].
+    pub fn expand_power(&self, max_exp: i64) -> Atom {
+        self.as_view().expand_power(max_exp)
+    }
 }
 
 impl<'a> AtomView<'a> {
@@ -51,6 +57,46 @@ impl<'a> AtomView<'a> {
         Workspace::get_local().with(|ws| self.expand_with_ws_into(ws, var, out))
     }
 
+    /// Only expand powers of sums `(a+b+...)^n` with a non-negative integer exponent `n` of at most `max_exp`,
+    /// using the binomial theorem. Products, and powers with a larger, negative or symbolic exponent, are left
+    /// untouched, such that `(x+1)^2*(x+2)` becomes `(x^2+2*x+1)*(x+2)`.
+    pub fn expand_power(&self, max_exp: i64) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.expand_power_no_norm(max_exp, ws, &mut out);
+
+            let mut norm = ws.new_atom();
+            out.as_view().normalize(ws, &mut norm);
+            norm.into_inner()
+        })
+    }
+
+    fn expand_power_no_norm(&self, max_exp: i64, ws: &Workspace, out: &mut Atom) {
+        match self {
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let base = base.expand_power(max_exp);
+                let exp = exp.expand_power(max_exp);
+
+                if let (AtomView::Add(a), AtomView::Num(n)) = (base.as_view(), exp.as_view()) {
+                    if let CoefficientView::Natural(n, 1) = n.get_coeff_view() {
+                        if n >= 0 && n <= max_exp {
+                            let terms: Vec<_> = a.iter().collect();
+                            let add = out.to_add();
+                            binomial_terms(&terms, n, Atom::new_num(1), &mut |t| {
+                                add.extend(t.as_view())
+                            });
+                            return;
+                        }
+                    }
+                }
+
+                out.to_pow(base.as_view(), exp.as_view());
+            }
+            _ => self.map_children_no_norm(ws, out, |a, o| a.expand_power_no_norm(max_exp, ws, o)),
+        }
+    }
+
     /// Expand an expression, returning `true` iff the expression changed.
     pub fn expand_with_ws_into(
         &self,
@@ -298,10 +344,67 @@ impl<'a> AtomView<'a> {
     }
 }
 
+/// Call `f` with every term of `factor * (terms[0] + terms[1] + ...)^n`, using
+/// `(a+b)^n = sum_k binom(n, k) a^k b^(n-k)` recursively.
+fn binomial_terms(terms: &[AtomView], n: i64, factor: Atom, f: &mut impl FnMut(Atom)) {
+    // take the power of every factor of a product, so that its coefficient is computed
+    let term_pow = |t: AtomView, k: i64| -> Atom {
+        if let AtomView::Mul(m) = t {
+            m.iter()
+                .fold(Atom::new_num(1), |acc, x| &acc * &x.to_owned().npow(k))
+        } else {
+            t.to_owned().npow(k)
+        }
+    };
+
+    if terms.len() == 1 {
+        f(&factor * &term_pow(terms[0], n));
+        return;
+    }
+
+    for k in 0..=n {
+        let c = Atom::new_num(Integer::binom(n, k));
+        binomial_terms(
+            &terms[1..],
+            n - k,
+            &(&factor * &c) * &term_pow(terms[0], k),
+            f,
+        );
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{atom::Atom, state::State};
 
+    #[test]
+    fn expand_power() {
+        let a = Atom::parse("(v1+1)^3").unwrap();
+        assert_eq!(
+            a.expand_power(5),
+            Atom::parse("v1^3+3*v1^2+3*v1+1").unwrap()
+        );
+
+        let a = Atom::parse("(v1+1)^10").unwrap();
+        assert_eq!(a.expand_power(5), a);
+
+        // products, negative and symbolic exponents are left untouched
+        let a = Atom::parse("f1((2*v1-v2)^2)*(v1+1)*(v2+1)+(v1+1)^-2+(v1+1)^v2").unwrap();
+        assert_eq!(
+            a.expand_power(5),
+            Atom::parse("f1(4*v1^2-4*v1*v2+v2^2)*(v1+1)*(v2+1)+(v1+1)^-2+(v1+1)^v2").unwrap()
+        );
+
+        let a = Atom::parse("(v1+v2+v3*(v1+1))^2").unwrap();
+        assert_eq!(
+            a.expand_power(2),
+            Atom::parse("v1^2+2*v1*v2+v2^2+2*v1*v3*(v1+1)+2*v2*v3*(v1+1)+v3^2*(v1+1)^2").unwrap()
+        );
+
+        let a = Atom::parse("1+(v1+v2)^(3-3)").unwrap();
+        assert_eq!(a.expand_power(5), Atom::new_num(2));
+    }
+
     #[test]
     fn exponent() {
         let exp = Atom::parse("(1+v1+v2)^4").unwrap().expand();
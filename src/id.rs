@@ -1,12 +1,20 @@
-use ahash::HashSet;
+use std::collections::BTreeSet;
+
+use ahash::{HashMap, HashSet};
 use dyn_clone::DynClone;
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
     atom::{representation::ListSlice, Atom, AtomType, AtomView, Num, SliceType, Symbol},
-    state::{State, Workspace},
+    state::{State, StateMap, Workspace},
     transformer::{Transformer, TransformerError},
 };
 
+/// A pattern that can be matched to an expression. Wildcards may occur anywhere,
+/// including in the exponent of a power. The wildcards in the right-hand side of a replacement
+/// are substituted before it is normalized, so that `x^n_ -> n_*x^(n_-1)` computes `n_-1`.
+///
+/// Since `x^1` is normalized to `x`, a power pattern such as `x^n_` does not match `x`.
 #[derive(Clone)]
 pub enum Pattern {
     Literal(Atom),
@@ -31,10 +39,76 @@ impl Atom {
         out
     }
 
+    /// Get the set of symbols the expression depends on, in a deterministic order.
+    /// See [AtomView::free_symbols].
+    pub fn free_symbols(&self, include_function_heads: bool) -> BTreeSet<Symbol> {
+        self.as_view().free_symbols(include_function_heads)
+    }
+
     /// Returns true iff `self` contains the symbol `s`.
     pub fn contains_symbol(&self, s: Symbol) -> bool {
         self.as_view().contains_symbol(s)
     }
+
+    /// Substitute the variable `x` by `value`, respecting bound variables.
+    /// See [AtomView::substitute].
+    pub fn substitute(&self, x: Symbol, value: AtomView, binders: &[(Symbol, usize)]) -> Atom {
+        self.as_view().substitute(x, value, binders)
+    }
+
+    /// Rename the symbol `from` to `to`, including function names.
+    /// See [AtomView::rename_symbol].
+    pub fn rename_symbol(&self, from: Symbol, to: Symbol) -> Atom {
+        self.as_view().rename_symbol(from, to)
+    }
+
+    /// Apply all replacements simultaneously. At every position in the expression, the
+    /// replacements are tried in order and only the first matching one is applied.
+    /// The right-hand side of a replacement is never matched again, so that
+    /// `{x -> y, y -> x}` swaps `x` and `y`.
+    pub fn replace_multiple(&self, replacements: &[Replacement<'_>]) -> Atom {
+        self.as_view().replace_multiple(replacements)
+    }
+
+    /// Replace all occurrences of `pattern` by `rhs`, returning the result and `true` iff
+    /// at least one replacement was applied. A replacement that yields the same expression,
+    /// such as `x -> x`, still counts as applied.
+    pub fn replace_all_checked(&self, pattern: &Pattern, rhs: &Pattern) -> (Atom, bool) {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            let matched =
+                pattern.replace_all_with_ws_into(self.as_view(), rhs, ws, None, None, &mut out);
+            (out.into_inner(), matched)
+        })
+    }
+
+    /// Replace all occurrences of `pattern` by `rhs` until the expression no longer changes
+    /// or `max_iterations` replacement rounds have been applied.
+    /// Returns the result and the number of rounds that changed the expression.
+    pub fn replace_all_repeat(
+        &self,
+        pattern: &Pattern,
+        rhs: &Pattern,
+        max_iterations: usize,
+    ) -> (Atom, usize) {
+        Workspace::get_local().with(|ws| {
+            let mut cur = ws.new_atom();
+            cur.set_from_view(&self.as_view());
+            let mut next = ws.new_atom();
+
+            for i in 0..max_iterations {
+                if !pattern.replace_all_with_ws_into(cur.as_view(), rhs, ws, None, None, &mut next)
+                    || *next == *cur
+                {
+                    return (cur.into_inner(), i);
+                }
+
+                std::mem::swap(&mut cur, &mut next);
+            }
+
+            (cur.into_inner(), max_iterations)
+        })
+    }
 }
 
 impl<'a> AtomView<'a> {
@@ -49,15 +123,28 @@ impl<'a> AtomView<'a> {
         out
     }
 
-    fn get_all_symbols_impl(&self, include_function_symbols: bool, out: &mut HashSet<Symbol>) {
+    /// Get the set of symbols the expression depends on, in a deterministic order.
+    /// Function heads are only included if `include_function_heads` is set.
+    /// Constant expressions yield an empty set.
+    pub fn free_symbols(&self, include_function_heads: bool) -> BTreeSet<Symbol> {
+        let mut out = BTreeSet::new();
+        self.get_all_symbols_impl(include_function_heads, &mut out);
+        out
+    }
+
+    pub(crate) fn get_all_symbols_impl<E: Extend<Symbol>>(
+        &self,
+        include_function_symbols: bool,
+        out: &mut E,
+    ) {
         match self {
             AtomView::Num(_) => {}
             AtomView::Var(v) => {
-                out.insert(v.get_symbol());
+                out.extend(std::iter::once(v.get_symbol()));
             }
             AtomView::Fun(f) => {
                 if include_function_symbols {
-                    out.insert(f.get_symbol());
+                    out.extend(std::iter::once(f.get_symbol()));
                 }
                 for arg in f.iter() {
                     arg.get_all_symbols_impl(include_function_symbols, out);
@@ -81,6 +168,47 @@ impl<'a> AtomView<'a> {
         }
     }
 
+    /// Apply all replacements simultaneously. At every position in the expression, the
+    /// replacements are tried in order and only the first matching one is applied.
+    /// The right-hand side of a replacement is never matched again, so that
+    /// `{x -> y, y -> x}` swaps `x` and `y`.
+    pub fn replace_multiple(&self, replacements: &[Replacement<'_>]) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.replace_multiple_with_ws_into(replacements, ws, &mut out);
+            out.into_inner()
+        })
+    }
+
+    /// Apply all replacements simultaneously, returning `true` iff a match was found.
+    /// See [AtomView::replace_multiple].
+    pub fn replace_multiple_with_ws_into(
+        &self,
+        replacements: &[Replacement<'_>],
+        workspace: &Workspace,
+        out: &mut Atom,
+    ) -> bool {
+        let mut matcher = workspace.take_matcher();
+        let matched = Replacement::replace_all_no_norm(
+            replacements,
+            *self,
+            workspace,
+            0,
+            &mut vec![],
+            &mut matcher,
+            out,
+        );
+        workspace.return_matcher(matcher);
+
+        if matched {
+            let mut norm = workspace.new_atom();
+            out.as_view().normalize(workspace, &mut norm);
+            std::mem::swap(out, &mut norm);
+        }
+
+        matched
+    }
+
     /// Returns true iff `self` contains the symbol `s`.
     pub fn contains_symbol(&self, s: Symbol) -> bool {
         match self {
@@ -106,6 +234,276 @@ impl<'a> AtomView<'a> {
             AtomView::Add(a) => a.iter().any(|x| x.contains_symbol(s)),
         }
     }
+
+    /// Substitute the variable `x` by `value`, respecting bound variables.
+    ///
+    /// Every entry `(f, i)` of `binders` declares that the `i`th argument of the function `f`
+    /// is a variable that is bound in all arguments of `f`, for example `(sum, 0)` for
+    /// `sum(k, 1, n, k^2)`. Substituting a bound variable has no effect inside its scope, and
+    /// a bound variable that would capture a symbol of `value` is renamed first.
+    pub fn substitute(&self, x: Symbol, value: AtomView, binders: &[(Symbol, usize)]) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.substitute_no_norm(
+                x,
+                value,
+                &value.get_all_symbols(false),
+                binders,
+                ws,
+                &mut out,
+            );
+
+            let mut norm = ws.new_atom();
+            out.as_view().normalize(ws, &mut norm);
+            norm.into_inner()
+        })
+    }
+
+    /// Rename the symbol `from` to `to`, including function names, in a single pass over
+    /// the expression without any pattern matching.
+    ///
+    /// The result is only renormalized when the renaming can change it, for example when `to`
+    /// already occurs, such that renaming `y` to `x` in `x+y` yields `2*x`, or when the
+    /// renaming changes the order of the symbols in the expression.
+    pub fn rename_symbol(&self, from: Symbol, to: Symbol) -> Atom {
+        if from == to || !self.contains_symbol(from) {
+            return self.to_owned();
+        }
+
+        let state_map = StateMap {
+            symbols: std::iter::once((from.get_id(), to)).collect(),
+            finite_fields: HashMap::default(),
+            galois_fields: HashMap::default(),
+            variables_lists: HashMap::default(),
+        };
+
+        // the term order and the normalization only depend on the relative order of the symbols and
+        // their attributes, so if those are unchanged and no symbols merge, the result is normalized
+        let (lo, hi) = (
+            from.get_id().min(to.get_id()),
+            from.get_id().max(to.get_id()),
+        );
+        let same_attributes = from.get_wildcard_level() == to.get_wildcard_level()
+            && from.is_symmetric() == to.is_symmetric()
+            && from.is_antisymmetric() == to.is_antisymmetric()
+            && from.is_linear() == to.is_linear();
+        let keeps_order = self
+            .get_all_symbols(true)
+            .iter()
+            .all(|s| s.get_id() <= lo || s.get_id() >= hi);
+        let is_plain = |s: Symbol| {
+            !State::is_builtin(s)
+                && State::get_normalization_rules(s).is_none()
+                && State::get_assumptions(s) == Default::default()
+        };
+
+        if !same_attributes
+            || !keeps_order
+            || self.contains_symbol(to)
+            || !is_plain(from)
+            || !is_plain(to)
+        {
+            return self.rename(&state_map);
+        }
+
+        Workspace::get_local().with(|ws| {
+            let mut out = Atom::new();
+            self.rename_no_norm(&state_map, true, ws, &mut out);
+            out
+        })
+    }
+
+    fn substitute_no_norm(
+        &self,
+        x: Symbol,
+        value: AtomView,
+        value_symbols: &HashSet<Symbol>,
+        binders: &[(Symbol, usize)],
+        ws: &Workspace,
+        out: &mut Atom,
+    ) {
+        match self {
+            AtomView::Var(v) => {
+                if v.get_symbol() == x {
+                    out.set_from_view(&value);
+                } else {
+                    out.set_from_view(self);
+                }
+            }
+            AtomView::Fun(f) => {
+                let bound = binders
+                    .iter()
+                    .find(|(s, _)| *s == f.get_symbol())
+                    .and_then(|(_, i)| f.iter().nth(*i));
+
+                if let Some(AtomView::Var(k)) = bound {
+                    let k = k.get_symbol();
+                    if k == x || !self.contains_symbol(x) {
+                        out.set_from_view(self);
+                        return;
+                    }
+
+                    if value_symbols.contains(&k) {
+                        // rename the bound variable to a symbol that does not occur anywhere
+                        let body_symbols = self.get_all_symbols(true);
+                        let name = State::get_name(k);
+                        let fresh = (1..)
+                            .map(|i| State::get_symbol(format!("{}{}", name, i)))
+                            .find(|s| !value_symbols.contains(s) && !body_symbols.contains(s))
+                            .unwrap();
+
+                        let fresh_var = Atom::new_var(fresh);
+                        let fresh_symbols = std::iter::once(fresh).collect();
+
+                        let mut renamed = ws.new_atom();
+                        let fun = renamed.to_fun(f.get_symbol());
+                        let mut arg_buf = ws.new_atom();
+                        for arg in f.iter() {
+                            arg.substitute_no_norm(
+                                k,
+                                fresh_var.as_view(),
+                                &fresh_symbols,
+                                binders,
+                                ws,
+                                &mut arg_buf,
+                            );
+                            fun.add_arg(arg_buf.as_view());
+                        }
+
+                        let mut renamed_norm = ws.new_atom();
+                        renamed.as_view().normalize(ws, &mut renamed_norm);
+                        renamed_norm.as_view().substitute_no_norm(
+                            x,
+                            value,
+                            value_symbols,
+                            binders,
+                            ws,
+                            out,
+                        );
+                        return;
+                    }
+                }
+
+                self.map_children_no_norm(ws, out, |a, o| {
+                    a.substitute_no_norm(x, value, value_symbols, binders, ws, o)
+                });
+            }
+            _ => self.map_children_no_norm(ws, out, |a, o| {
+                a.substitute_no_norm(x, value, value_symbols, binders, ws, o)
+            }),
+        }
+    }
+
+    /// Check if `self` and `other` are equal up to a consistent renaming of their variables.
+    /// Returns the renaming from the variables of `self` to those of `other` if one exists.
+    ///
+    /// Function names are never renamed and numbers must match exactly. The renaming is
+    /// injective, so that `x+x` and `a+b` are not equal up to renaming.
+    pub fn equal_up_to_renaming(&self, other: &AtomView) -> Option<HashMap<Symbol, Symbol>> {
+        let mut map = HashMap::default();
+        let mut inv = HashMap::default();
+        if self.match_renaming(other, &mut map, &mut inv) {
+            Some(map)
+        } else {
+            None
+        }
+    }
+
+    fn match_renaming(
+        &self,
+        other: &AtomView,
+        map: &mut HashMap<Symbol, Symbol>,
+        inv: &mut HashMap<Symbol, Symbol>,
+    ) -> bool {
+        match (self, other) {
+            (AtomView::Num(_), AtomView::Num(_)) => self == other,
+            (AtomView::Var(v1), AtomView::Var(v2)) => {
+                let (s1, s2) = (v1.get_symbol(), v2.get_symbol());
+                match (map.get(&s1), inv.get(&s2)) {
+                    (Some(t), _) => *t == s2,
+                    (None, Some(_)) => false,
+                    (None, None) => {
+                        map.insert(s1, s2);
+                        inv.insert(s2, s1);
+                        true
+                    }
+                }
+            }
+            (AtomView::Fun(f1), AtomView::Fun(f2)) => {
+                if f1.get_symbol() != f2.get_symbol() || f1.get_nargs() != f2.get_nargs() {
+                    return false;
+                }
+
+                if f1.get_symbol().is_symmetric() {
+                    let a: Vec<_> = f1.iter().collect();
+                    let b: Vec<_> = f2.iter().collect();
+                    Self::match_renaming_unordered(&a, &b, &mut vec![false; b.len()], map, inv)
+                } else {
+                    f1.iter()
+                        .zip(f2.iter())
+                        .all(|(a, b)| a.match_renaming(&b, map, inv))
+                }
+            }
+            (AtomView::Pow(p1), AtomView::Pow(p2)) => {
+                let (b1, e1) = p1.get_base_exp();
+                let (b2, e2) = p2.get_base_exp();
+                b1.match_renaming(&b2, map, inv) && e1.match_renaming(&e2, map, inv)
+            }
+            (AtomView::Mul(m1), AtomView::Mul(m2)) => {
+                if m1.get_nargs() != m2.get_nargs() {
+                    return false;
+                }
+
+                let a: Vec<_> = m1.iter().collect();
+                let b: Vec<_> = m2.iter().collect();
+                Self::match_renaming_unordered(&a, &b, &mut vec![false; b.len()], map, inv)
+            }
+            (AtomView::Add(a1), AtomView::Add(a2)) => {
+                if a1.get_nargs() != a2.get_nargs() {
+                    return false;
+                }
+
+                let a: Vec<_> = a1.iter().collect();
+                let b: Vec<_> = a2.iter().collect();
+                Self::match_renaming_unordered(&a, &b, &mut vec![false; b.len()], map, inv)
+            }
+            _ => false,
+        }
+    }
+
+    /// Match the arguments of a commutative operation in any order, backtracking
+    /// when a choice leads to an inconsistent renaming.
+    fn match_renaming_unordered(
+        a: &[AtomView],
+        b: &[AtomView],
+        used: &mut [bool],
+        map: &mut HashMap<Symbol, Symbol>,
+        inv: &mut HashMap<Symbol, Symbol>,
+    ) -> bool {
+        let Some((first, rest)) = a.split_first() else {
+            return true;
+        };
+
+        for (j, bb) in b.iter().enumerate() {
+            if used[j] {
+                continue;
+            }
+
+            let mut new_map = map.clone();
+            let mut new_inv = inv.clone();
+            if first.match_renaming(bb, &mut new_map, &mut new_inv) {
+                used[j] = true;
+                if Self::match_renaming_unordered(rest, b, used, &mut new_map, &mut new_inv) {
+                    *map = new_map;
+                    *inv = new_inv;
+                    return true;
+                }
+                used[j] = false;
+            }
+        }
+
+        false
+    }
 }
 
 impl Pattern {
@@ -498,7 +896,10 @@ impl Pattern {
                                     }
                                 },
                                 Match::FunctionName(_) => {
-                                    unreachable!("Wildcard cannot be function name")
+                                    // a function name is used as a symbol
+                                    let mut handle = workspace.new_atom();
+                                    w.to_atom(&mut handle);
+                                    func.add_arg(handle.as_view())
                                 }
                             }
 
@@ -525,14 +926,11 @@ impl Pattern {
                         if let Some(w) = match_stack.get(*w) {
                             match w {
                                 Match::Single(s) => out.set_from_view(s),
-                                Match::Multiple(_, _) => {
+                                Match::Multiple(_, _) | Match::FunctionName(_) => {
                                     let mut handle = workspace.new_atom();
                                     w.to_atom(&mut handle);
                                     out.set_from_view(&handle.as_view())
                                 }
-                                Match::FunctionName(_) => {
-                                    unreachable!("Wildcard cannot be function name")
-                                }
                             }
 
                             continue;
@@ -572,7 +970,9 @@ impl Pattern {
                                     }
                                 },
                                 Match::FunctionName(_) => {
-                                    unreachable!("Wildcard cannot be function name")
+                                    let mut handle = workspace.new_atom();
+                                    w.to_atom(&mut handle);
+                                    mul.extend(handle.as_view())
                                 }
                             }
 
@@ -610,7 +1010,9 @@ impl Pattern {
                                     }
                                 },
                                 Match::FunctionName(_) => {
-                                    unreachable!("Wildcard cannot be function name")
+                                    let mut handle = workspace.new_atom();
+                                    w.to_atom(&mut handle);
+                                    add.extend(handle.as_view())
                                 }
                             }
 
@@ -699,15 +1101,24 @@ impl Pattern {
         settings: Option<&MatchSettings>,
         out: &mut Atom,
     ) -> bool {
-        let matched = self.replace_all_no_norm(
-            target,
+        let r = Replacement {
+            pat: self,
             rhs,
+            conditions,
+            settings,
+        };
+
+        let mut matcher = workspace.take_matcher();
+        let matched = Replacement::replace_all_no_norm(
+            std::slice::from_ref(&r),
+            target,
             workspace,
-            conditions.unwrap_or(&Condition::default()),
-            settings.unwrap_or(&MatchSettings::default()),
             0,
+            &mut vec![],
+            &mut matcher,
             out,
         );
+        workspace.return_matcher(matcher);
 
         if matched {
             let mut norm = workspace.new_atom();
@@ -718,33 +1129,103 @@ impl Pattern {
         matched
     }
 
-    /// Replace all occurrences of the pattern in the target, without normalizing the output.
-    fn replace_all_no_norm(
-        &self,
-        target: AtomView<'_>,
-        rhs: &Pattern,
+    pub fn pattern_match<'a>(
+        &'a self,
+        target: AtomView<'a>,
+        conditions: &'a Condition<WildcardAndRestriction>,
+        settings: &'a MatchSettings,
+    ) -> PatternAtomTreeIterator<'a, 'a> {
+        PatternAtomTreeIterator::new(self, target, conditions, settings)
+    }
+}
+
+/// A replacement of a pattern by a right-hand side, with optional conditions and settings.
+/// Used to apply multiple replacements simultaneously with [Atom::replace_multiple].
+#[derive(Clone, Copy)]
+pub struct Replacement<'a> {
+    pat: &'a Pattern,
+    rhs: &'a Pattern,
+    conditions: Option<&'a Condition<WildcardAndRestriction>>,
+    settings: Option<&'a MatchSettings>,
+}
+
+impl<'a> Replacement<'a> {
+    /// Create a replacement of `pat` by `rhs`.
+    pub fn new(pat: &'a Pattern, rhs: &'a Pattern) -> Self {
+        Replacement {
+            pat,
+            rhs,
+            conditions: None,
+            settings: None,
+        }
+    }
+
+    /// Set the conditions on the wildcards of the pattern.
+    pub fn with_conditions(mut self, conditions: &'a Condition<WildcardAndRestriction>) -> Self {
+        self.conditions = Some(conditions);
+        self
+    }
+
+    /// Set the match settings of the pattern.
+    pub fn with_settings(mut self, settings: &'a MatchSettings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Replace all occurrences of the patterns in the target, without normalizing the output.
+    /// At every position the replacements are tried in order and the first one that matches is applied.
+    /// The `level` is the function depth and `ancestors` are the subexpressions that contain
+    /// `target`, outermost first. The number of ancestors is the expression tree depth.
+    fn replace_all_no_norm<'t>(
+        replacements: &[Replacement<'_>],
+        target: AtomView<'t>,
         workspace: &Workspace,
-        conditions: &Condition<WildcardAndRestriction>,
-        settings: &MatchSettings,
         level: usize,
+        ancestors: &mut Vec<AtomView<'t>>,
+        matcher: &mut Matcher,
         out: &mut Atom,
     ) -> bool {
-        if let Some(max_level) = settings.level_range.1 {
-            if level > max_level {
-                out.set_from_view(&target);
-                return false;
+        let default_settings = MatchSettings::default();
+        let default_conditions = Condition::default();
+
+        let tree_level = ancestors.len();
+        let mut all_above_max_level = true;
+        for r in replacements {
+            let settings = r.settings.unwrap_or(&default_settings);
+            let conditions = r.conditions.unwrap_or(&default_conditions);
+            let cur_level = if settings.level_is_tree_depth {
+                tree_level
+            } else {
+                level
+            };
+
+            if let Some(max_level) = settings.level_range.1 {
+                if cur_level > max_level {
+                    continue;
+                }
             }
-        }
 
-        if level >= settings.level_range.0 && self.could_match(target) {
-            let mut match_stack = MatchStack::new(conditions, settings);
+            if settings.scope == ReplaceScope::TopLevel && tree_level > 1 {
+                continue;
+            }
+            all_above_max_level = false;
 
-            let mut it = AtomMatchIterator::new(self, target);
-            //let mut it = SubSliceIterator::new(self, target, &match_stack, true);
+            if cur_level < settings.level_range.0
+                || !settings.scope.contains(ancestors.iter().copied())
+                || !r.pat.could_match(target)
+            {
+                continue;
+            }
+
+            let mut match_stack = matcher.acquire(conditions, settings);
+
+            let mut it = AtomMatchIterator::new(r.pat, target);
             if let Some((_, used_flags)) = it.next(&mut match_stack) {
                 let mut rhs_subs = workspace.new_atom();
-                rhs.substitute_wildcards(workspace, &mut rhs_subs, &match_stack)
+                r.rhs
+                    .substitute_wildcards(workspace, &mut rhs_subs, &match_stack)
                     .unwrap(); // TODO: escalate?
+                matcher.release(match_stack);
 
                 if used_flags.iter().all(|x| *x) {
                     // all used, return rhs
@@ -782,9 +1263,17 @@ impl Pattern {
 
                 return true;
             }
+
+            matcher.release(match_stack);
+        }
+
+        if all_above_max_level && !replacements.is_empty() {
+            out.set_from_view(&target);
+            return false;
         }
 
         // no match found at this level, so check the children
+        ancestors.push(target);
         let submatch = match target {
             AtomView::Fun(f) => {
                 let out = out.to_fun(f.get_symbol());
@@ -794,13 +1283,13 @@ impl Pattern {
                 for child in f.iter() {
                     let mut child_buf = workspace.new_atom();
 
-                    submatch |= self.replace_all_no_norm(
+                    submatch |= Replacement::replace_all_no_norm(
+                        replacements,
                         child,
-                        rhs,
                         workspace,
-                        conditions,
-                        settings,
                         level + 1,
+                        ancestors,
+                        matcher,
                         &mut child_buf,
                     );
 
@@ -814,32 +1303,24 @@ impl Pattern {
                 let (base, exp) = p.get_base_exp();
 
                 let mut base_out = workspace.new_atom();
-                let mut submatch = self.replace_all_no_norm(
+                let mut submatch = Replacement::replace_all_no_norm(
+                    replacements,
                     base,
-                    rhs,
                     workspace,
-                    conditions,
-                    settings,
-                    if settings.level_is_tree_depth {
-                        level + 1
-                    } else {
-                        level
-                    },
+                    level,
+                    ancestors,
+                    matcher,
                     &mut base_out,
                 );
 
                 let mut exp_out = workspace.new_atom();
-                submatch |= self.replace_all_no_norm(
+                submatch |= Replacement::replace_all_no_norm(
+                    replacements,
                     exp,
-                    rhs,
                     workspace,
-                    conditions,
-                    settings,
-                    if settings.level_is_tree_depth {
-                        level + 1
-                    } else {
-                        level
-                    },
+                    level,
+                    ancestors,
+                    matcher,
                     &mut exp_out,
                 );
 
@@ -854,17 +1335,13 @@ impl Pattern {
                 for child in m.iter() {
                     let mut child_buf = workspace.new_atom();
 
-                    submatch |= self.replace_all_no_norm(
+                    submatch |= Replacement::replace_all_no_norm(
+                        replacements,
                         child,
-                        rhs,
                         workspace,
-                        conditions,
-                        settings,
-                        if settings.level_is_tree_depth {
-                            level + 1
-                        } else {
-                            level
-                        },
+                        level,
+                        ancestors,
+                        matcher,
                         &mut child_buf,
                     );
 
@@ -881,17 +1358,13 @@ impl Pattern {
                 for child in a.iter() {
                     let mut child_buf = workspace.new_atom();
 
-                    submatch |= self.replace_all_no_norm(
+                    submatch |= Replacement::replace_all_no_norm(
+                        replacements,
                         child,
-                        rhs,
                         workspace,
-                        conditions,
-                        settings,
-                        if settings.level_is_tree_depth {
-                            level + 1
-                        } else {
-                            level
-                        },
+                        level,
+                        ancestors,
+                        matcher,
                         &mut child_buf,
                     );
 
@@ -905,17 +1378,144 @@ impl Pattern {
                 false
             }
         };
+        ancestors.pop();
 
         submatch
     }
+}
 
-    pub fn pattern_match<'a>(
-        &'a self,
-        target: AtomView<'a>,
-        conditions: &'a Condition<WildcardAndRestriction>,
-        settings: &'a MatchSettings,
-    ) -> PatternAtomTreeIterator<'a, 'a> {
-        PatternAtomTreeIterator::new(self, target, conditions, settings)
+/// A pattern together with its conditions and match settings, that is constructed once
+/// and can then be reused for many replacements. Besides the pattern tree, the symbols that
+/// every match must contain are computed ahead of time, so that expressions that cannot
+/// match are returned without walking the matcher over every subexpression.
+/// The conditions are owned, so that pattern restrictions with closures are carried along.
+///
+/// # Examples
+///
+/// ```
+/// use symbolica::{atom::Atom, id::{CompiledPattern, Pattern}};
+///
+/// let p = CompiledPattern::new("x_^2").unwrap();
+/// let rhs = Pattern::parse("f(x_)").unwrap();
+///
+/// let a = Atom::parse("y^2 + g(z^2)").unwrap();
+/// assert_eq!(a.replace_all(&p, &rhs), Atom::parse("f(y) + g(f(z))").unwrap());
+/// ```
+#[derive(Clone)]
+pub struct CompiledPattern {
+    pattern: Pattern,
+    conditions: Condition<WildcardAndRestriction>,
+    settings: MatchSettings,
+    required_symbols: Vec<Symbol>,
+}
+
+impl CompiledPattern {
+    /// Parse and compile a pattern.
+    pub fn new(input: &str) -> Result<CompiledPattern, String> {
+        Ok(Pattern::parse(input)?.into())
+    }
+
+    /// Set the conditions on the wildcards of the pattern.
+    pub fn with_conditions(mut self, conditions: Condition<WildcardAndRestriction>) -> Self {
+        self.conditions = conditions;
+        self
+    }
+
+    /// Set the match settings of the pattern.
+    pub fn with_settings(mut self, settings: MatchSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Get the underlying pattern.
+    pub fn get_pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Create a replacement of this pattern by `rhs`, that can be used in [Atom::replace_multiple].
+    pub fn replacement<'a>(&'a self, rhs: &'a Pattern) -> Replacement<'a> {
+        Replacement::new(&self.pattern, rhs)
+            .with_conditions(&self.conditions)
+            .with_settings(&self.settings)
+    }
+
+    /// Returns `true` if the pattern could match a subexpression of `target`,
+    /// i.e., if `target` contains all the symbols that occur literally in the pattern.
+    pub fn could_match_in(&self, target: AtomView<'_>) -> bool {
+        self.required_symbols
+            .iter()
+            .all(|s| target.contains_symbol(*s))
+    }
+
+    /// Collect the symbols that any atom matched by `pattern` must contain.
+    fn collect_required_symbols(pattern: &Pattern, out: &mut HashSet<Symbol>) {
+        match pattern {
+            Pattern::Literal(a) => a.as_view().get_all_symbols_impl(true, out),
+            Pattern::Wildcard(_) | Pattern::Transformer(_) => {}
+            Pattern::Fn(name, args) => {
+                if name.get_wildcard_level() == 0 {
+                    out.insert(*name);
+                }
+                for arg in args {
+                    Self::collect_required_symbols(arg, out);
+                }
+            }
+            Pattern::Pow(base_exp) => {
+                for arg in base_exp.iter() {
+                    Self::collect_required_symbols(arg, out);
+                }
+            }
+            Pattern::Mul(args) | Pattern::Add(args) => {
+                for arg in args {
+                    Self::collect_required_symbols(arg, out);
+                }
+            }
+        }
+    }
+}
+
+impl From<Pattern> for CompiledPattern {
+    fn from(pattern: Pattern) -> Self {
+        let mut required_symbols = HashSet::default();
+        CompiledPattern::collect_required_symbols(&pattern, &mut required_symbols);
+
+        CompiledPattern {
+            pattern,
+            conditions: Condition::default(),
+            settings: MatchSettings::default(),
+            required_symbols: required_symbols.into_iter().collect(),
+        }
+    }
+}
+
+impl From<&Atom> for CompiledPattern {
+    fn from(atom: &Atom) -> Self {
+        atom.into_pattern().into()
+    }
+}
+
+impl Atom {
+    /// Replace all occurrences of the compiled pattern by `rhs`.
+    /// See [Pattern::replace_all].
+    pub fn replace_all(&self, pattern: &CompiledPattern, rhs: &Pattern) -> Atom {
+        self.as_view().replace_all(pattern, rhs)
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Replace all occurrences of the compiled pattern by `rhs`.
+    /// See [Pattern::replace_all].
+    pub fn replace_all(&self, pattern: &CompiledPattern, rhs: &Pattern) -> Atom {
+        if !pattern.could_match_in(*self) {
+            return self.to_owned();
+        }
+
+        pattern.pattern.replace_all(
+            *self,
+            rhs,
+            Some(&pattern.conditions),
+            Some(&pattern.settings),
+        )
     }
 }
 
@@ -1309,6 +1909,40 @@ pub struct MatchSettings {
     pub level_range: (usize, Option<usize>),
     /// Determine whether a level reflects the expression tree depth or the function depth.
     pub level_is_tree_depth: bool,
+    /// Restrict the positions in the expression at which the pattern is allowed to match.
+    pub scope: ReplaceScope,
+}
+
+/// The positions in an expression at which a pattern is allowed to match.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplaceScope {
+    /// Match anywhere in the expression.
+    #[default]
+    Everywhere,
+    /// Only match the expression itself or, if it is a sum, its summands.
+    TopLevel,
+    /// Only match inside the arguments of a function with the given head.
+    /// The function itself is not matched, unless it is nested in
+    /// another function with the same head.
+    InsideFunction(Symbol),
+}
+
+impl ReplaceScope {
+    /// Check if a subexpression whose ancestors are `ancestors`, outermost first,
+    /// is inside the scope.
+    fn contains<'a>(&self, mut ancestors: impl ExactSizeIterator<Item = AtomView<'a>>) -> bool {
+        match self {
+            ReplaceScope::Everywhere => true,
+            ReplaceScope::TopLevel => match ancestors.len() {
+                0 => true,
+                1 => matches!(ancestors.next(), Some(AtomView::Add(_))),
+                _ => false,
+            },
+            ReplaceScope::InsideFunction(s) => {
+                ancestors.any(|a| matches!(a, AtomView::Fun(f) if f.get_symbol() == *s))
+            }
+        }
+    }
 }
 
 /// An insertion-ordered map of wildcard identifiers to a subexpressions.
@@ -1381,6 +2015,12 @@ impl<'a, 'b> MatchStack<'a, 'b> {
         self.stack.len()
     }
 
+    /// Remove all matches, keeping the allocated memory.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.stack.clear()
+    }
+
     /// Truncate the stack to `len`.
     #[inline]
     pub fn truncate(&mut self, len: usize) {
@@ -1426,6 +2066,63 @@ impl<'a, 'b> MatchStack<'a, 'b> {
     }
 }
 
+/// A pool of match stacks that can be reused between pattern matches, also on different targets,
+/// to avoid allocating a fresh stack for every match. The workspace keeps a pool that is used
+/// by [Pattern::replace_all] and the other replacement functions.
+///
+/// A stack obtained with [Matcher::acquire] is owned by the caller until it is handed back
+/// with [Matcher::release], so nested matches never share a stack.
+#[derive(Default)]
+pub struct Matcher {
+    pool: Vec<Vec<(Symbol, Match<'static>)>>,
+}
+
+impl Matcher {
+    /// Create an empty pool.
+    pub const fn new() -> Matcher {
+        Matcher { pool: vec![] }
+    }
+
+    /// Returns `true` if the pool holds no released stacks.
+    pub fn is_empty(&self) -> bool {
+        self.pool.is_empty()
+    }
+
+    /// Get an empty match stack with the given conditions and settings,
+    /// reusing the memory of a previously released one if possible.
+    pub fn acquire<'a, 'b>(
+        &mut self,
+        conditions: &'b Condition<WildcardAndRestriction>,
+        settings: &'b MatchSettings,
+    ) -> MatchStack<'a, 'b> {
+        MatchStack {
+            stack: self.pool.pop().map(Matcher::recycle).unwrap_or_default(),
+            conditions,
+            settings,
+        }
+    }
+
+    /// Return a match stack to the pool.
+    pub fn release(&mut self, stack: MatchStack<'_, '_>) {
+        self.pool.push(Matcher::recycle(stack.stack));
+    }
+
+    /// Empty the stack and reuse its allocation for matches on a target with a different lifetime.
+    fn recycle<'a, 'b>(mut stack: Vec<(Symbol, Match<'a>)>) -> Vec<(Symbol, Match<'b>)> {
+        stack.clear();
+        let mut stack = std::mem::ManuallyDrop::new(stack);
+        // SAFETY: the vector is empty and its element type only differs from the new
+        // element type in a lifetime, so the allocation has the same layout
+        unsafe {
+            Vec::from_raw_parts(
+                stack.as_mut_ptr() as *mut (Symbol, Match<'b>),
+                0,
+                stack.capacity(),
+            )
+        }
+    }
+}
+
 impl<'a, 'b, 'c> IntoIterator for &'c MatchStack<'a, 'b> {
     type Item = &'c (Symbol, Match<'a>);
     type IntoIter = std::slice::Iter<'c, (Symbol, Match<'a>)>;
@@ -2179,9 +2876,14 @@ impl<'a> Iterator for AtomTreeIterator<'a> {
                     level
                 };
 
+                let in_scope = self
+                    .settings
+                    .scope
+                    .contains(self.stack.iter().map(|(_, _, a)| *a));
+
                 self.stack.push((Some(0), new_level, atom));
 
-                if level >= self.settings.level_range.0 {
+                if level >= self.settings.level_range.0 && in_scope {
                     return Some((location, atom));
                 }
             }
@@ -2428,11 +3130,196 @@ impl<'a: 'b, 'b> ReplaceIterator<'a, 'b> {
     }
 }
 
+/// A replacement rule, consisting of a pattern, a right-hand side and optional conditions.
+///
+/// A rule can be serialized if its patterns contain no transformers and its
+/// conditions contain no filter or comparison functions, as these cannot be stored as data.
+#[derive(Clone)]
+pub struct Rule {
+    pub lhs: Pattern,
+    pub rhs: Pattern,
+    pub conditions: Option<Condition<WildcardAndRestriction>>,
+}
+
+impl Rule {
+    pub fn new(
+        lhs: Pattern,
+        rhs: Pattern,
+        conditions: Option<Condition<WildcardAndRestriction>>,
+    ) -> Rule {
+        Rule {
+            lhs,
+            rhs,
+            conditions,
+        }
+    }
+
+    /// Replace all occurrences of the pattern in `target`.
+    pub fn apply(&self, target: AtomView<'_>) -> Atom {
+        self.lhs
+            .replace_all(target, &self.rhs, self.conditions.as_ref(), None)
+    }
+}
+
+/// An ordered list of rules that can be stored as data, for example to load
+/// a large rule set from a file at runtime.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RewriteSystem {
+    pub rules: Vec<Rule>,
+}
+
+impl RewriteSystem {
+    pub fn new(rules: Vec<Rule>) -> RewriteSystem {
+        RewriteSystem { rules }
+    }
+
+    /// Apply every rule once, in order, to `target`.
+    pub fn apply(&self, target: AtomView<'_>) -> Atom {
+        let mut r = target.to_owned();
+        for rule in &self.rules {
+            r = rule.apply(r.as_view());
+        }
+        r
+    }
+}
+
+/// The serialized form of a [PatternRestriction], where wildcards are stored as atoms
+/// so that their names are mapped to the symbols of the current state.
+#[derive(Serialize, Deserialize)]
+enum SerializedRestriction {
+    Length(usize, Option<usize>),
+    IsAtomType(AtomType),
+    IsLiteralWildcard(Atom),
+    NotGreedy,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SerializedCondition {
+    And(Box<(SerializedCondition, SerializedCondition)>),
+    Or(Box<(SerializedCondition, SerializedCondition)>),
+    Not(Box<SerializedCondition>),
+    Yield(Atom, SerializedRestriction),
+    True,
+    False,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedRule {
+    lhs: Atom,
+    rhs: Atom,
+    conditions: Option<SerializedCondition>,
+}
+
+impl SerializedCondition {
+    fn new(c: &Condition<WildcardAndRestriction>) -> Result<SerializedCondition, &'static str> {
+        Ok(match c {
+            Condition::And(a) => SerializedCondition::And(Box::new((
+                SerializedCondition::new(&a.0)?,
+                SerializedCondition::new(&a.1)?,
+            ))),
+            Condition::Or(a) => SerializedCondition::Or(Box::new((
+                SerializedCondition::new(&a.0)?,
+                SerializedCondition::new(&a.1)?,
+            ))),
+            Condition::Not(a) => SerializedCondition::Not(Box::new(SerializedCondition::new(a)?)),
+            Condition::Yield((v, r)) => SerializedCondition::Yield(
+                Atom::new_var(*v),
+                match r {
+                    PatternRestriction::Length(min, max) => {
+                        SerializedRestriction::Length(*min, *max)
+                    }
+                    PatternRestriction::IsAtomType(t) => SerializedRestriction::IsAtomType(*t),
+                    PatternRestriction::IsLiteralWildcard(w) => {
+                        SerializedRestriction::IsLiteralWildcard(Atom::new_var(*w))
+                    }
+                    PatternRestriction::NotGreedy => SerializedRestriction::NotGreedy,
+                    PatternRestriction::Filter(_) | PatternRestriction::Cmp(_, _) => {
+                        return Err("Conditions with functions cannot be serialized")
+                    }
+                },
+            ),
+            Condition::True => SerializedCondition::True,
+            Condition::False => SerializedCondition::False,
+        })
+    }
+
+    fn to_condition(&self) -> Result<Condition<WildcardAndRestriction>, &'static str> {
+        let get_symbol = |a: &Atom| match a.as_view() {
+            AtomView::Var(v) => Ok(v.get_symbol()),
+            _ => Err("Expected a wildcard in a condition"),
+        };
+
+        Ok(match self {
+            SerializedCondition::And(a) => {
+                Condition::And(Box::new((a.0.to_condition()?, a.1.to_condition()?)))
+            }
+            SerializedCondition::Or(a) => {
+                Condition::Or(Box::new((a.0.to_condition()?, a.1.to_condition()?)))
+            }
+            SerializedCondition::Not(a) => Condition::Not(Box::new(a.to_condition()?)),
+            SerializedCondition::Yield(v, r) => Condition::Yield((
+                get_symbol(v)?,
+                match r {
+                    SerializedRestriction::Length(min, max) => {
+                        PatternRestriction::Length(*min, *max)
+                    }
+                    SerializedRestriction::IsAtomType(t) => PatternRestriction::IsAtomType(*t),
+                    SerializedRestriction::IsLiteralWildcard(w) => {
+                        PatternRestriction::IsLiteralWildcard(get_symbol(w)?)
+                    }
+                    SerializedRestriction::NotGreedy => PatternRestriction::NotGreedy,
+                },
+            )),
+            SerializedCondition::True => Condition::True,
+            SerializedCondition::False => Condition::False,
+        })
+    }
+}
+
+impl Serialize for Rule {
+    /// Serialize the rule, or yield an error if it contains a transformer or
+    /// a condition with a function.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedRule {
+            lhs: self.lhs.to_atom().map_err(S::Error::custom)?,
+            rhs: self.rhs.to_atom().map_err(S::Error::custom)?,
+            conditions: self
+                .conditions
+                .as_ref()
+                .map(SerializedCondition::new)
+                .transpose()
+                .map_err(S::Error::custom)?,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let r = SerializedRule::deserialize(deserializer)?;
+        Ok(Rule {
+            lhs: r.lhs.into_pattern(),
+            rhs: r.rhs.into_pattern(),
+            conditions: r
+                .conditions
+                .map(|c| c.to_condition())
+                .transpose()
+                .map_err(D::Error::custom)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::atom::Atom;
+    use crate::{
+        atom::{Atom, AtomType, AtomView},
+        state::{State, Workspace},
+    };
 
-    use super::Pattern;
+    use super::{
+        AtomMatchIterator, CompiledPattern, Condition, Match, MatchSettings, MatchStack, Matcher,
+        Pattern, PatternRestriction, ReplaceScope, RewriteSystem, Rule,
+    };
 
     #[test]
     fn overlap() {
@@ -2444,4 +3331,439 @@ mod test {
         let res = Atom::parse("x*(y+y^2+1)+y*(y+1)").unwrap();
         assert_eq!(r, res);
     }
+
+    #[test]
+    fn free_symbols() {
+        let a = Atom::parse("f(x) + y").unwrap();
+        let (x, y, f) = (
+            State::get_symbol("x"),
+            State::get_symbol("y"),
+            State::get_symbol("f"),
+        );
+
+        let s = a.free_symbols(false);
+        assert_eq!(s.into_iter().collect::<Vec<_>>(), {
+            let mut v = vec![x, y];
+            v.sort();
+            v
+        });
+
+        let s = a.free_symbols(true);
+        assert_eq!(s.len(), 3);
+        assert!(s.contains(&x) && s.contains(&y) && s.contains(&f));
+
+        assert!(Atom::parse("2 + 3^(1/2)")
+            .unwrap()
+            .free_symbols(true)
+            .is_empty());
+    }
+
+    #[test]
+    fn reused_match_stack() {
+        fn all_matches<'a, 'b>(
+            p: &'b Pattern,
+            target: AtomView<'a>,
+            stack: &mut MatchStack<'a, 'b>,
+        ) -> Vec<String> {
+            let mut it = AtomMatchIterator::new(p, target);
+            let mut res = vec![];
+            while let Some((_, used)) = it.next(stack) {
+                res.push(format!("{:?} {:?}", stack, used));
+            }
+            res
+        }
+
+        let a = Atom::parse("f(v1,v2,v3)*f(v2,v1)*v1^2").unwrap();
+        let p = Pattern::parse("f(x__,y_)*v1^z_").unwrap();
+        let conditions = Condition::default();
+        let settings = MatchSettings::default();
+
+        let fresh = all_matches(
+            &p,
+            a.as_view(),
+            &mut MatchStack::new(&conditions, &settings),
+        );
+        assert!(!fresh.is_empty());
+
+        // the stack is reused for a match on another target
+        let b = Atom::parse("f(v1,v2)*f(v3,v4,v5)").unwrap();
+        let p2 = Pattern::parse("f(x_,y_)*f(z__)").unwrap();
+        let mut matcher = Matcher::new();
+        let mut stack = matcher.acquire(&conditions, &settings);
+        assert!(!all_matches(&p2, b.as_view(), &mut stack).is_empty());
+        let (ptr, capacity) = (stack.stack.as_ptr() as usize, stack.stack.capacity());
+        assert!(capacity > 0);
+        matcher.release(stack);
+        assert!(!matcher.is_empty());
+
+        let mut stack = matcher.acquire(&conditions, &settings);
+        assert_eq!(stack.len(), 0);
+        assert_eq!(
+            (stack.stack.as_ptr() as usize, stack.stack.capacity()),
+            (ptr, capacity)
+        );
+        assert!(matcher.is_empty());
+        assert_eq!(all_matches(&p, a.as_view(), &mut stack), fresh);
+
+        // a nested acquire gets a different stack, since the pool is empty
+        let nested = matcher.acquire(&conditions, &settings);
+        assert_eq!(nested.stack.capacity(), 0);
+        matcher.release(nested);
+        matcher.release(stack);
+        assert_eq!(matcher.pool.len(), 2);
+
+        // replacements reuse the pool of the workspace
+        let r = p2.replace_all(b.as_view(), &Pattern::parse("1").unwrap(), None, None);
+        assert_eq!(r, Atom::new_num(1));
+        Workspace::get_local().with(|ws| {
+            let m = ws.take_matcher();
+            assert!(!m.is_empty());
+            ws.return_matcher(m);
+        });
+    }
+
+    #[test]
+    fn substitute() {
+        let sum = State::get_symbol("sum");
+        let (k, j, n, x) = (
+            State::get_symbol("k"),
+            State::get_symbol("j"),
+            State::get_symbol("n"),
+            State::get_symbol("x"),
+        );
+        let binders = [(sum, 0)];
+
+        let a = Atom::parse("sum(k,1,n,k^2)").unwrap();
+        let r = a.substitute(k, Atom::new_var(j).as_view(), &binders);
+        assert_eq!(r, a);
+
+        let r = a.substitute(n, Atom::new_var(j).as_view(), &binders);
+        assert_eq!(r, Atom::parse("sum(k,1,j,k^2)").unwrap());
+
+        // the bound variable is renamed to avoid capturing k
+        let r = a.substitute(n, Atom::new_var(k).as_view(), &binders);
+        assert_eq!(r, Atom::parse("sum(k1,1,k,k1^2)").unwrap());
+
+        let a = Atom::parse("x*sum(k,1,n,k*x)").unwrap();
+        let r = a.substitute(x, Atom::parse("k+1").unwrap().as_view(), &binders);
+        assert_eq!(r, Atom::parse("(k+1)*sum(k1,1,n,k1*(k+1))").unwrap());
+
+        // without declaring the binder, the substitution captures k
+        let r = a.substitute(x, Atom::new_var(k).as_view(), &[]);
+        assert_eq!(r, Atom::parse("k*sum(k,1,n,k^2)").unwrap());
+    }
+
+    #[test]
+    fn rename_symbol() {
+        let (x, y, f, g) = (
+            State::get_symbol("v1"),
+            State::get_symbol("v2"),
+            State::get_symbol("f1"),
+            State::get_symbol("f2"),
+        );
+
+        let a = Atom::parse("v1+v2").unwrap();
+        assert_eq!(a.rename_symbol(y, x), Atom::parse("2*v1").unwrap());
+        assert_eq!(a.rename_symbol(f, g), a);
+
+        let a = Atom::parse("f1(v2)*v2^2+f2(v1)*v1^2+f1(f1(v1))").unwrap();
+        assert_eq!(
+            a.rename_symbol(f, g),
+            Atom::parse("f2(v2)*v2^2+f2(v1)*v1^2+f2(f2(v1))").unwrap()
+        );
+        assert_eq!(
+            a.rename_symbol(y, x),
+            Atom::parse("f1(v1)*v1^2+f2(v1)*v1^2+f1(f1(v1))").unwrap()
+        );
+
+        // symbols that are defined later sort later
+        let (r1, r2, r3) = (
+            State::get_symbol("rename_symbol_1"),
+            State::get_symbol("rename_symbol_2"),
+            State::get_symbol("rename_symbol_3"),
+        );
+
+        // the order is unchanged, so the result is not renormalized
+        let a =
+            Atom::parse("2*rename_symbol_1*f1(rename_symbol_1,v1)+rename_symbol_1^2+v1").unwrap();
+        assert_eq!(
+            a.rename_symbol(r1, r3),
+            Atom::parse("2*rename_symbol_3*f1(rename_symbol_3,v1)+rename_symbol_3^2+v1").unwrap()
+        );
+
+        // rename_symbol_2 sorts between the old and the new name, so the terms are reordered
+        let a = Atom::parse("rename_symbol_1*v1+rename_symbol_2*v1").unwrap();
+        assert_eq!(
+            a.rename_symbol(r1, r3),
+            Atom::parse("rename_symbol_3*v1+rename_symbol_2*v1").unwrap()
+        );
+        assert_eq!(
+            a.rename_symbol(r2, r3),
+            Atom::parse("rename_symbol_1*v1+rename_symbol_3*v1").unwrap()
+        );
+    }
+
+    #[test]
+    fn any_arity() {
+        let p = Pattern::parse("f1(x1___)").unwrap();
+        let rhs = Pattern::parse("f2(x1___)").unwrap();
+
+        for (input, res) in [
+            ("f1(v1)", "f2(v1)"),
+            ("f1(v1,v2)", "f2(v1,v2)"),
+            ("f1()", "f2()"),
+            ("f3(f1(),f1(v1,v2,v3))", "f3(f2(),f2(v1,v2,v3))"),
+        ] {
+            let r = p.replace_all(Atom::parse(input).unwrap().as_view(), &rhs, None, None);
+            assert_eq!(r, Atom::parse(res).unwrap());
+        }
+
+        // the outermost call is replaced first and its arguments are kept as they are
+        let a = Atom::parse("f1(f1(v1),v2)").unwrap();
+        let r = p.replace_all(a.as_view(), &rhs, None, None);
+        assert_eq!(r, Atom::parse("f2(f1(v1),v2)").unwrap());
+        let (r, _) = a.replace_all_repeat(&p, &rhs, 10);
+        assert_eq!(r, Atom::parse("f2(f2(v1),v2)").unwrap());
+
+        // bind the head as well and use it as an argument
+        let p = Pattern::parse("f1_(x1___)").unwrap();
+        let rhs = Pattern::parse("f2(f1_,x1___,x1___)").unwrap();
+        let r = p.replace_all(
+            Atom::parse("f3(v1,v2)").unwrap().as_view(),
+            &rhs,
+            None,
+            None,
+        );
+        assert_eq!(r, Atom::parse("f2(f3,v1,v2,v1,v2)").unwrap());
+        let r = p.replace_all(Atom::parse("f3()").unwrap().as_view(), &rhs, None, None);
+        assert_eq!(r, Atom::parse("f2(f3)").unwrap());
+    }
+
+    #[test]
+    fn replace_scope() {
+        let f1 = State::get_symbol("f1");
+        let inside = MatchSettings {
+            scope: ReplaceScope::InsideFunction(f1),
+            ..MatchSettings::default()
+        };
+        let top = MatchSettings {
+            scope: ReplaceScope::TopLevel,
+            ..MatchSettings::default()
+        };
+
+        let p = Pattern::parse("v1").unwrap();
+        let rhs = Pattern::parse("v2").unwrap();
+        let a = Atom::parse("v1+v1*v3+f1(v1,v1*v3,f2(v1))+f2(v1)").unwrap();
+
+        let r = p.replace_all(a.as_view(), &rhs, None, Some(&inside));
+        assert_eq!(
+            r,
+            Atom::parse("v1+v1*v3+f1(v2,v2*v3,f2(v2))+f2(v1)").unwrap()
+        );
+
+        let r = p.replace_all(a.as_view(), &rhs, None, Some(&top));
+        assert_eq!(
+            r,
+            Atom::parse("v2+v1*v3+f1(v1,v1*v3,f2(v1))+f2(v1)").unwrap()
+        );
+
+        let r = p.replace_all(Atom::parse("v1").unwrap().as_view(), &rhs, None, Some(&top));
+        assert_eq!(r, Atom::parse("v2").unwrap());
+
+        // the head itself is not matched, only its arguments
+        let p = Pattern::parse("f1(x1_)").unwrap();
+        let rhs = Pattern::parse("f2(x1_)").unwrap();
+        let a = Atom::parse("f1(v1)+f1(f1(v1))").unwrap();
+        let r = p.replace_all(a.as_view(), &rhs, None, Some(&inside));
+        assert_eq!(r, Atom::parse("f1(v1)+f1(f2(v1))").unwrap());
+
+        let conditions = Condition::default();
+        let p = Pattern::parse("v1").unwrap();
+        let a = Atom::parse("v1*f1(v1,f2(v1))").unwrap();
+        let mut it = p.pattern_match(a.as_view(), &conditions, &inside);
+        let mut n = 0;
+        while it.next().is_some() {
+            n += 1;
+        }
+        assert_eq!(n, 2);
+    }
+
+    #[test]
+    fn compiled_pattern() {
+        let p = CompiledPattern::new("f1(x1_)").unwrap().with_conditions(
+            (
+                State::get_symbol("x1_"),
+                PatternRestriction::Filter(Box::new(|m| {
+                    matches!(m, Match::Single(AtomView::Num(_)))
+                })),
+            )
+                .into(),
+        );
+        let rhs = Pattern::parse("x1_+1").unwrap();
+
+        for (input, res) in [
+            ("f1(2)+f1(v1)", "3+f1(v1)"),
+            ("f2(f1(3))*f1(f1(v2))", "f2(4)*f1(f1(v2))"),
+            ("f2(3)+v1", "f2(3)+v1"),
+        ] {
+            let a = Atom::parse(input).unwrap();
+            let r = a.replace_all(&p, &rhs);
+            assert_eq!(r, Atom::parse(res).unwrap());
+            assert_eq!(
+                r,
+                p.get_pattern()
+                    .replace_all(a.as_view(), &rhs, Some(&p.conditions), None)
+            );
+        }
+
+        // the pattern requires f1 and v1, so an expression without v1 cannot match
+        let p = CompiledPattern::new("f1(x1_,v1)*x1_^2").unwrap();
+        assert!(p.could_match_in(Atom::parse("f1(v2,v1)*v2^2").unwrap().as_view()));
+        assert!(!p.could_match_in(Atom::parse("f1(v2,v3)*v2^2").unwrap().as_view()));
+        assert_eq!(
+            Atom::parse("f1(v2,v1)*v2^2+v3")
+                .unwrap()
+                .replace_all(&p, &rhs),
+            Atom::parse("v2+1+v3").unwrap()
+        );
+    }
+
+    #[test]
+    fn replace_all_repeat() {
+        let pattern = Pattern::parse("f1(f1(x1_))").unwrap();
+        let rhs = Pattern::parse("x1_").unwrap();
+
+        let a = Atom::parse("f1(f1(f1(v1)))").unwrap();
+        let (r, n) = a.replace_all_repeat(&pattern, &rhs, 10);
+        assert_eq!(r, Atom::parse("f1(v1)").unwrap());
+        assert_eq!(n, 1);
+
+        let a = Atom::parse("f1(f1(f1(f1(f1(v1)))))").unwrap();
+        let (r, n) = a.replace_all_repeat(&pattern, &rhs, 10);
+        assert_eq!(r, Atom::parse("f1(v1)").unwrap());
+        assert_eq!(n, 2);
+
+        // the cap stops the iteration early
+        let (r, n) = a.replace_all_repeat(&pattern, &rhs, 1);
+        assert_eq!(r, Atom::parse("f1(f1(f1(v1)))").unwrap());
+        assert_eq!(n, 1);
+
+        // a rule that matches but does not change the expression terminates immediately
+        let (r, n) = a.replace_all_repeat(&rhs, &rhs, 10);
+        assert_eq!(r, a);
+        assert_eq!(n, 0);
+    }
+
+    #[test]
+    fn replace_all_checked() {
+        let pattern = Pattern::parse("f1(x1_)").unwrap();
+        let rhs = Pattern::parse("f2(x1_)").unwrap();
+
+        let a = Atom::parse("f1(v1)+v2").unwrap();
+        let (r, matched) = a.replace_all_checked(&pattern, &rhs);
+        assert_eq!(r, Atom::parse("f2(v1)+v2").unwrap());
+        assert!(matched);
+
+        let (r, matched) = r.replace_all_checked(&pattern, &rhs);
+        assert_eq!(r, Atom::parse("f2(v1)+v2").unwrap());
+        assert!(!matched);
+
+        // a replacement that does not change the expression is still reported
+        let (r, matched) = a.replace_all_checked(&pattern, &pattern);
+        assert_eq!(r, a);
+        assert!(matched);
+    }
+
+    #[test]
+    fn exponent_patterns() {
+        let pattern = Pattern::parse("v1^x1_").unwrap();
+        let rhs = Pattern::parse("x1_*v1^(x1_-1)").unwrap();
+        let d = |s: &str, conditions: Option<&Condition<_>>| {
+            pattern.replace_all(Atom::parse(s).unwrap().as_view(), &rhs, conditions, None)
+        };
+
+        assert_eq!(d("v1^5", None), Atom::parse("5*v1^4").unwrap());
+        assert_eq!(
+            d("v1^2 + f1(v1^-1)", None),
+            Atom::parse("2*v1 + f1(-v1^-2)").unwrap()
+        );
+
+        // a symbolic exponent binds to the wildcard
+        assert_eq!(d("v1^v2", None), Atom::parse("v2*v1^(v2-1)").unwrap());
+        assert_eq!(d("v1^(v2+1)", None), Atom::parse("(v2+1)*v1^v2").unwrap());
+
+        // unless the wildcard is restricted to numbers
+        let numeric = (
+            State::get_symbol("x1_"),
+            PatternRestriction::IsAtomType(AtomType::Num),
+        )
+            .into();
+        assert_eq!(d("v1^v2", Some(&numeric)), Atom::parse("v1^v2").unwrap());
+        assert_eq!(d("v1^3", Some(&numeric)), Atom::parse("3*v1^2").unwrap());
+
+        // `v1^1` is normalized to `v1`, which is not a power
+        assert_eq!(d("v1^1", None), Atom::parse("v1").unwrap());
+        assert_eq!(d("v1^2*v1^-1", None), Atom::parse("v1").unwrap());
+    }
+
+    #[test]
+    fn serialize_rules() {
+        let x1 = State::get_symbol("x1_");
+        let rules = RewriteSystem::new(vec![
+            Rule::new(
+                Pattern::parse("f1(x1_)").unwrap(),
+                Pattern::parse("f2(x1_, x1_)").unwrap(),
+                Some((x1, PatternRestriction::IsAtomType(AtomType::Var)).into()),
+            ),
+            Rule::new(
+                Pattern::parse("f2(x1_, x2_)").unwrap(),
+                Pattern::parse("x1_^x2_").unwrap(),
+                None,
+            ),
+        ]);
+
+        let data = bincode::serialize(&rules).unwrap();
+        let rules: RewriteSystem = bincode::deserialize(&data).unwrap();
+        assert_eq!(rules.rules.len(), 2);
+
+        let a = Atom::parse("f1(v1) + f1(2) + f2(v2, 3)").unwrap();
+        assert_eq!(
+            rules.apply(a.as_view()),
+            Atom::parse("v1^v1 + f1(2) + v2^3").unwrap()
+        );
+
+        // conditions with functions cannot be stored as data
+        let filter = (x1, PatternRestriction::Filter(Box::new(|_| true))).into();
+        let rule = Rule::new(
+            Pattern::parse("f1(x1_)").unwrap(),
+            Pattern::parse("x1_").unwrap(),
+            Some(filter),
+        );
+        assert!(bincode::serialize(&rule).is_err());
+    }
+
+    #[test]
+    fn equal_up_to_renaming() {
+        let a = Atom::parse("v1+v2").unwrap();
+        let b = Atom::parse("v3+v4").unwrap();
+        let map = a.as_view().equal_up_to_renaming(&b.as_view()).unwrap();
+        assert_eq!(map.len(), 2);
+
+        let a = Atom::parse("v1+v1").unwrap();
+        assert!(a.as_view().equal_up_to_renaming(&b.as_view()).is_none());
+        assert!(b.as_view().equal_up_to_renaming(&a.as_view()).is_none());
+
+        // the renaming has to be consistent across terms that are sorted differently
+        let a = Atom::parse("v1^2*f1(v2)+v2").unwrap();
+        let b = Atom::parse("v4^2*f1(v3)+v3").unwrap();
+        let map = a.as_view().equal_up_to_renaming(&b.as_view()).unwrap();
+        assert_eq!(map[&State::get_symbol("v1")], State::get_symbol("v4"));
+        assert_eq!(map[&State::get_symbol("v2")], State::get_symbol("v3"));
+
+        // coefficients and function names must match exactly
+        let b = Atom::parse("2*v4^2*f1(v3)+v3").unwrap();
+        assert!(a.as_view().equal_up_to_renaming(&b.as_view()).is_none());
+        let b = Atom::parse("v4^2*f2(v3)+v3").unwrap();
+        assert!(a.as_view().equal_up_to_renaming(&b.as_view()).is_none());
+    }
 }
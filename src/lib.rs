@@ -18,7 +18,14 @@
 //!
 //! Check out the [guide](https://symbolica.io/docs/get_started.html) for more information, examples,
 //! and additional documentation.
+//!
+//! Without the default `std` feature, only the [packed] encoding of rational numbers is available.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
+#[cfg(feature = "std")]
 use std::{
     collections::HashMap,
     env,
@@ -29,39 +36,71 @@ use std::{
     time::{Duration, SystemTime},
 };
 
+#[cfg(feature = "std")]
 use colored::Colorize;
+#[cfg(feature = "std")]
 use once_cell::sync::OnceCell;
+#[cfg(feature = "std")]
 use tinyjson::JsonValue;
 
+#[cfg(feature = "std")]
 mod api;
+#[cfg(feature = "std")]
 pub mod atom;
+#[cfg(feature = "std")]
 pub mod coefficient;
+#[cfg(feature = "std")]
 mod collect;
+#[cfg(feature = "std")]
 pub mod combinatorics;
+#[cfg(feature = "std")]
 mod derivative;
+#[cfg(feature = "std")]
 pub mod domains;
+#[cfg(feature = "std")]
 pub mod evaluate;
+#[cfg(feature = "std")]
 mod expand;
+#[cfg(feature = "std")]
 pub mod id;
+#[cfg(feature = "std")]
 mod normalize;
+#[cfg(feature = "std")]
 pub mod numerical_integration;
+pub mod packed;
+#[cfg(feature = "std")]
 pub mod parser;
+#[cfg(feature = "std")]
 pub mod poly;
+#[cfg(feature = "std")]
 pub mod printer;
+#[cfg(feature = "std")]
 mod solve;
+#[cfg(feature = "std")]
 pub mod state;
+#[cfg(feature = "std")]
 pub mod streaming;
+#[cfg(feature = "std")]
+mod summation;
+#[cfg(feature = "std")]
 pub mod tensors;
+#[cfg(feature = "std")]
 pub mod transformer;
+#[cfg(feature = "std")]
 pub mod utils;
+#[cfg(feature = "std")]
+pub mod visitor;
 
 #[cfg(feature = "faster_alloc")]
 #[global_allocator]
 static ALLOC: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
+#[cfg(feature = "std")]
 static LICENSE_KEY: OnceCell<String> = OnceCell::new();
+#[cfg(feature = "std")]
 static LICENSE_MANAGER: OnceCell<LicenseManager> = OnceCell::new();
 
+#[cfg(feature = "std")]
 #[allow(dead_code)]
 pub struct LicenseManager {
     lock: Option<TcpListener>,
@@ -71,31 +110,37 @@ pub struct LicenseManager {
     has_license: bool,
 }
 
+#[cfg(feature = "std")]
 const MULTIPLE_INSTANCE_WARNING: &str = "┌───────────────────────────────────────────────────────────────────────────────────────────────────────────┐
 │ Cannot start new unlicensed Symbolica instance since there is already another one running on the machine. │
 └───────────────────────────────────────────────────────────────────────────────────────────────────────────┘"
 ;
 
+#[cfg(feature = "std")]
 const NETWORK_ERROR: &str = "┌────────────────────────────────────────────────┐
 │ Could not connect to Symbolica license server. │
 │                                                │
 │ Please check your network configuration.       │
 └────────────────────────────────────────────────┘";
 
+#[cfg(feature = "std")]
 const ACTIVATION_ERROR: &str = "┌──────────────────────────────────────────┐
 │ Could not activate the Symbolica license │
 └──────────────────────────────────────────┘";
 
+#[cfg(feature = "std")]
 const MISSING_LICENSE_ERROR: &str = "┌───────────────────────────────┐
 │ Symbolica license key missing │
 └───────────────────────────────┘";
 
+#[cfg(feature = "std")]
 impl Default for LicenseManager {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "std")]
 impl LicenseManager {
     pub fn new() -> LicenseManager {
         let pid = std::process::id();
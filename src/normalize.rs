@@ -1,17 +1,177 @@
-use std::{cmp::Ordering, ops::DerefMut};
+use std::{cell::Cell, cmp::Ordering, ops::DerefMut};
 
 use smallvec::SmallVec;
 
 use crate::{
-    atom::{Atom, AtomView, Fun, Symbol},
+    atom::{Atom, AtomView, Fun, SortKey, SortKeyItem, Symbol},
     coefficient::{Coefficient, CoefficientView},
-    domains::{integer::Z, rational::Q},
+    domains::{
+        integer::Z,
+        rational::{Rational, Q},
+    },
+    id::{MatchSettings, Pattern},
     poly::Variable,
-    state::{RecycledAtom, State, Workspace},
+    state::{Assumption, NormalizationRule, RecycledAtom, State, Workspace},
 };
 
+/// Compare functions argument by argument instead of by their raw data.
+const FULL_FN_CMP: bool = cfg!(feature = "full_fn_cmp");
+
+thread_local!(
+    /// The number of nested applications of normalization rules.
+    static NORMALIZATION_RULE_DEPTH: Cell<usize> = const { Cell::new(0) }
+);
+
+/// Apply the first of the user-defined `rules` and hooks that matches the function `f` and write the
+/// normalized result in `out`. Returns `false` if no rule matches or if the maximal nesting depth
+/// of rule applications has been reached.
+fn apply_normalization_rules(
+    rules: &[NormalizationRule],
+    f: AtomView,
+    workspace: &Workspace,
+    out: &mut Atom,
+) -> bool {
+    let depth = NORMALIZATION_RULE_DEPTH.with(|d| d.get());
+    if depth >= State::get_max_normalization_rule_depth() {
+        return false;
+    }
+
+    // only match the function itself and not its arguments, which are already normalized
+    let settings = MatchSettings {
+        level_range: (0, Some(0)),
+        ..MatchSettings::default()
+    };
+
+    NORMALIZATION_RULE_DEPTH.with(|d| d.set(depth + 1));
+    let matched = rules.iter().any(|r| match r {
+        NormalizationRule::Pattern(lhs, rhs) => {
+            lhs.replace_all_with_ws_into(f, rhs, workspace, None, Some(&settings), out)
+        }
+        NormalizationRule::Hook(hook) => {
+            let mut res = workspace.new_atom();
+            if hook(f, &mut res) {
+                res.as_view().normalize(workspace, out);
+                true
+            } else {
+                false
+            }
+        }
+    });
+    NORMALIZATION_RULE_DEPTH.with(|d| d.set(depth));
+
+    matched
+}
+
+/// Write the `n`th root of `b` as `q * r^(1/n)` with perfect `n`th powers extracted into `q`.
+/// For a negative `b` and `n = 2`, a factor `𝑖` is added.
+/// The root is written as `sqrt(r)` if `as_sqrt` is set.
+/// Returns `false` if no simplification is possible.
+fn extract_root(
+    workspace: &Workspace,
+    b: &Rational,
+    n: u32,
+    as_sqrt: bool,
+    out: &mut Atom,
+) -> bool {
+    let (q, r) = b.split_nth_root(n);
+    if q.is_one() && !b.is_negative() {
+        return false;
+    }
+
+    let mut mul_h = workspace.new_atom();
+    let mul = mul_h.to_mul();
+
+    if !r.is_one() {
+        let rad = workspace.new_num(r);
+        let mut root = workspace.new_atom();
+        if as_sqrt {
+            root.to_fun(State::SQRT).add_arg(rad.as_view());
+        } else {
+            root.to_pow(rad.as_view(), workspace.new_num((1, n as i64)).as_view());
+        }
+        mul.extend(root.as_view());
+    }
+
+    if b.is_negative() {
+        mul.extend(workspace.new_var(State::I).as_view());
+    }
+
+    mul.extend(workspace.new_num(q).as_view());
+    mul_h.as_view().normalize(workspace, out);
+    true
+}
+
+impl Atom {
+    /// Get a key that sorts in the same order as the atom. See [AtomView::sort_key].
+    pub fn sort_key(&self) -> SortKey {
+        self.as_view().sort_key()
+    }
+}
+
 impl<'a> AtomView<'a> {
-    /// Compare two atoms.
+    /// Get a key that sorts in the same order as [AtomView::cmp], so that
+    /// it can be computed once per atom when sorting. Equal atoms have equal keys.
+    pub fn sort_key(&self) -> SortKey {
+        let mut key = vec![];
+        self.sort_key_impl(&mut key);
+        SortKey(key)
+    }
+
+    fn sort_key_impl(&self, key: &mut Vec<SortKeyItem>) {
+        // the tags follow the order of the atom types in `cmp`
+        match self {
+            AtomView::Var(v) => {
+                key.push(SortKeyItem::Tag(0));
+                key.push(SortKeyItem::Symbol(v.get_symbol()));
+            }
+            AtomView::Pow(p) => {
+                key.push(SortKeyItem::Tag(1));
+                let (b, e) = p.get_base_exp();
+                b.sort_key_impl(key);
+                e.sort_key_impl(key);
+            }
+            AtomView::Mul(m) => {
+                key.push(SortKeyItem::Tag(2));
+                let s = m.to_slice();
+                key.push(SortKeyItem::Len(s.len()));
+                for x in s.iter() {
+                    x.sort_key_impl(key);
+                }
+            }
+            AtomView::Add(a) => {
+                key.push(SortKeyItem::Tag(3));
+                let s = a.to_slice();
+                key.push(SortKeyItem::Len(s.len()));
+                for x in s.iter() {
+                    x.sort_key_impl(key);
+                }
+            }
+            AtomView::Fun(f) => {
+                key.push(SortKeyItem::Tag(4));
+                key.push(SortKeyItem::Symbol(f.get_symbol()));
+
+                if FULL_FN_CMP {
+                    key.push(SortKeyItem::Len(f.get_nargs()));
+                    for x in f.iter() {
+                        x.sort_key_impl(key);
+                    }
+                } else {
+                    key.push(SortKeyItem::Data(self.get_data().to_vec()));
+                }
+            }
+            AtomView::Num(_) => {
+                key.push(SortKeyItem::Tag(5));
+                key.push(SortKeyItem::Num(self.to_owned()));
+            }
+        }
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Compare two atoms. This is a total order that agrees with equality
+    /// and it is the order used to sort terms and factors during normalization:
+    /// variables come first, followed by powers, products, sums and functions,
+    /// and numbers last.
     pub fn cmp(&self, other: &AtomView<'_>) -> Ordering {
         if self == other {
             // equality comparison is a fast check
@@ -79,7 +239,7 @@ impl<'a> AtomView<'a> {
                     return name_comp;
                 }
 
-                if cfg!(feature = "full_fn_cmp") {
+                if FULL_FN_CMP {
                     let len_cmp = f1.get_nargs().cmp(&f2.get_nargs());
                     if len_cmp != Ordering::Equal {
                         return len_cmp;
@@ -153,7 +313,7 @@ impl<'a> AtomView<'a> {
                     return name_comp;
                 }
 
-                if cfg!(feature = "full_fn_cmp") {
+                if FULL_FN_CMP {
                     let len_cmp = f1.get_nargs().cmp(&f2.get_nargs());
                     if len_cmp != Ordering::Equal {
                         return len_cmp;
@@ -250,7 +410,7 @@ impl<'a> AtomView<'a> {
                     return name_comp;
                 }
 
-                if cfg!(feature = "full_fn_cmp") {
+                if FULL_FN_CMP {
                     let len_cmp = f1.get_nargs().cmp(&f2.get_nargs());
                     if len_cmp != Ordering::Equal {
                         return len_cmp;
@@ -664,7 +824,150 @@ impl Atom {
     }
 }
 
+impl Atom {
+    /// Wrap the expression in an inert `hold` function, whose argument
+    /// will not be simplified by the normalizer.
+    pub fn hold(&self) -> Atom {
+        self.as_view().hold()
+    }
+
+    /// Remove all `hold` wrappers and normalize the result.
+    pub fn release(&self) -> Atom {
+        self.as_view().release()
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Get the value of a rational number.
+    fn get_rational(&self) -> Option<Rational> {
+        if let AtomView::Num(n) = self {
+            match n.get_coeff_view() {
+                CoefficientView::Natural(n, d) => Some(Rational::Natural(n, d)),
+                CoefficientView::Large(r) => Some(Rational::Large(r.to_rat())),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Check if the expression is a variable that satisfies the assumption.
+    fn has_assumption(&self, assumption: Assumption) -> bool {
+        if let AtomView::Var(v) = self {
+            State::get_assumptions(v.get_symbol()).contains(assumption)
+        } else {
+            false
+        }
+    }
+}
+
+impl Atom {
+    /// Rewrite all nested powers `(x^a)^b` as `x^(a*b)`. See [AtomView::combine_nested_powers].
+    pub fn combine_nested_powers(&self) -> Atom {
+        self.as_view().combine_nested_powers()
+    }
+}
+
 impl<'a> AtomView<'a> {
+    /// Rewrite all nested powers `(x^a)^b` as `x^(a*b)`, assuming that all bases are positive.
+    ///
+    /// The normalization only combines nested powers when this is valid for any base, i.e.,
+    /// when `b` is an integer or when `a` is a rational number with `-1 < a <= 1`.
+    /// For example, `(x^2)^(1/2)` is `abs(x)` for real `x`, which this function rewrites to `x`.
+    pub fn combine_nested_powers(&self) -> Atom {
+        let lhs = Pattern::parse("(x_^y_)^z_").unwrap();
+        let rhs = Pattern::parse("x_^(y_*z_)").unwrap();
+        self.to_owned().replace_all_repeat(&lhs, &rhs, usize::MAX).0
+    }
+
+    /// Wrap the expression in an inert `hold` function, whose argument
+    /// will not be simplified by the normalizer.
+    pub fn hold(&self) -> Atom {
+        let mut out = Atom::new();
+        let f = out.to_fun(State::HOLD);
+        f.add_arg(*self);
+        f.set_normalized(true);
+        out
+    }
+
+    /// Remove all `hold` wrappers and normalize the result.
+    pub fn release(&self) -> Atom {
+        Workspace::get_local().with(|ws| {
+            let mut out = ws.new_atom();
+            self.release_with_ws_into(ws, &mut out);
+            out.into_inner()
+        })
+    }
+
+    /// Remove all `hold` wrappers and normalize the result, writing it in `out`.
+    pub fn release_with_ws_into(&self, workspace: &Workspace, out: &mut Atom) {
+        if !self.contains_symbol(State::HOLD) {
+            out.set_from_view(self);
+            return;
+        }
+
+        let mut a = workspace.new_atom();
+        self.release_no_norm(workspace, &mut a);
+        a.as_view().normalize(workspace, out);
+    }
+
+    /// Remove all `hold` wrappers, but do not normalize the result.
+    fn release_no_norm(&self, workspace: &Workspace, out: &mut Atom) {
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => out.set_from_view(self),
+            AtomView::Fun(f) => {
+                if f.get_symbol() == State::HOLD && f.get_nargs() == 1 {
+                    f.iter().next().unwrap().release_no_norm(workspace, out);
+                    return;
+                }
+
+                // a hold with multiple arguments becomes a sequence of arguments
+                let id = if f.get_symbol() == State::HOLD {
+                    State::ARG
+                } else {
+                    f.get_symbol()
+                };
+
+                let mut fun_h = workspace.new_atom();
+                let fun = fun_h.to_fun(id);
+                let mut arg_h = workspace.new_atom();
+                for a in f.iter() {
+                    a.release_no_norm(workspace, &mut arg_h);
+                    fun.add_arg(arg_h.as_view());
+                }
+                std::mem::swap(out, &mut fun_h);
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let mut base_h = workspace.new_atom();
+                base.release_no_norm(workspace, &mut base_h);
+                let mut exp_h = workspace.new_atom();
+                exp.release_no_norm(workspace, &mut exp_h);
+                out.to_pow(base_h.as_view(), exp_h.as_view());
+            }
+            AtomView::Mul(m) => {
+                let mut mul_h = workspace.new_atom();
+                let mul = mul_h.to_mul();
+                let mut arg_h = workspace.new_atom();
+                for a in m.iter() {
+                    a.release_no_norm(workspace, &mut arg_h);
+                    mul.extend(arg_h.as_view());
+                }
+                std::mem::swap(out, &mut mul_h);
+            }
+            AtomView::Add(a) => {
+                let mut add_h = workspace.new_atom();
+                let add = add_h.to_add();
+                let mut arg_h = workspace.new_atom();
+                for a in a.iter() {
+                    a.release_no_norm(workspace, &mut arg_h);
+                    add.extend(arg_h.as_view());
+                }
+                std::mem::swap(out, &mut add_h);
+            }
+        }
+    }
+
     #[inline(always)]
     pub fn needs_normalization(&self) -> bool {
         match self {
@@ -687,6 +990,7 @@ impl<'a> AtomView<'a> {
             AtomView::Mul(t) => {
                 let mut atom_test_buf: SmallVec<[_; 20]> = SmallVec::new();
 
+                let flatten = workspace.flatten_assoc();
                 let mut is_zero = false;
                 for a in t.iter() {
                     let mut handle = workspace.new_atom();
@@ -697,6 +1001,12 @@ impl<'a> AtomView<'a> {
                         handle.set_from_view(&a);
                     }
 
+                    if !flatten && matches!(handle.as_view(), AtomView::Mul(_)) {
+                        // preserve the nesting
+                        let held = handle.as_view().hold();
+                        handle.set_from_view(&held.as_view());
+                    }
+
                     if let Atom::Mul(mul) = handle.deref_mut() {
                         for c in mul.to_mul_view().iter() {
                             // TODO: remove this copy
@@ -804,6 +1114,15 @@ impl<'a> AtomView<'a> {
                 let id = f.get_symbol();
                 let out_f = out.to_fun(id);
 
+                if id == State::HOLD {
+                    // held expressions are kept as they are
+                    for a in f.iter() {
+                        out_f.add_arg(a);
+                    }
+                    out_f.set_normalized(true);
+                    return;
+                }
+
                 /// Add an argument `a` to `f` and flatten nested `arg`s.
                 #[inline(always)]
                 fn add_arg(f: &mut Fun, a: AtomView) {
@@ -859,6 +1178,17 @@ impl<'a> AtomView<'a> {
 
                 out_f.set_normalized(true);
 
+                if let Some(rules) = State::get_normalization_rules(id) {
+                    if apply_normalization_rules(&rules, out.as_view(), workspace, &mut handle) {
+                        out.set_from_view(&handle.as_view());
+                        return;
+                    }
+                }
+
+                let Atom::Fun(out_f) = out else {
+                    unreachable!()
+                };
+
                 if [State::COS, State::SIN, State::EXP, State::LOG].contains(&id)
                     && out_f.to_fun_view().get_nargs() == 1
                 {
@@ -878,6 +1208,65 @@ impl<'a> AtomView<'a> {
                     }
                 }
 
+                if id == State::SQRT && out_f.to_fun_view().get_nargs() == 1 {
+                    // rewrite sqrt(x^2) as abs(x) for a real x
+                    if let AtomView::Pow(p) = out_f.to_fun_view().iter().next().unwrap() {
+                        let (base, exp) = p.get_base_exp();
+                        if exp.get_rational() == Some(Rational::Natural(2, 1))
+                            && base.has_assumption(Assumption::Real)
+                        {
+                            let mut abs = workspace.new_atom();
+                            abs.to_fun(State::ABS).add_arg(base);
+                            abs.as_view().normalize(workspace, out);
+                            return;
+                        }
+                    }
+
+                    if let AtomView::Num(n) = out_f.to_fun_view().iter().next().unwrap() {
+                        if let Coefficient::Rational(r) = n.get_coeff_view().to_owned() {
+                            let mut buffer = workspace.new_atom();
+                            if extract_root(workspace, &r, 2, true, &mut buffer) {
+                                out.set_from_view(&buffer.as_view());
+                                return;
+                            }
+                        }
+                    }
+                }
+
+                if [State::ABS, State::FLOOR, State::CEIL].contains(&id)
+                    && out_f.to_fun_view().get_nargs() == 1
+                {
+                    if let AtomView::Num(n) = out_f.to_fun_view().iter().next().unwrap() {
+                        if let Coefficient::Rational(r) = n.get_coeff_view().to_owned() {
+                            let r = match id {
+                                State::ABS => r.abs(),
+                                State::FLOOR => r.floor().into(),
+                                _ => r.ceil().into(),
+                            };
+                            let buffer = workspace.new_num(r);
+                            out.set_from_view(&buffer.as_view());
+                            return;
+                        }
+                    }
+                }
+
+                if id == State::ABS && out_f.to_fun_view().get_nargs() == 1 {
+                    let arg = out_f.to_fun_view().iter().next().unwrap();
+                    if arg.has_assumption(Assumption::Nonnegative) {
+                        let mut buffer = workspace.new_atom();
+                        buffer.set_from_view(&arg);
+                        out.set_from_view(&buffer.as_view());
+                        return;
+                    } else if arg.has_assumption(Assumption::Negative) {
+                        let mut mul_h = workspace.new_atom();
+                        let mul = mul_h.to_mul();
+                        mul.extend(arg);
+                        mul.extend(workspace.new_num(-1).as_view());
+                        mul_h.as_view().normalize(workspace, out);
+                        return;
+                    }
+                }
+
                 if id == State::EXP && out_f.to_fun_view().get_nargs() == 1 {
                     let arg = out_f.to_fun_view().iter().next().unwrap();
                     // simplify logs inside exp
@@ -1040,6 +1429,7 @@ impl<'a> AtomView<'a> {
                             m.extend(handle.as_view());
                             handle.to_num((-1).into());
                             m.extend(handle.as_view());
+                            m.set_has_coefficient(true);
                             m.set_normalized(true);
 
                             return;
@@ -1099,11 +1489,46 @@ impl<'a> AtomView<'a> {
                                 break 'pow_simplify;
                             }
 
+                            // extract perfect powers from the root of a rational number
+                            if let (
+                                Coefficient::Rational(b),
+                                Coefficient::Rational(Rational::Natural(1, d)),
+                            ) = (&new_base_num, &new_exp_num)
+                            {
+                                if let Ok(d) = u32::try_from(*d) {
+                                    let is_sqrt = exp_num == CoefficientView::Natural(1, 2);
+                                    if (!b.is_negative() || is_sqrt)
+                                        && extract_root(workspace, b, d, false, out)
+                                    {
+                                        break 'pow_simplify;
+                                    }
+                                }
+                            }
+
                             base_handle.to_num(new_base_num);
                             exp_handle.to_num(new_exp_num);
                         } else if let AtomView::Var(v) = base_handle.as_view() {
                             if v.get_symbol() == State::I {
                                 if let CoefficientView::Natural(n, d) = exp_num {
+                                    if d != 1 {
+                                        // reduce the exponent modulo 4
+                                        if let Some(period) = d.checked_mul(4) {
+                                            let m = n.rem_euclid(period);
+                                            if m != n {
+                                                let mut new_exp = workspace.new_atom();
+                                                new_exp.to_num((m, d).into());
+                                                out.to_pow(
+                                                    base_handle.as_view(),
+                                                    new_exp.as_view(),
+                                                );
+                                                break 'pow_simplify;
+                                            }
+                                        }
+
+                                        out.to_pow(base_handle.as_view(), exp_handle.as_view());
+                                        break 'pow_simplify;
+                                    }
+
                                     let mut new_base = workspace.new_atom();
 
                                     // the case n < 0 is handled automagically
@@ -1125,14 +1550,7 @@ impl<'a> AtomView<'a> {
                                         std::mem::swap(&mut new_base, &mut helper);
                                     }
 
-                                    if d == 1 {
-                                        out.set_from_view(&new_base.as_view());
-                                    } else {
-                                        let mut new_exp = workspace.new_atom();
-                                        new_exp.to_num((1i64, d).into());
-                                        out.to_pow(new_base.as_view(), new_exp.as_view());
-                                    }
-
+                                    out.set_from_view(&new_base.as_view());
                                     break 'pow_simplify;
                                 }
                             }
@@ -1168,6 +1586,45 @@ impl<'a> AtomView<'a> {
                             }
                         }
                     }
+
+                    if let AtomView::Pow(p_base) = base_handle.as_view() {
+                        let (p_base_base, p_base_exp) = p_base.get_base_exp();
+                        let a = p_base_exp.get_rational();
+
+                        // rewrite (x^a)^y as x^(a*y), which holds for all x and y if -1 < a <= 1,
+                        // for a nonnegative x and a real a, and for an integer y
+                        if a.as_ref()
+                            .is_some_and(|a| *a > (-1).into() && *a <= 1.into())
+                            || p_base_base.has_assumption(Assumption::Nonnegative)
+                                && (a.is_some() || p_base_exp.has_assumption(Assumption::Real))
+                            || exp_handle.as_view().has_assumption(Assumption::Integer)
+                        {
+                            let mut mul_h = workspace.new_atom();
+                            let mul = mul_h.to_mul();
+                            mul.extend(p_base_exp);
+                            mul.extend(exp_handle.as_view());
+                            let mut exp_h = workspace.new_atom();
+                            mul.as_view().normalize(workspace, &mut exp_h);
+
+                            mul_h.to_pow(p_base_base, exp_h.as_view());
+                            mul_h.as_view().normalize(workspace, out);
+                            break 'pow_simplify;
+                        }
+
+                        // rewrite (x^(2*k))^(1/(2*k)) as abs(x) for a real x
+                        if let (Some(a), Some(e)) = (a, exp_handle.as_view().get_rational()) {
+                            if (&a * &Rational::Natural(1, 2)).is_integer()
+                                && (&a * &e).is_one()
+                                && p_base_base.has_assumption(Assumption::Real)
+                            {
+                                let mut abs = workspace.new_atom();
+                                abs.to_fun(State::ABS).add_arg(p_base_base);
+                                abs.as_view().normalize(workspace, out);
+                                break 'pow_simplify;
+                            }
+                        }
+                    }
+
                     out.to_pow(base_handle.as_view(), exp_handle.as_view());
                 }
 
@@ -1179,6 +1636,7 @@ impl<'a> AtomView<'a> {
 
                 let mut atom_sort_buf: SmallVec<[_; 20]> = SmallVec::new();
 
+                let flatten = workspace.flatten_assoc();
                 let mut norm_arg = workspace.new_atom();
                 for a in a.iter() {
                     let r = if a.needs_normalization() {
@@ -1189,7 +1647,10 @@ impl<'a> AtomView<'a> {
                         a
                     };
 
-                    if let AtomView::Add(new_add) = r {
+                    if !flatten && matches!(r, AtomView::Add(_)) {
+                        // preserve the nesting
+                        ns.extend(r.hold().as_view());
+                    } else if let AtomView::Add(new_add) = r {
                         for c in new_add.iter() {
                             if let AtomView::Num(n) = c {
                                 if n.is_zero() {
@@ -1272,7 +1733,185 @@ impl<'a> AtomView<'a> {
 
 #[cfg(test)]
 mod test {
-    use crate::{atom::Atom, state::State};
+    use crate::{
+        atom::{Atom, AtomView},
+        id::Pattern,
+        state::{Assumption, FunctionAttribute, State, Workspace},
+    };
+
+    #[test]
+    fn nested_powers() {
+        for (a, r) in [
+            ("(v1^2)^3", "v1^6"),
+            ("(v1^(1/2))^(1/3)", "v1^(1/6)"),
+            ("(v1^(-1/2))^v2", "v1^(-v2/2)"),
+            ("((v1^v2)^v3)^2", "(v1^v2)^(2*v3)"),
+        ] {
+            assert_eq!(Atom::parse(a).unwrap(), Atom::parse(r).unwrap());
+        }
+
+        // sqrt(x^2) is not x for negative x
+        for a in ["(v1^2)^(1/2)", "(v1^-1)^(1/2)", "(v1^v2)^v3"] {
+            let a = Atom::parse(a).unwrap();
+            let AtomView::Pow(p) = a.as_view() else {
+                panic!("Expected a power")
+            };
+            assert!(matches!(p.get_base_exp().0, AtomView::Pow(_)));
+        }
+
+        for (a, r) in [
+            ("(v1^2)^(1/2)", "v1"),
+            (
+                "((v1^v2)^v3)^v4+f1((v1^-1)^(1/2))",
+                "v1^(v2*v3*v4)+f1(v1^(-1/2))",
+            ),
+        ] {
+            assert_eq!(
+                Atom::parse(a).unwrap().combine_nested_powers(),
+                Atom::parse(r).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn assumptions() {
+        let (p, r, n, i) = (
+            State::get_symbol("xpos"),
+            State::get_symbol("xreal"),
+            State::get_symbol("xneg"),
+            State::get_symbol("xint"),
+        );
+        State::add_assumption(p, Assumption::Positive).unwrap();
+        State::add_assumption(r, Assumption::Real).unwrap();
+        State::add_assumption(n, Assumption::Negative).unwrap();
+        State::add_assumption(i, Assumption::Integer).unwrap();
+
+        assert!(State::get_assumptions(p).contains(Assumption::Nonnegative));
+        assert!(State::get_assumptions(i).contains(Assumption::Real));
+        assert!(!State::get_assumptions(r).contains(Assumption::Positive));
+        assert!(State::add_assumption(n, Assumption::Nonnegative).is_err());
+        assert!(State::add_assumption(p, Assumption::Negative).is_err());
+        assert!(State::get_assumptions(State::get_symbol("v1")).is_empty());
+
+        for (a, res) in [
+            ("sqrt(xpos^2)", "xpos"),
+            ("(xpos^2)^(1/2)", "xpos"),
+            ("sqrt(xreal^2)", "abs(xreal)"),
+            ("(xreal^4)^(1/4)", "abs(xreal)"),
+            ("abs(xneg)", "-xneg"),
+            ("(xpos^2)^v1", "xpos^(2*v1)"),
+            ("(xpos^xreal)^v1", "xpos^(xreal*v1)"),
+            ("(v1^v2)^xint", "v1^(v2*xint)"),
+        ] {
+            assert_eq!(Atom::parse(a).unwrap(), Atom::parse(res).unwrap());
+        }
+
+        // an unconstrained variable keeps the conservative behavior
+        let a = Atom::parse("sqrt(v1^2)").unwrap();
+        assert!(matches!(a.as_view(), AtomView::Fun(_)));
+        let a = Atom::parse("(xreal^v1)^v2").unwrap();
+        assert!(matches!(a.as_view(), AtomView::Pow(_)));
+        assert_ne!(a, Atom::parse("xreal^(v1*v2)").unwrap());
+
+        State::clear_assumptions(r);
+        assert!(State::get_assumptions(r).is_empty());
+        assert_ne!(
+            Atom::parse("sqrt(xreal^2)").unwrap(),
+            Atom::parse("abs(xreal)").unwrap()
+        );
+    }
+
+    #[test]
+    fn normalization_rules() {
+        let (f27, f28, f29) = (
+            State::get_symbol("f27"),
+            State::get_symbol("f28"),
+            State::get_symbol("f29"),
+        );
+
+        // a product rule for a logarithm
+        for (lhs, rhs) in [
+            ("f27(x_*y_)", "f27(x_)+f27(y_)"),
+            ("f27(x_^y_)", "y_*f27(x_)"),
+        ] {
+            State::add_normalization_rule(
+                f27,
+                Pattern::parse(lhs).unwrap(),
+                Pattern::parse(rhs).unwrap(),
+            )
+            .unwrap();
+        }
+
+        let r = Atom::parse("f27(v1*v2)").unwrap();
+        assert_eq!(format!("{}", r), "f27(v1)+f27(v2)");
+        let r = Atom::parse("f27((v1*v2)^2)+f1(f27(v3))").unwrap();
+        assert_eq!(format!("{}", r), "f1(f27(v3))+2*f27(v1)+2*f27(v2)");
+
+        assert!(State::add_normalization_rule(
+            f27,
+            Pattern::parse("f28(x_)").unwrap(),
+            Pattern::parse("x_").unwrap()
+        )
+        .is_err());
+
+        // mutually recursive rules stop at the maximal depth
+        let rules = [
+            (f28, Pattern::parse("f28(x_)"), Pattern::parse("f29(x_+1)")),
+            (f29, Pattern::parse("f29(x_)"), Pattern::parse("f28(x_)")),
+        ];
+        for (s, lhs, rhs) in rules {
+            State::add_normalization_rule(s, lhs.unwrap(), rhs.unwrap()).unwrap();
+        }
+        let r = Atom::parse("f28(v1)").unwrap();
+        assert_eq!(format!("{}", r), "f28(v1+5)");
+    }
+
+    #[test]
+    fn normalization_hooks() {
+        let (f30, f31) = (State::get_symbol("f30"), State::get_symbol("f31"));
+
+        // a Kronecker delta that evaluates for equal arguments
+        State::add_normalization_hook(f30, |f, out| {
+            let AtomView::Fun(f) = f else {
+                return false;
+            };
+
+            let mut args = f.iter();
+            match (args.next(), args.next()) {
+                (Some(a), Some(b)) if a == b => {
+                    *out = Atom::new_num(1);
+                    true
+                }
+                _ => false,
+            }
+        });
+
+        let r = Atom::parse("f30(v1,v1)+f30(v1,v2)").unwrap();
+        assert_eq!(r, Atom::parse("1+f30(v1,v2)").unwrap());
+
+        // hooks and rules are tried in order and the result is normalized again
+        State::add_normalization_rule(
+            f31,
+            Pattern::parse("f31(x_,x_)").unwrap(),
+            Pattern::parse("f30(x_,x_)").unwrap(),
+        )
+        .unwrap();
+        State::add_normalization_hook(f31, |f, out| {
+            let AtomView::Fun(f) = f else {
+                return false;
+            };
+
+            *out = f.iter().next().unwrap().to_owned() * &Atom::new_num(2);
+            true
+        });
+
+        let r = Atom::parse("f31(v1,v1)+f31(v2,v3)").unwrap();
+        assert_eq!(r, Atom::parse("1+2*v2").unwrap());
+
+        State::clear_normalization_rules(f31);
+        let r = Atom::parse("f31(v1,v1)").unwrap();
+        assert_eq!(format!("{}", r), "f31(v1,v1)");
+    }
 
     #[test]
     fn pow_apart() {
@@ -1307,4 +1946,213 @@ mod test {
         let refr = -Atom::new_var(State::E);
         assert_eq!(res, refr);
     }
+
+    #[test]
+    fn function_attributes() {
+        State::get_symbol_with_attributes("attr_sym", &[FunctionAttribute::Symmetric]).unwrap();
+        State::get_symbol_with_attributes("attr_lin", &[FunctionAttribute::Linear]).unwrap();
+        State::get_symbol_with_attributes("attr_anti", &[FunctionAttribute::Antisymmetric])
+            .unwrap();
+        State::get_symbol_with_attributes("attr_comm", &[FunctionAttribute::Commutative]).unwrap();
+
+        let res = Atom::parse("attr_sym(v2,v1)").unwrap();
+        assert_eq!(res, Atom::parse("attr_sym(v1,v2)").unwrap());
+
+        let res = Atom::parse("attr_comm(v3,v1,v2)").unwrap();
+        assert_eq!(res, Atom::parse("attr_comm(v1,v2,v3)").unwrap());
+        assert!(
+            State::get_symbol_with_attributes("attr_comm", &[FunctionAttribute::Symmetric]).is_ok()
+        );
+
+        let res = Atom::parse("attr_lin(v1+v2)").unwrap();
+        assert_eq!(res, Atom::parse("attr_lin(v1)+attr_lin(v2)").unwrap());
+
+        let res = Atom::parse("attr_anti(v2,v1)").unwrap();
+        assert_eq!(res, Atom::parse("-attr_anti(v1,v2)").unwrap());
+
+        let res = Atom::parse("attr_anti(v1,v2,v1)").unwrap();
+        assert!(res.is_zero());
+
+        assert!(
+            State::get_symbol_with_attributes("attr_sym", &[FunctionAttribute::Linear]).is_err()
+        );
+    }
+
+    #[test]
+    fn hold() {
+        let res = Atom::parse("hold(2+3)").unwrap();
+        assert_eq!(format!("{}", res), "2+3");
+        assert_eq!(res.release(), Atom::parse("5").unwrap());
+
+        let res = Atom::parse("v1*hold(v2+v2)").unwrap();
+        assert_eq!(format!("{}", res), "v1*(v2+v2)");
+        assert_eq!(res.release(), Atom::parse("2*v1*v2").unwrap());
+
+        let res = Atom::parse("hold(hold(2+3)^2)").unwrap();
+        assert_eq!(format!("{}", res), "(2+3)^2");
+        assert_eq!(res.release(), Atom::parse("25").unwrap());
+
+        // nested held sums and unnormalized held products
+        for (input, out) in [
+            ("v1*hold(v2*hold(v3+v4))", "v1*(v2*(v3+v4))"),
+            ("hold(hold(v1+v2)*v3)+v4", "v4+(v1+v2)*v3"),
+            ("v1+hold(-2*v2)", "v1-2*v2"),
+            ("v1+hold(2*hold(v2+v3))", "v1+2*(v2+v3)"),
+        ] {
+            assert_eq!(format!("{}", Atom::parse(input).unwrap()), out);
+        }
+
+        // replacements still apply inside a hold
+        let res = Pattern::parse("v2").unwrap().replace_all(
+            Atom::parse("hold(v2+v2)").unwrap().as_view(),
+            &Pattern::parse("v3").unwrap(),
+            None,
+            None,
+        );
+        assert_eq!(format!("{}", res), "v3+v3");
+
+        let res = Atom::parse("v1+v1").unwrap().hold();
+        assert_eq!(res.release(), Atom::parse("2*v1").unwrap());
+    }
+
+    #[test]
+    fn flatten_assoc() {
+        let (v1, v2, v3) = (
+            Atom::parse("v1").unwrap(),
+            Atom::parse("v2").unwrap(),
+            Atom::parse("v3").unwrap(),
+        );
+
+        let (sum, prod) = Workspace::get_local()
+            .with(|ws| ws.with_flatten_assoc(false, || (&v1 + &(&v2 + &v3), &v1 * &(&v2 * &v3))));
+        assert_eq!(format!("{}", sum), "v1+(v2+v3)");
+        assert_eq!(format!("{}", prod), "v1*(v2*v3)");
+        assert_ne!(sum, Atom::parse("v1+v2+v3").unwrap());
+
+        assert_eq!(sum.release(), Atom::parse("v1+v2+v3").unwrap());
+        assert_eq!(prod.release(), Atom::parse("v1*v2*v3").unwrap());
+
+        let sum =
+            Workspace::get_local().with(|ws| ws.with_flatten_assoc(true, || &v1 + &(&v2 + &v3)));
+        assert_eq!(sum, Atom::parse("v1+v2+v3").unwrap());
+
+        // the setting is restored when the computation panics
+        Workspace::get_local().with(|ws| {
+            let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                ws.with_flatten_assoc(false, || panic!("aborted"))
+            }));
+            assert!(r.is_err());
+            assert!(ws.flatten_assoc());
+        });
+        assert_eq!(&v1 + &(&v2 + &v3), Atom::parse("v1+v2+v3").unwrap());
+    }
+
+    #[test]
+    fn imaginary_unit_powers() {
+        let i = Atom::new_var(State::I);
+        assert_eq!(&i * &i, Atom::new_num(-1));
+        assert_eq!(&(&i * &i) * &i, -i.clone());
+        assert_eq!(i.npow(3), -i.clone());
+        assert_eq!(i.npow(4), Atom::new_num(1));
+        assert_eq!(i.npow(-1), -i.clone());
+        assert_eq!(i.npow(-6), Atom::new_num(-1));
+
+        let res = Atom::parse("𝑖^(9/2)").unwrap();
+        assert_eq!(res, Atom::parse("𝑖^(1/2)").unwrap());
+        let res = Atom::parse("𝑖^(-1/2)").unwrap();
+        assert_eq!(res, Atom::parse("𝑖^(7/2)").unwrap());
+
+        let res = Atom::parse("f1(𝑖)").unwrap();
+        assert_eq!(format!("{}", res), "f1(𝑖)");
+
+        let res = Atom::parse("(v1+v2*𝑖)*(v3+v4*𝑖)").unwrap().expand();
+        assert_eq!(
+            res,
+            Atom::parse("v1*v3-v2*v4+𝑖*(v1*v4+v2*v3)").unwrap().expand()
+        );
+    }
+
+    #[test]
+    fn abs_floor_ceil() {
+        assert_eq!(Atom::parse("floor(7/2)").unwrap(), Atom::new_num(3));
+        assert_eq!(Atom::parse("floor(-7/2)").unwrap(), Atom::new_num(-4));
+        assert_eq!(Atom::parse("floor(-4)").unwrap(), Atom::new_num(-4));
+        assert_eq!(Atom::parse("ceil(7/2)").unwrap(), Atom::new_num(4));
+        assert_eq!(Atom::parse("ceil(-7/2)").unwrap(), Atom::new_num(-3));
+        assert_eq!(
+            Atom::parse("abs(-3/4)").unwrap(),
+            Atom::parse("3/4").unwrap()
+        );
+        assert_eq!(Atom::parse("abs(5)").unwrap(), Atom::new_num(5));
+
+        for f in ["abs", "floor", "ceil"] {
+            let res = Atom::parse(&format!("{}(v1+1)", f)).unwrap();
+            assert_eq!(format!("{}", res), format!("{}(v1+1)", f));
+        }
+
+        let res = Atom::parse("abs(v1)-abs(-4)+floor(2*3/4)").unwrap();
+        assert_eq!(res, Atom::parse("abs(v1)-3").unwrap());
+    }
+
+    #[test]
+    fn root_extraction() {
+        let res = Atom::parse("sqrt(12)").unwrap();
+        assert_eq!(format!("{}", res), "2*sqrt(3)");
+        let res = Atom::parse("sqrt(1/4)").unwrap();
+        assert_eq!(res, Atom::parse("1/2").unwrap());
+        let res = Atom::parse("sqrt(1/2)").unwrap();
+        assert_eq!(format!("{}", res), "1/2*sqrt(2)");
+        let res = Atom::parse("sqrt(-12)").unwrap();
+        assert_eq!(format!("{}", res), "2*𝑖*sqrt(3)");
+
+        let res = Atom::parse("12^(1/2)").unwrap();
+        assert_eq!(format!("{}", res), "2*3^(1/2)");
+        let res = Atom::parse("12^(-1/2)").unwrap();
+        assert_eq!(format!("{}", res), "1/6*3^(1/2)");
+        let res = Atom::parse("24^(1/3)").unwrap();
+        assert_eq!(format!("{}", res), "2*3^(1/3)");
+        let res = Atom::parse("(-12)^(1/2)").unwrap();
+        assert_eq!(format!("{}", res), "2*𝑖*3^(1/2)");
+        let res = Atom::parse("v1*12^(1/2)-2*3^(1/2)*v1").unwrap();
+        assert_eq!(res, Atom::new_num(0));
+
+        // general exponents and prime factors above 1000
+        let res = Atom::parse("12^(3/2)").unwrap();
+        assert_eq!(format!("{}", res), "24*3^(1/2)");
+        let res = Atom::parse("sqrt(3054243)").unwrap();
+        assert_eq!(format!("{}", res), "1009*sqrt(3)");
+    }
+
+    #[test]
+    fn total_order() {
+        use std::cmp::Ordering;
+
+        let mut atoms: Vec<_> = ["2", "f(x)", "x^2", "x"]
+            .iter()
+            .map(|x| Atom::parse(x).unwrap())
+            .collect();
+        atoms.sort();
+        let expected: Vec<_> = ["x", "x^2", "f(x)", "2"]
+            .iter()
+            .map(|x| Atom::parse(x).unwrap())
+            .collect();
+        assert_eq!(atoms, expected);
+
+        let x = Atom::parse("x").unwrap();
+        let two = Atom::parse("2").unwrap();
+        assert_eq!(x.cmp(&two), Ordering::Less);
+        assert_eq!(two.cmp(&x), Ordering::Greater);
+
+        let f1 = Atom::parse("f(x)").unwrap();
+        let f2 = Atom::parse("f(x,y)").unwrap();
+        assert_ne!(f1.cmp(&f2), Ordering::Equal);
+        assert_eq!(f1.cmp(&f2), f2.cmp(&f1).reverse());
+
+        let a = Atom::parse("x*y+f(x)").unwrap();
+        let b = Atom::parse("f(x)+y*x").unwrap();
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        let mut v = vec![(a, 0), (b, 1)];
+        v.sort_by(|x, y| x.0.cmp(&y.0));
+        assert_eq!(v[0].1, 0);
+    }
 }
@@ -0,0 +1,534 @@
+//! The packed encoding of rational numbers that is used in the representation of atoms.
+//!
+//! This module only depends on `core` and `alloc` and is also available when
+//! the crate is compiled without the default `std` feature. Fractions of
+//! machine-size integers can be written and read with [PackedRationalNumberWriter]
+//! and [PackedRationalNumberReader]. Large numbers, finite field elements and rational
+//! polynomials can only be decoded with `std`, as they are stored in GMP's format
+//! or refer to the global state.
+
+use alloc::{format, string::String, vec::Vec};
+
+use bytes::{Buf, BufMut};
+
+pub(crate) const U8_NUM: u8 = 0b00000001;
+pub(crate) const U16_NUM: u8 = 0b00000010;
+pub(crate) const U32_NUM: u8 = 0b00000011;
+pub(crate) const U64_NUM: u8 = 0b00000100;
+pub(crate) const FIN_NUM: u8 = 0b00000101;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) const GAL_NUM: u8 = 0b00000110;
+pub(crate) const ARB_NUM: u8 = 0b00000111;
+pub(crate) const RAT_POLY: u8 = 0b00001000;
+pub(crate) const U8_DEN: u8 = 0b00010000;
+pub(crate) const U16_DEN: u8 = 0b00100000;
+pub(crate) const U32_DEN: u8 = 0b00110000;
+pub(crate) const U64_DEN: u8 = 0b01000000;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) const ARB_DEN: u8 = 0b01110000;
+pub(crate) const NUM_MASK: u8 = 0b00001111;
+pub(crate) const DEN_MASK: u8 = 0b01110000;
+pub(crate) const SIGN: u8 = 0b10000000;
+
+const U8_NUM_U8_DEN: u8 = U8_NUM | U8_DEN;
+const U16_NUM_U8_DEN: u8 = U16_NUM | U8_DEN;
+const U32_NUM_U8_DEN: u8 = U32_NUM | U8_DEN;
+const U64_NUM_U8_DEN: u8 = U64_NUM | U8_DEN;
+const U8_NUM_U16_DEN: u8 = U8_NUM | U16_DEN;
+const U16_NUM_U16_DEN: u8 = U16_NUM | U16_DEN;
+const U32_NUM_U16_DEN: u8 = U32_NUM | U16_DEN;
+const U64_NUM_U16_DEN: u8 = U64_NUM | U16_DEN;
+const U8_NUM_U32_DEN: u8 = U8_NUM | U32_DEN;
+const U16_NUM_U32_DEN: u8 = U16_NUM | U32_DEN;
+const U32_NUM_U32_DEN: u8 = U32_NUM | U32_DEN;
+const U64_NUM_U32_DEN: u8 = U64_NUM | U32_DEN;
+const U8_NUM_U64_DEN: u8 = U8_NUM | U64_DEN;
+const U16_NUM_U64_DEN: u8 = U16_NUM | U64_DEN;
+const U32_NUM_U64_DEN: u8 = U32_NUM | U64_DEN;
+const U64_NUM_U64_DEN: u8 = U64_NUM | U64_DEN;
+
+#[inline(always)]
+const fn get_size_of_natural(num_type: u8) -> u8 {
+    match num_type {
+        0 => 0,
+        U8_NUM => 1,
+        U16_NUM => 2,
+        U32_NUM => 4,
+        U64_NUM => 8,
+        _ => unreachable!(),
+    }
+}
+
+/// A generalized rational number. The first byte indicates the sign, size and type of the numerator and denominator.
+/// The highest four bits give the byte size of the numerator and the lower bits of the denominator.
+pub trait PackedRationalNumberWriter {
+    /// Write a single number.
+    fn write_packed(&self, dest: &mut Vec<u8>);
+    /// Write a fraction to a fixed-size buffer.
+    fn write_packed_fixed(&self, dest: &mut [u8]);
+    /// Get the number of bytes of the packed representation.
+    fn get_packed_size(&self) -> u64;
+}
+
+/// A reader for generalized rational numbers. See [`PackedRationalNumberWriter`].
+pub trait PackedRationalNumberReader {
+    fn get_frac_u64(&self) -> (u64, u64, &[u8]);
+    fn get_frac_i64(&self) -> (i64, i64, &[u8]);
+    /// Read a fraction of natural numbers, or return an error if the buffer is
+    /// truncated or does not start with a fraction of natural numbers.
+    fn try_get_frac_u64(&self) -> Result<(u64, u64, &[u8]), String>;
+    /// Read a signed fraction, or return an error if the buffer is
+    /// truncated or does not start with a fraction of natural numbers.
+    fn try_get_frac_i64(&self) -> Result<(i64, i64, &[u8]), String>;
+    fn skip_rational(&self) -> &[u8];
+    fn is_zero_rat(&self) -> bool;
+    fn is_one_rat(&self) -> bool;
+}
+
+impl PackedRationalNumberReader for [u8] {
+    #[inline(always)]
+    fn get_frac_u64(&self) -> (u64, u64, &[u8]) {
+        let mut source = self;
+        let disc = source.get_u8();
+        match disc & (NUM_MASK | DEN_MASK) {
+            U8_NUM => {
+                let n = source.get_u8();
+                (n as u64, 1, source)
+            }
+            U16_NUM => {
+                let n = source.get_u16_le();
+                (n as u64, 1, source)
+            }
+            U32_NUM => {
+                let n = source.get_u32_le();
+                (n as u64, 1, source)
+            }
+            U64_NUM => {
+                let n = source.get_u64_le();
+                (n, 1, source)
+            }
+            U8_NUM_U8_DEN => {
+                let n = source.get_u8();
+                let d = source.get_u8();
+                (n as u64, d as u64, source)
+            }
+            U16_NUM_U8_DEN => {
+                let n = source.get_u16_le();
+                let d = source.get_u8();
+                (n as u64, d as u64, source)
+            }
+            U32_NUM_U8_DEN => {
+                let n = source.get_u32_le();
+                let d = source.get_u8();
+                (n as u64, d as u64, source)
+            }
+            U64_NUM_U8_DEN => {
+                let n = source.get_u64_le();
+                let d = source.get_u8();
+                (n, d as u64, source)
+            }
+            U8_NUM_U16_DEN => {
+                let n = source.get_u8();
+                let d = source.get_u16_le();
+                (n as u64, d as u64, source)
+            }
+            U16_NUM_U16_DEN => {
+                let n = source.get_u16_le();
+                let d = source.get_u16_le();
+                (n as u64, d as u64, source)
+            }
+            U32_NUM_U16_DEN => {
+                let n = source.get_u32_le();
+                let d = source.get_u16_le();
+                (n as u64, d as u64, source)
+            }
+            U64_NUM_U16_DEN => {
+                let n = source.get_u64_le();
+                let d = source.get_u16_le();
+                (n, d as u64, source)
+            }
+            U8_NUM_U32_DEN => {
+                let n = source.get_u8();
+                let d = source.get_u32_le();
+                (n as u64, d as u64, source)
+            }
+            U16_NUM_U32_DEN => {
+                let n = source.get_u16_le();
+                let d = source.get_u32_le();
+                (n as u64, d as u64, source)
+            }
+            U32_NUM_U32_DEN => {
+                let n = source.get_u32_le();
+                let d = source.get_u32_le();
+                (n as u64, d as u64, source)
+            }
+            U64_NUM_U32_DEN => {
+                let n = source.get_u64_le();
+                let d = source.get_u32_le();
+                (n, d as u64, source)
+            }
+            U8_NUM_U64_DEN => {
+                let n = source.get_u8();
+                let d = source.get_u64_le();
+                (n as u64, d, source)
+            }
+            U16_NUM_U64_DEN => {
+                let n = source.get_u16_le();
+                let d = source.get_u64_le();
+                (n as u64, d, source)
+            }
+            U32_NUM_U64_DEN => {
+                let n = source.get_u32_le();
+                let d = source.get_u64_le();
+                (n as u64, d, source)
+            }
+            U64_NUM_U64_DEN => {
+                let n = source.get_u64_le();
+                let d = source.get_u64_le();
+                (n, d, source)
+            }
+            x => {
+                unreachable!("Unsupported numerator/denominator type {}", x)
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn get_frac_i64(&self) -> (i64, i64, &[u8]) {
+        let mut source = self;
+        let disc = source.get_u8();
+        let num;
+        (num, source) = match disc & NUM_MASK {
+            U8_NUM => {
+                let v = source.get_u8();
+                (v as i64, source)
+            }
+            U16_NUM => {
+                let v = source.get_u16_le();
+                (v as i64, source)
+            }
+            U32_NUM => {
+                let v = source.get_u32_le();
+                (v as i64, source)
+            }
+            U64_NUM => {
+                let v = source.get_u64_le();
+                (v as i64, source)
+            }
+            x => {
+                unreachable!("Unsupported numerator type {}", x)
+            }
+        };
+
+        let den;
+        (den, source) = match disc & DEN_MASK {
+            0 => (1i64, source),
+            U8_DEN => {
+                let v = source.get_u8();
+                (v as i64, source)
+            }
+            U16_DEN => {
+                let v = source.get_u16_le();
+                (v as i64, source)
+            }
+            U32_DEN => {
+                let v = source.get_u32_le();
+                (v as i64, source)
+            }
+            U64_DEN => {
+                let v = source.get_u64_le();
+                (v as i64, source)
+            }
+            x => {
+                unreachable!("Unsupported denominator type {}", x)
+            }
+        };
+
+        if disc & SIGN != 0 {
+            // the magnitude of i64::MIN is stored as 2^63, which wraps to i64::MIN
+            (num.wrapping_neg(), den, source)
+        } else {
+            (num, den, source)
+        }
+    }
+
+    #[inline(always)]
+    fn skip_rational(&self) -> &[u8] {
+        let mut dest = self;
+        let disc = dest.get_u8();
+
+        match disc & (NUM_MASK | DEN_MASK) {
+            U8_NUM => {
+                dest.advance(1);
+            }
+            U16_NUM | U8_NUM_U8_DEN => {
+                dest.advance(2);
+            }
+            U16_NUM_U8_DEN | U8_NUM_U16_DEN => {
+                dest.advance(3);
+            }
+            U32_NUM | U16_NUM_U16_DEN => {
+                dest.advance(4);
+            }
+            U32_NUM_U8_DEN | U8_NUM_U32_DEN => {
+                dest.advance(5);
+            }
+            U32_NUM_U16_DEN | U16_NUM_U32_DEN => {
+                dest.advance(6);
+            }
+            U64_NUM | U32_NUM_U32_DEN => {
+                dest.advance(8);
+            }
+            U64_NUM_U8_DEN | U8_NUM_U64_DEN => {
+                dest.advance(9);
+            }
+            U64_NUM_U16_DEN | U16_NUM_U64_DEN => {
+                dest.advance(10);
+            }
+            U64_NUM_U32_DEN | U32_NUM_U64_DEN => {
+                dest.advance(12);
+            }
+            U64_NUM_U64_DEN => {
+                dest.advance(16);
+            }
+            x => {
+                let v_num = x & NUM_MASK;
+                if v_num == ARB_NUM {
+                    let (num_size, den_size);
+                    (num_size, den_size, dest) = dest.get_frac_i64();
+                    let num_size = num_size.unsigned_abs() as usize;
+                    let den_size = den_size.unsigned_abs() as usize;
+                    dest.advance(num_size + den_size);
+                } else if v_num == RAT_POLY {
+                    let size = dest.get_u32_le() as usize;
+                    dest.advance(size);
+                } else if v_num == FIN_NUM {
+                    let var_size = dest.get_u8();
+                    let size = get_size_of_natural(var_size & NUM_MASK)
+                        + get_size_of_natural((var_size & DEN_MASK) >> 4);
+                    dest.advance(size as usize);
+                } else {
+                    unreachable!("Unsupported numerator/denominator type {}", disc)
+                }
+            }
+        }
+
+        dest
+    }
+
+    #[inline(always)]
+    fn is_zero_rat(&self) -> bool {
+        // TODO: make a zero have no number at all (i.e., self[1] = 0)
+        self[1] == 1 && self[2] == 0
+    }
+
+    #[inline(always)]
+    fn is_one_rat(&self) -> bool {
+        self[1] == 1 && self[2] == 1
+    }
+
+    fn try_get_frac_u64(&self) -> Result<(u64, u64, &[u8]), String> {
+        match read_natural_frac(self) {
+            Some((tag, num, den, rest)) if tag & SIGN == 0 => Ok((num, den, rest)),
+            Some((tag, ..)) => Err(format!("Negative fraction with tag {:#04x}", tag)),
+            None => Err(format!(
+                "Invalid or truncated packed fraction starting with {:02x?}",
+                self.first()
+            )),
+        }
+    }
+
+    fn try_get_frac_i64(&self) -> Result<(i64, i64, &[u8]), String> {
+        match read_natural_frac(self) {
+            Some((tag, num, den, rest)) => {
+                if tag & SIGN != 0 {
+                    // the magnitude of i64::MIN is stored as 2^63, which wraps to i64::MIN
+                    Ok(((num as i64).wrapping_neg(), den as i64, rest))
+                } else {
+                    Ok((num as i64, den as i64, rest))
+                }
+            }
+            None => Err(format!(
+                "Invalid or truncated packed fraction starting with {:02x?}",
+                self.first()
+            )),
+        }
+    }
+}
+
+impl PackedRationalNumberWriter for (i64, i64) {
+    #[inline(always)]
+    fn write_packed(&self, dest: &mut Vec<u8>) {
+        let p = dest.len();
+
+        let num_u64 = self.0.unsigned_abs();
+        let den_u64 = self.1.unsigned_abs();
+        (num_u64, den_u64).write_packed(dest);
+
+        if self.0 >= 0 && self.1 < 0 || self.0 < 0 && self.1 >= 0 {
+            dest[p] |= SIGN;
+        }
+    }
+
+    #[inline(always)]
+    fn write_packed_fixed(&self, dest: &mut [u8]) {
+        let num_u64 = self.0.unsigned_abs();
+        let den_u64 = self.1.unsigned_abs();
+        (num_u64, den_u64).write_packed_fixed(dest);
+
+        if self.0 >= 0 && self.1 < 0 || self.0 < 0 && self.1 >= 0 {
+            dest[0] |= SIGN;
+        }
+    }
+
+    fn get_packed_size(&self) -> u64 {
+        (self.0.unsigned_abs(), self.1.unsigned_abs()).get_packed_size()
+    }
+}
+
+impl PackedRationalNumberWriter for (u64, u64) {
+    #[inline(always)]
+    fn write_packed(&self, dest: &mut Vec<u8>) {
+        let p = dest.len();
+
+        if self.0 <= u8::MAX as u64 {
+            dest.put_u8(U8_NUM);
+            dest.put_u8(self.0 as u8);
+        } else if self.0 <= u16::MAX as u64 {
+            dest.put_u8(U16_NUM);
+            dest.put_u16_le(self.0 as u16);
+        } else if self.0 <= u32::MAX as u64 {
+            dest.put_u8(U32_NUM);
+            dest.put_u32_le(self.0 as u32);
+        } else {
+            dest.put_u8(U64_NUM);
+            dest.put_u64_le(self.0);
+        }
+
+        if self.1 == 1 {
+        } else if self.1 <= u8::MAX as u64 {
+            dest[p] |= U8_DEN;
+            dest.put_u8(self.1 as u8);
+        } else if self.1 <= u16::MAX as u64 {
+            dest[p] |= U16_DEN;
+            dest.put_u16_le(self.1 as u16);
+        } else if self.1 <= u32::MAX as u64 {
+            dest[p] |= U32_DEN;
+            dest.put_u32_le(self.1 as u32);
+        } else {
+            dest[p] |= U64_DEN;
+            dest.put_u64_le(self.1);
+        }
+    }
+
+    #[inline(always)]
+    fn write_packed_fixed(&self, dest: &mut [u8]) {
+        let (tag, mut dest) = dest.split_first_mut().unwrap();
+
+        if self.0 <= u8::MAX as u64 {
+            *tag = U8_NUM;
+            dest.put_u8(self.0 as u8);
+        } else if self.0 <= u16::MAX as u64 {
+            *tag = U16_NUM;
+            dest.put_u16_le(self.0 as u16);
+        } else if self.0 <= u32::MAX as u64 {
+            *tag = U32_NUM;
+            dest.put_u32_le(self.0 as u32);
+        } else {
+            *tag = U64_NUM;
+            dest.put_u64_le(self.0);
+        }
+
+        if self.1 == 1 {
+        } else if self.1 <= u8::MAX as u64 {
+            *tag |= U8_DEN;
+            dest.put_u8(self.1 as u8);
+        } else if self.1 <= u16::MAX as u64 {
+            *tag |= U16_DEN;
+            dest.put_u16_le(self.1 as u16);
+        } else if self.1 <= u32::MAX as u64 {
+            *tag |= U32_DEN;
+            dest.put_u32_le(self.1 as u32);
+        } else {
+            *tag |= U64_DEN;
+            dest.put_u64_le(self.1);
+        }
+    }
+
+    fn get_packed_size(&self) -> u64 {
+        let mut size = 1;
+        size += if self.0 <= u8::MAX as u64 {
+            get_size_of_natural(U8_NUM)
+        } else if self.0 <= u16::MAX as u64 {
+            get_size_of_natural(U16_NUM)
+        } else if self.0 <= u32::MAX as u64 {
+            get_size_of_natural(U32_NUM)
+        } else {
+            get_size_of_natural(U64_NUM)
+        };
+
+        size += if self.1 == 1 {
+            0
+        } else if self.1 <= u8::MAX as u64 {
+            get_size_of_natural(U8_NUM)
+        } else if self.1 <= u16::MAX as u64 {
+            get_size_of_natural(U16_NUM)
+        } else if self.1 <= u32::MAX as u64 {
+            get_size_of_natural(U32_NUM)
+        } else {
+            get_size_of_natural(U64_NUM)
+        };
+        size as u64
+    }
+}
+
+/// Get the byte width of a natural number type tag, which is either a numerator tag
+/// or a denominator tag shifted to the numerator position.
+pub(crate) fn natural_width(t: u8) -> Option<usize> {
+    match t {
+        0 => Some(0),
+        U8_NUM => Some(1),
+        U16_NUM => Some(2),
+        U32_NUM => Some(4),
+        U64_NUM => Some(8),
+        _ => None,
+    }
+}
+
+/// Read a packed fraction of natural numbers, returning the tag, numerator and denominator.
+pub(crate) fn read_natural_frac(source: &[u8]) -> Option<(u8, u64, u64, &[u8])> {
+    let (&tag, source) = source.split_first()?;
+    let num_width = natural_width(tag & NUM_MASK).filter(|w| *w > 0)?;
+    let den_width = natural_width((tag & DEN_MASK) >> 4)?;
+
+    let read = |b: &[u8]| b.iter().rev().fold(0u64, |acc, x| acc << 8 | *x as u64);
+
+    let num = read(source.get(..num_width)?);
+    let den = if den_width == 0 {
+        1
+    } else {
+        read(source.get(num_width..num_width + den_width)?)
+    };
+
+    Some((tag, num, den, &source[num_width + den_width..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PackedRationalNumberReader, PackedRationalNumberWriter};
+
+    #[test]
+    fn try_get_frac() {
+        let mut buf = vec![];
+        (-300i64, 7i64).write_packed(&mut buf);
+        (5u64, 1u64).write_packed(&mut buf);
+
+        let (num, den, rest) = buf.try_get_frac_i64().unwrap();
+        assert_eq!((num, den), (-300, 7));
+        assert_eq!(rest.try_get_frac_u64().unwrap(), (5, 1, &[][..]));
+        assert!(buf.try_get_frac_u64().is_err());
+
+        assert!(buf[..2].try_get_frac_i64().is_err());
+        assert!([].try_get_frac_u64().is_err());
+    }
+}
@@ -1,4 +1,9 @@
-use std::{fmt::Write, string::String, sync::Arc};
+use std::{
+    fmt::Write,
+    io::{BufRead, BufReader, Read},
+    string::String,
+    sync::Arc,
+};
 
 use bytes::Buf;
 use rug::Integer as MultiPrecisionInteger;
@@ -7,7 +12,7 @@ use smallvec::SmallVec;
 use smartstring::{LazyCompact, SmartString};
 
 use crate::{
-    atom::Atom,
+    atom::{Atom, AtomView, Symbol},
     coefficient::ConvertToRing,
     domains::{integer::Integer, Ring},
     poly::{polynomial::MultivariatePolynomial, Exponent, Variable},
@@ -64,6 +69,65 @@ const HEX_TO_DIGIT: [u8; 24] = [
     0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 0, 0, 0, 0, 0, 0, 10, 11, 12, 13, 14, 15, 0,
 ];
 
+/// Settings for parsing expressions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseSettings {
+    /// Interpret juxtaposed factors, such as `2x`, `x y` and `(x+1)(x-1)`, as
+    /// a multiplication. If disabled, an explicit `*` is required.
+    ///
+    /// Identifiers are maximal runs of characters that are not operators or whitespace,
+    /// so `xy` and `x2` are single symbols, whereas `2x` is `2*x` since an identifier
+    /// cannot start with a digit. An identifier directly followed by `(` is always
+    /// a function call.
+    pub implicit_multiplication: bool,
+    /// The maximal nesting depth of the parsed expression, counting parentheses,
+    /// function calls and exponentiations. Deeper input yields an error instead of
+    /// overflowing the stack during the conversion to an atom and its normalization.
+    /// The default is 10000. Note that atoms constructed in code are not subject to this limit.
+    pub max_depth: usize,
+    /// A character that marks a wildcard when it precedes a name, in addition to the native
+    /// trailing underscores. For example, with `Some('?')`, `?x` is parsed as `x_` and the sequence
+    /// wildcards `??x` and `???x` as `x__` and `x___`. The marker has no special meaning
+    /// elsewhere in a name.
+    pub wildcard_prefix: Option<char>,
+}
+
+impl Default for ParseSettings {
+    fn default() -> Self {
+        ParseSettings {
+            implicit_multiplication: true,
+            max_depth: 10_000,
+            wildcard_prefix: None,
+        }
+    }
+}
+
+/// Write a name that starts with the wildcard marker `prefix` in the native notation,
+/// where every marker becomes a trailing underscore.
+fn wildcard_from_prefix(name: &str, prefix: char) -> Option<String> {
+    let stripped = name.trim_start_matches(prefix);
+    if stripped.len() == name.len() {
+        return None;
+    }
+
+    let level = name[..name.len() - stripped.len()].chars().count();
+    Some(format!("{}{}", stripped, "_".repeat(level)))
+}
+
+fn max_depth_exceeded(max_depth: usize, line: usize, column: usize) -> String {
+    format!(
+        "Error at line {} and position {}: maximal nesting depth of {} exceeded",
+        line, column, max_depth
+    )
+}
+
+fn missing_operator(c: char, line: usize, column: usize) -> String {
+    format!(
+        "Missing operator before '{}' at line {} and column {}: implicit multiplication is disabled",
+        c, line, column
+    )
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum ParseState {
     Identifier,
@@ -104,6 +168,12 @@ impl Operator {
         }
     }
 
+    /// Get the precedence of the operator, where a higher value binds more tightly.
+    /// From loose to tight, the order is `,`, `+`, `*`, `/`, unary `-` and `^`.
+    ///
+    /// As a result, `-x^2` is parsed as `-(x^2)` and `-2^2` as `-4`. A unary sign
+    /// is allowed at the start of an exponent, where it only applies to the next factor,
+    /// such that `2^-3` is `1/8` and `2^-x*3` is `2^(-x)*3`.
     #[inline]
     pub fn get_precedence(&self) -> u8 {
         match self {
@@ -234,6 +304,30 @@ impl Token {
         }
     }
 
+    /// Get the nesting depth of the atom that the token represents.
+    fn depth(&self) -> usize {
+        let mut max = 0;
+        let mut stack = vec![(self, 0)];
+        while let Some((t, level)) = stack.pop() {
+            max = max.max(level);
+            match t {
+                Token::Op(_, _, Operator::Pow, args) => {
+                    // a^b^c is nested as a^(b^c)
+                    for (i, a) in args.iter().enumerate() {
+                        stack.push((a, level + i.min(args.len().saturating_sub(2)) + 1));
+                    }
+                }
+                Token::Op(_, _, _, args) | Token::Fn(_, args) => {
+                    for a in args {
+                        stack.push((a, level + 1));
+                    }
+                }
+                _ => {}
+            }
+        }
+        max
+    }
+
     /// Get the precedence of the token.
     #[inline]
     fn get_precedence(&self) -> u8 {
@@ -613,6 +707,11 @@ impl Token {
 
     /// Parse a Symbolica expression.
     pub fn parse(input: &str) -> Result<Token, String> {
+        Self::parse_with_settings(input, &ParseSettings::default())
+    }
+
+    /// Parse a Symbolica expression with custom settings.
+    pub fn parse_with_settings(input: &str, settings: &ParseSettings) -> Result<Token, String> {
         let mut stack: Vec<_> = Vec::with_capacity(20);
         stack.push(Token::Start);
         let mut state = ParseState::Any;
@@ -629,13 +728,24 @@ impl Token {
 
         let mut line_counter = 1;
         let mut column_counter = 1;
+        let mut depth = 0;
 
         loop {
             match state {
                 ParseState::Identifier => {
                     if ops.contains(&c) || whitespace.contains(&c) {
                         state = ParseState::Any;
-                        stack.push(Token::ID(id_buffer.as_str().into()));
+                        match settings
+                            .wildcard_prefix
+                            .and_then(|p| wildcard_from_prefix(&id_buffer, p))
+                        {
+                            Some(name) if name.trim_end_matches('_').is_empty() => Err(format!(
+                                "Wildcard marker without a name at line {} and column {}",
+                                line_counter, column_counter
+                            ))?,
+                            Some(name) => stack.push(Token::ID(name.as_str().into())),
+                            None => stack.push(Token::ID(id_buffer.as_str().into())),
+                        }
                         id_buffer.clear();
                     } else if !forbidden.contains(&c) {
                         id_buffer.push(c);
@@ -727,19 +837,44 @@ impl Token {
                     '(' => {
                         // check if the opening bracket belongs to a function
                         if let Some(Token::ID(_)) = stack.last() {
+                            depth += 1;
+                            if depth > settings.max_depth {
+                                Err(max_depth_exceeded(
+                                    settings.max_depth,
+                                    line_counter,
+                                    column_counter,
+                                ))?;
+                            }
+
                             let name = unsafe { stack.pop().unwrap_unchecked() };
                             if let Token::ID(_) = name {
                                 stack.push(Token::Fn(true, vec![name])); // serves as open paren
                             }
                         } else if unsafe { stack.last().unwrap_unchecked() }.is_normal() {
+                            if !settings.implicit_multiplication {
+                                Err(missing_operator(c, line_counter, column_counter))?;
+                            }
+
                             // insert multiplication: x(...) -> x*(...)
                             stack.push(Token::Op(true, true, Operator::Mul, vec![]));
                             extra_ops.push(c);
                         } else {
+                            depth += 1;
+                            if depth > settings.max_depth {
+                                Err(max_depth_exceeded(
+                                    settings.max_depth,
+                                    line_counter,
+                                    column_counter,
+                                ))?;
+                            }
+
                             stack.push(Token::OpenParenthesis)
                         }
                     }
-                    ')' => stack.push(Token::CloseParenthesis),
+                    ')' => {
+                        depth = depth.saturating_sub(1);
+                        stack.push(Token::CloseParenthesis)
+                    }
                     '/' => {
                         if matches!(
                             stack.last().unwrap(),
@@ -759,6 +894,10 @@ impl Token {
                     '\0' => stack.push(Token::EOF),
                     '[' => {
                         if unsafe { stack.last().unwrap_unchecked() }.is_normal() {
+                            if !settings.implicit_multiplication {
+                                Err(missing_operator(c, line_counter, column_counter))?;
+                            }
+
                             // insert multiplication: x[3,4] -> x*[3,4]
                             stack.push(Token::Op(true, true, Operator::Mul, vec![]));
                             extra_ops.push(c);
@@ -768,6 +907,10 @@ impl Token {
                     }
                     _ => {
                         if unsafe { stack.last().unwrap_unchecked() }.is_normal() {
+                            if !settings.implicit_multiplication {
+                                Err(missing_operator(c, line_counter, column_counter))?;
+                            }
+
                             // insert multiplication: x y -> x*y
                             stack.push(Token::Op(true, true, Operator::Mul, vec![]));
                             extra_ops.push(c);
@@ -923,7 +1066,15 @@ impl Token {
         }
 
         if stack.len() == 1 {
-            Ok(stack.pop().unwrap())
+            let t = stack.pop().unwrap();
+            if t.depth() > settings.max_depth {
+                Err(max_depth_exceeded(
+                    settings.max_depth,
+                    line_counter,
+                    column_counter,
+                ))?;
+            }
+            Ok(t)
         } else {
             match stack.get(stack.len() - 2) {
                 Some(Token::Op(false, true, op, _)) => Err(format!(
@@ -1154,11 +1305,287 @@ impl Token {
     }
 }
 
+/// Bindings of symbols to shared subexpressions, where every binding only refers to earlier ones.
+pub type Bindings = Vec<(Symbol, Atom)>;
+
+impl Workspace {
+    /// Parse a batch of expressions using this workspace.
+    /// A malformed input only yields an error for its own entry.
+    pub fn parse_many(&self, inputs: &[&str]) -> Vec<Result<Atom, String>> {
+        inputs
+            .iter()
+            .map(|input| Token::parse(input)?.to_atom(self))
+            .collect()
+    }
+
+    /// Parse a batch of expressions and replace the subexpressions that occur more than once
+    /// in the batch by fresh symbols `Z0`, `Z1`, ..., which are bound to these subexpressions.
+    ///
+    /// Every shared subexpression is stored once, in its binding, so that the bindings and the
+    /// returned expressions together form a DAG. A binding only refers to earlier bindings.
+    /// Substituting the bindings in reverse order yields the expressions returned by
+    /// [Workspace::parse_many]. Note that the symbols `Zi` are registered in the global state.
+    ///
+    /// A malformed input only yields an error for its own entry.
+    /// See [AtomView::cse_many](crate::atom::AtomView::cse_many).
+    pub fn parse_many_cse(&self, inputs: &[&str]) -> (Bindings, Vec<Result<Atom, String>>) {
+        let parsed = self.parse_many(inputs);
+
+        let views: Vec<_> = parsed.iter().flatten().map(|a| a.as_view()).collect();
+        let (bindings, shared) = AtomView::cse_many(&views, 2);
+
+        let mut shared = shared.into_iter();
+        let r = parsed
+            .iter()
+            .map(|p| match p {
+                Ok(_) => Ok(shared.next().unwrap()),
+                Err(e) => Err(e.clone()),
+            })
+            .collect();
+        (bindings, r)
+    }
+}
+
+impl Atom {
+    /// Parse a Lisp-like s-expression, such as `(+ (* 3 (^ x 2)) 1)` for `3*x^2+1`.
+    /// This is the inverse of [AtomView::to_sexpr](crate::atom::AtomView::to_sexpr).
+    ///
+    /// A list starts with `+`, `*`, `^`, `/`, `-` or a function name, followed by its arguments.
+    /// `(/ a)` is `1/a`, `(/ a b)` is `a/b`, `(- a)` is `-a` and `(- a b)` is `a-b`.
+    /// All other tokens must be numbers or variables.
+    pub fn parse_sexpr(input: &str) -> Result<Atom, String> {
+        let mut tokens = vec![];
+        let mut start = None;
+        for (i, c) in input.char_indices() {
+            if c.is_whitespace() || c == '(' || c == ')' {
+                if let Some(s) = start.take() {
+                    tokens.push(&input[s..i]);
+                }
+                if !c.is_whitespace() {
+                    tokens.push(&input[i..i + 1]);
+                }
+            } else if start.is_none() {
+                start = Some(i);
+            }
+        }
+        if let Some(s) = start {
+            tokens.push(&input[s..]);
+        }
+
+        Workspace::get_local().with(|ws| {
+            let mut pos = 0;
+            let mut a = ws.new_atom();
+            Atom::parse_sexpr_no_norm(&tokens, &mut pos, ws, &mut a)?;
+            if let Some(t) = tokens.get(pos) {
+                return Err(format!("Unexpected token '{}' after the expression", t));
+            }
+
+            let mut out = Atom::new();
+            a.as_view().normalize(ws, &mut out);
+            Ok(out)
+        })
+    }
+
+    fn parse_sexpr_no_norm(
+        tokens: &[&str],
+        pos: &mut usize,
+        ws: &Workspace,
+        out: &mut Atom,
+    ) -> Result<(), String> {
+        let Some(&token) = tokens.get(*pos) else {
+            return Err("Unexpected end of input".to_owned());
+        };
+        *pos += 1;
+
+        if token == ")" {
+            return Err("Unbalanced parentheses".to_owned());
+        }
+
+        if token != "(" {
+            let a = Atom::parse(token)?;
+            if !matches!(a, Atom::Num(_) | Atom::Var(_)) {
+                return Err(format!("'{}' is not a number or a variable", token));
+            }
+            *out = a;
+            return Ok(());
+        }
+
+        let head = match tokens.get(*pos) {
+            Some(&"(") | Some(&")") | None => {
+                return Err("A list must start with an operator or a function name".to_owned())
+            }
+            Some(h) => *h,
+        };
+        *pos += 1;
+
+        let mut args = vec![];
+        loop {
+            match tokens.get(*pos) {
+                Some(&")") => {
+                    *pos += 1;
+                    break;
+                }
+                Some(_) => {
+                    let mut a = ws.new_atom();
+                    Atom::parse_sexpr_no_norm(tokens, pos, ws, &mut a)?;
+                    args.push(a);
+                }
+                None => return Err("Unbalanced parentheses".to_owned()),
+            }
+        }
+
+        let minus_one = Atom::new_num(-1);
+        match (head, args.as_slice()) {
+            ("+", []) => *out = Atom::new_num(0),
+            ("*", []) => *out = Atom::new_num(1),
+            ("+", _) => {
+                let add = out.to_add();
+                for a in &args {
+                    add.extend(a.as_view());
+                }
+            }
+            ("*", _) => {
+                let mul = out.to_mul();
+                for a in &args {
+                    mul.extend(a.as_view());
+                }
+            }
+            ("^", [b, e]) => {
+                out.to_pow(b.as_view(), e.as_view());
+            }
+            ("/", [a]) => {
+                out.to_pow(a.as_view(), minus_one.as_view());
+            }
+            ("/", [a, b]) => {
+                let mut inv = ws.new_atom();
+                inv.to_pow(b.as_view(), minus_one.as_view());
+                let mul = out.to_mul();
+                mul.extend(a.as_view());
+                mul.extend(inv.as_view());
+            }
+            ("-", [a]) => {
+                let mul = out.to_mul();
+                mul.extend(a.as_view());
+                mul.extend(minus_one.as_view());
+            }
+            ("-", [a, b]) => {
+                let mut neg = ws.new_atom();
+                let mul = neg.to_mul();
+                mul.extend(b.as_view());
+                mul.extend(minus_one.as_view());
+                let add = out.to_add();
+                add.extend(a.as_view());
+                add.extend(neg.as_view());
+            }
+            ("^" | "/" | "-", _) => {
+                return Err(format!(
+                    "Incorrect number of arguments for '{}': {}",
+                    head,
+                    args.len()
+                ))
+            }
+            _ => {
+                let Atom::Var(v) = Atom::parse(head)? else {
+                    return Err(format!("'{}' is not a valid function name", head));
+                };
+
+                let fun = out.to_fun(v.get_symbol());
+                for a in &args {
+                    fun.add_arg(a.as_view());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse expressions from a reader, one at a time. Expressions are separated by newlines,
+/// but an expression with unbalanced brackets continues on the next line.
+/// Empty lines are skipped.
+pub fn parse_stream<R: Read>(reader: R) -> impl Iterator<Item = Result<Atom, String>> {
+    StreamParser {
+        reader: BufReader::new(reader),
+        line: String::new(),
+        buffer: String::new(),
+        depth: 0,
+        done: false,
+    }
+}
+
+/// An iterator over the expressions in a reader, created by [parse_stream].
+struct StreamParser<R: Read> {
+    reader: BufReader<R>,
+    line: String,
+    buffer: String,
+    /// The bracket depth at the end of the buffer.
+    depth: isize,
+    done: bool,
+}
+
+impl<R: Read> StreamParser<R> {
+    /// Update the bracket depth with the brackets of the line that was read last.
+    fn update_depth(&mut self) {
+        for c in self.line.chars() {
+            match c {
+                '(' | '[' => self.depth += 1,
+                ')' | ']' => self.depth -= 1,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for StreamParser<R> {
+    type Item = Result<Atom, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.update_depth();
+                    self.buffer.push_str(&self.line);
+                    if self.buffer.trim().is_empty() {
+                        self.buffer.clear();
+                    } else if self.depth <= 0 {
+                        let r = Atom::parse(&self.buffer);
+                        self.buffer.clear();
+                        self.depth = 0;
+                        return Some(r);
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(format!("Could not read input: {}", e)));
+                }
+            }
+        }
+
+        if self.buffer.trim().is_empty() {
+            None
+        } else {
+            // parse the final expression, which may be unbalanced
+            let r = Atom::parse(&self.buffer);
+            self.buffer.clear();
+            Some(r)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::sync::Arc;
 
-    use crate::{atom::Atom, domains::integer::Z, parser::Token, state::State};
+    use crate::{
+        atom::Atom,
+        domains::integer::Z,
+        id::Pattern,
+        parser::{parse_stream, ParseSettings, Token},
+        printer::{AtomPrinter, PrintOptions},
+        state::{State, Workspace},
+    };
 
     #[test]
     fn pow() {
@@ -1176,6 +1603,57 @@ mod test {
 
         let input = Atom::parse("(-x)^z").unwrap();
         assert_eq!(format!("{}", input), "(-x)^z");
+
+        assert_eq!(Atom::parse("-x^2").unwrap(), -&Atom::parse("x^2").unwrap());
+        assert_eq!(Atom::parse("-2^2").unwrap(), Atom::new_num(-4));
+        assert_eq!(Atom::parse("(-2)^2").unwrap(), Atom::new_num(4));
+        assert_eq!(Atom::parse("2^-3").unwrap(), Atom::new_num((1, 8)));
+        assert_eq!(Atom::parse("2^+3").unwrap(), Atom::new_num(8));
+        assert_eq!(Atom::parse("-2^-2").unwrap(), Atom::new_num((-1, 4)));
+        assert_eq!(
+            Atom::parse("2^-x*3").unwrap(),
+            Atom::parse("3*(2^(-x))").unwrap()
+        );
+        assert_eq!(
+            Atom::parse("x^-y^2").unwrap(),
+            Atom::parse("x^(-(y^2))").unwrap()
+        );
+    }
+
+    #[test]
+    fn sexpr() {
+        let a = Atom::parse_sexpr("(+ (* 3 (^ x 2)) 1)").unwrap();
+        assert_eq!(a, Atom::parse("3*x^2+1").unwrap());
+        assert_eq!(Atom::parse_sexpr(&a.to_sexpr()).unwrap(), a);
+
+        for input in [
+            "-2/3*f(x,y^-1)+g()",
+            "x^(1/2)*(x+y)^-3",
+            "1/123456789012345678901234567890*x",
+            "5",
+        ] {
+            let a = Atom::parse(input).unwrap();
+            assert_eq!(Atom::parse_sexpr(&a.to_sexpr()).unwrap(), a);
+        }
+
+        assert_eq!(
+            Atom::parse_sexpr("(- (/ x 2) (- y) (/ 4))").unwrap_err(),
+            "Incorrect number of arguments for '-': 3"
+        );
+        assert_eq!(
+            Atom::parse_sexpr("(- (/ x 2) (- y))").unwrap(),
+            Atom::parse("x/2+y").unwrap()
+        );
+        assert_eq!(
+            Atom::parse_sexpr("(f (+) (*) (/ 4))").unwrap(),
+            Atom::parse("f(0,1,1/4)").unwrap()
+        );
+
+        for input in [
+            "(+ 1 2", "(+ 1 2))", "()", "((f) x)", "(^ x)", "x+y", "(x+y 1)",
+        ] {
+            assert!(Atom::parse_sexpr(input).is_err());
+        }
     }
 
     #[test]
@@ -1189,6 +1667,134 @@ mod test {
         assert_eq!(input, res);
     }
 
+    #[test]
+    fn parse_many() {
+        let r = Workspace::get_local().with(|ws| ws.parse_many(&["v1^2+1", "v1^2+(", "v1^2+2"]));
+        assert_eq!(r.len(), 3);
+        assert_eq!(r[0], Ok(Atom::parse("v1^2+1").unwrap()));
+        assert!(r[1].is_err());
+        assert_eq!(r[2], Ok(Atom::parse("v1^2+2").unwrap()));
+    }
+
+    #[test]
+    fn parse_many_cse() {
+        let (bindings, r) =
+            Workspace::get_local().with(|ws| ws.parse_many_cse(&["v1^2+1", "v1^2+(", "v1^2+2"]));
+        let z0 = State::get_symbol("Z0");
+        assert_eq!(bindings, vec![(z0, Atom::parse("v1^2").unwrap())]);
+        assert_eq!(r.len(), 3);
+        assert_eq!(r[0], Ok(Atom::parse("Z0+1").unwrap()));
+        assert!(r[1].is_err());
+        assert_eq!(r[2], Ok(Atom::parse("Z0+2").unwrap()));
+
+        let (pattern, rhs) = (
+            Pattern::parse("Z0").unwrap(),
+            Pattern::parse("v1^2").unwrap(),
+        );
+        assert_eq!(
+            pattern.replace_all(r[2].as_ref().unwrap().as_view(), &rhs, None, None),
+            Atom::parse("v1^2+2").unwrap()
+        );
+
+        // nothing is shared between unrelated inputs
+        let (bindings, r) = Workspace::get_local().with(|ws| ws.parse_many_cse(&["v1+1", "v2"]));
+        assert!(bindings.is_empty());
+        assert_eq!(r[1], Ok(Atom::parse("v2").unwrap()));
+    }
+
+    #[test]
+    fn max_depth() {
+        let n = 100_000;
+        let input = format!("{}v1{}", "(".repeat(n), ")".repeat(n));
+        let err = Atom::parse(&input).unwrap_err();
+        assert!(err.contains("maximal nesting depth of 10000 exceeded"));
+
+        let input = format!("{}v1{}", "f1(".repeat(n), ")".repeat(n));
+        assert!(Atom::parse(&input).is_err());
+
+        // moderately deep input parses with the default settings
+        let input = format!("{}v1{}", "(".repeat(600), ")".repeat(600));
+        assert_eq!(Atom::parse(&input).unwrap(), Atom::parse("v1").unwrap());
+
+        let settings = ParseSettings {
+            max_depth: 10,
+            ..Default::default()
+        };
+
+        let input = format!("{}v1{}", "f1(".repeat(10), ")".repeat(10));
+        Atom::parse_with_settings(&input, &settings).unwrap();
+        let input = format!("{}v1{}", "f1(".repeat(11), ")".repeat(11));
+        assert!(Atom::parse_with_settings(&input, &settings).is_err());
+
+        let input = vec!["v1"; 11].join("^");
+        assert!(Atom::parse_with_settings(&input, &settings).is_ok());
+        let input = vec!["v1"; 12].join("^");
+        assert!(Atom::parse_with_settings(&input, &settings).is_err());
+    }
+
+    #[test]
+    fn wildcard_prefix() {
+        let settings = ParseSettings {
+            wildcard_prefix: Some('?'),
+            ..Default::default()
+        };
+
+        let a = Atom::parse_with_settings("?x*?f(??y,???z)+a?b+w_", &settings).unwrap();
+        assert_eq!(a, Atom::parse("x_*f_(y__,z___)+a?b+w_").unwrap());
+
+        let opts = PrintOptions {
+            wildcard_prefix: Some('?'),
+            ..PrintOptions::file()
+        };
+        let printed = format!("{}", AtomPrinter::new_with_options(a.as_view(), opts));
+        assert_eq!(
+            Atom::parse_with_settings(&printed, &settings).unwrap(),
+            a,
+            "{}",
+            printed
+        );
+        assert!(
+            printed.contains("??y") && printed.contains("?w"),
+            "{}",
+            printed
+        );
+
+        assert!(Atom::parse_with_settings("?+x", &settings).is_err());
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        for (input, res) in [
+            ("2x", "2*x"),
+            ("2x^2", "2*x^2"),
+            ("(x+1)(x-1)", "(x+1)*(x-1)"),
+            ("x y", "x*y"),
+            ("2(x+1)", "2*(x+1)"),
+        ] {
+            assert_eq!(Atom::parse(input).unwrap(), Atom::parse(res).unwrap());
+        }
+
+        // `xy` is a single symbol and `f(x)` is a call
+        assert_eq!(
+            Atom::parse("xy").unwrap(),
+            Atom::new_var(State::get_symbol("xy"))
+        );
+        assert_eq!(format!("{}", Atom::parse("f(x)").unwrap()), "f(x)");
+
+        let settings = ParseSettings {
+            implicit_multiplication: false,
+            ..Default::default()
+        };
+        for input in ["2x", "2x^2", "(x+1)(x-1)", "x y", "2(x+1)"] {
+            let err = Token::parse_with_settings(input, &settings).unwrap_err();
+            assert!(err.contains("implicit multiplication"), "{}", err);
+        }
+
+        for input in ["2*x^2", "f(x)*xy", "(x+1)*(x-1)"] {
+            assert!(Atom::parse_with_settings(input, &settings).is_ok());
+        }
+    }
+
     #[test]
     fn poly() {
         let var_names = ["v1".into(), "v2".into()];
@@ -1207,4 +1813,27 @@ mod test {
                 .to_polynomial(&Z, var_map.clone().into())
         );
     }
+
+    #[test]
+    fn stream() {
+        let input = std::io::Cursor::new("v1+v2\n\nf1(v1,\n  v2)*v3\n2^(3\n)");
+        let mut it = parse_stream(input);
+
+        assert_eq!(it.next(), Some(Atom::parse("v1+v2")));
+        assert_eq!(it.next(), Some(Atom::parse("f1(v1,v2)*v3")));
+        assert_eq!(it.next(), Some(Atom::parse("8")));
+        assert_eq!(it.next(), None);
+
+        // the depth is reset after an expression with too many closing brackets
+        let input = std::io::Cursor::new(
+            "v1)
+f1(
+v2)
+",
+        );
+        let mut it = parse_stream(input);
+        assert!(it.next().unwrap().is_err());
+        assert_eq!(it.next(), Some(Atom::parse("f1(v2)")));
+        assert_eq!(it.next(), None);
+    }
 }
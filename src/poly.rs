@@ -1,7 +1,11 @@
+pub mod dense;
 pub mod evaluate;
 pub mod factor;
 pub mod gcd;
 pub mod groebner;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod packed;
 pub mod polynomial;
 pub mod resultant;
 pub mod series;
@@ -24,7 +28,8 @@ use crate::coefficient::{Coefficient, CoefficientView, ConvertToRing};
 use crate::domains::factorized_rational_polynomial::{
     FactorizedRationalPolynomial, FromNumeratorAndFactorizedDenominator,
 };
-use crate::domains::integer::Integer;
+use crate::domains::integer::{Integer, IntegerRing, Z};
+use crate::domains::rational::{RationalField, Q};
 use crate::domains::rational_polynomial::{FromNumeratorAndDenominator, RationalPolynomial};
 use crate::domains::{EuclideanDomain, Ring};
 use crate::parser::{Operator, Token};
@@ -60,6 +65,18 @@ pub trait Exponent:
     fn to_u32(&self) -> u32;
     /// Convert from `u32`. This function may panic if the exponent is too large.
     fn from_u32(n: u32) -> Self;
+    /// Convert from `u32`, or return `None` if the exponent is too large.
+    fn checked_from_u32(n: u32) -> Option<Self> {
+        // build `n` bit by bit, so that `checked_add` detects an overflow where `from_u32` may panic
+        let mut r = Self::zero();
+        for i in (0..u32::BITS - n.leading_zeros()).rev() {
+            r = r.checked_add(&r)?;
+            if n & (1 << i) != 0 {
+                r = r.checked_add(&Self::one())?;
+            }
+        }
+        Some(r)
+    }
     fn is_zero(&self) -> bool;
     fn checked_add(&self, other: &Self) -> Option<Self>;
     fn gcd(&self, other: &Self) -> Self;
@@ -105,6 +122,11 @@ impl Exponent for u32 {
         *self == 0
     }
 
+    #[inline]
+    fn checked_from_u32(n: u32) -> Option<Self> {
+        Some(n)
+    }
+
     #[inline]
     fn checked_add(&self, other: &Self) -> Option<Self> {
         u32::checked_add(*self, *other)
@@ -178,6 +200,11 @@ impl Exponent for u16 {
         *self == 0
     }
 
+    #[inline]
+    fn checked_from_u32(n: u32) -> Option<Self> {
+        u16::try_from(n).ok()
+    }
+
     #[inline]
     fn checked_add(&self, other: &Self) -> Option<Self> {
         u16::checked_add(*self, *other)
@@ -252,6 +279,11 @@ impl Exponent for u8 {
         *self == 0
     }
 
+    #[inline]
+    fn checked_from_u32(n: u32) -> Option<Self> {
+        u8::try_from(n).ok()
+    }
+
     #[inline]
     fn checked_add(&self, other: &Self) -> Option<Self> {
         u8::checked_add(*self, *other)
@@ -433,7 +465,72 @@ impl Variable {
     }
 }
 
+/// A polynomial whose coefficient ring is detected from its coefficients,
+/// as returned by [Atom::parse_polynomial].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParsedPolynomial<E: Exponent = u16> {
+    Integer(MultivariatePolynomial<IntegerRing, E>),
+    Rational(MultivariatePolynomial<RationalField, E>),
+}
+
+impl<E: Exponent> ParsedPolynomial<E> {
+    /// Get the polynomial over the rationals.
+    pub fn to_rational(&self) -> MultivariatePolynomial<RationalField, E> {
+        match self {
+            ParsedPolynomial::Integer(p) => p.map_coeff(|c| c.into(), Q),
+            ParsedPolynomial::Rational(p) => p.clone(),
+        }
+    }
+}
+
 impl Atom {
+    /// Parse a polynomial with rational coefficients, whose variables are the symbols that appear in
+    /// the input. The polynomial is defined over the integers if all coefficients
+    /// are integers and over the rationals otherwise. Returns an error if the input is not
+    /// a polynomial, for example `1/x` or `f(x)`.
+    pub fn parse_polynomial<E: Exponent>(input: &str) -> Result<ParsedPolynomial<E>, String> {
+        let a = Atom::parse(input)?.expand();
+
+        // check the exponents before the conversion, as it panics when an exponent does not fit in `E`
+        let terms: Vec<_> = match a.as_view() {
+            AtomView::Add(add) => add.iter().collect(),
+            t => vec![t],
+        };
+        for t in terms {
+            let factors: Vec<_> = match t {
+                AtomView::Mul(m) => m.iter().collect(),
+                f => vec![f],
+            };
+            for f in factors {
+                if let AtomView::Pow(p) = f {
+                    if let AtomView::Num(n) = p.get_base_exp().1 {
+                        if let CoefficientView::Natural(e, 1) = n.get_coeff_view() {
+                            if e > 0
+                                && u32::try_from(e)
+                                    .ok()
+                                    .and_then(E::checked_from_u32)
+                                    .is_none()
+                            {
+                                return Err(format!("Exponent {} is too large", e));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let p = a
+            .as_view()
+            .to_polynomial_expanded::<_, E>(&Q, None, true)
+            .map_err(|e| format!("Not a polynomial: {}", e))?;
+
+        if p.coefficients.iter().all(|c| c.is_integer()) {
+            Ok(ParsedPolynomial::Integer(p.map_coeff(|c| c.numerator(), Z)))
+        } else {
+            Ok(ParsedPolynomial::Rational(p))
+        }
+    }
+
     /// Convert the atom to a polynomial, optionally in the variable ordering
     /// specified by `var_map`. If new variables are encountered, they are
     /// added to the variable map. Similarly, non-polynomial parts are automatically
@@ -1240,6 +1337,36 @@ impl<R: Ring, E: Exponent> RationalPolynomial<R, E> {
     }
 }
 
+impl<E: Exponent> TryFrom<&Atom> for RationalPolynomial<IntegerRing, E> {
+    type Error = String;
+
+    /// Write the expression over a common denominator as a rational polynomial with
+    /// integer coefficients. The variables are the symbols that occur in the expression.
+    /// An error is returned if the expression contains functions or non-integer powers.
+    fn try_from(value: &Atom) -> Result<Self, Self::Error> {
+        let r = value.to_rational_polynomial(&Q, &Z, None);
+
+        if let Some(v) = r
+            .get_variables()
+            .iter()
+            .find(|v| !matches!(v, Variable::Symbol(_)))
+        {
+            return Err(format!("{} is not a rational function of symbols", v));
+        }
+
+        Ok(r)
+    }
+}
+
+impl<R: Ring, E: Exponent> From<RationalPolynomial<R, E>> for Atom
+where
+    R::Element: Into<Coefficient>,
+{
+    fn from(value: RationalPolynomial<R, E>) -> Self {
+        value.to_expression()
+    }
+}
+
 impl Token {
     pub fn to_polynomial<R: Ring + ConvertToRing, E: Exponent>(
         &self,
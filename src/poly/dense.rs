@@ -0,0 +1,194 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::domains::Ring;
+
+use super::{polynomial::MultivariatePolynomial, Exponent, Variable};
+
+/// The maximal number of coefficients of a polynomial that is automatically stored densely.
+pub const MAX_AUTO_DENSE_SIZE: usize = 1 << 20;
+/// The minimal fraction of non-zero coefficients of a polynomial that is automatically stored densely.
+pub const MIN_AUTO_DENSITY: f64 = 0.25;
+
+/// A multivariate polynomial with a dense representation. The coefficient of the monomial
+/// with exponents `e` is stored at the packed index `(..((e[0] * shape[1] + e[1]) * shape[2] + e[2])..)`,
+/// where `shape[i]` is one more than the degree in the `i`th variable.
+#[derive(Clone)]
+pub struct DensePolynomial<F: Ring, E: Exponent = u16> {
+    pub coefficients: Vec<F::Element>,
+    pub shape: Vec<usize>,
+    pub field: F,
+    pub variables: Arc<Vec<Variable>>,
+    _phantom: PhantomData<E>,
+}
+
+impl<F: Ring, E: Exponent> DensePolynomial<F, E> {
+    /// Get the number of variables.
+    pub fn nvars(&self) -> usize {
+        self.shape.len()
+    }
+
+    /// Get the packed index of the monomial with the given exponents,
+    /// or `None` if it is outside of the stored range.
+    pub fn index(&self, exponents: &[E]) -> Option<usize> {
+        let mut index = 0;
+        for (e, s) in exponents.iter().zip(&self.shape) {
+            let e = e.to_u32() as usize;
+            if e >= *s {
+                return None;
+            }
+            index = index * s + e;
+        }
+        Some(index)
+    }
+
+    /// Get the coefficient of the monomial with the given exponents.
+    pub fn coefficient(&self, exponents: &[E]) -> F::Element {
+        self.index(exponents)
+            .map(|i| self.coefficients[i].clone())
+            .unwrap_or_else(|| self.field.zero())
+    }
+
+    /// Convert to the sparse representation.
+    pub fn to_sparse(&self) -> MultivariatePolynomial<F, E> {
+        let nterms = self.coefficients.iter().filter(|c| !F::is_zero(c)).count();
+        let mut r = MultivariatePolynomial::new(&self.field, Some(nterms), self.variables.clone());
+
+        let mut exp = vec![E::zero(); self.nvars()];
+        for (i, c) in self.coefficients.iter().enumerate() {
+            if F::is_zero(c) {
+                continue;
+            }
+
+            let mut p = i;
+            for (e, s) in exp.iter_mut().zip(&self.shape).rev() {
+                *e = E::from_u32((p % s) as u32);
+                p /= s;
+            }
+
+            // the packed index increases in lexicographical order, so the terms are appended at the back
+            r.append_monomial(c.clone(), &exp);
+        }
+
+        r
+    }
+}
+
+impl<F: Ring, E: Exponent> MultivariatePolynomial<F, E> {
+    /// Get the number of coefficients of the dense representation, or `None`
+    /// if it does not fit in a `usize`.
+    pub fn dense_size(&self) -> Option<usize> {
+        (0..self.nvars()).try_fold(1usize, |acc, i| {
+            acc.checked_mul(self.degree(i).to_u32() as usize + 1)
+        })
+    }
+
+    /// Returns `true` if the dense representation is expected to be more efficient, i.e., if
+    /// at least a fraction [MIN_AUTO_DENSITY] of its coefficients is non-zero and if it has
+    /// at most [MAX_AUTO_DENSE_SIZE] coefficients.
+    pub fn prefers_dense(&self) -> bool {
+        match self.dense_size() {
+            Some(size) if size <= MAX_AUTO_DENSE_SIZE => {
+                self.nterms() as f64 >= MIN_AUTO_DENSITY * size as f64
+            }
+            _ => false,
+        }
+    }
+
+    /// Convert to the dense representation, if it has at most `max_size` coefficients.
+    /// Use `usize::MAX` to force the conversion regardless of the memory usage.
+    pub fn to_dense(&self, max_size: usize) -> Option<DensePolynomial<F, E>> {
+        let size = self.dense_size().filter(|s| *s <= max_size)?;
+
+        let mut r = DensePolynomial {
+            coefficients: vec![self.field.zero(); size],
+            shape: (0..self.nvars())
+                .map(|i| self.degree(i).to_u32() as usize + 1)
+                .collect(),
+            field: self.field.clone(),
+            variables: self.variables.clone(),
+            _phantom: PhantomData,
+        };
+
+        for t in self {
+            let i = r.index(t.exponents).unwrap();
+            r.coefficients[i] = t.coefficient.clone();
+        }
+
+        Some(r)
+    }
+}
+
+/// A polynomial in either the sparse or the dense representation.
+#[derive(Clone)]
+pub enum PolynomialStorage<F: Ring, E: Exponent = u16> {
+    Sparse(MultivariatePolynomial<F, E>),
+    Dense(DensePolynomial<F, E>),
+}
+
+impl<F: Ring, E: Exponent> PolynomialStorage<F, E> {
+    /// Get the polynomial in the sparse representation.
+    pub fn to_sparse(&self) -> MultivariatePolynomial<F, E> {
+        match self {
+            PolynomialStorage::Sparse(p) => p.clone(),
+            PolynomialStorage::Dense(p) => p.to_sparse(),
+        }
+    }
+}
+
+impl<F: Ring, E: Exponent> From<MultivariatePolynomial<F, E>> for PolynomialStorage<F, E> {
+    /// Select the representation with [MultivariatePolynomial::prefers_dense].
+    fn from(value: MultivariatePolynomial<F, E>) -> Self {
+        if value.prefers_dense() {
+            PolynomialStorage::Dense(value.to_dense(MAX_AUTO_DENSE_SIZE).unwrap())
+        } else {
+            PolynomialStorage::Sparse(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{atom::Atom, domains::integer::Z, state::State};
+
+    use super::PolynomialStorage;
+
+    #[test]
+    fn dense_conversion() {
+        let vars = Arc::new(vec![
+            State::get_symbol("v1").into(),
+            State::get_symbol("v2").into(),
+        ]);
+        let p = Atom::parse("3+5*v1*v2^2-2*v2+v1^2")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Z, Some(vars));
+
+        let d = p.to_dense(usize::MAX).unwrap();
+        assert_eq!(d.shape, vec![3, 3]);
+        assert_eq!(d.coefficients.len(), 9);
+        assert_eq!(d.coefficient(&[1, 2]), 5.into());
+        assert_eq!(d.coefficient(&[0, 1]), (-2).into());
+        assert_eq!(d.coefficient(&[5, 0]), 0.into());
+        assert_eq!(d.to_sparse(), p);
+
+        assert!(p.prefers_dense());
+        assert!(matches!(
+            PolynomialStorage::from(p.clone()),
+            PolynomialStorage::Dense(_)
+        ));
+
+        // a sparse polynomial of high degree is not stored densely unless requested
+        let s = Atom::parse("v1^200*v2^200*v3^200+v4")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Z, None);
+        assert!(!s.prefers_dense());
+        assert!(s.to_dense(1 << 20).is_none());
+        let storage = PolynomialStorage::from(s.clone());
+        assert!(matches!(storage, PolynomialStorage::Sparse(_)));
+        assert_eq!(storage.to_sparse(), s);
+
+        let zero = p.zero();
+        assert_eq!(zero.to_dense(1).unwrap().to_sparse(), zero);
+    }
+}
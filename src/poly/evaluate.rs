@@ -4,7 +4,7 @@ use std::{
 };
 
 use ahash::{AHasher, HashMap, HashSet, HashSetExt};
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use crate::{
     atom::Symbol,
@@ -15,6 +15,7 @@ use crate::{
         EuclideanDomain,
     },
     state::Workspace,
+    utils,
 };
 use crate::{
     atom::{Atom, AtomView},
@@ -588,7 +589,7 @@ impl HornerScheme<RationalField> {
         }
 
         let mut best_scheme = scheme.clone();
-        let mut rng = thread_rng();
+        let mut rng = utils::rng();
 
         let mut new_best = Vec::with_capacity(polys.len());
 
@@ -1281,8 +1282,8 @@ impl InstructionList {
 
 /// A list of instructions suitable for fast numerical evaluation.
 pub struct InstructionListOutput<N: NumericalFloatLike> {
-    instr: Vec<(usize, Instruction<N>)>,
-    input_map: Vec<super::Variable>,
+    pub(crate) instr: Vec<(usize, Instruction<N>)>,
+    pub(crate) input_map: Vec<super::Variable>,
 }
 
 /// An efficient structure that performs a range of operations.
@@ -1406,6 +1407,96 @@ impl<'a, N: NumericalFloatLike> InstructionEvaluator<N> {
 
         &self.out
     }
+
+    /// Evaluate the converted polynomials for a batch of sample points, given in
+    /// a structure-of-arrays layout: `inputs[i][j]` is the value of variable `i` at point `j`.
+    /// The result of output `k` at point `j` is written to `out[k * n + j]`, where `n` is the
+    /// number of points.
+    ///
+    /// Every instruction is applied to a block of points at once, so that
+    /// the inner loops can be vectorized by the compiler.
+    pub fn eval_slice(&mut self, inputs: &[&[N]], out: &mut [N]) -> Result<(), String> {
+        const BLOCK_SIZE: usize = 64;
+
+        if inputs.len() != self.input_map.len() {
+            return Err(format!(
+                "Expected {} input columns, got {}",
+                self.input_map.len(),
+                inputs.len()
+            ));
+        }
+
+        let n = inputs.first().map(|c| c.len()).unwrap_or(0);
+        if inputs.iter().any(|c| c.len() != n) {
+            return Err("Input columns have different lengths".to_owned());
+        }
+
+        let out_len = self.out.len();
+        if out.len() != n * out_len {
+            return Err(format!(
+                "Output buffer has length {}, expected {}",
+                out.len(),
+                n * out_len
+            ));
+        }
+
+        if n == 0 {
+            return Ok(());
+        }
+
+        // broadcast all constants over the block
+        let mut buffer = Vec::with_capacity(self.eval.len() * BLOCK_SIZE);
+        for e in &self.eval {
+            buffer.extend(std::iter::repeat(e.clone()).take(BLOCK_SIZE));
+        }
+        let mut tmp = vec![N::zero(); BLOCK_SIZE];
+
+        for start in (0..n).step_by(BLOCK_SIZE) {
+            let len = BLOCK_SIZE.min(n - start);
+
+            for (i, c) in inputs.iter().enumerate() {
+                buffer[i * BLOCK_SIZE..i * BLOCK_SIZE + len]
+                    .clone_from_slice(&c[start..start + len]);
+            }
+
+            let mut out_counter = 0;
+            for x in &self.instr {
+                match x {
+                    InstructionRange::Add(reg, pos, len_args)
+                    | InstructionRange::Mul(reg, pos, len_args) => {
+                        let is_add = matches!(x, InstructionRange::Add(..));
+                        let args = &self.indices[*pos..*pos + *len_args];
+
+                        // the register may be one of the arguments, so write to a temporary first
+                        let first = args[0] * BLOCK_SIZE;
+                        tmp[..len].clone_from_slice(&buffer[first..first + len]);
+                        for a in &args[1..] {
+                            let arg = &buffer[a * BLOCK_SIZE..a * BLOCK_SIZE + len];
+                            if is_add {
+                                for (t, v) in tmp[..len].iter_mut().zip(arg) {
+                                    *t += v;
+                                }
+                            } else {
+                                for (t, v) in tmp[..len].iter_mut().zip(arg) {
+                                    *t *= v;
+                                }
+                            }
+                        }
+
+                        buffer[reg * BLOCK_SIZE..reg * BLOCK_SIZE + len]
+                            .clone_from_slice(&tmp[..len]);
+                    }
+                    InstructionRange::Out(pos) => {
+                        out[out_counter * n + start..out_counter * n + start + len]
+                            .clone_from_slice(&buffer[pos * BLOCK_SIZE..pos * BLOCK_SIZE + len]);
+                        out_counter += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<N: Real + for<'b> From<&'b Rational>> InstructionEvaluator<N> {
@@ -1709,6 +1800,388 @@ impl<'a> std::fmt::Display for InstructionSetPrinter<'a> {
     }
 }
 
+/// An evaluator over `f64` that runs native code compiled at runtime if the `jit`
+/// feature is enabled, and that falls back to the [InstructionEvaluator] otherwise.
+pub struct CompiledEvaluator {
+    #[cfg(feature = "jit")]
+    function: super::jit::JitFunction,
+    #[cfg(not(feature = "jit"))]
+    evaluator: InstructionEvaluator<f64>,
+    input_len: usize,
+}
+
+impl CompiledEvaluator {
+    /// Get the number of inputs.
+    pub fn input_len(&self) -> usize {
+        self.input_len
+    }
+
+    /// Evaluate the instructions at the given inputs, of which there must
+    /// be [CompiledEvaluator::input_len]. Panics if the number of inputs is incorrect.
+    pub fn evaluate(&mut self, inputs: &[f64]) -> &[f64] {
+        assert_eq!(inputs.len(), self.input_len, "Incorrect number of inputs");
+
+        #[cfg(feature = "jit")]
+        {
+            self.function.evaluate(inputs)
+        }
+        #[cfg(not(feature = "jit"))]
+        {
+            self.evaluator.evaluate_with_input(inputs)
+        }
+    }
+}
+
+impl InstructionListOutput<Rational> {
+    /// Create an evaluator over `f64` that is compiled to native code if the `jit` feature
+    /// is enabled. Returns an error if the code generation fails for the host machine.
+    pub fn compile(&self) -> Result<CompiledEvaluator, String> {
+        Ok(CompiledEvaluator {
+            #[cfg(feature = "jit")]
+            function: super::jit::JitFunction::new(self)?,
+            #[cfg(not(feature = "jit"))]
+            evaluator: self.convert::<f64>().evaluator(),
+            input_len: self.input_map.len(),
+        })
+    }
+}
+
+/// Settings for the generation of C code. See [InstructionListOutput::to_c_with_settings].
+#[derive(Clone, Copy)]
+pub struct CSettings {
+    /// Write repeated factors and integer powers as multiplications
+    /// instead of calls to `pow`.
+    pub expand_integer_powers: bool,
+}
+
+impl Default for CSettings {
+    fn default() -> Self {
+        CSettings {
+            expand_integer_powers: true,
+        }
+    }
+}
+
+/// Identifiers that cannot be used for symbols in the generated C code.
+const C_RESERVED: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "exp", "log", "sin", "cos", "sqrt", "fabs",
+    "floor", "ceil", "pow", "out",
+];
+
+/// Integer powers with a larger exponent are written with `pow`,
+/// even if [CSettings::expand_integer_powers] is set.
+const C_MAX_EXPANDED_POWER: u64 = 8;
+
+/// A printer of C expressions that collects the symbols that become arguments of the generated function.
+struct CPrinter {
+    settings: CSettings,
+    scalars: Vec<Symbol>,
+    arrays: Vec<Symbol>,
+    /// The C identifiers of the symbols, which are unique, valid and not reserved.
+    names: HashMap<Symbol, String>,
+    func_name: String,
+}
+
+impl CPrinter {
+    fn format_number(num: &Rational) -> String {
+        let r = if num.is_integer() {
+            format!("{}.", num.numerator())
+        } else {
+            format!("{}./{}.", num.numerator(), num.denominator())
+        };
+
+        if num.is_negative() || !num.is_integer() {
+            format!("({})", r)
+        } else {
+            r
+        }
+    }
+
+    /// Write `base` to the power `exp`, where `base` is a C expression.
+    fn format_power(&self, base: &str, exp: i64) -> String {
+        if self.settings.expand_integer_powers
+            && exp != 0
+            && exp.unsigned_abs() <= C_MAX_EXPANDED_POWER
+        {
+            let m = vec![base; exp.unsigned_abs() as usize].join("*");
+            if exp < 0 {
+                format!("(1./({}))", m)
+            } else {
+                format!("({})", m)
+            }
+        } else {
+            format!("pow({}, {}.)", base, exp)
+        }
+    }
+
+    /// Get the array `f` and the index `n` if the function is `f(n)` for a natural number `n`.
+    fn array_element(f: AtomView) -> Option<(Symbol, i64)> {
+        if let AtomView::Fun(f) = f {
+            if f.get_nargs() == 1 {
+                if let Some(AtomView::Num(n)) = f.iter().next() {
+                    if let CoefficientView::Natural(n, 1) = n.get_coeff_view() {
+                        if n >= 0 {
+                            return Some((f.get_symbol(), n));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Get the C identifier of the symbol `s`. Characters that are not allowed
+    /// are replaced by `_`, and a suffix is added to names that are reserved or
+    /// that are already used by another symbol.
+    fn name(&mut self, s: Symbol) -> String {
+        if let Some(n) = self.names.get(&s) {
+            return n.clone();
+        }
+
+        let mut name: String = State::get_name(s)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            name.insert(0, 's');
+        }
+
+        let is_used = |n: &str, names: &HashMap<Symbol, String>| {
+            C_RESERVED.contains(&n)
+                || n == self.func_name
+                || n.strip_prefix('t')
+                    .is_some_and(|i| i.chars().all(|c| c.is_ascii_digit()))
+                || names.values().any(|x| x == n)
+        };
+
+        let mut candidate = name.clone();
+        let mut i = 0;
+        while is_used(&candidate, &self.names) {
+            i += 1;
+            candidate = format!("{}_{}", name, i);
+        }
+
+        self.names.insert(s, candidate.clone());
+        candidate
+    }
+
+    fn symbol(&mut self, s: Symbol) -> String {
+        match s {
+            State::E => format!("{:?}", std::f64::consts::E),
+            State::PI => format!("{:?}", std::f64::consts::PI),
+            _ => {
+                if !self.scalars.contains(&s) {
+                    self.scalars.push(s);
+                }
+                self.name(s)
+            }
+        }
+    }
+
+    fn atom(&mut self, a: AtomView) -> Result<String, String> {
+        Ok(match a {
+            AtomView::Num(n) => match n.get_coeff_view() {
+                CoefficientView::Natural(n, d) => Self::format_number(&Rational::Natural(n, d)),
+                CoefficientView::Large(r) => Self::format_number(&Rational::Large(r.to_rat())),
+                CoefficientView::FiniteField(_, _) | CoefficientView::RationalPolynomial(_) => {
+                    return Err(format!("Cannot write {} as a C expression", a));
+                }
+            },
+            AtomView::Var(v) => self.symbol(v.get_symbol()),
+            AtomView::Fun(f) => {
+                if let Some((s, n)) = Self::array_element(a) {
+                    if !self.arrays.contains(&s) {
+                        self.arrays.push(s);
+                    }
+                    return Ok(format!("{}[{}]", self.name(s), n));
+                }
+
+                let s = f.get_symbol();
+                let name = match s {
+                    State::EXP => "exp".to_owned(),
+                    State::LOG => "log".to_owned(),
+                    State::SIN => "sin".to_owned(),
+                    State::COS => "cos".to_owned(),
+                    State::SQRT => "sqrt".to_owned(),
+                    State::ABS => "fabs".to_owned(),
+                    State::FLOOR => "floor".to_owned(),
+                    State::CEIL => "ceil".to_owned(),
+                    _ => self.name(s),
+                };
+                let args = f
+                    .iter()
+                    .map(|arg| self.atom(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("{}({})", name, args.join(", "))
+            }
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let base = self.atom(base)?;
+                if let AtomView::Num(n) = exp {
+                    if let CoefficientView::Natural(e, 1) = n.get_coeff_view() {
+                        return Ok(self.format_power(&base, e));
+                    }
+                }
+                format!("pow({}, {})", base, self.atom(exp)?)
+            }
+            AtomView::Mul(m) => {
+                let args = m
+                    .iter()
+                    .map(|arg| self.atom(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("({})", args.join("*"))
+            }
+            AtomView::Add(a) => {
+                let args = a
+                    .iter()
+                    .map(|arg| self.atom(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("({})", args.join("+"))
+            }
+        })
+    }
+
+    fn input(&mut self, v: &super::Variable) -> Result<String, String> {
+        match v {
+            super::Variable::Symbol(s) => Ok(self.symbol(*s)),
+            super::Variable::Temporary(_) => Err("Temporary variable in input".to_owned()),
+            super::Variable::Function(_, a) | super::Variable::Other(a) => self.atom(a.as_view()),
+        }
+    }
+}
+
+impl InstructionListOutput<Rational> {
+    /// Write the instructions as a self-contained C function `func_name` using `double` arithmetic.
+    /// See [InstructionListOutput::to_c_with_settings].
+    pub fn to_c(&self, func_name: &str) -> Result<String, String> {
+        self.to_c_with_settings(func_name, CSettings::default())
+    }
+
+    /// Write the instructions as a self-contained C function `func_name` using `double` arithmetic,
+    /// where every instruction is assigned to a temporary `double t0 = ...;`.
+    ///
+    /// Every symbol in the input becomes a `double` argument, in order of appearance, and
+    /// functions `f(n)` with natural number arguments are read from an array argument `const double* f`.
+    /// The built-in functions map to their `<math.h>` counterparts and other functions must
+    /// be provided by the caller. If there is a single output, it is returned, otherwise the function
+    /// writes them to an output array argument `double* out`.
+    ///
+    /// The arithmetic follows IEEE 754 without any checks: a division by zero
+    /// or a logarithm of a negative number yields `inf` or `NaN`, which propagates to the output.
+    /// This is only guaranteed when the C code is compiled without `-ffast-math`.
+    ///
+    /// Symbol names that are not valid C identifiers, or that clash with C keywords,
+    /// `<math.h>` functions or the generated variables, are mangled. An error is returned
+    /// if the input contains coefficients that cannot be written as a `double`, such as
+    /// finite field elements.
+    pub fn to_c_with_settings(
+        &self,
+        func_name: &str,
+        settings: CSettings,
+    ) -> Result<String, String> {
+        let mut printer = CPrinter {
+            settings,
+            scalars: vec![],
+            arrays: vec![],
+            names: HashMap::default(),
+            func_name: func_name.to_owned(),
+        };
+
+        let mut body = String::new();
+        let max_register = self.instr.iter().map(|r| r.0).max().unwrap_or(0);
+        let mut declared = vec![false; max_register + 1];
+        let mut outputs = vec![];
+
+        for (reg, x) in &self.instr {
+            let value = match x {
+                Instruction::Init(Variable::Constant(c)) => CPrinter::format_number(c),
+                Instruction::Init(Variable::Var(v, index)) => {
+                    let mut s = printer.input(&self.input_map[*v])?;
+                    if let Some(index) = index {
+                        s.push_str(&format!("[{}]", index));
+                    }
+                    s
+                }
+                Instruction::Add(a) => a
+                    .iter()
+                    .map(|x| format!("t{}", x))
+                    .collect::<Vec<_>>()
+                    .join("+"),
+                Instruction::Mul(m) => {
+                    if printer.settings.expand_integer_powers {
+                        m.iter()
+                            .map(|x| format!("t{}", x))
+                            .collect::<Vec<_>>()
+                            .join("*")
+                    } else {
+                        // collect repeated factors into powers
+                        let mut factors: Vec<(usize, i64)> = vec![];
+                        for x in m {
+                            if let Some(f) = factors.iter_mut().find(|f| f.0 == *x) {
+                                f.1 += 1;
+                            } else {
+                                factors.push((*x, 1));
+                            }
+                        }
+
+                        factors
+                            .iter()
+                            .map(|(x, p)| {
+                                if *p == 1 {
+                                    format!("t{}", x)
+                                } else {
+                                    printer.format_power(&format!("t{}", x), *p)
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                            .join("*")
+                    }
+                }
+                Instruction::Yield(y) => {
+                    outputs.push(*y);
+                    continue;
+                }
+                Instruction::Empty => continue,
+            };
+
+            if declared[*reg] {
+                body.push_str(&format!("\tt{} = {};\n", reg, value));
+            } else {
+                declared[*reg] = true;
+                body.push_str(&format!("\tdouble t{} = {};\n", reg, value));
+            }
+        }
+
+        let mut args: Vec<_> = printer
+            .scalars
+            .clone()
+            .into_iter()
+            .map(|s| format!("double {}", printer.name(s)))
+            .collect();
+        for s in printer.arrays.clone() {
+            args.push(format!("const double* {}", printer.name(s)));
+        }
+
+        let mut c = String::from("#include <math.h>\n\n");
+        if outputs.len() == 1 {
+            body.push_str(&format!("\treturn t{};\n", outputs[0]));
+            c.push_str(&format!("double {}({}) {{\n", func_name, args.join(", ")));
+        } else {
+            for (i, y) in outputs.iter().enumerate() {
+                body.push_str(&format!("\tout[{}] = t{};\n", i, y));
+            }
+            args.push("double* out".to_owned());
+            c.push_str(&format!("void {}({}) {{\n", func_name, args.join(", ")));
+        }
+        c.push_str(&body);
+        c.push_str("}\n");
+        Ok(c)
+    }
+}
+
 /// A computational graph with efficient output evaluation for a nesting of variable identifications (`x_n = x_{n-1} + 2*x_{n-2}`, etc).
 pub struct ExpressionEvaluator {
     operations: Vec<(
@@ -1892,15 +2365,26 @@ auto 𝑖 = 1i;\n",
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
     use crate::{
         atom::Atom,
-        domains::{float::Complex, rational::Q},
+        coefficient::Coefficient,
+        domains::{
+            finite_field::{FiniteFieldCore, FiniteFieldElement, Zp64},
+            float::Complex,
+            rational::Q,
+        },
         poly::{
-            evaluate::{BorrowedHornerScheme, InstructionSetPrinter},
+            evaluate::{BorrowedHornerScheme, CSettings, InstructionSetPrinter},
             polynomial::MultivariatePolynomial,
+            Variable,
         },
+        state::State,
+        utils,
     };
 
+    use rand::Rng;
     use wide::f64x4;
 
     const RES_53: &str = "-a5^3*b0^5+a4*a5^2*b0^4*b1-a4^2*a5*b0^4*b2+a4^3*b0^4*b3-a3*a5^2*
@@ -2012,6 +2496,25 @@ a0^2*a2*b2^2*b3^3-2*a0^2*a2*b1*b3^4-a0^2*a1*b2*b3^4+a0^3*b3^5";
 
         assert_eq!(res, f64x4::new([280944.0, 645000.0, 1774950.0, 4985154.0]));
 
+        // evaluate a batch of points
+        let columns: Vec<Vec<f64>> = (0..poly.nvars())
+            .map(|x| (0..100).map(|p| x as f64 + 1. + p as f64 * 0.25).collect())
+            .collect();
+        let column_refs: Vec<&[f64]> = columns.iter().map(|c| c.as_slice()).collect();
+        let mut evaluator = o_f64.evaluator();
+        let mut out = vec![0.; 100];
+        evaluator.eval_slice(&column_refs, &mut out).unwrap();
+
+        for (p, r) in out.iter().enumerate() {
+            let point: Vec<f64> = columns.iter().map(|c| c[p]).collect();
+            assert_eq!(*r, evaluator.evaluate_with_input(&point)[0]);
+        }
+
+        assert!(evaluator.eval_slice(&column_refs[1..], &mut out).is_err());
+        assert!(evaluator.eval_slice(&column_refs, &mut out[1..]).is_err());
+        let empty_columns = vec![[].as_slice(); poly.nvars()];
+        assert_eq!(evaluator.eval_slice(&empty_columns, &mut []), Ok(()));
+
         // evaluate with complex numbers
         let mut complex_evaluator = o.convert::<Complex<f64>>().evaluator();
         let res = complex_evaluator.evaluate_with_input(
@@ -2023,4 +2526,119 @@ a0^2*a2*b2^2*b3^3-2*a0^2*a2*b1*b3^4-a0^2*a1*b2*b3^4+a0^3*b3^5";
             (res.re - 3230756.634848104).abs() < 1e-6 && (res.im - 2522437.0904901037).abs() < 1e-6
         );
     }
+
+    #[test]
+    fn c_code() {
+        let poly: MultivariatePolynomial<_, u8> =
+            Atom::parse("x^2+1").unwrap().to_polynomial(&Q, None);
+        let (h, _ops, _scheme) = poly.optimize_horner_scheme(10);
+        let mut i = h.to_instr(poly.nvars());
+        i.fuse_operations();
+        let o = i.to_output(poly.variables.as_ref().to_vec(), true);
+
+        assert_eq!(
+            o.to_c("square").unwrap(),
+            "#include <math.h>
+
+double square(double x) {
+	double t0 = x;
+	double t1 = 1.;
+	double t2 = t0*t0;
+	t2 = t1+t2;
+	return t2;
+}
+"
+        );
+
+        let c = o
+            .to_c_with_settings(
+                "square",
+                CSettings {
+                    expand_integer_powers: false,
+                },
+            )
+            .unwrap();
+        assert!(c.contains("\tdouble t2 = pow(t0, 2.);\n"));
+
+        // inputs that are functions are written using <math.h>
+        let poly: MultivariatePolynomial<_, u8> = Atom::parse("sin(x)*y^-2+f(1)")
+            .unwrap()
+            .to_polynomial(&Q, None);
+        let i = poly.to_horner_scheme(&[0, 1, 2]).to_instr(poly.nvars());
+        let c = i
+            .to_output(poly.variables.as_ref().to_vec(), false)
+            .to_c("g")
+            .unwrap();
+        assert!(c.contains("sin(x)"));
+        assert!(c.contains("(1./(y*y))"));
+        assert!(c.contains("f[1]"));
+        assert!(c.contains("double x") && c.contains("double y"));
+        assert!(c.contains("const double* f)"));
+
+        // names are mangled and constants are written as literals
+        let (x, int, t0, dot) = (
+            Atom::new_var(State::get_symbol("x")),
+            Atom::new_var(State::get_symbol("int")),
+            Atom::new_var(State::get_symbol("t0")),
+            Atom::new_var(State::get_symbol("a.b")),
+        );
+        let e = &(&(&x + &int) + &t0) * &(&dot + &Atom::new_var(State::PI));
+        let poly: MultivariatePolynomial<_, u8> = e.to_polynomial(&Q, None);
+        let i = poly
+            .to_horner_scheme(&(0..poly.nvars()).collect::<Vec<_>>())
+            .to_instr(poly.nvars());
+        let mut o = i.to_output(poly.variables.as_ref().to_vec(), false);
+        let c = o.to_c("f").unwrap();
+        for s in [
+            "double x",
+            "double int_1",
+            "double t0_1",
+            "double a_b",
+            "3.141592653589793",
+        ] {
+            assert!(c.contains(s), "{} is not in {}", s, c);
+        }
+        assert!(!c.contains("double int,") && !c.contains("M_PI"));
+
+        // large powers are not expanded
+        let index = o
+            .input_map
+            .iter()
+            .position(|v| v == &Variable::Symbol(State::get_symbol("x")))
+            .unwrap();
+        o.input_map[index] = Variable::Other(Arc::new(x.npow(20)));
+        let c = o.to_c("f").unwrap();
+        assert!(c.contains("pow(x, 20.)"), "{}", c);
+
+        // finite field coefficients cannot be written
+        let field = State::get_or_insert_finite_field(Zp64::new(7));
+        o.input_map[0] = Variable::Other(Arc::new(Atom::new_num(Coefficient::FiniteField(
+            FiniteFieldElement(1),
+            field,
+        ))));
+        assert!(o.to_c("g").is_err());
+    }
+
+    #[test]
+    fn compiled() {
+        let poly: MultivariatePolynomial<_, u8> = Atom::parse("x^3*y-3/4*x*y^2+2*x*z+y^4-7")
+            .unwrap()
+            .to_polynomial(&Q, None);
+        let (h, _ops, _scheme) = poly.optimize_horner_scheme(10);
+        let mut i = h.to_instr(poly.nvars());
+        i.fuse_operations();
+        let o = i.to_output(poly.variables.as_ref().to_vec(), true);
+
+        let mut compiled = o.compile().unwrap();
+        let mut evaluator = o.convert::<f64>().evaluator();
+        assert_eq!(compiled.input_len(), 3);
+
+        let mut rng = utils::rng();
+        for _ in 0..100 {
+            let point: Vec<f64> = (0..3).map(|_| rng.gen_range(-10.0..10.0)).collect();
+            let expected = evaluator.evaluate_with_input(&point)[0];
+            let r = compiled.evaluate(&point)[0];
+            assert!((r - expected).abs() <= 1e-12 * expected.abs().max(1.));
+        }
+    }
 }
@@ -1,7 +1,7 @@
 use std::cmp::Reverse;
 
 use ahash::HashMap;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use tracing::debug;
 
 use crate::{
@@ -342,6 +342,139 @@ impl<E: Exponent> Factorize for MultivariatePolynomial<RationalField, E, LexOrde
     }
 }
 
+impl<UField: FiniteFieldWorkspace, E: Exponent>
+    MultivariatePolynomial<FiniteField<UField>, E, LexOrder>
+where
+    FiniteField<UField>: Field + FiniteFieldCore<UField>,
+{
+    /// Returns `true` if the polynomial is irreducible. Constant polynomials are units or zero
+    /// and are therefore not irreducible, while linear polynomials are always irreducible.
+    ///
+    /// A univariate polynomial `f` of degree `n` is tested with Rabin's test: `f` is irreducible
+    /// if and only if `f` divides `x^(p^n)-x` and `gcd(f, x^(p^(n/q))-x) = 1` for every prime `q` that divides `n`.
+    ///
+    /// Panics if the polynomial is multivariate.
+    pub fn is_irreducible(&self) -> bool {
+        let vars: Vec<_> = (0..self.nvars())
+            .filter(|v| self.degree(*v) > E::zero())
+            .collect();
+
+        match vars.len() {
+            0 => return false,
+            1 => {}
+            _ => panic!("Irreducibility testing of multivariate polynomials over finite fields is not supported"),
+        }
+
+        let var = vars[0];
+        let n = self.degree(var).to_u32() as usize;
+        if n == 1 {
+            return true;
+        }
+
+        // the prime divisors of the degree
+        let mut primes = vec![];
+        let mut m = n;
+        let mut q = 2;
+        while m > 1 {
+            if m % q == 0 {
+                primes.push(q);
+                while m % q == 0 {
+                    m /= q;
+                }
+            }
+            q += 1;
+        }
+
+        let mut f = self.clone().make_monic();
+        let mut e = vec![E::zero(); self.nvars()];
+        e[var] = E::one();
+        let x = self.monomial(self.field.one(), e);
+        let p: Integer = self.field.get_prime().to_u64().into();
+
+        // compute x^(p^i) mod f, using that each step is a p-th power of the previous one
+        let mut h = x.clone();
+        for i in 1..=n {
+            h = h.exp_mod_univariate(p.clone(), &mut f);
+
+            if i < n && primes.iter().any(|q| n / q == i) {
+                let d = &h - &x;
+                if d.is_zero() || !f.eea_univariate(&d).0.is_one() {
+                    return false;
+                }
+            }
+        }
+
+        h == x
+    }
+}
+
+impl<R: Ring, E: Exponent> MultivariatePolynomial<R, E, LexOrder>
+where
+    Self: Factorize,
+{
+    /// Returns `true` if the factorization consists of a single non-constant factor with multiplicity one.
+    fn is_irreducible_by_factorization(&self) -> bool {
+        let mut non_constant = self.factor().into_iter().filter(|(f, _)| !f.is_constant());
+        matches!(
+            (non_constant.next(), non_constant.next()),
+            (Some((_, 1)), None)
+        )
+    }
+}
+
+impl<E: Exponent> MultivariatePolynomial<RationalField, E, LexOrder> {
+    /// Returns `true` if the polynomial is irreducible over the rationals. Constant polynomials
+    /// are units or zero and are therefore not irreducible, while linear polynomials are always irreducible.
+    ///
+    /// For a univariate polynomial, the images modulo a few primes that do not divide the leading
+    /// coefficient are tested first, as an irreducible image proves that the polynomial is irreducible.
+    /// If no image is irreducible, or if the polynomial is multivariate, the polynomial is factored.
+    pub fn is_irreducible(&self) -> bool {
+        let vars: Vec<_> = (0..self.nvars())
+            .filter(|v| self.degree(*v) > E::zero())
+            .collect();
+
+        match vars.len() {
+            0 => return false,
+            1 if self.degree(vars[0]) == E::one() => return true,
+            1 => {}
+            _ => return self.is_irreducible_by_factorization(),
+        }
+
+        let c = self.content();
+        let stripped = self.map_coeff(
+            |coeff| {
+                let coeff = self.field.div(coeff, &c);
+                debug_assert!(coeff.is_integer());
+                coeff.numerator()
+            },
+            Z,
+        );
+
+        let lcoeff = stripped.lcoeff();
+        let mut pi = PrimeIteratorU64::new(101);
+        let mut tries = 0;
+        while tries < 5 {
+            let p = pi.next().unwrap() as u32;
+            if (&lcoeff % &Integer::Natural(p as i64)).is_zero() {
+                continue;
+            }
+            tries += 1;
+
+            // the image has the same degree, so a factorization over the integers yields one modulo p
+            let field = Zp::new(p);
+            if stripped
+                .map_coeff(|f| f.to_finite_field(&field), field.clone())
+                .is_irreducible()
+            {
+                return true;
+            }
+        }
+
+        stripped.is_irreducible_by_factorization()
+    }
+}
+
 impl<UField: FiniteFieldWorkspace, E: Exponent> Factorize
     for MultivariatePolynomial<FiniteField<UField>, E, LexOrder>
 where
@@ -603,7 +736,7 @@ where
             return vec![s];
         }
 
-        let mut rng = thread_rng();
+        let mut rng = utils::rng();
         let mut random_poly = self.zero_with_capacity(d);
         let mut exp = vec![E::zero(); self.nvars()];
 
@@ -1225,7 +1358,7 @@ where
             order[1..].iter().map(|i| (*i, self.field.zero())).collect();
         let mut uni_f;
         let mut biv_f;
-        let mut rng = thread_rng();
+        let mut rng = utils::rng();
         let degree = self.degree(order[0]);
 
         let uni_lcoeff = self.univariate_lcoeff(order[0]);
@@ -2708,7 +2841,7 @@ impl<E: Exponent> MultivariatePolynomial<IntegerRing, E, LexOrder> {
             order[1..].iter().map(|i| (*i, Integer::zero())).collect();
         let mut cur_uni_f;
         let mut cur_biv_f;
-        let mut rng = thread_rng();
+        let mut rng = utils::rng();
         let degree = self.degree(order[0]);
         let mut bivariate_factors: Vec<_>;
 
@@ -3099,9 +3232,58 @@ impl<E: Exponent> MultivariatePolynomial<FiniteField<Integer>, E, LexOrder> {
 mod test {
     use crate::{
         atom::Atom,
-        domains::{finite_field::Zp, integer::Z},
+        domains::{
+            finite_field::{FiniteField, FiniteFieldCore, Zp},
+            integer::{Integer, Z},
+            rational::Q,
+        },
         poly::factor::Factorize,
+        utils,
     };
+    use rand::RngCore;
+
+    #[test]
+    fn irreducible() {
+        for (f, r) in [
+            ("v1^2+1", true),
+            ("2*v1+3", true),
+            ("3/4*v1^3-v1+1/2", true),
+            ("v1^4+1", true),
+            ("v1^2-1", false),
+            ("(v1^2+1)^2", false),
+            ("3", false),
+            ("v1*v2+1", true),
+            ("v1*v2", false),
+        ] {
+            let p = Atom::parse(f)
+                .unwrap()
+                .expand()
+                .to_polynomial::<_, u8>(&Q, None);
+            assert_eq!(p.is_irreducible(), r, "{}", f);
+        }
+
+        let gf2 = FiniteField::<Integer>::new(2.into());
+        for (f, r) in [
+            ("v1^2+1", false),
+            ("v1^2+v1+1", true),
+            ("v1^4+v1+1", true),
+            ("v1^6+v1^3+1", true),
+            ("(v1^3+v1+1)*(v1^3+v1^2+1)", false),
+            ("v1", true),
+            ("1", false),
+        ] {
+            let p = Atom::parse(f)
+                .unwrap()
+                .expand()
+                .to_polynomial::<_, u8>(&gf2, None);
+            assert_eq!(p.is_irreducible(), r, "{}", f);
+        }
+
+        let p = Atom::parse("v1^2+1")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Zp::new(3), None);
+        assert!(p.is_irreducible());
+    }
 
     #[test]
     fn factor_ff_square_free() {
@@ -3325,4 +3507,25 @@ mod test {
         r.sort_by(|a, b| a.partial_cmp(&b).unwrap());
         assert_eq!(r, res);
     }
+
+    #[test]
+    fn factor_seeded() {
+        let ff = Atom::parse("(v1^2+v1+1)*(v1^2+2)*(v1^3+v1+3)*(v1+4)")
+            .unwrap()
+            .expand()
+            .to_polynomial::<_, u8>(&Zp::new(7), None);
+        let z = Atom::parse("(v1*v2+v3+1)*(v1^2+3*v2*v3+v2+2)*(v1*v3+v2^2+5)")
+            .unwrap()
+            .expand()
+            .to_polynomial::<_, u8>(&Z, None);
+
+        let run = || {
+            utils::set_rng_seed(Some(42));
+            let r = (ff.factor(), z.factor(), utils::rng().next_u64());
+            utils::set_rng_seed(None);
+            r
+        };
+
+        assert_eq!(run(), run());
+    }
 }
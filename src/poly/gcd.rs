@@ -1,5 +1,4 @@
 use ahash::{HashMap, HashSet, HashSetExt};
-use rand;
 use smallvec::{smallvec, SmallVec};
 use std::borrow::Cow;
 use std::cmp::{max, min, Ordering};
@@ -16,6 +15,7 @@ use crate::domains::rational::{Rational, RationalField, Q};
 use crate::domains::{EuclideanDomain, Field, Ring};
 use crate::poly::INLINED_EXPONENTS;
 use crate::tensors::matrix::{Matrix, MatrixError};
+use crate::utils;
 
 use super::polynomial::MultivariatePolynomial;
 use super::Exponent;
@@ -269,7 +269,7 @@ impl<F: Field, E: Exponent> MultivariatePolynomial<F, E> {
     /// upper bound could be too tight due to an unfortunate
     /// sample point, but this is rare.
     fn get_gcd_var_bound(ap: &Self, bp: &Self, vars: &[usize], var: usize) -> E {
-        let mut rng = rand::thread_rng();
+        let mut rng = utils::rng();
 
         // store a table for variables raised to a certain power
         let mut cache = (0..ap.nvars())
@@ -482,7 +482,7 @@ impl<F: Field, E: Exponent> MultivariatePolynomial<F, E> {
             return Err(GCDError::BadOriginalImage);
         }
 
-        let mut rng = rand::thread_rng();
+        let mut rng = utils::rng();
 
         let mut failure_count = 0;
 
@@ -694,7 +694,7 @@ impl<F: Field, E: Exponent> MultivariatePolynomial<F, E> {
         main_var: usize,
         shape: &[(MultivariatePolynomial<F, E>, E)],
     ) -> Result<MultivariatePolynomial<F, E>, GCDError> {
-        let mut rng = rand::thread_rng();
+        let mut rng = utils::rng();
 
         let mut failure_count = 0;
 
@@ -1108,7 +1108,7 @@ impl<F: Field + PolynomialGCD<E>, E: Exponent> MultivariatePolynomial<F, E> {
             .lcoeff_last_varorder(vars)
             .univariate_gcd(&b.lcoeff_last_varorder(vars));
 
-        let mut rng = rand::thread_rng();
+        let mut rng = utils::rng();
 
         let mut failure_count = 0;
 
@@ -0,0 +1,154 @@
+//! Compilation of instruction lists to native code at runtime using Cranelift.
+
+use cranelift_codegen::{
+    ir::{types, AbiParam, InstBuilder, MemFlags},
+    settings::{self, Configurable},
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Linkage, Module};
+
+use crate::domains::rational::Rational;
+
+use super::evaluate::{Instruction, InstructionListOutput};
+
+/// The signature of the compiled function, which reads the inputs from
+/// the first pointer and writes the outputs to the second.
+type JitFn = unsafe extern "C" fn(*const f64, *mut f64);
+
+/// An instruction list that is compiled to native code.
+///
+/// The generated code only reads `input_len` values from the input and only
+/// writes `output_len` values to the output, which is checked by [JitFunction::evaluate].
+pub struct JitFunction {
+    module: Option<JITModule>,
+    function: JitFn,
+    input_len: usize,
+    out: Vec<f64>,
+}
+
+impl JitFunction {
+    /// Compile the instructions for the host machine.
+    pub fn new(instr: &InstructionListOutput<Rational>) -> Result<JitFunction, String> {
+        let mut flag_builder = settings::builder();
+        flag_builder
+            .set("opt_level", "speed")
+            .map_err(|e| e.to_string())?;
+        let isa = cranelift_native::builder()
+            .map_err(|e| e.to_string())?
+            .finish(settings::Flags::new(flag_builder))
+            .map_err(|e| e.to_string())?;
+
+        let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+        let mut ctx = module.make_context();
+        let ptr = module.target_config().pointer_type();
+        ctx.func.signature.params.push(AbiParam::new(ptr));
+        ctx.func.signature.params.push(AbiParam::new(ptr));
+
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut b = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+        let block = b.create_block();
+        b.append_block_params_for_function_params(block);
+        b.switch_to_block(block);
+        b.seal_block(block);
+        let (input, output) = (b.block_params(block)[0], b.block_params(block)[1]);
+
+        let max_register = instr.instr.iter().map(|r| r.0).max().unwrap_or(0);
+        let registers: Vec<_> = (0..=max_register)
+            .map(|i| {
+                let v = Variable::from_u32(i as u32);
+                b.declare_var(v, types::F64);
+                v
+            })
+            .collect();
+
+        let input_len = instr.input_map.len();
+        let mut output_len = 0;
+        for (reg, x) in &instr.instr {
+            let value = match x {
+                Instruction::Init(super::evaluate::Variable::Constant(c)) => {
+                    b.ins().f64const(f64::from(c))
+                }
+                Instruction::Init(super::evaluate::Variable::Var(v, None)) => {
+                    if *v >= input_len {
+                        return Err(format!("Input {} is out of bounds", v));
+                    }
+                    b.ins()
+                        .load(types::F64, MemFlags::trusted(), input, (8 * v) as i32)
+                }
+                Instruction::Init(super::evaluate::Variable::Var(_, Some(_))) => {
+                    return Err("Indexed variables are not supported".to_owned())
+                }
+                Instruction::Add(a) | Instruction::Mul(a) => {
+                    let mut acc = b.use_var(registers[a[0]]);
+                    for r in &a[1..] {
+                        let v = b.use_var(registers[*r]);
+                        acc = if let Instruction::Add(_) = x {
+                            b.ins().fadd(acc, v)
+                        } else {
+                            b.ins().fmul(acc, v)
+                        };
+                    }
+                    acc
+                }
+                Instruction::Yield(y) => {
+                    let v = b.use_var(registers[*y]);
+                    b.ins()
+                        .store(MemFlags::trusted(), v, output, (8 * output_len) as i32);
+                    output_len += 1;
+                    continue;
+                }
+                Instruction::Empty => continue,
+            };
+            b.def_var(registers[*reg], value);
+        }
+
+        b.ins().return_(&[]);
+        b.finalize();
+
+        let id = module
+            .declare_function("evaluate", Linkage::Export, &ctx.func.signature)
+            .map_err(|e| e.to_string())?;
+        module
+            .define_function(id, &mut ctx)
+            .map_err(|e| e.to_string())?;
+        module.clear_context(&mut ctx);
+        module.finalize_definitions().map_err(|e| e.to_string())?;
+
+        // SAFETY: the function has been defined with the signature of `JitFn`
+        // and its code lives as long as the module
+        let function =
+            unsafe { std::mem::transmute::<*const u8, JitFn>(module.get_finalized_function(id)) };
+
+        Ok(JitFunction {
+            module: Some(module),
+            function,
+            input_len,
+            out: vec![0.; output_len],
+        })
+    }
+
+    /// Get the number of inputs.
+    pub fn input_len(&self) -> usize {
+        self.input_len
+    }
+
+    /// Evaluate the compiled function. Panics if the number of inputs is incorrect.
+    pub fn evaluate(&mut self, inputs: &[f64]) -> &[f64] {
+        assert_eq!(inputs.len(), self.input_len, "Incorrect number of inputs");
+
+        // SAFETY: the code only reads the first `input_len` inputs and only writes to
+        // the first `out.len()` entries of the output
+        unsafe { (self.function)(inputs.as_ptr(), self.out.as_mut_ptr()) };
+        &self.out
+    }
+}
+
+impl Drop for JitFunction {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: the function pointer is dropped together with the module
+            unsafe { module.free_memory() };
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    fmt::Debug,
+    hash::Hash,
+    ops::{Add, BitAnd, BitOr, Shl, Shr},
+};
+
+use super::Exponent;
+
+/// An unsigned integer that stores packed exponents.
+pub trait PackedWord:
+    Copy
+    + Debug
+    + Hash
+    + Ord
+    + Add<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + Shl<u32, Output = Self>
+    + Shr<u32, Output = Self>
+{
+    const BITS: u32;
+    const ZERO: Self;
+
+    fn from_u32(n: u32) -> Self;
+    /// Get the lowest 32 bits.
+    fn low_u32(self) -> u32;
+}
+
+macro_rules! impl_packed_word {
+    ($t:ty) => {
+        impl PackedWord for $t {
+            const BITS: u32 = <$t>::BITS;
+            const ZERO: Self = 0;
+
+            #[inline]
+            fn from_u32(n: u32) -> Self {
+                n as $t
+            }
+
+            #[inline]
+            fn low_u32(self) -> u32 {
+                self as u32
+            }
+        }
+    };
+}
+
+impl_packed_word!(u64);
+impl_packed_word!(u128);
+
+/// The exponents of a monomial, packed into a single word.
+/// The ordering of packed monomials is the lexicographical ordering of their exponents.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PackedMonomial<W: PackedWord>(pub W);
+
+/// A monomial that is packed if its exponents fit in the field width of a [PackedLayout],
+/// and that is stored as a list of exponents otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Monomial<W: PackedWord, E: Exponent> {
+    Packed(PackedMonomial<W>),
+    Wide(Vec<E>),
+}
+
+/// The layout of the exponents of `nvars` variables packed into a word, with `bits` bits per variable.
+/// The first variable is stored in the most significant bits.
+///
+/// The highest bit of every field is a guard bit that detects overflows, so that
+/// monomials can be multiplied by adding the packed words. As a result, the largest exponent
+/// that can be stored is `2^(bits-1) - 1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackedLayout<W: PackedWord> {
+    bits: u32,
+    nvars: usize,
+    guard_mask: W,
+}
+
+impl<W: PackedWord> PackedLayout<W> {
+    /// Create a layout for `nvars` variables with `bits` bits per variable.
+    /// Returns an error if the fields do not fit in the word.
+    pub fn new(nvars: usize, bits: u32) -> Result<PackedLayout<W>, String> {
+        if !(2..=32).contains(&bits) {
+            return Err(format!(
+                "The number of bits per variable must be between 2 and 32, not {}",
+                bits
+            ));
+        }
+
+        if nvars as u64 * bits as u64 > W::BITS as u64 {
+            return Err(format!(
+                "{} variables with {} bits each do not fit in {} bits",
+                nvars,
+                bits,
+                W::BITS
+            ));
+        }
+
+        let mut guard_mask = W::ZERO;
+        for _ in 0..nvars {
+            guard_mask = (guard_mask << bits) | W::from_u32(1 << (bits - 1));
+        }
+
+        Ok(PackedLayout {
+            bits,
+            nvars,
+            guard_mask,
+        })
+    }
+
+    /// Get the number of variables.
+    pub fn nvars(&self) -> usize {
+        self.nvars
+    }
+
+    /// Get the largest exponent that can be stored.
+    pub fn max_exponent(&self) -> u32 {
+        (1 << (self.bits - 1)) - 1
+    }
+
+    /// Pack the exponents, or return `None` if an exponent exceeds [PackedLayout::max_exponent].
+    /// The number of exponents must be equal to the number of variables.
+    pub fn pack<E: Exponent>(&self, exponents: &[E]) -> Option<PackedMonomial<W>> {
+        assert_eq!(exponents.len(), self.nvars, "Incorrect number of exponents");
+
+        let max = self.max_exponent();
+        let mut w = W::ZERO;
+        for e in exponents {
+            let e = e.to_u32();
+            if e > max {
+                return None;
+            }
+            w = (w << self.bits) | W::from_u32(e);
+        }
+
+        Some(PackedMonomial(w))
+    }
+
+    /// Unpack the exponents into `out`, which must have a length equal to the number of variables.
+    pub fn unpack<E: Exponent>(&self, m: PackedMonomial<W>, out: &mut [E]) {
+        assert_eq!(out.len(), self.nvars, "Incorrect number of exponents");
+
+        let mask = W::from_u32(u32::MAX >> (32 - self.bits));
+        let mut w = m.0;
+        for o in out.iter_mut().rev() {
+            *o = E::from_u32((w & mask).low_u32());
+            w = w >> self.bits;
+        }
+    }
+
+    /// Multiply two packed monomials, or return `None` if an exponent of the
+    /// product exceeds [PackedLayout::max_exponent].
+    #[inline]
+    pub fn mul(&self, a: PackedMonomial<W>, b: PackedMonomial<W>) -> Option<PackedMonomial<W>> {
+        // the fields of both arguments have a zero guard bit, so the addition does not carry into the next field
+        let r = a.0 + b.0;
+        if r & self.guard_mask == W::ZERO {
+            Some(PackedMonomial(r))
+        } else {
+            None
+        }
+    }
+
+    /// Create a monomial that is packed if all exponents fit.
+    pub fn monomial<E: Exponent>(&self, exponents: &[E]) -> Monomial<W, E> {
+        match self.pack(exponents) {
+            Some(p) => Monomial::Packed(p),
+            None => Monomial::Wide(exponents.to_vec()),
+        }
+    }
+
+    /// Get the exponents of a monomial.
+    pub fn exponents<E: Exponent>(&self, m: &Monomial<W, E>) -> Vec<E> {
+        match m {
+            Monomial::Packed(p) => {
+                let mut out = vec![E::zero(); self.nvars];
+                self.unpack(*p, &mut out);
+                out
+            }
+            Monomial::Wide(e) => e.clone(),
+        }
+    }
+
+    /// Multiply two monomials, falling back to the wide representation if the product does not fit.
+    /// Panics if the exponents overflow the type `E`.
+    pub fn mul_monomials<E: Exponent>(
+        &self,
+        a: &Monomial<W, E>,
+        b: &Monomial<W, E>,
+    ) -> Monomial<W, E> {
+        if let (Monomial::Packed(a), Monomial::Packed(b)) = (a, b) {
+            if let Some(r) = self.mul(*a, *b) {
+                return Monomial::Packed(r);
+            }
+        }
+
+        let e = self
+            .exponents(a)
+            .iter()
+            .zip(&self.exponents(b))
+            .map(|(a, b)| a.checked_add(b).expect("Exponent overflow"))
+            .collect::<Vec<_>>();
+        self.monomial(&e)
+    }
+}
+
+/// A layout over 64-bit words.
+pub type PackedLayout64 = PackedLayout<u64>;
+/// A layout over 128-bit words.
+pub type PackedLayout128 = PackedLayout<u128>;
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use crate::utils;
+
+    use super::{Monomial, PackedLayout};
+
+    #[test]
+    fn packed_monomials() {
+        let layout = PackedLayout::<u64>::new(5, 8).unwrap();
+        assert_eq!(layout.max_exponent(), 127);
+
+        let mut rng = utils::rng();
+        for _ in 0..1000 {
+            let a: Vec<u16> = (0..5).map(|_| rng.gen_range(0..100)).collect();
+            let b: Vec<u16> = (0..5).map(|_| rng.gen_range(0..100)).collect();
+            let (pa, pb) = (layout.pack(&a).unwrap(), layout.pack(&b).unwrap());
+
+            let mut out = [0u16; 5];
+            layout.unpack(pa, &mut out);
+            assert_eq!(out.as_slice(), a.as_slice());
+
+            // the packed ordering is the lexicographical ordering
+            assert_eq!(pa.cmp(&pb), a.cmp(&b));
+
+            let sum: Vec<u16> = a.iter().zip(&b).map(|(a, b)| a + b).collect();
+            match layout.mul(pa, pb) {
+                Some(p) => {
+                    layout.unpack(p, &mut out);
+                    assert_eq!(out.as_slice(), sum.as_slice());
+                }
+                None => assert!(sum.iter().any(|e| *e > 127)),
+            }
+
+            let m = layout.mul_monomials(&layout.monomial(&a), &layout.monomial(&b));
+            assert_eq!(layout.exponents(&m), sum);
+            assert_eq!(
+                matches!(m, Monomial::Packed(_)),
+                sum.iter().all(|e| *e <= 127)
+            );
+        }
+
+        // large exponents use the wide representation
+        let layout = PackedLayout::<u128>::new(20, 6).unwrap();
+        let mut e = vec![1u32; 20];
+        e[3] = 31;
+        let a = layout.monomial(&e);
+        assert!(matches!(a, Monomial::Packed(_)));
+        let b = layout.mul_monomials(&a, &layout.monomial(&[1u32; 20]));
+        assert!(matches!(b, Monomial::Wide(_)));
+        assert_eq!(layout.exponents(&b)[3], 32);
+        e[3] = 1000;
+        assert_eq!(layout.monomial(&e), Monomial::Wide(e.clone()));
+
+        // too many variables
+        assert!(PackedLayout::<u64>::new(9, 8).is_err());
+        assert!(PackedLayout::<u128>::new(9, 8).is_ok());
+        assert!(PackedLayout::<u64>::new(2, 1).is_err());
+    }
+}
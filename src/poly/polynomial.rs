@@ -8,7 +8,9 @@ use std::mem;
 use std::ops::{Add, Div, Mul, Neg, Sub};
 use std::sync::Arc;
 
+use crate::atom::Symbol;
 use crate::domains::algebraic_number::AlgebraicNumberRing;
+use crate::domains::finite_field::{FiniteFieldCore, ToFiniteField, Zp};
 use crate::domains::integer::{Integer, IntegerRing};
 use crate::domains::rational::RationalField;
 use crate::domains::{EuclideanDomain, Field, Ring};
@@ -574,6 +576,45 @@ impl<F: Ring, E: Exponent, O: MonomialOrder> MultivariatePolynomial<F, E, O> {
         }
     }
 
+    /// Bring a polynomial whose terms were written directly into `coefficients`
+    /// and `exponents` into canonical form: the terms are sorted by the monomial order,
+    /// like terms are combined and zero terms are removed. Afterwards, equality is a
+    /// direct comparison of the terms. Coefficients of the ring are already in
+    /// reduced form, for example fractions are always in lowest terms.
+    pub fn canonicalize(&mut self) {
+        let nvars = self.nvars();
+        let mut order: Vec<usize> = (0..self.nterms()).collect();
+        order.sort_by(|&i, &j| O::cmp(self.exponents(i), self.exponents(j)));
+
+        let coefficients = std::mem::take(&mut self.coefficients);
+        let exponents = std::mem::take(&mut self.exponents);
+        let mut coefficients: Vec<_> = coefficients.into_iter().map(Some).collect();
+
+        for i in order {
+            let c = coefficients[i].take().unwrap();
+            let e = &exponents[i * nvars..(i + 1) * nvars];
+
+            // merge like terms, also when their sum is zero
+            let n = self.nterms();
+            if n > 0 && e == self.last_exponents() {
+                self.field.add_assign(&mut self.coefficients[n - 1], &c);
+            } else {
+                if n > 0 && F::is_zero(&self.coefficients[n - 1]) {
+                    self.coefficients.pop();
+                    self.exponents.truncate((n - 1) * nvars);
+                }
+                self.coefficients.push(c);
+                self.exponents.extend_from_slice(e);
+            }
+        }
+
+        let n = self.nterms();
+        if n > 0 && F::is_zero(&self.coefficients[n - 1]) {
+            self.coefficients.pop();
+            self.exponents.truncate((n - 1) * nvars);
+        }
+    }
+
     /// Append a monomial to the back. It merges with the last monomial if the
     /// exponents are equal.
     #[inline]
@@ -1193,6 +1234,33 @@ impl<F: Ring, E: Exponent, O: MonomialOrder> MultivariatePolynomial<F, E, O> {
         self.coefficients.last().unwrap().clone()
     }
 
+    /// Get the leading coefficient under the monomial order of the polynomial.
+    /// The leading coefficient of the zero polynomial is zero.
+    pub fn leading_coefficient(&self) -> F::Element {
+        self.lcoeff()
+    }
+
+    /// Get the exponents of the leading monomial under the monomial order of the polynomial,
+    /// or `None` for the zero polynomial.
+    pub fn leading_monomial(&self) -> Option<&[E]> {
+        if self.is_zero() {
+            None
+        } else {
+            Some(self.last_exponents())
+        }
+    }
+
+    /// Get the polynomial without its leading term.
+    pub fn tail(&self) -> Self {
+        let mut r = self.clone();
+        if !r.is_zero() {
+            let nvars = r.nvars();
+            r.coefficients.pop();
+            r.exponents.truncate(r.exponents.len() - nvars);
+        }
+        r
+    }
+
     /// Perform self % var^pow.
     pub fn mod_var(&self, var: usize, pow: E) -> Self {
         let mut m = self.zero();
@@ -1529,30 +1597,131 @@ impl<F: Ring, E: Exponent> MultivariatePolynomial<F, E, LexOrder> {
         res
     }
 
-    /// Replace a variable `n` in the polynomial by a polynomial `v`.
-    pub fn replace_with_poly(&self, n: usize, v: &Self) -> Self {
-        assert_eq!(self.variables, v.variables);
+    /// Evaluate the polynomial by binding every variable to an element of the ring.
+    /// The powers of every binding are computed only once.
+    /// Yields an error if a variable that occurs in the polynomial is not bound.
+    pub fn evaluate(&self, bindings: &[(Symbol, F::Element)]) -> Result<F::Element, String> {
+        let mut powers = Vec::with_capacity(self.nvars());
+        for (i, v) in self.variables.iter().enumerate() {
+            let binding = match v {
+                Variable::Symbol(s) => bindings.iter().find(|(b, _)| b == s).map(|(_, e)| e),
+                _ => None,
+            };
 
-        if v.is_constant() {
-            return self.replace(n, &v.lcoeff());
+            if let Some(b) = binding {
+                powers.push(self.binding_powers(i, b));
+            } else if self.degree(i) > E::zero() {
+                return Err(format!("Variable {} is not bound", v));
+            } else {
+                powers.push(vec![]);
+            }
         }
 
-        let mut res = self.zero_with_capacity(self.nterms());
-        let mut exp = vec![E::zero(); self.nvars()];
+        let mut res = self.field.zero();
         for t in self {
-            if t.exponents[n] == E::zero() {
-                res.append_monomial(t.coefficient.clone(), &t.exponents[..self.nvars()]);
-                continue;
+            let mut c = t.coefficient.clone();
+            for (p, e) in powers.iter().zip(t.exponents) {
+                if *e > E::zero() {
+                    self.field.mul_assign(&mut c, &p[e.to_u32() as usize]);
+                }
+            }
+            self.field.add_assign(&mut res, &c);
+        }
+
+        Ok(res)
+    }
+
+    /// Evaluate the polynomial by binding some of the variables to an element of the ring.
+    /// The bound variables are removed from the variable map of the result.
+    pub fn evaluate_partial(&self, bindings: &[(Symbol, F::Element)]) -> Self {
+        let mut powers = Vec::with_capacity(self.nvars());
+        let mut new_vars = vec![];
+        for (i, v) in self.variables.iter().enumerate() {
+            let binding = match v {
+                Variable::Symbol(s) => bindings.iter().find(|(b, _)| b == s).map(|(_, e)| e),
+                _ => None,
+            };
+
+            if let Some(b) = binding {
+                powers.push(Some(self.binding_powers(i, b)));
+            } else {
+                powers.push(None);
+                new_vars.push(v.clone());
+            }
+        }
+
+        let mut res =
+            MultivariatePolynomial::new(&self.field, Some(self.nterms()), Arc::new(new_vars));
+        let mut exp = Vec::with_capacity(res.nvars());
+        for t in self {
+            let mut c = t.coefficient.clone();
+            exp.clear();
+            for (p, e) in powers.iter().zip(t.exponents) {
+                if let Some(p) = p {
+                    if *e > E::zero() {
+                        self.field.mul_assign(&mut c, &p[e.to_u32() as usize]);
+                    }
+                } else {
+                    exp.push(*e);
+                }
             }
 
-            exp.copy_from_slice(t.exponents);
-            exp[n] = E::zero();
+            if !F::is_zero(&c) {
+                res.append_monomial(c, &exp);
+            }
+        }
+
+        res
+    }
 
-            // TODO: cache v^e
-            res = res
-                + (&v.pow(t.exponents[n].to_u32() as usize)
-                    * &self.monomial(t.coefficient.clone(), exp.clone()));
+    /// Compute all powers of `v` up to the degree of variable `var`.
+    fn binding_powers(&self, var: usize, v: &F::Element) -> Vec<F::Element> {
+        let d = self.degree(var).to_u32() as usize;
+        let mut powers = Vec::with_capacity(d + 1);
+        powers.push(self.field.one());
+        for i in 0..d {
+            let p = self.field.mul(&powers[i], v);
+            powers.push(p);
         }
+        powers
+    }
+
+    /// Replace a variable `n` in the polynomial by a polynomial `v`,
+    /// which must have the same variable map. See [Self::compose].
+    pub fn replace_with_poly(&self, n: usize, v: &Self) -> Self {
+        assert_eq!(self.variables, v.variables);
+        self.compose(n, v)
+    }
+
+    /// Compose the polynomial with `q` by replacing the variable `var` by `q`,
+    /// using Horner's scheme in `var`. The degree of the result is at most the
+    /// degree of `var` times the degree of `q`.
+    ///
+    /// The variable map of the result is the one of `self`, extended by the variables of `q`.
+    pub fn compose(&self, var: usize, q: &Self) -> Self {
+        let mut p = self.clone();
+        let mut q = q.clone();
+        p.unify_variables(&mut q);
+
+        if q.is_constant() {
+            return p.replace(var, &q.lcoeff());
+        }
+
+        let mut res = p.zero();
+        let mut last_deg = 0;
+        for (c, d) in p.to_univariate_polynomial_list(var).into_iter().rev() {
+            let d = d.to_u32() as usize;
+            if !res.is_zero() {
+                res = &res * &q.pow(last_deg - d);
+            }
+            res = res + c;
+            last_deg = d;
+        }
+
+        if last_deg > 0 {
+            res = &res * &q.pow(last_deg);
+        }
+
         res
     }
 
@@ -2339,8 +2508,14 @@ impl<F: Ring, E: Exponent> MultivariatePolynomial<F, E, LexOrder> {
         (q, r)
     }
 
-    /// Shift a variable `var` to `var+shift`.
+    /// Shift a variable `var` to `var+shift`, i.e., compute `p(.., var+shift, ..)`, using
+    /// a Taylor shift that performs `O(d^2)` additions and multiplications of the coefficient polynomials
+    /// in the other variables, where `d` is the degree in `var`.
     pub fn shift_var(&self, var: usize, shift: &F::Element) -> Self {
+        if F::is_zero(shift) {
+            return self.clone();
+        }
+
         let d = self.degree(var).to_u32() as usize;
 
         let y_poly = self.to_univariate_polynomial_list(var);
@@ -3123,6 +3298,47 @@ impl<F: Field, E: Exponent, O: MonomialOrder> MultivariatePolynomial<F, E, O> {
 }
 
 impl<F: Field, E: Exponent> MultivariatePolynomial<F, E, LexOrder> {
+    /// Construct the unique polynomial in the variable `var` of degree less than the number of
+    /// points that goes through all `points`, given as pairs `(x, y)`, using Newton's divided differences.
+    /// The field and the variable map are inherited from `self`.
+    pub fn interpolate_univariate(
+        &self,
+        var: usize,
+        points: &[(F::Element, F::Element)],
+    ) -> Result<Self, String> {
+        for (i, (x, _)) in points.iter().enumerate() {
+            if points[..i].iter().any(|(xx, _)| xx == x) {
+                return Err("Duplicate x-value in interpolation points".to_owned());
+            }
+        }
+
+        if points.is_empty() {
+            return Ok(self.zero());
+        }
+
+        // compute the divided differences in place
+        let mut coeffs: Vec<_> = points.iter().map(|(_, y)| y.clone()).collect();
+        for j in 1..points.len() {
+            for i in (j..points.len()).rev() {
+                let num = self.field.sub(&coeffs[i], &coeffs[i - 1]);
+                let den = self.field.sub(&points[i].0, &points[i - j].0);
+                coeffs[i] = self.field.div(&num, &den);
+            }
+        }
+
+        // convert the Newton form to the standard form
+        let mut e = vec![E::zero(); self.nvars()];
+        e[var] = E::one();
+        let x = self.monomial(self.field.one(), e);
+
+        let mut res = self.constant(coeffs.pop().unwrap());
+        for (c, (xk, _)) in coeffs.into_iter().zip(points).rev() {
+            res = &res * &(x.clone() - self.constant(xk.clone())) + self.constant(c);
+        }
+
+        Ok(res)
+    }
+
     /// Optimized division routine for univariate polynomials over a field, which
     /// makes the divisor monic first.
     pub fn quot_rem_univariate(
@@ -3381,6 +3597,27 @@ impl<R: Ring, E: Exponent> MultivariatePolynomial<AlgebraicNumberRing<R>, E> {
     }
 }
 
+impl<E: Exponent, O: MonomialOrder> MultivariatePolynomial<RationalField, E, O> {
+    /// Map the polynomial to the finite field `field`, by reducing every coefficient modulo the prime.
+    /// Terms whose coefficient vanishes are removed. Yields an error if the denominator
+    /// of a coefficient is divisible by the prime, as the image is then undefined.
+    pub fn map_to_field(&self, field: &Zp) -> Result<MultivariatePolynomial<Zp, E, O>, String> {
+        let p = Integer::from(field.get_prime() as i64);
+        if let Some(c) = self
+            .coefficients
+            .iter()
+            .find(|c| (&c.denominator() % &p).is_zero())
+        {
+            return Err(format!(
+                "The denominator of the coefficient {} is divisible by {}",
+                c, p
+            ));
+        }
+
+        Ok(self.map_coeff(|c| c.to_finite_field(field), field.clone()))
+    }
+}
+
 impl<E: Exponent> From<&MultivariatePolynomial<IntegerRing, E>>
     for MultivariatePolynomial<RationalField, E>
 {
@@ -3443,7 +3680,203 @@ impl<'a, F: Ring, E: Exponent, O: MonomialOrder> IntoIterator
 
 #[cfg(test)]
 mod test {
-    use crate::{atom::Atom, domains::integer::Z};
+    use crate::{
+        atom::Atom,
+        domains::{
+            finite_field::{FiniteFieldCore, Zp},
+            integer::Z,
+            rational::Q,
+        },
+        poly::{GrevLexOrder, ParsedPolynomial},
+        state::State,
+    };
+
+    #[test]
+    fn evaluate() {
+        let x = State::get_symbol("v1");
+        let y = State::get_symbol("v2");
+
+        let p = Atom::parse("v1^2+1")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, None);
+        assert_eq!(p.evaluate(&[(x, 3.into())]), Ok(10.into()));
+
+        let field = Zp::new(7);
+        let p = Atom::parse("v1^2+1")
+            .unwrap()
+            .to_polynomial::<_, u8>(&field, None);
+        assert_eq!(
+            p.evaluate(&[(x, field.to_element(3))]),
+            Ok(field.to_element(3))
+        );
+
+        let p = Atom::parse("v1^2*v2+v1+v2^3")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, None);
+        assert!(p.evaluate(&[(x, 3.into())]).is_err());
+
+        let r = p.evaluate_partial(&[(x, 2.into())]);
+        assert_eq!(r.nvars(), 1);
+        assert_eq!(
+            r,
+            Atom::parse("4*v2+2+v2^3")
+                .unwrap()
+                .to_polynomial::<_, u8>(&Q, Some(r.get_vars()))
+        );
+        assert_eq!(
+            r.evaluate(&[(y, 1.into())]),
+            p.evaluate(&[(x, 2.into()), (y, 1.into())])
+        );
+
+        let c = p.constant(5.into());
+        assert_eq!(c.evaluate(&[]), Ok(5.into()));
+    }
+
+    #[test]
+    fn leading_term() {
+        let p = Atom::parse("v1+v2^2")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Z, None);
+
+        assert_eq!(p.leading_monomial(), Some([1, 0].as_slice()));
+        assert_eq!(p.leading_coefficient(), 1.into());
+        assert_eq!(
+            p.tail(),
+            Atom::parse("v2^2")
+                .unwrap()
+                .to_polynomial(&Z, Some(p.variables.clone()))
+        );
+
+        let p = p.reorder::<GrevLexOrder>();
+        assert_eq!(p.leading_monomial(), Some([0, 2].as_slice()));
+        assert_eq!(p.tail().leading_monomial(), Some([1, 0].as_slice()));
+        assert!(p.tail().tail().is_zero());
+
+        let z = p.zero();
+        assert_eq!(z.leading_coefficient(), 0.into());
+        assert_eq!(z.leading_monomial(), None);
+        assert!(z.tail().is_zero());
+    }
+
+    #[test]
+    fn derivative_integrate() {
+        let p = Atom::parse("v1^3+v2")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, None);
+        let vars = p.variables.clone();
+        let x = vars
+            .iter()
+            .position(|v| *v == State::get_symbol("v1").into())
+            .unwrap();
+
+        let res = Atom::parse("3*v1^2")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, vars.clone().into());
+        assert_eq!(p.derivative(x), res);
+        assert!(p.constant(5.into()).derivative(x).is_zero());
+
+        let p = Atom::parse("v1^2")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, vars.clone().into());
+        let res = Atom::parse("1/3*v1^3")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, vars.into());
+        assert_eq!(p.integrate(x), res);
+        assert_eq!(p.integrate(x).derivative(x), p);
+    }
+
+    #[test]
+    fn canonicalize() {
+        let vars = std::sync::Arc::new(vec![
+            State::get_symbol("v1").into(),
+            State::get_symbol("v2").into(),
+        ]);
+        let p = Atom::parse("v1^2+v2")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, Some(vars.clone()));
+
+        // write the terms of v2+v1^2 in reverse order, with a split term and a zero term
+        let mut q = p.zero();
+        for (c, e) in [
+            ((1, 2), [0, 1]),
+            ((3, 1), [1, 1]),
+            ((1, 1), [2, 0]),
+            ((1, 2), [0, 1]),
+            ((0, 1), [3, 0]),
+            ((-3, 1), [1, 1]),
+        ] {
+            q.coefficients.push(c.into());
+            q.exponents.extend_from_slice(&e);
+        }
+        assert_ne!(p, q);
+
+        q.canonicalize();
+        q.check_consistency();
+        assert_eq!(p, q);
+
+        q.coefficients.push((2, 1).into());
+        q.exponents.extend_from_slice(&[2, 0]);
+        q.coefficients.push((-3, 1).into());
+        q.exponents.extend_from_slice(&[2, 0]);
+        q.canonicalize();
+        assert_eq!(q, Atom::parse("v2").unwrap().to_polynomial(&Q, Some(vars)));
+    }
+
+    #[test]
+    fn compose() {
+        let vars = std::sync::Arc::new(vec![
+            State::get_symbol("v1").into(),
+            State::get_symbol("v2").into(),
+        ]);
+        let poly = |s: &str| {
+            Atom::parse(s)
+                .unwrap()
+                .to_polynomial::<_, u8>(&Q, Some(vars.clone()))
+        };
+
+        assert_eq!(poly("v1^2").compose(0, &poly("v1+1")), poly("v1^2+2*v1+1"));
+
+        // composing with a constant evaluates
+        let p = poly("v1^3*v2+v1+2");
+        assert_eq!(p.compose(0, &poly("2")), poly("8*v2+4"));
+
+        let q = poly("v1^2+v2");
+        let r = p.compose(0, &q);
+        assert_eq!(r.degree(0), 6);
+        assert_eq!(r, p.replace_with_poly(0, &q));
+        assert_eq!(r, poly("v1^6*v2+3*v1^4*v2^2+3*v1^2*v2^3+v2^4+v1^2+v2+2"));
+
+        // the variable map is extended by the variables of the substitute
+        let p = Atom::parse("v1^2+v1")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, None);
+        let q = Atom::parse("v3-1")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, None);
+        let r = p.compose(0, &q);
+        assert_eq!(r.to_expression(), Atom::parse("v3^2-v3").unwrap());
+    }
+
+    #[test]
+    fn interpolate_univariate() {
+        let p = Atom::parse("v1").unwrap().to_polynomial::<_, u8>(&Q, None);
+
+        let points = [
+            (0.into(), 0.into()),
+            (1.into(), 1.into()),
+            (2.into(), 4.into()),
+        ];
+        let r = p.interpolate_univariate(0, &points).unwrap();
+        assert_eq!(r, &p * &p);
+
+        let r = p
+            .interpolate_univariate(0, &[(3.into(), 7.into())])
+            .unwrap();
+        assert_eq!(r, p.constant(7.into()));
+
+        let points = [(1.into(), 0.into()), (1.into(), 1.into())];
+        assert!(p.interpolate_univariate(0, &points).is_err());
+    }
 
     #[test]
     fn mul_packed() {
@@ -3513,4 +3946,99 @@ mod test {
             Atom::parse("1-v8-v8*v9-v7-v6-v5-v4+v3-4*v2+v2*v3^2+v2^2*v3").unwrap()
         );
     }
+
+    #[test]
+    fn map_to_field() {
+        let field = Zp::new(7);
+        let p = Atom::parse("3*v1^2+7-1/2*v1*v2")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, None);
+        assert_eq!(
+            p.map_to_field(&field).unwrap(),
+            Atom::parse("3*v1^2+3*v1*v2")
+                .unwrap()
+                .to_polynomial::<_, u8>(&field, p.variables.clone().into())
+        );
+
+        let q = Atom::parse("v1/14+1")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, None);
+        assert!(q.map_to_field(&field).is_err());
+    }
+
+    #[test]
+    fn shift_var() {
+        let p = Atom::parse("v1^2")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Z, None);
+        assert_eq!(
+            p.shift_var(0, &1.into()),
+            Atom::parse("v1^2+2*v1+1")
+                .unwrap()
+                .to_polynomial::<_, u8>(&Z, p.variables.clone().into())
+        );
+        assert_eq!(p.shift_var(0, &0.into()), p);
+
+        let p = Atom::parse("v1^3*v2+v2^2*v1-5")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Q, None);
+        let r = p.shift_var(1, &(-2, 3).into());
+        assert_eq!(
+            r,
+            Atom::parse("v1^3*(v2-2/3)+(v2-2/3)^2*v1-5")
+                .unwrap()
+                .expand()
+                .to_polynomial::<_, u8>(&Q, p.variables.clone().into())
+        );
+        assert_eq!(r.shift_var(1, &(2, 3).into()), p);
+    }
+
+    #[test]
+    fn parse_polynomial() {
+        let ParsedPolynomial::Integer(p) = Atom::parse_polynomial::<u8>("3*v1^2*v2+2-v2").unwrap()
+        else {
+            panic!("Expected an integer polynomial");
+        };
+        let v1 = p
+            .get_vars_ref()
+            .iter()
+            .position(|v| *v == State::get_symbol("v1").into())
+            .unwrap();
+        assert_eq!(p.nvars(), 2);
+        assert_eq!(p.degree(v1), 2);
+        assert_eq!(p.degree(1 - v1), 1);
+        assert_eq!(
+            p,
+            Atom::parse("3*v1^2*v2+2-v2")
+                .unwrap()
+                .to_polynomial::<_, u8>(&Z, p.variables.clone().into())
+        );
+
+        let ParsedPolynomial::Rational(p) = Atom::parse_polynomial::<u8>("3/4*v1").unwrap() else {
+            panic!("Expected a rational polynomial");
+        };
+        assert_eq!(p.coefficients, vec![(3, 4).into()]);
+
+        // the input is expanded
+        let p = Atom::parse_polynomial::<u8>("(v1+1)^2").unwrap();
+        assert_eq!(
+            p.to_rational(),
+            Atom::parse("v1^2+2*v1+1")
+                .unwrap()
+                .to_polynomial::<_, u8>(&Q, None)
+        );
+
+        for input in ["1/v1", "f1(v1)", "v1^(1/2)", "v1^v2", "v1+"] {
+            assert!(Atom::parse_polynomial::<u8>(input).is_err());
+        }
+
+        // the exponents must fit in the exponent type
+        assert_eq!(
+            Atom::parse_polynomial::<u8>("v1^300+v2"),
+            Err("Exponent 300 is too large".to_owned())
+        );
+        assert!(Atom::parse_polynomial::<u8>("(v1^20+1)^20").is_err());
+        assert!(Atom::parse_polynomial::<u16>("v1^300").is_ok());
+        assert!(Atom::parse_polynomial::<u8>("v1^255").is_ok());
+    }
 }
@@ -1,6 +1,12 @@
-use crate::domains::{EuclideanDomain, Field};
+use crate::{
+    atom::Symbol,
+    domains::{EuclideanDomain, Field, Ring},
+};
 
-use super::univariate::UnivariatePolynomial;
+use super::{
+    polynomial::MultivariatePolynomial, univariate::UnivariatePolynomial, Exponent, LexOrder,
+    Variable,
+};
 
 impl<F: EuclideanDomain> UnivariatePolynomial<F> {
     /// Compute the resultant using Brown's polynomial remainder sequence algorithm.
@@ -98,12 +104,118 @@ impl<F: Field> UnivariatePolynomial<F> {
     }
 }
 
+impl<F: Ring, E: Exponent> MultivariatePolynomial<F, E, LexOrder> {
+    /// Compute the pseudo-remainder of `self` divided by `other` in the variable `var`,
+    /// i.e., the `r` in `lc(other)^(deg(self) - deg(other) + 1) * self = q * other + r`,
+    /// where the degrees and leading coefficient are in `var`. Only ring operations are used.
+    ///
+    /// Both polynomials should have the same variable map.
+    pub fn pseudo_remainder(&self, other: &Self, var: Symbol) -> Self {
+        if other.is_zero() {
+            panic!("Cannot divide by 0 polynomial");
+        }
+
+        let Some(x) = self
+            .get_vars_ref()
+            .iter()
+            .position(|v| *v == Variable::Symbol(var))
+        else {
+            // both polynomials are constant in `var`
+            return self.zero();
+        };
+
+        let deg_other = other.degree(x).to_u32();
+        let deg_self = self.degree(x).to_u32();
+        if self.is_zero() || deg_self < deg_other {
+            return self.clone();
+        }
+
+        let lc = other.univariate_lcoeff(x);
+        let mut r = self.clone();
+        let mut e = deg_self - deg_other + 1;
+        let mut shift = vec![E::zero(); self.nvars()];
+        while !r.is_zero() && r.degree(x).to_u32() >= deg_other {
+            shift[x] = E::from_u32(r.degree(x).to_u32() - deg_other);
+            let s = r.univariate_lcoeff(x).mul_exp(&shift);
+            r = &r * &lc - (&s * other);
+            e -= 1;
+        }
+
+        r * &lc.pow(e as usize)
+    }
+}
+
+impl<F: EuclideanDomain, E: Exponent> MultivariatePolynomial<F, E, LexOrder> {
+    /// Compute the subresultant polynomial remainder sequence of `self` and `other` in the variable `var`,
+    /// starting with the input polynomials, where the polynomial of highest degree in `var` comes first.
+    /// The last entry is zero in `var` if and only if the polynomials have a common factor that depends on `var`.
+    ///
+    /// Both polynomials should have the same variable map.
+    pub fn subresultant_prs(&self, other: &Self, var: Symbol) -> Vec<Self> {
+        let Some(x) = self
+            .get_vars_ref()
+            .iter()
+            .position(|v| *v == Variable::Symbol(var))
+        else {
+            return vec![self.clone(), other.clone()];
+        };
+
+        let (a, b) = if self.degree(x) < other.degree(x) {
+            (other, self)
+        } else {
+            (self, other)
+        };
+
+        let mut prs = vec![a.clone(), b.clone()];
+        if b.is_zero() {
+            return prs;
+        }
+
+        let mut d = a.degree(x).to_u32() - b.degree(x).to_u32();
+        let mut psi = -a.one();
+        let mut beta = if d % 2 == 0 { -a.one() } else { a.one() };
+
+        loop {
+            let (prev, cur) = (&prs[prs.len() - 2], &prs[prs.len() - 1]);
+            let r = prev.pseudo_remainder(cur, var);
+            if r.is_zero() {
+                break;
+            }
+
+            let r = r.divides(&beta).unwrap();
+            let gamma = cur.univariate_lcoeff(x);
+
+            psi = if d == 0 {
+                psi
+            } else {
+                (-gamma.clone())
+                    .pow(d as usize)
+                    .divides(&psi.pow(d as usize - 1))
+                    .unwrap()
+            };
+
+            let new_d = cur.degree(x).to_u32() - r.degree(x).to_u32();
+            beta = -gamma * &psi.pow(new_d as usize);
+            d = new_d;
+
+            let is_constant = r.degree(x).is_zero();
+            prs.push(r);
+            if is_constant {
+                break;
+            }
+        }
+
+        prs
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::atom::Atom;
     use crate::domains::integer::Z;
     use crate::domains::rational::Q;
     use crate::poly::polynomial::MultivariatePolynomial;
+    use crate::state::State;
 
     #[test]
     fn resultant() {
@@ -156,4 +268,40 @@ mod test {
 
         assert_eq!(r, res);
     }
+
+    #[test]
+    fn subresultant_prs() {
+        let a = Atom::parse("v1^8+v1^6-3v1^4-3v1^3+8v1^2+2v1-5")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Z, None);
+        let b = Atom::parse("3v1^6+5v1^4-4v1^2-9v1+21")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Z, a.variables.clone().into());
+        let x = State::get_symbol("v1");
+
+        let r = a.pseudo_remainder(&b, x);
+        let res = Atom::parse("-15v1^4+3v1^2-9")
+            .unwrap()
+            .to_polynomial::<_, u8>(&Z, a.variables.clone().into());
+        assert_eq!(r, res);
+
+        // a constant divisor leaves no remainder
+        assert!(a.pseudo_remainder(&a.constant(3.into()), x).is_zero());
+
+        let prs = b.subresultant_prs(&a, x);
+        let res = [
+            "v1^8+v1^6-3v1^4-3v1^3+8v1^2+2v1-5",
+            "3v1^6+5v1^4-4v1^2-9v1+21",
+            "15v1^4-3v1^2+9",
+            "65v1^2+125v1-245",
+            "9326v1-12300",
+            "260708",
+        ]
+        .map(|s| {
+            Atom::parse(s)
+                .unwrap()
+                .to_polynomial::<_, u8>(&Z, a.variables.clone().into())
+        });
+        assert_eq!(prs, res);
+    }
 }
@@ -1,10 +1,17 @@
-use std::fmt::{self, Display, Write};
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Write},
+};
+
+use ahash::HashMap;
 
 use colored::Colorize;
+use rug::{Integer as MultiPrecisionInteger, Rational as MultiPrecisionRational};
 
 use crate::{
     atom::{
-        representation::FunView, AddView, AtomView, MulView, NumView, PowView, Symbol, VarView,
+        representation::FunView, AddView, Atom, AtomView, MulView, NumView, PowView, Symbol,
+        VarView,
     },
     coefficient::CoefficientView,
     domains::{
@@ -29,6 +36,18 @@ pub struct PrintOptions {
     pub square_brackets_for_function: bool,
     pub num_exp_as_superscript: bool,
     pub latex: bool,
+    /// Print non-integer rational coefficients as decimals with
+    /// at most this many digits after the decimal point. Coefficients that
+    /// are too small for this precision are printed in scientific notation.
+    pub decimal_precision: Option<usize>,
+    /// Print wildcards with this marker in front of their name instead of with
+    /// trailing underscores, for example `?x` instead of `x_` for `Some('?')`.
+    /// See [ParseSettings::wildcard_prefix](crate::parser::ParseSettings::wildcard_prefix).
+    pub wildcard_prefix: Option<char>,
+    /// Print the numerators and denominators of coefficients with more than this many digits
+    /// as their first and last digits, followed by the number of digits,
+    /// for example `1234...5678 (312 digits)` for `Some(8)`. This is only meant for display.
+    pub max_coeff_digits: Option<usize>,
 }
 
 impl PrintOptions {
@@ -46,6 +65,9 @@ impl PrintOptions {
             square_brackets_for_function: true,
             num_exp_as_superscript: false,
             latex: false,
+            decimal_precision: None,
+            wildcard_prefix: None,
+            max_coeff_digits: None,
         }
     }
 
@@ -63,6 +85,9 @@ impl PrintOptions {
             square_brackets_for_function: false,
             num_exp_as_superscript: false,
             latex: true,
+            decimal_precision: None,
+            wildcard_prefix: None,
+            max_coeff_digits: None,
         }
     }
 
@@ -80,6 +105,9 @@ impl PrintOptions {
             square_brackets_for_function: false,
             num_exp_as_superscript: false,
             latex: false,
+            decimal_precision: None,
+            wildcard_prefix: None,
+            max_coeff_digits: None,
         }
     }
 }
@@ -98,10 +126,28 @@ impl Default for PrintOptions {
             square_brackets_for_function: false,
             num_exp_as_superscript: false,
             latex: false,
+            decimal_precision: None,
+            wildcard_prefix: None,
+            max_coeff_digits: None,
         }
     }
 }
 
+/// Get the name of a symbol, with the wildcard marker of `opts` in front
+/// instead of the trailing underscores if the symbol is a wildcard.
+fn symbol_name(id: Symbol, opts: &PrintOptions) -> Cow<'static, str> {
+    let name = State::get_name(id);
+    match opts.wildcard_prefix {
+        Some(prefix) if id.get_wildcard_level() > 0 => {
+            let level = id.get_wildcard_level() as usize;
+            let mut s: String = std::iter::repeat(prefix).take(level).collect();
+            s.push_str(&name[..name.len() - level]);
+            Cow::Owned(s)
+        }
+        _ => Cow::Borrowed(name),
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct PrintState {
     pub level: usize,
@@ -193,7 +239,7 @@ impl<'a> AtomView<'a> {
         opts: &PrintOptions,
         print_state: PrintState,
     ) -> fmt::Result {
-        match self {
+        match self.strip_hold() {
             AtomView::Num(n) => n.fmt_output(fmt, opts, print_state),
             AtomView::Var(v) => v.fmt_output(fmt, opts, print_state),
             AtomView::Fun(f) => f.fmt_output(fmt, opts, print_state),
@@ -204,6 +250,187 @@ impl<'a> AtomView<'a> {
     }
 }
 
+impl<'a> AtomView<'a> {
+    /// Get the argument of a (nested) `hold`, so that held expressions are printed verbatim.
+    fn strip_hold(&self) -> AtomView<'a> {
+        let mut a = *self;
+        while let AtomView::Fun(f) = a {
+            if f.get_symbol() == State::HOLD && f.get_nargs() == 1 {
+                a = f.iter().next().unwrap();
+            } else {
+                break;
+            }
+        }
+        a
+    }
+}
+
+impl Atom {
+    /// Write the expression as a Graphviz DOT graph. See [AtomView::to_dot].
+    pub fn to_dot(&self) -> String {
+        self.as_view().to_dot()
+    }
+
+    /// Write the expression as a Graphviz DOT graph with at most `max_nodes` nodes.
+    /// See [AtomView::to_dot_with_limit].
+    pub fn to_dot_with_limit(&self, max_nodes: usize) -> String {
+        self.as_view().to_dot_with_limit(max_nodes)
+    }
+
+    /// Write the expression as an s-expression. See [AtomView::to_sexpr].
+    pub fn to_sexpr(&self) -> String {
+        self.as_view().to_sexpr()
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Write the expression as a Graphviz DOT graph, where structurally identical
+    /// subexpressions are merged into a single node.
+    pub fn to_dot(self) -> String {
+        self.to_dot_with_limit(usize::MAX)
+    }
+
+    /// Write the expression as a Graphviz DOT graph with at most `max_nodes` distinct
+    /// subexpressions. All subexpressions that do not fit are represented by a single node `...`.
+    pub fn to_dot_with_limit(self, max_nodes: usize) -> String {
+        let mut out = String::from("digraph {\n");
+        self.to_dot_impl(max_nodes, &mut HashMap::default(), &mut false, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    /// Write the expression as a Lisp-like s-expression, such as `(+ (* 3 (^ x 2)) 1)` for `3*x^2+1`.
+    /// Fractions are written as `(/ p q)` and functions as `(f a b)`. The output can be parsed
+    /// with [Atom::parse_sexpr], except for finite field and rational polynomial coefficients,
+    /// which are written in the infix notation.
+    pub fn to_sexpr(self) -> String {
+        let mut out = String::new();
+        self.to_sexpr_impl(&mut out);
+        out
+    }
+
+    fn to_sexpr_impl(self, out: &mut String) {
+        let (head, args) = match self {
+            AtomView::Num(n) => {
+                let (num, den) = match n.get_coeff_view() {
+                    CoefficientView::Natural(n, d) => (n.to_string(), d.to_string()),
+                    CoefficientView::Large(r) => {
+                        let r = r.to_rat();
+                        (r.numer().to_string(), r.denom().to_string())
+                    }
+                    CoefficientView::FiniteField(_, _) | CoefficientView::RationalPolynomial(_) => {
+                        write!(
+                            out,
+                            "{}",
+                            AtomPrinter::new_with_options(self, PrintOptions::file())
+                        )
+                        .unwrap();
+                        return;
+                    }
+                };
+
+                if den == "1" {
+                    out.push_str(&num);
+                } else {
+                    write!(out, "(/ {} {})", num, den).unwrap();
+                }
+                return;
+            }
+            AtomView::Var(v) => {
+                write!(out, "{}", v.get_symbol()).unwrap();
+                return;
+            }
+            AtomView::Fun(f) => (f.get_symbol().to_string(), f.iter().collect::<Vec<_>>()),
+            AtomView::Pow(p) => {
+                let (b, e) = p.get_base_exp();
+                out.push_str("(^ ");
+                b.to_sexpr_impl(out);
+                out.push(' ');
+                e.to_sexpr_impl(out);
+                out.push(')');
+                return;
+            }
+            AtomView::Mul(m) => {
+                // write the coefficient first
+                let mut args: Vec<_> = m.iter().collect();
+                if let Some(AtomView::Num(_)) = args.last() {
+                    args.rotate_right(1);
+                }
+                ("*".to_owned(), args)
+            }
+            AtomView::Add(a) => ("+".to_owned(), a.iter().collect()),
+        };
+
+        out.push('(');
+        out.push_str(&head);
+        for a in args {
+            out.push(' ');
+            a.to_sexpr_impl(out);
+        }
+        out.push(')');
+    }
+
+    fn to_dot_impl(
+        self,
+        max_nodes: usize,
+        nodes: &mut HashMap<AtomView<'a>, usize>,
+        truncated: &mut bool,
+        out: &mut String,
+    ) -> usize {
+        if let Some(id) = nodes.get(&self) {
+            return *id;
+        }
+
+        if nodes.len() >= max_nodes {
+            if !*truncated {
+                *truncated = true;
+                writeln!(out, "  n{} [label=\"...\"];", max_nodes).unwrap();
+            }
+            return max_nodes;
+        }
+
+        let id = nodes.len();
+        nodes.insert(self, id);
+
+        let label = match self {
+            AtomView::Num(_) => {
+                AtomPrinter::new_with_options(self, PrintOptions::file()).to_string()
+            }
+            AtomView::Var(v) => v.get_symbol().to_string(),
+            AtomView::Fun(f) => f.get_symbol().to_string(),
+            AtomView::Pow(_) => "^".to_owned(),
+            AtomView::Mul(_) => "*".to_owned(),
+            AtomView::Add(_) => "+".to_owned(),
+        };
+        writeln!(
+            out,
+            "  n{} [label=\"{}\"];",
+            id,
+            label.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+        .unwrap();
+
+        let mut add_edge = |child: AtomView<'a>, out: &mut String| {
+            let child_id = child.to_dot_impl(max_nodes, nodes, truncated, out);
+            writeln!(out, "  n{} -> n{};", id, child_id).unwrap();
+        };
+
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => {}
+            AtomView::Fun(f) => f.iter().for_each(|c| add_edge(c, out)),
+            AtomView::Pow(p) => {
+                let (b, e) = p.get_base_exp();
+                add_edge(b, out);
+                add_edge(e, out);
+            }
+            AtomView::Mul(m) => m.iter().for_each(|c| add_edge(c, out)),
+            AtomView::Add(a) => a.iter().for_each(|c| add_edge(c, out)),
+        }
+
+        id
+    }
+}
+
 impl<'a> fmt::Debug for AtomView<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         self.fmt_debug(fmt)
@@ -226,7 +453,8 @@ impl<'a> FormattedPrintVar for VarView<'a> {
         }
 
         let id = self.get_symbol();
-        let name = State::get_name(id);
+        let name = symbol_name(id, opts);
+        let name = name.as_ref();
 
         if opts.latex {
             match id {
@@ -235,7 +463,7 @@ impl<'a> FormattedPrintVar for VarView<'a> {
                 State::I => f.write_char('i'),
                 _ => f.write_str(name),
             }
-        } else if opts.color_builtin_symbols && name.ends_with('_') {
+        } else if opts.color_builtin_symbols && id.get_wildcard_level() > 0 {
             f.write_fmt(format_args!("{}", name.cyan().italic()))
         } else if opts.color_builtin_symbols && State::is_builtin(id) {
             f.write_fmt(format_args!("{}", name.purple()))
@@ -294,6 +522,59 @@ impl<'a> FormattedPrintNum for NumView<'a> {
             }
         }
 
+        /// Write a non-negative rational as a decimal number, rounded to `precision` digits
+        /// after the decimal point. Trailing zeros are omitted. A non-zero number that
+        /// would be rounded to zero is written in scientific notation instead.
+        fn format_decimal(r: MultiPrecisionRational, precision: usize) -> String {
+            let (num, den) = r.into_numer_denom();
+            let scaled = num.clone()
+                * MultiPrecisionInteger::from(MultiPrecisionInteger::u_pow_u(10, precision as u32));
+            // round half away from zero
+            let digits: MultiPrecisionInteger = (scaled * 2u32 + &den) / (den.clone() * 2u32);
+
+            if digits == 0 && num != 0 {
+                // scale the mantissa to [1, 10)
+                let mut mantissa = num;
+                let mut exp = 0;
+                while mantissa < den {
+                    mantissa *= 10u32;
+                    exp += 1;
+                }
+
+                let m = format_decimal(MultiPrecisionRational::from((mantissa, den)), precision);
+                return if m == "10" {
+                    format!("1e-{}", exp - 1)
+                } else {
+                    format!("{}e-{}", m, exp)
+                };
+            }
+
+            let mut s = format!("{:0>width$}", digits.to_string(), width = precision + 1);
+
+            let int_len = s.len() - precision;
+            let frac = s[int_len..].trim_end_matches('0').to_owned();
+            s.truncate(int_len);
+            if !frac.is_empty() {
+                s.push('.');
+                s.push_str(&frac);
+            }
+            s
+        }
+
+        /// Shorten a string of more than `max_digits` digits to its first and last digits.
+        fn truncate_digits(s: String, max_digits: usize) -> String {
+            if s.len() <= max_digits {
+                return s;
+            }
+
+            format!(
+                "{}...{} ({} digits)",
+                &s[..max_digits.div_ceil(2)],
+                &s[s.len() - max_digits / 2..],
+                s.len()
+            )
+        }
+
         let d = self.get_coeff_view();
 
         let is_negative = match d {
@@ -318,6 +599,42 @@ impl<'a> FormattedPrintNum for NumView<'a> {
             }
         }
 
+        if let Some(precision) = opts.decimal_precision {
+            let rat = match d {
+                CoefficientView::Natural(num, den) if den != 1 => {
+                    Some(MultiPrecisionRational::from((num, den)))
+                }
+                CoefficientView::Large(r) => Some(r.to_rat()).filter(|r| !r.is_integer()),
+                _ => None,
+            };
+
+            if let Some(rat) = rat {
+                return f.write_str(&format_decimal(rat.abs(), precision));
+            }
+        }
+
+        if let Some(max_digits) = opts.max_coeff_digits.filter(|_| !print_state.superscript) {
+            let digits = match d {
+                CoefficientView::Natural(num, den) => {
+                    Some((num.unsigned_abs().to_string(), den.to_string()))
+                }
+                CoefficientView::Large(r) => {
+                    let rat = r.to_rat().abs();
+                    Some((rat.numer().to_string(), rat.denom().to_string()))
+                }
+                _ => None,
+            };
+
+            if let Some((num, den)) = digits.filter(|(n, d)| n.len().max(d.len()) > max_digits) {
+                f.write_str(&truncate_digits(num, max_digits))?;
+                if den != "1" {
+                    f.write_char('/')?;
+                    f.write_str(&truncate_digits(den, max_digits))?;
+                }
+                return Ok(());
+            }
+        }
+
         match d {
             CoefficientView::Natural(num, den) => {
                 if !opts.latex
@@ -391,10 +708,16 @@ impl<'a> FormattedPrintMul for MulView<'a> {
         opts: &PrintOptions,
         mut print_state: PrintState,
     ) -> fmt::Result {
-        // write the coefficient first
+        // write the coefficient first, which is the last factor of a normalized product
+        // and may be the first factor of a held product
         let mut first = true;
-        let mut skip_num = false;
-        if let Some(AtomView::Num(n)) = self.iter().last() {
+        let mut skip_num = None;
+        let coeff = match (self.iter().next(), self.iter().last()) {
+            (_, Some(AtomView::Num(n))) => Some((n, self.get_nargs() - 1)),
+            (Some(AtomView::Num(n)), _) => Some((n, 0)),
+            _ => None,
+        };
+        if let Some((n, pos)) = coeff {
             // write -1*x as -x
             if n.get_coeff_view() == CoefficientView::Natural(-1, 1) {
                 if print_state.top_level_add_child && opts.color_top_level_sum {
@@ -409,7 +732,7 @@ impl<'a> FormattedPrintMul for MulView<'a> {
                 first = false;
             }
 
-            skip_num = true;
+            skip_num = Some(pos);
         } else if print_state.explicit_sign {
             if print_state.top_level_add_child && opts.color_top_level_sum {
                 f.write_fmt(format_args!("{}", "+".yellow()))?;
@@ -421,11 +744,11 @@ impl<'a> FormattedPrintMul for MulView<'a> {
         print_state.top_level_add_child = false;
         print_state.level += 1;
         print_state.explicit_sign = false;
-        for x in self.iter().take(if skip_num {
-            self.get_nargs() - 1
-        } else {
-            self.get_nargs()
-        }) {
+        for (i, x) in self.iter().enumerate() {
+            if skip_num == Some(i) {
+                continue;
+            }
+
             if !first {
                 if opts.latex {
                     f.write_char(' ')?;
@@ -435,7 +758,7 @@ impl<'a> FormattedPrintMul for MulView<'a> {
             }
             first = false;
 
-            if let AtomView::Add(_) = x {
+            if let AtomView::Add(_) | AtomView::Mul(_) = x.strip_hold() {
                 if opts.latex {
                     f.write_str("\\left(")?;
                 } else {
@@ -471,7 +794,12 @@ impl<'a> FormattedPrintFn for FunView<'a> {
         }
 
         let id = self.get_symbol();
-        let name = State::get_name(id);
+        let name = symbol_name(id, opts);
+        let name = name.as_ref();
+
+        if id == State::PIECEWISE && self.get_nargs() > 0 && !opts.square_brackets_for_function {
+            return self.fmt_piecewise(f, opts, print_state);
+        }
 
         if opts.latex {
             if name == "cos" || name == "sin" || name == "exp" || name == "log" {
@@ -480,7 +808,7 @@ impl<'a> FormattedPrintFn for FunView<'a> {
                 f.write_fmt(format_args!("{}\\!\\left(", name))?;
             }
         } else {
-            if opts.color_builtin_symbols && name.ends_with('_') {
+            if opts.color_builtin_symbols && id.get_wildcard_level() > 0 {
                 f.write_fmt(format_args!("{}", name.cyan().italic()))?;
             } else if opts.color_builtin_symbols && State::is_builtin(id) {
                 f.write_fmt(format_args!("{}", name.purple()))?;
@@ -522,6 +850,110 @@ impl<'a> FormattedPrintFn for FunView<'a> {
     }
 }
 
+impl<'a> FunView<'a> {
+    /// Print `piecewise(c1,v1,...,cn,vn,default)` as a list of `(condition,value)` cases
+    /// followed by the optional default. If `terms_on_new_line` is set, every case is printed
+    /// on a new line with the values aligned. The output can be parsed again.
+    fn fmt_piecewise(
+        &self,
+        f: &mut fmt::Formatter,
+        opts: &PrintOptions,
+        mut print_state: PrintState,
+    ) -> fmt::Result {
+        print_state.top_level_add_child = false;
+        print_state.level += 1;
+        print_state.explicit_sign = false;
+
+        let args: Vec<_> = self.iter().collect();
+        let (cases, default) = if args.len() % 2 == 1 {
+            (&args[..args.len() - 1], args.last())
+        } else {
+            (&args[..], None)
+        };
+
+        if opts.latex {
+            f.write_str("\\begin{cases} ")?;
+            for c in cases.chunks(2) {
+                c[1].fmt_output(f, opts, print_state)?;
+                f.write_str(" & ")?;
+                c[0].fmt_output(f, opts, print_state)?;
+                f.write_str(" \\\\ ")?;
+            }
+            if let Some(d) = default {
+                d.fmt_output(f, opts, print_state)?;
+                f.write_str(" & \\text{otherwise} ")?;
+            }
+            return f.write_str("\\end{cases}");
+        }
+
+        if opts.color_builtin_symbols {
+            f.write_fmt(format_args!("{}", "piecewise".purple()))?;
+        } else {
+            f.write_str("piecewise")?;
+        }
+        f.write_char('(')?;
+
+        // measure the conditions without colors, so that the values can be aligned
+        let widths: Vec<_> = if opts.terms_on_new_line {
+            let plain = PrintOptions {
+                color_top_level_sum: false,
+                color_builtin_symbols: false,
+                ..*opts
+            };
+            cases
+                .chunks(2)
+                .map(|c| {
+                    AtomPrinter::new_with_options(c[0], plain)
+                        .to_string()
+                        .chars()
+                        .count()
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let max_width = widths.iter().max().cloned().unwrap_or(0);
+
+        let mut first = true;
+        for (i, c) in cases.chunks(2).enumerate() {
+            if !first {
+                f.write_char(',')?;
+            }
+            first = false;
+
+            if opts.terms_on_new_line {
+                f.write_str("\n    ")?;
+            }
+
+            f.write_char('(')?;
+            c[0].fmt_output(f, opts, print_state)?;
+            f.write_char(',')?;
+            if opts.terms_on_new_line {
+                for _ in widths[i]..max_width {
+                    f.write_char(' ')?;
+                }
+            }
+            c[1].fmt_output(f, opts, print_state)?;
+            f.write_char(')')?;
+        }
+
+        if let Some(d) = default {
+            if !first {
+                f.write_char(',')?;
+            }
+            if opts.terms_on_new_line {
+                f.write_str("\n    ")?;
+            }
+            d.fmt_output(f, opts, print_state)?;
+        }
+
+        if opts.terms_on_new_line {
+            f.write_char('\n')?;
+        }
+        f.write_char(')')
+    }
+}
+
 impl<'a> FormattedPrintPow for PowView<'a> {
     fn fmt_output(
         &self,
@@ -537,8 +969,8 @@ impl<'a> FormattedPrintPow for PowView<'a> {
             }
         }
 
-        let b = self.get_base();
-        let e = self.get_exp();
+        let b = self.get_base().strip_hold();
+        let e = self.get_exp().strip_hold();
 
         print_state.top_level_add_child = false;
         print_state.level += 1;
@@ -635,7 +1067,32 @@ impl<'a> FormattedPrintAdd for AddView<'a> {
             print_state.explicit_sign = !first;
             first = false;
 
-            x.fmt_output(f, opts, print_state)?;
+            if let AtomView::Add(_) = x.strip_hold() {
+                // a held sum that is not flattened
+                if print_state.explicit_sign {
+                    if print_state.top_level_add_child && opts.color_top_level_sum {
+                        f.write_fmt(format_args!("{}", "+".yellow()))?;
+                    } else {
+                        f.write_char('+')?;
+                    }
+                }
+
+                let mut inner_state = print_state;
+                inner_state.explicit_sign = false;
+                if opts.latex {
+                    f.write_str("\\left(")?;
+                } else {
+                    f.write_char('(')?;
+                }
+                x.fmt_output(f, opts, inner_state)?;
+                if opts.latex {
+                    f.write_str("\\right)")?;
+                } else {
+                    f.write_char(')')?;
+                }
+            } else {
+                x.fmt_output(f, opts, print_state)?;
+            }
         }
         Ok(())
     }
@@ -1351,7 +1808,10 @@ mod test {
                         multiplication_operator: ' ',
                         square_brackets_for_function: false,
                         num_exp_as_superscript: true,
-                        latex: false
+                        latex: false,
+                        decimal_precision: None,
+                        wildcard_prefix: None,
+                        max_coeff_digits: None,
                     }
                 )
             ),
@@ -1359,6 +1819,96 @@ mod test {
         );
     }
 
+    #[test]
+    fn piecewise() {
+        let a = Atom::parse("piecewise((v1,v2+1),(v3,v4),v5)").unwrap();
+
+        let mut opts = PrintOptions::file();
+        let s = format!("{}", AtomPrinter::new_with_options(a.as_view(), opts));
+        assert_eq!(s, "piecewise((v1,v2+1),(v3,v4),v5)");
+        assert_eq!(Atom::parse(&s).unwrap(), a);
+
+        opts.terms_on_new_line = true;
+        let a = Atom::parse("piecewise((f1(v1),v2),(v3,v4))").unwrap();
+        let s = format!("{}", AtomPrinter::new_with_options(a.as_view(), opts));
+        assert_eq!(s, "piecewise(\n    (f1(v1),v2),\n    (v3,    v4)\n)");
+        assert_eq!(Atom::parse(&s).unwrap(), a);
+
+        let a = Atom::parse("piecewise((v1,v2))").unwrap();
+        let s = format!("{}", AtomPrinter::new_with_options(a.as_view(), opts));
+        assert_eq!(s, "piecewise(\n    (v1,v2)\n)");
+        assert_eq!(Atom::parse(&s).unwrap(), a);
+
+        let a = Atom::parse("piecewise((v1,v2),v3)").unwrap();
+        assert_eq!(
+            format!(
+                "{}",
+                AtomPrinter::new_with_options(a.as_view(), PrintOptions::latex())
+            ),
+            "\\begin{cases} v2 & v1 \\\\ v3 & \\text{otherwise} \\end{cases}"
+        );
+    }
+
+    #[test]
+    fn max_coeff_digits() {
+        let a = Atom::parse("-12345678901234567890123456789*v1+1/98765432109876543210987654321+7")
+            .unwrap();
+        let mut opts = PrintOptions::file();
+        assert_eq!(
+            format!("{}", AtomPrinter::new_with_options(a.as_view(), opts)),
+            "-12345678901234567890123456789*v1+691358024769135802476913580248/98765432109876543210987654321"
+        );
+
+        opts.max_coeff_digits = Some(8);
+        assert_eq!(
+            format!("{}", AtomPrinter::new_with_options(a.as_view(), opts)),
+            "-1234...6789 (29 digits)*v1+6913...0248 (30 digits)/9876...4321 (29 digits)"
+        );
+
+        let a = Atom::parse("-123/4*v1^20").unwrap();
+        assert_eq!(
+            format!("{}", AtomPrinter::new_with_options(a.as_view(), opts)),
+            "-123/4*v1^20"
+        );
+    }
+
+    #[test]
+    fn decimal_precision() {
+        let mut opts = PrintOptions::file();
+        opts.decimal_precision = Some(4);
+
+        let a = Atom::parse("1/3*x-2/3").unwrap();
+        assert_eq!(
+            format!("{}", AtomPrinter::new_with_options(a.as_view(), opts)),
+            "0.3333*x-0.6667"
+        );
+
+        opts.decimal_precision = Some(2);
+        let a = Atom::parse("1/2+5").unwrap();
+        assert_eq!(
+            format!("{}", AtomPrinter::new_with_options(a.as_view(), opts)),
+            "5.5"
+        );
+
+        let a = Atom::parse("v1/2").unwrap();
+        assert_eq!(
+            format!("{}", AtomPrinter::new_with_options(a.as_view(), opts)),
+            "0.5*v1"
+        );
+
+        let a = Atom::parse("123456789012345678901234567891/7").unwrap();
+        assert_eq!(
+            format!("{}", AtomPrinter::new_with_options(a.as_view(), opts)),
+            "17636684144620811271604938270.14"
+        );
+
+        let a = Atom::parse("v1/300000-2/3+v2/19999").unwrap();
+        assert_eq!(
+            format!("{}", AtomPrinter::new_with_options(a.as_view(), opts)),
+            "3.33e-6*v1+5e-5*v2-0.67"
+        );
+    }
+
     #[test]
     fn polynomials() {
         let a = Atom::parse("15 x^2")
@@ -1380,7 +1930,10 @@ mod test {
                         multiplication_operator: ' ',
                         square_brackets_for_function: false,
                         num_exp_as_superscript: false,
-                        latex: false
+                        latex: false,
+                        decimal_precision: None,
+                        wildcard_prefix: None,
+                        max_coeff_digits: None,
                     }
                 )
             ),
@@ -1418,4 +1971,40 @@ mod test {
                 || format!("{}", a) == "3*(2+5*x^2)/((2+x)(1+x))"
         );
     }
+
+    #[test]
+    fn dot() {
+        let a = Atom::parse("x*x + x").unwrap();
+        let dot = a.to_dot();
+
+        let x_nodes: Vec<_> = dot
+            .lines()
+            .filter(|l| l.ends_with("[label=\"x\"];"))
+            .collect();
+        assert_eq!(x_nodes.len(), 1);
+
+        let x_id = x_nodes[0].trim().split(' ').next().unwrap();
+        let edges = dot
+            .lines()
+            .filter(|l| l.trim().ends_with(&format!("-> {};", x_id)))
+            .count();
+        assert_eq!(edges, 2);
+
+        let a = Atom::parse("f(v1,v2,v3,v4,v5)").unwrap();
+        let dot = a.to_dot_with_limit(3);
+        assert_eq!(dot.matches("[label=").count(), 4);
+        assert_eq!(dot.matches("-> n3;").count(), 3);
+    }
+
+    #[test]
+    fn sexpr() {
+        let a = Atom::parse("3*x^2+1").unwrap();
+        assert_eq!(a.to_sexpr(), "(+ (* 3 (^ x 2)) 1)");
+
+        let a = Atom::parse("-2/3*f(x,y^-1)").unwrap();
+        assert_eq!(a.to_sexpr(), "(* (/ -2 3) (f x (^ y -1)))");
+
+        let a = Atom::parse("1/123456789012345678901234567890").unwrap();
+        assert_eq!(a.to_sexpr(), "(/ 1 123456789012345678901234567890)");
+    }
 }
@@ -2,11 +2,11 @@ use byteorder::{ReadBytesExt, WriteBytesExt};
 use std::hash::Hash;
 use std::io::{Read, Write};
 use std::mem::ManuallyDrop;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use std::thread::LocalKey;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::hash_map::Entry,
     ops::{Deref, DerefMut},
 };
@@ -18,46 +18,127 @@ use once_cell::sync::Lazy;
 use smartstring::alias::String;
 
 use crate::domains::finite_field::Zp64;
+use crate::domains::galois_field::GaloisField;
+use crate::id::{Matcher, Pattern};
 use crate::poly::Variable;
 use crate::{
-    atom::{Atom, Symbol},
+    atom::{Atom, AtomView, Symbol},
     coefficient::Coefficient,
     domains::finite_field::FiniteFieldCore,
     LicenseManager, LICENSE_MANAGER,
 };
 
-pub const EXPORT_FORMAT_VERSION: u16 = 1;
+pub const EXPORT_FORMAT_VERSION: u16 = 2;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct FiniteFieldIndex(pub(crate) usize);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GaloisFieldIndex(pub(crate) usize);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct VariableListIndex(pub(crate) usize);
 
+/// A user-defined simplification of a function, registered with [State::add_normalization_hook].
+/// The hook receives the normalized function and writes its simplified form in the output atom,
+/// returning `false` if it does not apply.
+pub type NormalizationHook = Arc<dyn Fn(AtomView, &mut Atom) -> bool + Send + Sync>;
+
+/// A rewrite rule or hook that is applied when a function is normalized.
+#[derive(Clone)]
+pub(crate) enum NormalizationRule {
+    Pattern(Pattern, Pattern),
+    Hook(NormalizationHook),
+}
+
+/// Rewrite rules that are applied when a function is normalized.
+type NormalizationRules = Arc<Vec<NormalizationRule>>;
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum FunctionAttribute {
     Symmetric,
     Antisymmetric,
     Linear,
+    /// The arguments of the function commute. For normalization this is
+    /// the same as [FunctionAttribute::Symmetric].
+    Commutative,
+}
+
+/// An assumption about the values of a variable, which enables simplifications
+/// that do not hold in general. See [State::add_assumption].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assumption {
+    Positive,
+    Negative,
+    Nonnegative,
+    Real,
+    Integer,
+}
+
+impl Assumption {
+    fn bits(&self) -> u8 {
+        match self {
+            // the bits of the assumption and all assumptions it implies
+            Assumption::Real => 0b00001,
+            Assumption::Nonnegative => 0b00011,
+            Assumption::Positive => 0b00111,
+            Assumption::Negative => 0b01001,
+            Assumption::Integer => 0b10001,
+        }
+    }
+}
+
+/// The set of assumptions of a variable, including the implied ones.
+/// For example, a positive variable is also nonnegative and real.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Assumptions(u8);
+
+impl Assumptions {
+    /// Check if the assumption holds.
+    pub fn contains(&self, assumption: Assumption) -> bool {
+        self.0 & assumption.bits() == assumption.bits()
+    }
+
+    /// Check if there are no assumptions.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
 }
 
 pub struct StateMap {
     pub(crate) symbols: HashMap<u32, Symbol>,
     pub(crate) finite_fields: HashMap<FiniteFieldIndex, FiniteFieldIndex>,
+    pub(crate) galois_fields: HashMap<GaloisFieldIndex, GaloisFieldIndex>,
     pub(crate) variables_lists: HashMap<u64, Arc<Vec<Variable>>>,
 }
 
 impl StateMap {
     pub fn is_empty(&self) -> bool {
-        self.symbols.is_empty() && self.finite_fields.is_empty() && self.variables_lists.is_empty()
+        self.symbols.is_empty()
+            && self.finite_fields.is_empty()
+            && self.galois_fields.is_empty()
+            && self.variables_lists.is_empty()
+    }
+
+    /// Get the index in the current state of the Galois field with index `fi` in the imported state.
+    pub fn get_galois_field_index(&self, fi: GaloisFieldIndex) -> GaloisFieldIndex {
+        self.galois_fields.get(&fi).copied().unwrap_or(fi)
     }
 }
 
 static STATE: Lazy<RwLock<State>> = Lazy::new(|| RwLock::new(State::new()));
 static ID_TO_STR: AppendOnlyVec<(Symbol, String)> = AppendOnlyVec::new();
 static FINITE_FIELDS: AppendOnlyVec<Zp64> = AppendOnlyVec::new();
+static GALOIS_FIELDS: AppendOnlyVec<GaloisField> = AppendOnlyVec::new();
 static VARIABLE_LISTS: AppendOnlyVec<Arc<Vec<Variable>>> = AppendOnlyVec::new();
 static SYMBOL_OFFSET: AtomicUsize = AtomicUsize::new(0);
+static NORMALIZATION_RULES: Lazy<RwLock<HashMap<Symbol, NormalizationRules>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+static HAS_NORMALIZATION_RULES: AtomicBool = AtomicBool::new(false);
+static MAX_NORMALIZATION_RULE_DEPTH: AtomicUsize = AtomicUsize::new(10);
+static ASSUMPTIONS: Lazy<RwLock<HashMap<Symbol, Assumptions>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+static HAS_ASSUMPTIONS: AtomicBool = AtomicBool::new(false);
 
 thread_local!(
     /// A thread-local workspace, that stores recyclable atoms. By making it const and
@@ -90,9 +171,29 @@ impl State {
     pub const E: Symbol = Symbol::init_var(8, 0);
     pub const I: Symbol = Symbol::init_var(9, 0);
     pub const PI: Symbol = Symbol::init_var(10, 0);
-
-    pub const BUILTIN_VAR_LIST: [&'static str; 11] = [
-        "arg", "coeff", "exp", "log", "sin", "cos", "sqrt", "der", "𝑒", "𝑖", "𝜋",
+    pub const HOLD: Symbol = Symbol::init_fn(11, 0, false, false, false);
+    pub const ABS: Symbol = Symbol::init_fn(12, 0, false, false, false);
+    pub const FLOOR: Symbol = Symbol::init_fn(13, 0, false, false, false);
+    pub const CEIL: Symbol = Symbol::init_fn(14, 0, false, false, false);
+    pub const PIECEWISE: Symbol = Symbol::init_fn(15, 0, false, false, false);
+
+    pub const BUILTIN_VAR_LIST: [&'static str; 16] = [
+        "arg",
+        "coeff",
+        "exp",
+        "log",
+        "sin",
+        "cos",
+        "sqrt",
+        "der",
+        "𝑒",
+        "𝑖",
+        "𝜋",
+        "hold",
+        "abs",
+        "floor",
+        "ceil",
+        "piecewise",
     ];
 
     fn new() -> State {
@@ -170,6 +271,12 @@ impl State {
         state.str_to_id.clear();
         SYMBOL_OFFSET.store(ID_TO_STR.len(), Ordering::Relaxed);
 
+        NORMALIZATION_RULES.write().unwrap().clear();
+        HAS_NORMALIZATION_RULES.store(false, Ordering::Relaxed);
+
+        ASSUMPTIONS.write().unwrap().clear();
+        HAS_ASSUMPTIONS.store(false, Ordering::Relaxed);
+
         for x in Self::BUILTIN_VAR_LIST {
             state.get_symbol_impl(x);
         }
@@ -180,6 +287,20 @@ impl State {
         }
     }
 
+    /// Get the number of bytes used by the symbol interner, which stores the names
+    /// of all symbols and the map from names to symbols.
+    pub fn interner_memory_usage() -> usize {
+        let names = ID_TO_STR
+            .iter()
+            .map(|(_, name)| std::mem::size_of::<(Symbol, String)>() + name.capacity())
+            .sum::<usize>();
+
+        let state = STATE.read().unwrap();
+        names
+            + state.str_to_id.capacity() * std::mem::size_of::<(String, Symbol)>()
+            + state.str_to_id.keys().map(|k| k.capacity()).sum::<usize>()
+    }
+
     /// Iterate over all defined symbols.
     pub fn symbol_iter() -> impl Iterator<Item = (Symbol, &'static str)> {
         ID_TO_STR
@@ -258,7 +379,8 @@ impl State {
                 let new_id = Symbol::init_fn(
                     r.get_id(),
                     r.get_wildcard_level(),
-                    attributes.contains(&FunctionAttribute::Symmetric),
+                    attributes.contains(&FunctionAttribute::Symmetric)
+                        || attributes.contains(&FunctionAttribute::Commutative),
                     attributes.contains(&FunctionAttribute::Antisymmetric),
                     attributes.contains(&FunctionAttribute::Linear),
                 );
@@ -290,7 +412,8 @@ impl State {
                 let new_symbol = Symbol::init_fn(
                     id as u32,
                     wildcard_level,
-                    attributes.contains(&FunctionAttribute::Symmetric),
+                    attributes.contains(&FunctionAttribute::Symmetric)
+                        || attributes.contains(&FunctionAttribute::Commutative),
                     attributes.contains(&FunctionAttribute::Antisymmetric),
                     attributes.contains(&FunctionAttribute::Linear),
                 );
@@ -310,10 +433,169 @@ impl State {
         &ID_TO_STR[id.get_id() as usize + SYMBOL_OFFSET.load(Ordering::Relaxed)].1
     }
 
+    /// Attach the rule `lhs -> rhs` to the function `symbol`, so that it is applied
+    /// whenever a function with this symbol is normalized. The rules of a symbol are tried
+    /// in the order in which they were added, and the first rule that matches the function
+    /// is applied. Since the result is normalized again, rules are applied until a fixpoint is reached.
+    ///
+    /// The pattern `lhs` must be a function with head `symbol`.
+    /// Rules that keep on producing new matches, possibly through rules of other symbols,
+    /// stop being applied at the depth set by [State::set_max_normalization_rule_depth].
+    pub fn add_normalization_rule(
+        symbol: Symbol,
+        lhs: Pattern,
+        rhs: Pattern,
+    ) -> Result<(), String> {
+        let head = match &lhs {
+            Pattern::Fn(s, _) => Some(*s),
+            Pattern::Literal(a) => match a.as_view() {
+                AtomView::Fun(f) => Some(f.get_symbol()),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if head != Some(symbol) {
+            return Err(format!(
+                "The pattern of a normalization rule must be a function with head {}",
+                Self::get_name(symbol)
+            )
+            .into());
+        }
+
+        Self::push_normalization_rule(symbol, NormalizationRule::Pattern(lhs, rhs));
+        Ok(())
+    }
+
+    /// Attach a custom simplification `hook` to the function `symbol`. The hook is called
+    /// with the normalized function and should write its simplified form in the output atom
+    /// and return `true`, or return `false` if it does not apply.
+    ///
+    /// Hooks and normalization rules added with [State::add_normalization_rule] are tried
+    /// in the order in which they were added. The result is normalized again, so a hook
+    /// that always returns `true` is stopped only by the depth set by
+    /// [State::set_max_normalization_rule_depth].
+    ///
+    /// # Example
+    /// ```
+    /// # use symbolica::{atom::{Atom, AtomView}, state::State};
+    /// let delta = State::get_symbol("delta");
+    /// State::add_normalization_hook(delta, |f, out| {
+    ///     let AtomView::Fun(f) = f else { return false };
+    ///     let mut args = f.iter();
+    ///     match (args.next(), args.next()) {
+    ///         (Some(a), Some(b)) if a == b => {
+    ///             *out = Atom::new_num(1);
+    ///             true
+    ///         }
+    ///         _ => false,
+    ///     }
+    /// });
+    ///
+    /// let e = Atom::parse("delta(x, x)").unwrap();
+    /// assert_eq!(e, Atom::new_num(1));
+    /// ```
+    pub fn add_normalization_hook(
+        symbol: Symbol,
+        hook: impl Fn(AtomView, &mut Atom) -> bool + Send + Sync + 'static,
+    ) {
+        Self::push_normalization_rule(symbol, NormalizationRule::Hook(Arc::new(hook)));
+    }
+
+    fn push_normalization_rule(symbol: Symbol, rule: NormalizationRule) {
+        let mut rules = NORMALIZATION_RULES.write().unwrap();
+        let r = rules.entry(symbol).or_default();
+        Arc::make_mut(r).push(rule);
+        HAS_NORMALIZATION_RULES.store(true, Ordering::Relaxed);
+    }
+
+    /// Remove all normalization rules and hooks of the function `symbol`.
+    pub fn clear_normalization_rules(symbol: Symbol) {
+        let mut rules = NORMALIZATION_RULES.write().unwrap();
+        rules.remove(&symbol);
+        HAS_NORMALIZATION_RULES.store(!rules.is_empty(), Ordering::Relaxed);
+    }
+
+    /// Set the maximal number of nested normalization rule applications. The default is 10.
+    pub fn set_max_normalization_rule_depth(depth: usize) {
+        MAX_NORMALIZATION_RULE_DEPTH.store(depth, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get_max_normalization_rule_depth() -> usize {
+        MAX_NORMALIZATION_RULE_DEPTH.load(Ordering::Relaxed)
+    }
+
+    /// Get the normalization rules and hooks of the function `symbol`.
+    #[inline]
+    pub(crate) fn get_normalization_rules(symbol: Symbol) -> Option<NormalizationRules> {
+        if !HAS_NORMALIZATION_RULES.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        NORMALIZATION_RULES.read().unwrap().get(&symbol).cloned()
+    }
+
+    /// Assume that the variable `symbol` satisfies `assumption`, so that the normalization
+    /// can apply simplifications that are only valid under this assumption. For example,
+    /// `sqrt(x^2)` becomes `abs(x)` for a real `x` and `x` for a nonnegative `x`.
+    ///
+    /// Returns an error if the assumption conflicts with an earlier assumption, such as
+    /// a negative variable that is assumed to be positive. Expressions that were
+    /// normalized before the assumption was added are not simplified again.
+    pub fn add_assumption(symbol: Symbol, assumption: Assumption) -> Result<(), String> {
+        let mut assumptions = ASSUMPTIONS.write().unwrap();
+        let a = assumptions.entry(symbol).or_default();
+        let new = Assumptions(a.0 | assumption.bits());
+
+        if new.contains(Assumption::Negative) && new.contains(Assumption::Nonnegative) {
+            return Err(format!(
+                "The assumption {:?} conflicts with the earlier assumptions of {}",
+                assumption,
+                Self::get_name(symbol)
+            )
+            .into());
+        }
+
+        *a = new;
+        HAS_ASSUMPTIONS.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Get the assumptions of the variable `symbol`.
+    #[inline]
+    pub fn get_assumptions(symbol: Symbol) -> Assumptions {
+        if !HAS_ASSUMPTIONS.load(Ordering::Relaxed) {
+            return Assumptions::default();
+        }
+
+        ASSUMPTIONS
+            .read()
+            .unwrap()
+            .get(&symbol)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Remove all assumptions of the variable `symbol`.
+    pub fn clear_assumptions(symbol: Symbol) {
+        let mut assumptions = ASSUMPTIONS.write().unwrap();
+        assumptions.remove(&symbol);
+        HAS_ASSUMPTIONS.store(!assumptions.is_empty(), Ordering::Relaxed);
+    }
+
     pub fn get_finite_field(fi: FiniteFieldIndex) -> &'static Zp64 {
         &FINITE_FIELDS[fi.0]
     }
 
+    /// Get the finite field with index `fi`, or `None` if no such field is registered.
+    pub fn try_get_finite_field(fi: FiniteFieldIndex) -> Option<&'static Zp64> {
+        if fi.0 < FINITE_FIELDS.len() {
+            Some(&FINITE_FIELDS[fi.0])
+        } else {
+            None
+        }
+    }
+
     pub fn get_or_insert_finite_field(f: Zp64) -> FiniteFieldIndex {
         STATE.write().unwrap().get_or_insert_finite_field_impl(f)
     }
@@ -329,6 +611,36 @@ impl State {
         FiniteFieldIndex(index)
     }
 
+    pub fn get_galois_field(fi: GaloisFieldIndex) -> &'static GaloisField {
+        &GALOIS_FIELDS[fi.0]
+    }
+
+    /// Get the Galois field with index `fi`, or `None` if no such field is registered.
+    pub fn try_get_galois_field(fi: GaloisFieldIndex) -> Option<&'static GaloisField> {
+        if fi.0 < GALOIS_FIELDS.len() {
+            Some(&GALOIS_FIELDS[fi.0])
+        } else {
+            None
+        }
+    }
+
+    /// Register the Galois field `f`, so that its elements can be written in packed form
+    /// and its defining polynomial is recorded in the exported state.
+    pub fn get_or_insert_galois_field(f: GaloisField) -> GaloisFieldIndex {
+        STATE.write().unwrap().get_or_insert_galois_field_impl(f)
+    }
+
+    pub(crate) fn get_or_insert_galois_field_impl(&mut self, f: GaloisField) -> GaloisFieldIndex {
+        for (i, f2) in GALOIS_FIELDS.iter().enumerate() {
+            if &f == f2 {
+                return GaloisFieldIndex(i);
+            }
+        }
+
+        let index = GALOIS_FIELDS.push(f);
+        GaloisFieldIndex(index)
+    }
+
     pub fn get_variable_list(fi: VariableListIndex) -> Arc<Vec<Variable>> {
         VARIABLE_LISTS[fi.0].clone()
     }
@@ -374,6 +686,15 @@ impl State {
             dest.write_u64::<LittleEndian>(x.get_prime())?;
         }
 
+        dest.write_u64::<LittleEndian>(GALOIS_FIELDS.len() as u64)?;
+        for x in GALOIS_FIELDS.iter() {
+            dest.write_u64::<LittleEndian>(x.get_prime())?;
+            dest.write_u64::<LittleEndian>(x.get_modulus().len() as u64)?;
+            for c in x.get_modulus() {
+                dest.write_u64::<LittleEndian>(*c)?;
+            }
+        }
+
         dest.write_u64::<LittleEndian>(VARIABLE_LISTS.len() as u64)?;
         for x in VARIABLE_LISTS.iter() {
             dest.write_u64::<LittleEndian>(x.len() as u64)?;
@@ -423,6 +744,7 @@ impl State {
         let mut state_map = StateMap {
             symbols: HashMap::default(),
             finite_fields: HashMap::default(),
+            galois_fields: HashMap::default(),
             variables_lists: HashMap::default(),
         };
 
@@ -496,6 +818,25 @@ impl State {
             }
         }
 
+        let n_galois_fields = source.read_u64::<LittleEndian>()?;
+        for x in 0..n_galois_fields {
+            let prime = source.read_u64::<LittleEndian>()?;
+            let n_coeffs = source.read_u64::<LittleEndian>()?;
+            let mut modulus = vec![];
+            for _ in 0..n_coeffs {
+                modulus.push(source.read_u64::<LittleEndian>()?);
+            }
+
+            let field = GaloisField::new(prime, &modulus)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let id = State::get_or_insert_galois_field(field);
+            if x != id.0 as u64 {
+                state_map
+                    .galois_fields
+                    .insert(GaloisFieldIndex(x as usize), id);
+            }
+        }
+
         let n_variable_lists = source.read_u64::<LittleEndian>()?;
         for x in 0..n_variable_lists {
             let n_vars = source.read_u64::<LittleEndian>()?;
@@ -560,6 +901,8 @@ impl State {
 /// thread-local workspace (which may be a different one than the one it was created by).
 pub struct Workspace {
     atom_buffer: RefCell<Vec<Atom>>,
+    matcher: RefCell<Matcher>,
+    flatten_assoc: Cell<bool>,
 }
 
 impl Workspace {
@@ -569,6 +912,8 @@ impl Workspace {
     const fn new() -> Self {
         Workspace {
             atom_buffer: RefCell::new(Vec::new()),
+            matcher: RefCell::new(Matcher::new()),
+            flatten_assoc: Cell::new(true),
         }
     }
 
@@ -578,6 +923,39 @@ impl Workspace {
         &WORKSPACE
     }
 
+    /// Evaluate `f` while controlling whether nested sums and products that are created on the
+    /// current thread are flattened into a single sum or product. When `flatten` is `false`,
+    /// `a+(b+c)` normalizes to `a+hold(b+c)`, so that the nesting is preserved until the `hold`
+    /// is removed with [Atom::release](crate::atom::Atom::release). Nested settings are restored when `f` returns.
+    pub fn with_flatten_assoc<T>(&self, flatten: bool, f: impl FnOnce() -> T) -> T {
+        /// Restores the previous setting, also when `f` unwinds.
+        struct Restore<'a>(&'a Cell<bool>, bool);
+
+        impl Drop for Restore<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.1);
+            }
+        }
+
+        let _restore = Restore(&self.flatten_assoc, self.flatten_assoc.replace(flatten));
+        f()
+    }
+
+    /// Returns `true` if nested sums and products should be flattened,
+    /// see [Workspace::with_flatten_assoc].
+    #[inline]
+    pub(crate) fn flatten_assoc(&self) -> bool {
+        self.flatten_assoc.get()
+    }
+
+    /// Get the symbols for a list of names, registering the names that are new, while acquiring
+    /// the lock on the global state only once. The symbols are returned in the same order as the names,
+    /// and duplicate names yield the same symbol. See [State::get_symbol].
+    pub fn intern_batch(names: &[&str]) -> Vec<Symbol> {
+        let mut state = STATE.write().unwrap();
+        names.iter().map(|n| state.get_symbol_impl(n)).collect()
+    }
+
     /// Return a recycled atom from this workspace. The atom may have the same value as before.
     #[inline]
     pub fn new_atom(&self) -> RecycledAtom {
@@ -608,11 +986,38 @@ impl Workspace {
         owned
     }
 
+    /// Get the number of bytes allocated by the workspace for its recycled atoms,
+    /// plus the bytes used by the symbol interner of the global [State] (see [State::interner_memory_usage]).
+    /// The interner is shared between all workspaces, so summing this over several workspaces
+    /// counts it more than once.
+    pub fn memory_usage(&self) -> usize {
+        let buffer = self.atom_buffer.borrow();
+        State::interner_memory_usage()
+            + std::mem::size_of::<Workspace>()
+            + buffer.capacity() * std::mem::size_of::<Atom>()
+            + buffer.iter().map(|a| a.get_capacity()).sum::<usize>()
+    }
+
     pub fn return_atom(&self, atom: Atom) {
         if let Ok(mut a) = self.atom_buffer.try_borrow_mut() {
             a.push(atom);
         }
     }
+
+    /// Take the pool of match stacks of this workspace. If the pool is already taken,
+    /// for example by an enclosing replacement, an empty pool is returned.
+    pub(crate) fn take_matcher(&self) -> Matcher {
+        self.matcher.take()
+    }
+
+    /// Return a pool of match stacks to this workspace, if the workspace has no pool.
+    pub(crate) fn return_matcher(&self, matcher: Matcher) {
+        if let Ok(mut m) = self.matcher.try_borrow_mut() {
+            if m.is_empty() {
+                *m = matcher;
+            }
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Hash, Clone)]
@@ -713,7 +1118,47 @@ impl Drop for RecycledAtom {
 mod tests {
     use std::io::Cursor;
 
-    use super::State;
+    use crate::atom::Atom;
+
+    use super::{State, Workspace};
+
+    #[test]
+    fn intern_batch() {
+        let names: Vec<_> = (0..1000).map(|i| format!("intern_batch_{}", i)).collect();
+        let mut refs: Vec<_> = names.iter().map(|n| n.as_str()).collect();
+        refs.push("intern_batch_5");
+        refs.push("v1");
+
+        let symbols = Workspace::intern_batch(&refs);
+        assert_eq!(symbols.len(), 1002);
+
+        let mut unique = symbols[..1000].to_vec();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 1000);
+
+        assert_eq!(symbols[1000], symbols[5]);
+        assert_eq!(symbols[1001], State::get_symbol("v1"));
+        assert_eq!(State::get_symbol("intern_batch_999"), symbols[999]);
+        assert_eq!(State::get_name(symbols[42]), "intern_batch_42");
+    }
+
+    #[test]
+    fn workspace_memory_usage() {
+        let ws = Workspace::new();
+        let mut last = ws.memory_usage();
+        for i in 1..5 {
+            ws.return_atom(Atom::parse(&format!("v1^{}+v2", i)).unwrap());
+            let size = ws.memory_usage();
+            assert!(size > last);
+            last = size;
+        }
+
+        let interner = State::interner_memory_usage();
+        State::get_symbol("memory_usage_new_symbol_with_a_long_name");
+        assert!(State::interner_memory_usage() > interner);
+        assert!(ws.memory_usage() > last);
+    }
 
     #[test]
     fn state_export_import() {
@@ -0,0 +1,283 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use crate::{
+    atom::{Atom, AtomView, Symbol},
+    domains::{
+        integer::Z,
+        rational::{Rational, RationalField, Q},
+        rational_polynomial::RationalPolynomial,
+        Field, Ring,
+    },
+    poly::{factor::Factorize, polynomial::MultivariatePolynomial, Variable},
+};
+
+/// A univariate polynomial in the summation variable.
+type Poly = MultivariatePolynomial<RationalField, u16>;
+
+impl Atom {
+    /// Find an antidifference of the term in `var` using Gosper's algorithm.
+    /// See [AtomView::sum_closed_form].
+    pub fn sum_closed_form(&self, var: Symbol) -> Option<Atom> {
+        self.as_view().sum_closed_form(var)
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Find a hypergeometric antidifference `z(k)` of the term `t(k)` in the variable `k = var`
+    /// using Gosper's algorithm, such that `z(k+1) - z(k) = t(k)`. As a result,
+    ///
+    /// ```math
+    /// \sum_{k=a}^{b-1} t(k) = z(b) - z(a)
+    /// ```
+    ///
+    /// The ratio `t(k+1)/t(k)` must be a rational function in `k` with rational coefficients,
+    /// which holds for example for polynomials and for products of rational functions and powers `c^k`.
+    /// Returns `None` if the term is not of this form, or if it has no hypergeometric antidifference.
+    pub fn sum_closed_form(&self, var: Symbol) -> Option<Atom> {
+        if self.is_zero() {
+            return Some(Atom::new_num(0));
+        }
+
+        let k = Atom::new_var(var);
+        let shifted = k.into_pattern().replace_all(
+            *self,
+            &(&k + &Atom::new_num(1)).into_pattern(),
+            None,
+            None,
+        );
+
+        let vars = Arc::new(vec![Variable::Symbol(var)]);
+        let ratio: RationalPolynomial<_, u16> = (&shifted / &self.to_owned())
+            .expand()
+            .to_rational_polynomial(&Q, &Z, Some(vars.clone()));
+
+        if ratio.numerator.get_vars_ref() != vars.as_slice() {
+            return None; // the ratio depends on other symbols or is not rational in `var`
+        }
+
+        let num = ratio.numerator.map_coeff(|c| c.into(), Q);
+        let den = ratio.denominator.map_coeff(|c| c.into(), Q);
+
+        let (a, b, c) = gosper_petkovsek_form(num, den);
+
+        // solve a(k) x(k+1) - b(k-1) x(k) = c(k) for a polynomial x
+        let bs = b.shift_var(0, &(-1).into());
+        let x = solve_gosper_equation(&a, &bs, &c)?;
+
+        // z(k) = b(k-1) x(k) / c(k) t(k)
+        let mut z_num = &bs * &x;
+        let mut z_den = c;
+        let g = z_num.gcd(&z_den);
+        if !g.is_constant() {
+            z_num = z_num.divides(&g).unwrap();
+            z_den = z_den.divides(&g).unwrap();
+        }
+
+        Some(&(&z_num.to_expression() / &z_den.to_expression()) * &self.to_owned())
+    }
+}
+
+/// Get the coefficient of `k^d` of a univariate polynomial.
+fn coefficient(p: &Poly, d: usize) -> Rational {
+    p.into_iter()
+        .find(|t| t.exponents[0] as usize == d)
+        .map(|t| t.coefficient.clone())
+        .unwrap_or_else(|| Q.zero())
+}
+
+/// Write `a/b` as `a'(k)/b'(k) * c(k+1)/c(k)` such that `gcd(a'(k), b'(k+h)) = 1`
+/// for all non-negative integers `h`.
+fn gosper_petkovsek_form(mut a: Poly, mut b: Poly) -> (Poly, Poly, Poly) {
+    // the shifts `h` for which a factor of `a(k)` equals a factor of `b(k+h)`
+    let mut shifts = BTreeSet::new();
+    let (fa, fb) = (a.factor(), b.factor());
+    for (f, _) in fa.iter().filter(|(f, _)| !f.is_constant()) {
+        for (g, _) in fb.iter().filter(|(g, _)| g.degree(0) == f.degree(0)) {
+            let (f, g) = (f.clone().make_monic(), g.clone().make_monic());
+
+            // the subleading coefficient of g(k+h) is g_{d-1} + d*h
+            let d = f.degree(0) as usize;
+            let h = Q.div(
+                &Q.sub(&coefficient(&f, d - 1), &coefficient(&g, d - 1)),
+                &(d as i64).into(),
+            );
+
+            if h.is_integer() && !h.is_negative() {
+                if let Some(h) = h.numerator().to_i64() {
+                    if g.shift_var(0, &h.into()) == f {
+                        shifts.insert(h);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut c = a.one();
+    for h in shifts {
+        let s = a.gcd(&b.shift_var(0, &h.into()));
+        if s.is_constant() {
+            continue;
+        }
+
+        a = a.divides(&s).unwrap();
+        b = b.divides(&s.shift_var(0, &(-h).into())).unwrap();
+        for i in 1..=h {
+            c = &c * &s.shift_var(0, &(-i).into());
+        }
+    }
+
+    (a, b, c)
+}
+
+/// Find a polynomial `x` that satisfies `a(k) x(k+1) - b(k) x(k) = c(k)`.
+fn solve_gosper_equation(a: &Poly, b: &Poly, c: &Poly) -> Option<Poly> {
+    let (da, db, dc) = (a.degree(0) as i64, b.degree(0) as i64, c.degree(0) as i64);
+
+    // determine the degree of x from the leading terms of the equation
+    let degree = if da != db || a.lcoeff() != b.lcoeff() {
+        dc - da.max(db)
+    } else {
+        // the leading terms cancel and the next ones are lc(a)*deg(x) + a_{d-1} - b_{d-1}
+        let mut degree = dc - da + 1;
+        if da > 0 {
+            let d0 = Q.div(
+                &Q.sub(
+                    &coefficient(b, da as usize - 1),
+                    &coefficient(a, da as usize - 1),
+                ),
+                &a.lcoeff(),
+            );
+            if d0.is_integer() {
+                if let Some(d0) = d0.numerator().to_i64() {
+                    degree = degree.max(d0);
+                }
+            }
+        }
+        degree
+    };
+
+    if degree < 0 {
+        return None;
+    }
+    let degree = degree as usize;
+
+    // column j contains the coefficients of a(k) (k+1)^j - b(k) k^j
+    let k = a.variable(&a.get_vars_ref()[0]).unwrap();
+    let k1 = &k + &a.one();
+    let (mut kp, mut k1p) = (a.one(), a.one());
+    let mut columns = Vec::with_capacity(degree + 1);
+    for _ in 0..=degree {
+        columns.push(&(a * &k1p) - &(b * &kp));
+        kp = &kp * &k;
+        k1p = &k1p * &k1;
+    }
+
+    let nrows = columns
+        .iter()
+        .map(|p| p.degree(0) as usize)
+        .max()
+        .unwrap()
+        .max(dc as usize)
+        + 1;
+
+    let mut m: Vec<Vec<Rational>> = (0..nrows)
+        .map(|i| {
+            columns
+                .iter()
+                .map(|p| coefficient(p, i))
+                .chain(std::iter::once(coefficient(c, i)))
+                .collect()
+        })
+        .collect();
+
+    let sol = solve_linear(&mut m, degree + 1)?;
+
+    let mut x = a.zero();
+    for (i, s) in sol.into_iter().enumerate() {
+        if !s.is_zero() {
+            x = x + a.monomial(s, vec![i as u16]);
+        }
+    }
+    Some(x)
+}
+
+/// Solve the linear system with augmented matrix `m` and `ncols` unknowns, setting free unknowns to zero.
+/// Returns `None` if the system is inconsistent.
+fn solve_linear(m: &mut [Vec<Rational>], ncols: usize) -> Option<Vec<Rational>> {
+    let mut pivots = vec![];
+    let mut row = 0;
+    for col in 0..ncols {
+        let Some(r) = (row..m.len()).find(|r| !m[*r][col].is_zero()) else {
+            continue;
+        };
+        m.swap(row, r);
+
+        let inv = Q.inv(&m[row][col]);
+        for x in &mut m[row] {
+            Q.mul_assign(x, &inv);
+        }
+
+        for r in 0..m.len() {
+            if r != row && !m[r][col].is_zero() {
+                let f = m[r][col].clone();
+                let (pivot_row, cur) = if r < row {
+                    let (lo, hi) = m.split_at_mut(row);
+                    (&hi[0], &mut lo[r])
+                } else {
+                    let (lo, hi) = m.split_at_mut(r);
+                    (&lo[row], &mut hi[0])
+                };
+                for (x, p) in cur[col..].iter_mut().zip(&pivot_row[col..]) {
+                    Q.sub_assign(x, &Q.mul(&f, p));
+                }
+            }
+        }
+
+        pivots.push(col);
+        row += 1;
+    }
+
+    if m[row..].iter().any(|r| !r[ncols].is_zero()) {
+        return None;
+    }
+
+    let mut sol = vec![Q.zero(); ncols];
+    for (r, col) in pivots.into_iter().enumerate() {
+        sol[col] = m[r][ncols].clone();
+    }
+    Some(sol)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        atom::Atom,
+        domains::{integer::Z, rational::Q, rational_polynomial::RationalPolynomial},
+        state::State,
+    };
+
+    #[test]
+    fn gosper() {
+        let k = State::get_symbol("v1");
+
+        for (t, z) in [
+            ("v1", "v1*(v1-1)/2"),
+            ("v1^2", "v1*(v1-1)*(2*v1-1)/6"),
+            ("5", "5*v1"),
+            ("2^v1", "2^v1"),
+            ("v1*2^v1", "(v1-2)*2^v1"),
+            ("(v1*(v1+1))^-1", "-v1^-1"),
+            ("v2*v1*3^v1", "v2*(2*v1-3)*3^v1/4"),
+        ] {
+            let t = Atom::parse(t).unwrap();
+            let r = t.sum_closed_form(k).unwrap();
+            let diff: RationalPolynomial<_, u8> =
+                (&r - &Atom::parse(z).unwrap()).to_rational_polynomial(&Q, &Z, None);
+            assert!(diff.is_zero());
+        }
+
+        for t in ["v1^-1", "2^(v1^2)", "f1(v1)", "v2^v1"] {
+            assert_eq!(Atom::parse(t).unwrap().sum_closed_form(k), None);
+        }
+    }
+}
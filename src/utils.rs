@@ -1,3 +1,62 @@
+use std::cell::RefCell;
+
+use rand::{thread_rng, RngCore, SeedableRng};
+use rand_xoshiro::Xoshiro256StarStar;
+
+thread_local!(
+    static RNG_STATE: RefCell<Option<Xoshiro256StarStar>> = const { RefCell::new(None) };
+);
+
+/// Make the randomized algorithms on the current thread, such as polynomial factorization
+/// and GCD computations, reproducible by drawing all random numbers from a generator seeded with `seed`.
+/// Nested calls draw from the same stream. Set to `None` to use the default thread-local generator.
+pub fn set_rng_seed(seed: Option<u64>) {
+    RNG_STATE.with_borrow_mut(|s| *s = seed.map(Xoshiro256StarStar::seed_from_u64));
+}
+
+/// Get the random number generator used by the randomized algorithms on the current thread.
+/// See [set_rng_seed].
+pub fn rng() -> AlgorithmRng {
+    AlgorithmRng
+}
+
+/// A handle to the random number generator of the current thread, which is
+/// seeded if [set_rng_seed] was called and otherwise [rand::thread_rng].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlgorithmRng;
+
+impl AlgorithmRng {
+    #[inline]
+    fn with<T>(&mut self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        RNG_STATE.with_borrow_mut(|s| match s {
+            Some(r) => f(r),
+            None => f(&mut thread_rng()),
+        })
+    }
+}
+
+impl RngCore for AlgorithmRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.with(|r| r.next_u32())
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.with(|r| r.next_u64())
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.with(|r| r.fill_bytes(dest))
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.with(|r| r.try_fill_bytes(dest))
+    }
+}
+
 pub fn gcd_unsigned(mut a: u64, mut b: u64) -> u64 {
     let mut c;
     while a != 0 {
@@ -0,0 +1,190 @@
+//! Traversal of expressions without matching on the internal representation.
+//!
+//! A [Visitor] receives a callback for every node of an expression. Nodes are
+//! visited in pre-order: a node is visited before its children, and the children
+//! are visited in the order in which they are stored. For function, power, addition and
+//! multiplication nodes, [Visitor::leave] is called after all children have been visited,
+//! which can be used for post-order analyses.
+//!
+//! # Examples
+//!
+//! ```
+//! use symbolica::{atom::Atom, atom::representation::VarView, visitor::Visitor};
+//!
+//! struct CountVars(usize);
+//!
+//! impl Visitor for CountVars {
+//!     fn visit_var(&mut self, _v: VarView) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! let a = Atom::parse("x*y + f(x)").unwrap();
+//! let mut c = CountVars(0);
+//! a.as_view().accept(&mut c);
+//! assert_eq!(c.0, 3);
+//! ```
+
+use crate::atom::{
+    representation::FunView, AddView, Atom, AtomView, MulView, NumView, PowView, VarView,
+};
+
+/// A visitor of the nodes of an expression. All methods have a default
+/// implementation that does nothing, so that only the relevant ones need to be implemented.
+///
+/// The methods for composite nodes return whether their children should be visited.
+pub trait Visitor {
+    fn visit_num(&mut self, _n: NumView<'_>) {}
+
+    fn visit_var(&mut self, _v: VarView<'_>) {}
+
+    fn visit_fn(&mut self, _f: FunView<'_>) -> bool {
+        true
+    }
+
+    fn visit_pow(&mut self, _p: PowView<'_>) -> bool {
+        true
+    }
+
+    fn visit_add(&mut self, _a: AddView<'_>) -> bool {
+        true
+    }
+
+    fn visit_mul(&mut self, _m: MulView<'_>) -> bool {
+        true
+    }
+
+    /// Called after the children of a function, power, addition or multiplication
+    /// have been visited. It is not called if the children were skipped.
+    fn leave(&mut self, _view: AtomView<'_>) {}
+}
+
+impl Atom {
+    /// Visit all nodes of the expression in pre-order. See [AtomView::accept].
+    pub fn accept(&self, v: &mut impl Visitor) {
+        self.as_view().accept(v)
+    }
+
+    /// Fold over all nodes of the expression in pre-order. See [AtomView::fold].
+    pub fn fold<T>(&self, init: T, f: &mut impl FnMut(T, AtomView<'_>) -> T) -> T {
+        self.as_view().fold(init, f)
+    }
+}
+
+impl<'a> AtomView<'a> {
+    /// Visit all nodes of the expression in pre-order: a node is visited
+    /// before its children and the children are visited in storage order.
+    /// The base of a power is visited before the exponent.
+    pub fn accept(&self, v: &mut impl Visitor) {
+        match self {
+            AtomView::Num(n) => v.visit_num(*n),
+            AtomView::Var(x) => v.visit_var(*x),
+            AtomView::Fun(f) => {
+                if v.visit_fn(*f) {
+                    for arg in f.iter() {
+                        arg.accept(v);
+                    }
+                    v.leave(*self);
+                }
+            }
+            AtomView::Pow(p) => {
+                if v.visit_pow(*p) {
+                    let (base, exp) = p.get_base_exp();
+                    base.accept(v);
+                    exp.accept(v);
+                    v.leave(*self);
+                }
+            }
+            AtomView::Add(a) => {
+                if v.visit_add(*a) {
+                    for arg in a.iter() {
+                        arg.accept(v);
+                    }
+                    v.leave(*self);
+                }
+            }
+            AtomView::Mul(m) => {
+                if v.visit_mul(*m) {
+                    for arg in m.iter() {
+                        arg.accept(v);
+                    }
+                    v.leave(*self);
+                }
+            }
+        }
+    }
+
+    /// Fold `f` over all nodes of the expression, starting from `init`.
+    /// The nodes are visited in the same pre-order as [AtomView::accept].
+    pub fn fold<T>(&self, init: T, f: &mut impl FnMut(T, AtomView<'a>) -> T) -> T {
+        let acc = f(init, *self);
+
+        match self {
+            AtomView::Num(_) | AtomView::Var(_) => acc,
+            AtomView::Fun(fun) => fun.iter().fold(acc, |acc, arg| arg.fold(acc, f)),
+            AtomView::Pow(p) => {
+                let (base, exp) = p.get_base_exp();
+                let acc = base.fold(acc, f);
+                exp.fold(acc, f)
+            }
+            AtomView::Add(a) => a.iter().fold(acc, |acc, arg| arg.fold(acc, f)),
+            AtomView::Mul(m) => m.iter().fold(acc, |acc, arg| arg.fold(acc, f)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        atom::{representation::FunView, Atom, AtomView, Symbol, VarView},
+        state::State,
+    };
+
+    use super::Visitor;
+
+    #[derive(Default)]
+    struct Symbols {
+        vars: BTreeSet<Symbol>,
+        order: Vec<String>,
+    }
+
+    impl Visitor for Symbols {
+        fn visit_var(&mut self, v: VarView<'_>) {
+            self.vars.insert(v.get_symbol());
+            self.order.push(State::get_name(v.get_symbol()).to_string());
+        }
+
+        fn visit_fn(&mut self, f: FunView<'_>) -> bool {
+            self.order.push(State::get_name(f.get_symbol()).to_string());
+            true
+        }
+
+        fn leave(&mut self, view: AtomView<'_>) {
+            if let AtomView::Fun(f) = view {
+                self.order
+                    .push(format!("/{}", State::get_name(f.get_symbol())));
+            }
+        }
+    }
+
+    #[test]
+    fn visit() {
+        let a = Atom::parse("f1(v1, v2^v3) + v4*f2(v1, 5)").unwrap();
+
+        let mut v = Symbols::default();
+        a.accept(&mut v);
+        assert_eq!(v.vars, a.free_symbols(false));
+
+        let mut v = Symbols::default();
+        Atom::parse("f1(v1, f2(v2), v3)").unwrap().accept(&mut v);
+        assert_eq!(v.order, ["f1", "v1", "f2", "v2", "/f2", "v3", "/f1"]);
+
+        let count = a.fold(0, &mut |acc, n| match n {
+            AtomView::Var(_) => acc + 1,
+            _ => acc,
+        });
+        assert_eq!(count, 5);
+    }
+}
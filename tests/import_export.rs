@@ -2,10 +2,99 @@ use std::io::Cursor;
 
 use smartstring::SmartString;
 use symbolica::{
-    atom::Atom,
-    state::{FunctionAttribute, State},
+    atom::{read_packed_galois_field, write_packed_galois_field, Atom, AtomBatch},
+    domains::{galois_field::GaloisField, Ring},
+    state::{Assumption, FunctionAttribute, State},
 };
 
+fn batch() {
+    let atoms: Vec<_> = (0..100)
+        .map(|i| Atom::parse(&format!("f{}(x, y)*x^{} + {}/7", i % 5, i, i)).unwrap())
+        .collect();
+
+    let mut export = vec![];
+    AtomBatch::export(&atoms, &mut export).unwrap();
+
+    // reset the state and shuffle the symbol ids
+    unsafe { State::reset() };
+
+    State::get_symbol("y");
+    State::get_symbol("f3");
+
+    assert_eq!(AtomBatch::len(&export).unwrap(), 100);
+
+    let a42 = AtomBatch::load_one(&export, 42).unwrap();
+    assert_eq!(a42, Atom::parse("f2(x, y)*x^42 + 6").unwrap());
+
+    let all = AtomBatch::load(&export).unwrap();
+    let expected: Vec<_> = (0..100)
+        .map(|i| Atom::parse(&format!("f{}(x, y)*x^{} + {}/7", i % 5, i, i)).unwrap())
+        .collect();
+    assert_eq!(all, expected);
+
+    assert!(AtomBatch::load_one(&export, 100).is_err());
+
+    // point the entry of atom 42 past the start of the index
+    let index_offset = export.len() - 16 - 8 * 100;
+    let mut tampered = export.clone();
+    let entry = index_offset + 8 * 42;
+    tampered[entry..entry + 8].copy_from_slice(&(index_offset as u64 + 1).to_le_bytes());
+    assert_eq!(
+        AtomBatch::load_one(&tampered, 42).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+    assert!(AtomBatch::load(&tampered).is_err());
+
+    // offsets that are not increasing
+    let mut tampered = export.clone();
+    let entry = index_offset + 8 * 43;
+    let offset_42 = u64::from_le_bytes(tampered[entry - 8..entry].try_into().unwrap());
+    tampered[entry..entry + 8].copy_from_slice(&(offset_42 - 1).to_le_bytes());
+    assert_eq!(
+        AtomBatch::load_one(&tampered, 42).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+}
+
+fn galois_field() {
+    // GF(2^3) = Z_2[x] / (x^3 + x + 1)
+    let field = GaloisField::new(2, &[1, 1, 0, 1]).unwrap();
+    let index = State::get_or_insert_galois_field(field.clone());
+
+    let x = field.generator();
+    let a = field.mul(&x, &x);
+    let mut packed = vec![];
+    write_packed_galois_field(&a, index, &mut packed);
+
+    let mut state_export = vec![];
+    State::export(&mut state_export).unwrap();
+
+    unsafe { State::reset() };
+
+    let state_map = State::import(Cursor::new(&state_export), None).unwrap();
+    let (b, i, rest) = read_packed_galois_field(&packed, Some(&state_map)).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(State::get_galois_field(i), &field);
+    assert_eq!(b, a);
+    assert_eq!(field.mul(&b, &x), field.to_element(&[1, 1]));
+}
+
+fn assumptions() {
+    let x = State::get_symbol("xpos");
+    State::add_assumption(x, Assumption::Positive).unwrap();
+    assert_eq!(
+        Atom::parse("sqrt(xpos^2)").unwrap(),
+        Atom::parse("xpos").unwrap()
+    );
+
+    // the reset symbol id is reused, but the assumptions are not
+    unsafe { State::reset() };
+    let y = State::get_symbol("y");
+    assert_eq!(y, x);
+    assert!(State::get_assumptions(y).is_empty());
+    assert_ne!(Atom::parse("sqrt(y^2)").unwrap(), Atom::parse("y").unwrap());
+}
+
 fn conflict() {
     State::get_symbol("x");
     State::get_symbol("y");
@@ -38,6 +127,35 @@ fn conflict() {
     assert_eq!(a_rec, r);
 }
 
+fn serde() {
+    let atoms = [
+        Atom::parse("f1(x, y)*x^2 + 123456789012345678901234567890/7*y").unwrap(),
+        Atom::new(),
+        Atom::Empty,
+    ];
+
+    let encoded: Vec<_> = atoms
+        .iter()
+        .map(|a| bincode::serialize(a).unwrap())
+        .collect();
+
+    unsafe { State::reset() };
+    State::get_symbol("y");
+
+    let decoded: Vec<Atom> = encoded
+        .iter()
+        .map(|e| bincode::deserialize(e).unwrap())
+        .collect();
+    assert_eq!(
+        decoded[0],
+        Atom::parse("f1(x, y)*x^2 + 123456789012345678901234567890/7*y").unwrap()
+    );
+    assert_eq!(decoded[1], Atom::new());
+    assert_eq!(decoded[2], Atom::Empty);
+
+    assert!(bincode::deserialize::<Atom>(&encoded[0][..encoded[0].len() - 3]).is_err());
+}
+
 #[test]
 fn rational_rename() {
     State::get_symbol("x");
@@ -64,4 +182,16 @@ fn rational_rename() {
 
     unsafe { State::reset() };
     conflict();
+
+    unsafe { State::reset() };
+    batch();
+
+    unsafe { State::reset() };
+    serde();
+
+    unsafe { State::reset() };
+    assumptions();
+
+    unsafe { State::reset() };
+    galois_field();
 }
@@ -1,6 +1,6 @@
 use symbolica::{
     atom::{Atom, AtomView},
-    id::{Condition, Match, MatchSettings, Pattern, PatternRestriction},
+    id::{Condition, Match, MatchSettings, Pattern, PatternRestriction, Replacement},
     state::{RecycledAtom, State},
 };
 
@@ -77,3 +77,20 @@ fn replace_once() {
 
     assert_eq!(r, res);
 }
+
+#[test]
+fn replace_multiple() {
+    let expr = Atom::parse("a + 2*b + h(a, b)").unwrap();
+
+    let a = Pattern::parse("a").unwrap();
+    let b = Pattern::parse("b").unwrap();
+
+    let r = expr.replace_multiple(&[Replacement::new(&a, &b), Replacement::new(&b, &a)]);
+    assert_eq!(r, Atom::parse("b + 2*a + h(b, a)").unwrap());
+
+    // the first matching replacement takes precedence
+    let h = Pattern::parse("h(a_, b_)").unwrap();
+    let k = Pattern::parse("k(a_)").unwrap();
+    let r = expr.replace_multiple(&[Replacement::new(&h, &k), Replacement::new(&a, &b)]);
+    assert_eq!(r, Atom::parse("b + 2*b + k(a)").unwrap());
+}